@@ -0,0 +1,102 @@
+//! Parsing the weather API's response and rendering it as each view.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use weather::experiments::Assignments;
+use weather::view::{self, Device, Fragment, PageOptions, Place, View};
+use weather::APIResponse;
+use weather_helpers::Locale;
+
+const FIXTURE: &str = include_str!("../../tests/fixtures/onecall.json");
+
+// 2026-10-14 12:00 in London, when the fixtures were observed.
+const OBSERVED: i64 = 1_791_975_600;
+const UTC_OFFSET: i32 = 3600;
+
+// A view to benchmark: its name, how to build it, and the language for it.
+type Case = (&'static str, fn() -> View, Locale);
+
+fn parse() -> APIResponse {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+fn render(api_response: APIResponse, view: View, locale: Locale) -> String {
+    let experiments = Assignments::default();
+    let place = Place {
+        city: String::from("London"),
+        latitude: 51.51,
+        longitude: -0.13,
+        is_default: false,
+        map_url: None,
+    };
+
+    view::generate_view(
+        api_response,
+        &place,
+        weather_helpers::local_time(OBSERVED, UTC_OFFSET),
+        PageOptions {
+            units: "metric",
+            view,
+            experiments: &experiments,
+            canonical_url: "https://weather.example/?units=metric",
+            show_radar: false,
+            marine: None,
+            fire: None,
+            astronomy: None,
+            garden: None,
+            exercise: None,
+            laundry: None,
+            roads: None,
+            confidence: None,
+            chart: None,
+            wind_rose: None,
+            precipitation: None,
+            weekend: None,
+            day_parts: None,
+            pressure_trend: None,
+            show_details: false,
+            skin_type: None,
+            locale,
+            device: Device::Desktop,
+            nonce: "bench",
+            save_data: false,
+        },
+    )
+}
+
+fn parsing(c: &mut Criterion) {
+    c.bench_function("parse", |b| {
+        b.iter(|| serde_json::from_str::<APIResponse>(black_box(FIXTURE)).unwrap())
+    });
+}
+
+fn rendering(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render");
+    let cases: [Case; 5] = [
+        // No cache natively, so the page shell is rendered every time.
+        ("page", || View::Standard, Locale::En),
+        ("amp", || View::Amp, Locale::En),
+        ("current", || View::Fragment(Fragment::Current), Locale::En),
+        ("daily", || View::Fragment(Fragment::Daily), Locale::En),
+        ("daily_de", || View::Fragment(Fragment::Daily), Locale::De),
+    ];
+    for (name, view, locale) in cases {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                parse,
+                |api_response| render(api_response, view(), locale),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// The whole of the page handler's work between fetching and responding.
+fn end_to_end(c: &mut Criterion) {
+    c.bench_function("parse_and_render_page", |b| {
+        b.iter(|| render(parse(), View::Standard, Locale::En))
+    });
+}
+
+criterion_group!(benches, parsing, rendering, end_to_end);
+criterion_main!(benches);
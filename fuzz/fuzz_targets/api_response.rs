@@ -0,0 +1,81 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use weather::experiments::Assignments;
+use weather::view::{self, Device, Fragment, PageOptions, Place, View};
+use weather::APIResponse;
+use weather_helpers::Locale;
+
+// 2026-10-14 12:00 in London, when the fixtures were observed.
+const OBSERVED: i64 = 1_791_975_600;
+
+const FRAGMENTS: [Fragment; 10] = [
+    Fragment::Current,
+    Fragment::Conditions,
+    Fragment::Daily,
+    Fragment::Radar,
+    Fragment::Marine,
+    Fragment::Fire,
+    Fragment::Astronomy,
+    Fragment::Garden,
+    Fragment::Exercise,
+    Fragment::Laundry,
+];
+
+fuzz_target!(|body: &[u8]| {
+    // Anything the API could send must either fail to parse or render.
+    if serde_json::from_slice::<APIResponse>(body).is_err() {
+        return;
+    }
+
+    let views = [View::Standard, View::Amp]
+        .into_iter()
+        .chain(FRAGMENTS.into_iter().map(View::Fragment));
+    for view in views {
+        // The view takes the response by value, so parse a copy for each.
+        render(serde_json::from_slice(body).unwrap(), view);
+    }
+});
+
+fn render(api_response: APIResponse, view: View) {
+    let experiments = Assignments::default();
+    let place = Place {
+        city: String::from("London"),
+        latitude: 51.51,
+        longitude: -0.13,
+        is_default: false,
+        map_url: None,
+    };
+    view::generate_view(
+        api_response,
+        &place,
+        weather_helpers::local_time(OBSERVED, 3600),
+        PageOptions {
+            units: "metric",
+            view,
+            experiments: &experiments,
+            canonical_url: "https://weather.example/?units=metric",
+            show_radar: false,
+            marine: None,
+            fire: None,
+            astronomy: None,
+            garden: None,
+            exercise: None,
+            laundry: None,
+            roads: None,
+            confidence: None,
+            chart: None,
+            wind_rose: None,
+            precipitation: None,
+            weekend: None,
+            day_parts: None,
+            pressure_trend: None,
+            show_details: false,
+            skin_type: None,
+            locale: Locale::En,
+            device: Device::Desktop,
+            nonce: "fuzz",
+            save_data: false,
+        },
+    );
+}
@@ -0,0 +1,21 @@
+#![no_main]
+
+use fastly::http::Url;
+use fastly::Request;
+use libfuzzer_sys::fuzz_target;
+use weather::QueryParams;
+
+fuzz_target!(|query: &str| {
+    // `set_query` escapes whatever it's given, as a browser would.
+    let mut url = Url::parse("https://weather.example/").unwrap();
+    url.set_query(Some(query));
+    let mut req = Request::get(url);
+
+    if weather::sanitize_query(&mut req).is_some() {
+        return;
+    }
+    weather::normalize_query(&mut req);
+    if let Ok(params) = req.get_query::<QueryParams>() {
+        let _ = params.skin_type();
+    }
+});
@@ -0,0 +1,563 @@
+//! Integration test harness: runs the compiled service under Viceroy, with
+//! the weather API replaced by a mock server, and makes real HTTP requests
+//! to it.
+//!
+//! The harness runs natively, so it lives outside the service's workspace.
+//! Run it from this directory with `cargo test`. It builds the service for
+//! `wasm32-wasip1` (or `$WEATHER_WASM_TARGET`) first, and needs `viceroy`
+//! on the `PATH`.
+//!
+//! Every request comes from 127.0.0.1, which the generated Viceroy config
+//! places in London, unless the service is started unlocated.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use sha2::{Digest, Sha256};
+
+const FIXTURE: &str = include_str!("../../tests/fixtures/onecall.json");
+// What Open-Meteo answers for the same place and time
+const OPEN_METEO_FIXTURE: &str = include_str!("../../tests/fixtures/openmeteo.json");
+// What the geocoding API answers for any city search
+const GEOCODE_FIXTURE: &str =
+    r#"[{"name":"Paris","country":"FR","state":"Ile-de-France","lat":48.8589,"lon":2.32}]"#;
+
+/// A client id and its key in the `weather_api_keys` store, for signing
+/// requests to the JSON API
+pub const API_CLIENT: (&str, &str) = ("integration", "integration-test-api-key");
+
+// Signs share links, like the real key in the `weather_secrets` store.
+const SHARE_KEY: &str = "integration-test-share-key";
+
+/// The bearer token for the `/admin/` endpoints
+pub const ADMIN_TOKEN: &str = "integration-test-admin-token";
+
+// Signs Web Push, like a key from `web-push generate-vapid-keys`.
+const VAPID_PRIVATE_KEY: &str = "aW50ZWdyYXRpb24tdGVzdC12YXBpZC1rZXktMzJieXQ";
+
+/// The public half of the VAPID key
+pub const VAPID_PUBLIC_KEY: &str =
+    "BAM4c_0FSpDJvgEvFHjtZUBrPU-rNazqg9dvmfyUBv7iQ0UGTc7UVn-ZKsMUAC5MBoJWAVQYetHwlLuwdzYLxfA";
+
+// Viceroy compiles the module before it starts listening, which takes a while.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How the mock weather API answers
+#[derive(Clone)]
+pub enum MockApi {
+    /// The One Call fixture, for every request
+    Forecast,
+    /// An error status, with an optional `Retry-After`
+    Error {
+        status: u16,
+        retry_after: Option<&'static str>,
+    },
+}
+
+/// The service running under Viceroy
+pub struct Service {
+    viceroy: Child,
+    addr: SocketAddr,
+    api_calls: Arc<Mutex<Vec<String>>>,
+    config_path: PathBuf,
+}
+
+/// A response from the service
+pub struct Response {
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Service {
+    /// Start the service, with the weather API answering as `api` does.
+    pub fn start(api: MockApi) -> Self {
+        Service::start_with_config(api, &[])
+    }
+
+    /// Start the service with these `weather_config` values.
+    pub fn start_with_config(api: MockApi, config: &[(&str, &str)]) -> Self {
+        Service::launch(api, config, &[], true)
+    }
+
+    /// Start the service with these documents already in the KV Store.
+    pub fn start_with_kv(api: MockApi, kv: &[(&str, &str)]) -> Self {
+        Service::launch(api, &[], kv, true)
+    }
+
+    /// Start the service with these `weather_config` values and these
+    /// documents already in the KV Store.
+    pub fn start_with_config_and_kv(
+        api: MockApi,
+        config: &[(&str, &str)],
+        kv: &[(&str, &str)],
+    ) -> Self {
+        Service::launch(api, config, kv, true)
+    }
+
+    /// Start the service with no geolocation data, as for a visitor behind a
+    /// VPN whose exit can't be located.
+    pub fn start_unlocated(api: MockApi, config: &[(&str, &str)]) -> Self {
+        Service::launch(api, config, &[], false)
+    }
+
+    fn launch(
+        api: MockApi,
+        config: &[(&str, &str)],
+        kv: &[(&str, &str)],
+        geolocated: bool,
+    ) -> Self {
+        let wasm = service_wasm();
+        // Viceroy's port is only reserved once it's listening, so start one
+        // service at a time, or another test's mock API could take the port.
+        static STARTING: Mutex<()> = Mutex::new(());
+        let _starting = STARTING
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let (api_addr, api_calls) = start_mock_api(api);
+
+        let addr = free_addr();
+        let config_path = env::temp_dir().join(format!(
+            "weather-viceroy-{}-{}.toml",
+            std::process::id(),
+            addr.port()
+        ));
+        fs::write(
+            &config_path,
+            viceroy_config(api_addr, config, kv, geolocated),
+        )
+        .expect("writing the Viceroy config");
+
+        let viceroy = Command::new("viceroy")
+            .arg("serve")
+            .arg("--addr")
+            .arg(addr.to_string())
+            .arg("-C")
+            .arg(&config_path)
+            .arg(wasm)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("starting viceroy; is it installed?");
+
+        let service = Service {
+            viceroy,
+            addr,
+            api_calls,
+            config_path,
+        };
+        service.wait_until_listening();
+        service
+    }
+
+    /// Send a GET request for `path`.
+    pub fn get(&self, path: &str) -> Response {
+        self.request("GET", path, &[])
+    }
+
+    /// Send a request with extra headers.
+    pub fn request(&self, method: &str, path: &str, headers: &[(&str, &str)]) -> Response {
+        self.request_with_body(method, path, headers, "")
+    }
+
+    /// Send a request with extra headers and a body.
+    pub fn request_with_body(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> Response {
+        let mut stream = TcpStream::connect(self.addr).expect("connecting to viceroy");
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n",
+            method, path
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !body.is_empty() {
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).expect("reading the response");
+        Response::parse(&raw, method == "HEAD")
+    }
+
+    /// How many requests the mock weather API has answered.
+    pub fn api_calls(&self) -> usize {
+        self.api_requests().len()
+    }
+
+    /// The path and query of each request the mock weather API has answered.
+    pub fn api_requests(&self) -> Vec<String> {
+        self.api_calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn wait_until_listening(&self) {
+        let started = Instant::now();
+        while TcpStream::connect(self.addr).is_err() {
+            if started.elapsed() > STARTUP_TIMEOUT {
+                panic!("viceroy didn't start listening on {}", self.addr);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        let _ = self.viceroy.kill();
+        let _ = self.viceroy.wait();
+        let _ = fs::remove_file(&self.config_path);
+    }
+}
+
+impl Response {
+    /// The value of the header `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of the header `name`, ignoring case.
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    fn parse(raw: &[u8], is_head: bool) -> Self {
+        let split = raw
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .expect("a complete response head");
+        let head = String::from_utf8_lossy(&raw[..split]);
+        let mut lines = head.lines();
+
+        let status = lines
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse().ok())
+            .expect("a status line");
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        let mut response = Response {
+            status,
+            headers,
+            body: Vec::new(),
+        };
+        if !is_head {
+            let body = &raw[split + 4..];
+            response.body = match response.header("transfer-encoding") {
+                Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => dechunk(body),
+                _ => body.to_vec(),
+            };
+        }
+        response
+    }
+}
+
+/// The `Authorization` header for a request to the JSON API, signed now with
+/// `client`'s `key`. `path` includes the query string, sorted by name.
+pub fn api_authorization(client: &str, key: &str, method: &str, path: &str, body: &str) -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let body_hash: String = Sha256::digest(body.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let message = format!("{}\n{}\n{}\n{}", time, method, path, body_hash);
+    let signature: String = hmac_sha256(key.as_bytes(), message.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("HMAC {}:{}:{}", client, time, signature)
+}
+
+/// The path of the link that confirms the digest sign-up `id`, signed as the
+/// confirmation email has it.
+pub fn digest_confirmation(id: &str) -> String {
+    let message = format!("digest-confirm\n{}", id);
+    let signature: String = hmac_sha256(SHARE_KEY.as_bytes(), message.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("/api/digest/confirm?id={}&sig={}", id, signature)
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    block[..key.len()].copy_from_slice(key);
+    let pad = |byte: u8| block.map(|k| k ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .to_vec()
+}
+
+// Build the service once for every test in the binary.
+fn service_wasm() -> &'static PathBuf {
+    static WASM: OnceLock<PathBuf> = OnceLock::new();
+    WASM.get_or_init(|| {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        let target = env::var("WEATHER_WASM_TARGET").unwrap_or_else(|_| "wasm32-wasip1".into());
+        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+
+        let status = Command::new(cargo)
+            .current_dir(&root)
+            .args(["build", "--package", "weather", "--target", &target])
+            .status()
+            .expect("running cargo build");
+        assert!(status.success(), "building the service failed");
+
+        root.join("target")
+            .join(target)
+            .join("debug")
+            .join("weather.wasm")
+    })
+}
+
+// A port that was free a moment ago, for Viceroy to listen on.
+fn free_addr() -> SocketAddr {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|listener| listener.local_addr())
+        .expect("finding a free port")
+}
+
+// Answer every request on a background thread, counting the API calls.
+fn start_mock_api(api: MockApi) -> (SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).expect("binding the mock API");
+    let addr = listener.local_addr().unwrap();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let counter = Arc::clone(&calls);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = answer(stream, &api, &counter);
+        }
+    });
+    (addr, calls)
+}
+
+fn answer(stream: TcpStream, api: &MockApi, calls: &Mutex<Vec<String>>) -> std::io::Result<()> {
+    // Read the request head; the weather API only gets GETs, so there's no body.
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    // Viceroy checks each backend with a `GET /` when it starts; that's not a call.
+    let target = request_line.split_whitespace().nth(1);
+    match target {
+        Some("/") | None => {}
+        Some(target) => calls
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(target.to_string()),
+    }
+
+    let (status, retry_after, body) = match api {
+        MockApi::Forecast if target.is_some_and(|target| target.starts_with("/v1/forecast")) => {
+            (200, None, OPEN_METEO_FIXTURE.to_string())
+        }
+        MockApi::Forecast if target.is_some_and(|target| target.starts_with("/geo/1.0/direct")) => {
+            (200, None, GEOCODE_FIXTURE.to_string())
+        }
+        MockApi::Forecast => (200, None, FIXTURE.to_string()),
+        MockApi::Error {
+            status,
+            retry_after,
+        } => (
+            *status,
+            *retry_after,
+            format!(r#"{{"cod":{},"message":"mock error"}}"#, status),
+        ),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    if let Some(retry_after) = retry_after {
+        response.push_str(&format!("Retry-After: {}\r\n", retry_after));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes())
+}
+
+fn viceroy_config(
+    api_addr: SocketAddr,
+    config: &[(&str, &str)],
+    kv: &[(&str, &str)],
+    geolocated: bool,
+) -> String {
+    let mut contents = format!(
+        r#"[local_server]
+
+  [local_server.backends]
+
+    [local_server.backends."api.openweathermap.org"]
+      url = "http://{api_addr}"
+
+    [local_server.backends."api.fastly.com"]
+      url = "http://{api_addr}"
+
+    [local_server.backends."api.open-meteo.com"]
+      url = "http://{api_addr}"
+"#,
+        api_addr = api_addr
+    );
+    contents.push_str(if geolocated {
+        LONDON_GEOLOCATION
+    } else {
+        NO_GEOLOCATION
+    });
+    contents.push_str(
+        r#"
+  [local_server.config_stores]
+
+    [local_server.config_stores.weather_config]
+      format = "inline-toml"
+
+      [local_server.config_stores.weather_config.contents]
+"#,
+    );
+    for (key, value) in config {
+        contents.push_str(&format!("        {} = {:?}\n", key, value));
+    }
+    contents.push_str(&format!(
+        "\n    [local_server.config_stores.weather_api_keys]\n      format = \"inline-toml\"\n\n      [local_server.config_stores.weather_api_keys.contents]\n        {} = {:?}\n",
+        API_CLIENT.0, API_CLIENT.1
+    ));
+    contents.push_str(&format!(
+        "\n  [local_server.secret_stores]\n    weather_secrets = [\n      {{ key = \"share_key\", data = {:?} }},\n      {{ key = \"admin_token\", data = {:?} }},\n      {{ key = \"fastly_api_token\", data = \"integration-test-fastly-token\" }},\n      {{ key = \"vapid_private_key\", data = {:?} }},\n      {{ key = \"mail_api_token\", data = \"integration-test-mail-token\" }},\n    ]\n",
+        SHARE_KEY, ADMIN_TOKEN, VAPID_PRIVATE_KEY
+    ));
+    contents.push_str("\n  [local_server.kv_stores]\n    weather_kv = [\n");
+    for (key, data) in kv {
+        contents.push_str(&format!(
+            "      {{ key = {:?}, data = {:?} }},\n",
+            key, data
+        ));
+    }
+    contents.push_str("    ]\n");
+    contents
+}
+
+// Viceroy makes up a location for addresses it has no data for, so give
+// 127.0.0.1 the empty answer Fastly has for unknown ones.
+const NO_GEOLOCATION: &str = r#"
+  [local_server.geolocation]
+    format = "inline-toml"
+
+    [local_server.geolocation.addresses]
+
+      [local_server.geolocation.addresses."127.0.0.1"]
+        as_name = "?"
+        as_number = 0
+        area_code = 0
+        city = "?"
+        conn_speed = "?"
+        conn_type = "?"
+        continent = "?"
+        country_code = "?"
+        country_code3 = "?"
+        country_name = "?"
+        latitude = 0.0
+        longitude = 0.0
+        metro_code = 0
+        postal_code = "?"
+        proxy_description = "?"
+        proxy_type = "?"
+        region = "?"
+        utc_offset = 0
+"#;
+
+// Place 127.0.0.1, where every test request comes from, in London.
+const LONDON_GEOLOCATION: &str = r#"
+  [local_server.geolocation]
+    format = "inline-toml"
+
+    [local_server.geolocation.addresses]
+
+      [local_server.geolocation.addresses."127.0.0.1"]
+        as_name = "Test Network"
+        as_number = 64496
+        area_code = 0
+        city = "London"
+        conn_speed = "broadband"
+        conn_type = "wired"
+        continent = "EU"
+        country_code = "GB"
+        country_code3 = "GBR"
+        country_name = "United Kingdom"
+        latitude = 51.51
+        longitude = -0.13
+        metro_code = 0
+        postal_code = "WC2N"
+        proxy_description = "?"
+        proxy_type = "?"
+        region = "ENG"
+        utc_offset = 100
+"#;
+
+// Undo `Transfer-Encoding: chunked`.
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    while let Some(line_end) = body.windows(2).position(|window| window == b"\r\n") {
+        let size_line = String::from_utf8_lossy(&body[..line_end]);
+        let size =
+            usize::from_str_radix(size_line.split(';').next().unwrap().trim(), 16).unwrap_or(0);
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        let end = (start + size).min(body.len());
+        decoded.extend_from_slice(&body[start..end]);
+        body = &body[(end + 2).min(body.len())..];
+    }
+    decoded
+}
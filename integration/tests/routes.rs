@@ -0,0 +1,1569 @@
+//! Every route, served by the compiled service under Viceroy.
+
+use weather_integration::{
+    api_authorization, digest_confirmation, MockApi, Response, Service, ADMIN_TOKEN, API_CLIENT,
+    VAPID_PUBLIC_KEY,
+};
+
+fn service() -> Service {
+    Service::start(MockApi::Forecast)
+}
+
+#[test]
+fn index_page() {
+    let service = service();
+    let resp = service.get("/");
+
+    assert_eq!(resp.status, 200);
+    assert!(resp
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+    assert_eq!(resp.header("cache-control"), Some("private, max-age=300"));
+    assert_eq!(
+        resp.header("vary"),
+        Some("Cookie, Accept-Language, Save-Data, Sec-CH-UA-Mobile, Sec-CH-Viewport-Width, User-Agent")
+    );
+    // The composed page is streamed as it renders.
+    assert_eq!(resp.header("transfer-encoding"), Some("chunked"));
+    let body = resp.text();
+    assert!(body.contains("<!DOCTYPE html>"));
+    assert!(body.contains("London"));
+    assert!(body.trim_end().ends_with("</html>"));
+    // The favicon is drawn from the page's own forecast, not fetched again
+    assert!(body.contains(r#"<link rel="icon" href="data:image/svg+xml,"#));
+    assert!(!body.contains("/favicon.svg"));
+    assert_eq!(service.api_calls(), 1);
+}
+
+#[test]
+fn amp_page_and_fragments() {
+    let service = service();
+
+    let amp = service.get("/amp");
+    assert_eq!(amp.status, 200);
+    assert!(amp.text().contains("<html amp") || amp.text().contains("<html ⚡"));
+
+    let minimal = service.get("/m");
+    assert_eq!(minimal.status, 200);
+    let body = minimal.text();
+    assert!(body.contains("<h1>London</h1>"));
+    assert!(!body.contains("<script") && !body.contains("<style") && !body.contains("<esi:"));
+
+    let gemtext = service.get("/?format=gmi");
+    assert_eq!(gemtext.status, 200);
+    assert_eq!(
+        gemtext.header("content-type"),
+        Some("text/gemini; charset=utf-8")
+    );
+    let body = gemtext.text();
+    assert!(body.starts_with("# Weather in London\n"));
+    assert!(body.contains("## The day ahead"));
+
+    let current = service.get("/fragment/current");
+    assert_eq!(current.status, 200);
+    assert!(current
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(current.text().contains("London"));
+}
+
+#[test]
+fn page_layouts() {
+    let service = service();
+
+    for (path, class) in [
+        ("/widget", "widget"),
+        ("/kiosk", "kiosk"),
+        ("/print", "print"),
+    ] {
+        let resp = service.get(path);
+        assert_eq!(resp.status, 200, "{}", path);
+        let body = resp.text();
+        assert!(
+            body.contains(&format!(r#"<body class="{}">"#, class)),
+            "{}",
+            path
+        );
+        assert!(
+            body.contains(r#"<span class="location">London</span>"#),
+            "{}",
+            path
+        );
+        assert!(!body.contains("<esi:include"), "{}", path);
+        assert!(!body.contains("Switch units"), "{}", path);
+    }
+
+    assert!(service
+        .get("/kiosk")
+        .text()
+        .contains(r#"<meta http-equiv="refresh" content="600">"#));
+
+    // Sites embedding the widget can name the place themselves
+    let widget = service
+        .get("/widget?lat=-33.87&lon=151.21&city=Sydney")
+        .text();
+    assert!(widget.contains(r#"<span class="location">Sydney</span>"#));
+}
+
+#[test]
+fn background_image() {
+    let service = service();
+
+    // The selection route only redirects, to a photo that's cached for good.
+    for path in ["/bg-image.jpg", "/bg-image.jpg?season=winter"] {
+        let resp = service.get(path);
+        assert_eq!(resp.status, 302, "{}", path);
+        let photo = resp.header("location").unwrap().to_string();
+        assert!(photo.starts_with("/img/"), "{} went to {}", path, photo);
+
+        let resp = service.get(&photo);
+        assert_eq!(resp.status, 200, "{}", photo);
+        assert_eq!(resp.header("content-type"), Some("image/jpeg"));
+        assert!(resp
+            .header("cache-control")
+            .is_some_and(|value| value.contains("immutable")));
+        assert!(resp.body.starts_with(&[0xFF, 0xD8]));
+    }
+    assert_eq!(
+        service
+            .get("/bg-image.jpg?season=winter")
+            .header("location"),
+        Some("/img/winter.jpg")
+    );
+    assert_eq!(service.get("/img/nowhere.jpg").status, 404);
+
+    // Coordinates stand in for the visitor's location, a hemisphere apart
+    let north = service.get("/bg-image.jpg?lat=51.5&lon=-0.13");
+    let south = service.get("/bg-image.jpg?lat=-33.87&lon=151.21");
+    assert_ne!(north.header("location"), south.header("location"));
+
+    // The page picks the season's photo itself, unless the visitor is saving data.
+    let current = service.get("/fragment/current").text();
+    assert!(current.contains(r#"<div class="weather-side bg-"#));
+    assert!(!current.contains("bg-none"));
+    let saving = service
+        .request("GET", "/fragment/current", &[("Save-Data", "on")])
+        .text();
+    assert!(saving.contains(r#"<div class="weather-side bg-none">"#));
+}
+
+#[test]
+fn static_assets() {
+    let service = service();
+
+    for (path, content_type) in [
+        ("/style.css", "text/css"),
+        ("/style-rtl.css", "text/css"),
+        ("/icons.svg", "image/svg+xml"),
+        ("/rum.js", "text/javascript"),
+        ("/favicon.ico", "image/x-icon"),
+        ("/robots.txt", "text/plain"),
+    ] {
+        let resp = service.get(path);
+        assert_eq!(resp.status, 200, "{}", path);
+        assert!(
+            resp.header("content-type")
+                .is_some_and(|value| value.starts_with(content_type)),
+            "{} was served as {:?}",
+            path,
+            resp.header("content-type")
+        );
+        assert!(!resp.body.is_empty(), "{} is empty", path);
+    }
+}
+
+#[test]
+fn warm_up() {
+    let resp = service().get("/warmup");
+
+    assert_eq!(resp.status, 204);
+    assert_eq!(resp.header("cache-control"), Some("no-store"));
+}
+
+#[test]
+fn unknown_path() {
+    let service = service();
+    let resp = service.get("/no-such-page");
+
+    assert_eq!(resp.status, 404);
+    assert!(resp
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+    let body = resp.text();
+    assert!(body.contains("<code>/no-such-page</code>"));
+    assert!(body.contains(r#"<a href="/docs">API docs</a>"#));
+    assert!(body.contains(r#"id="picker-query""#));
+
+    let docs = service.get("/docs");
+    assert_eq!(docs.status, 200);
+    assert!(docs.text().contains("<code>GET /api/geocode?q=</code>"));
+}
+
+#[test]
+fn methods() {
+    let service = service();
+
+    let put = service.request("PUT", "/", &[]);
+    assert_eq!(put.status, 405);
+    assert_eq!(put.header("allow"), Some("GET, HEAD, OPTIONS"));
+
+    let options = service.request("OPTIONS", "/", &[]);
+    assert_eq!(options.status, 204);
+    assert_eq!(options.header("allow"), Some("GET, HEAD, OPTIONS"));
+
+    let head = service.request("HEAD", "/style.css", &[]);
+    assert_eq!(head.status, 200);
+    assert!(head.body.is_empty());
+}
+
+#[test]
+fn rate_limited_upstream() {
+    let service = Service::start(MockApi::Error {
+        status: 429,
+        retry_after: Some("120"),
+    });
+    let resp = service.get("/");
+
+    assert_eq!(resp.status, 503);
+    assert_eq!(resp.header("cache-control"), Some("no-store"));
+    assert_eq!(resp.header("retry-after"), Some("120"));
+    assert!(resp.text().contains("Forecast unavailable"));
+}
+
+#[test]
+fn mock_provider() {
+    let service = Service::start_with_config(
+        MockApi::Error {
+            status: 500,
+            retry_after: None,
+        },
+        &[("provider", "mock")],
+    );
+
+    let metric = service.get("/");
+    assert_eq!(metric.status, 200);
+    assert!(metric.text().contains("London"));
+
+    let imperial = service.get("/?units=imperial");
+    assert_eq!(imperial.status, 200);
+    assert_ne!(metric.text(), imperial.text());
+    assert_eq!(service.api_calls(), 0);
+}
+
+#[test]
+fn default_location() {
+    let defaults = [
+        ("default_lat", "48.86"),
+        ("default_lon", "2.35"),
+        ("default_city", "Paris"),
+    ];
+    let service = Service::start_unlocated(MockApi::Forecast, &defaults);
+    let page = service.get("/");
+    assert_eq!(page.status, 200);
+    assert!(page.text().contains("Paris"));
+    assert!(page.text().contains("location-banner"));
+
+    // Located visitors don't see the banner.
+    let located = Service::start_with_config(MockApi::Forecast, &defaults);
+    let page = located.get("/");
+    assert!(page.text().contains("London"));
+    assert!(!page.text().contains("location-banner"));
+
+    // Without a default, there's nothing to show.
+    let unconfigured = Service::start_unlocated(MockApi::Forecast, &[]);
+    let failed = unconfigured.get("/");
+    assert_eq!(failed.status, 500);
+    assert_eq!(
+        failed.header("content-type"),
+        Some("application/problem+json")
+    );
+    assert!(failed
+        .text()
+        .contains(r#""detail":"Something went wrong on our side""#));
+}
+
+#[test]
+fn privacy_mode() {
+    let private = Service::start_with_config(MockApi::Forecast, &[("privacy_mode", "true")]);
+    let page = private.get("/privacy");
+    assert_eq!(page.status, 200);
+    assert!(page.text().contains("Privacy mode is on"));
+
+    // London is at 51.51, -0.13, which truncates to one decimal place.
+    assert_eq!(private.get("/").status, 200);
+    let requests = private.api_requests();
+    assert!(!requests.is_empty());
+    assert!(requests
+        .iter()
+        .all(|request| request.contains("lat=51.5&lon=-0.1&")));
+
+    // So are the coordinates a widget names.
+    let widget = private.get("/widget?lat=-33.87&lon=151.21&city=Sydney");
+    assert_eq!(widget.status, 200);
+    assert!(private
+        .api_requests()
+        .iter()
+        .any(|request| request.contains("lat=-33.8&lon=151.2&")));
+    assert!(!private
+        .api_requests()
+        .iter()
+        .any(|request| request.contains("lat=-33.87")));
+
+    // An empty beacon is a 400, unless it's dropped unread for DNT.
+    let beacon = private.request("POST", "/rum", &[("DNT", "1"), ("Content-Length", "0")]);
+    assert_eq!(beacon.status, 204);
+
+    let off = service().get("/privacy");
+    assert!(!off.text().contains("Privacy mode is on"));
+}
+
+#[test]
+fn geolocation_opt_out() {
+    let service = service();
+
+    // Turning geolocation off shows the picker, without fetching a forecast.
+    let picker = service.get("/?geo=off");
+    assert_eq!(picker.status, 200);
+    assert!(picker.text().contains("Where are you?"));
+    assert!(picker
+        .header_all("set-cookie")
+        .iter()
+        .any(|cookie| cookie.starts_with("weather_geo=off;")));
+    let remembered = service.request("GET", "/", &[("Cookie", "weather_geo=off")]);
+    assert!(remembered.text().contains("Where are you?"));
+    assert_eq!(service.api_calls(), 0);
+
+    // Picking a place remembers it, and the forecast is for there.
+    let picked = service.get("/place?lat=48.86&lon=2.35&city=Paris");
+    assert_eq!(picked.status, 303);
+    assert_eq!(picked.header("location"), Some("/"));
+    let place = picked
+        .header("set-cookie")
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap()
+        .to_string();
+    let cookies = format!("weather_geo=off; {}", place);
+    let page = service.request("GET", "/", &[("Cookie", &cookies)]);
+    assert!(page.text().contains("Paris"));
+    assert!(service.api_requests()[0].contains("lat=48.86&lon=2.35&"));
+
+    // Opting back in forgets both.
+    let opted_in = service.request("GET", "/?geo=on", &[("Cookie", &cookies)]);
+    assert!(opted_in.text().contains("London"));
+    let cleared = opted_in.header_all("set-cookie");
+    assert!(cleared.contains(&"weather_geo=; Max-Age=0; Path=/; Secure; HttpOnly; SameSite=Lax"));
+    assert!(cleared.contains(&"weather_place=; Max-Age=0; Path=/; Secure; HttpOnly; SameSite=Lax"));
+
+    assert_eq!(service.get("/place?lat=200&lon=0").status, 400);
+
+    // Other sites can't pick a place for the visitor.
+    let path = "/place?lat=48.86&lon=2.35&city=Paris";
+    for site in ["cross-site", "same-site"] {
+        let forced = service.request("GET", path, &[("Sec-Fetch-Site", site)]);
+        assert_eq!(forced.status, 403, "{}", site);
+        assert!(forced.header("set-cookie").is_none(), "{}", site);
+    }
+    let own = service.request("GET", path, &[("Sec-Fetch-Site", "same-origin")]);
+    assert_eq!(own.status, 303);
+}
+
+#[test]
+fn share_links() {
+    let service = service();
+
+    // Share the place picked in Paris, from a London IP address.
+    let picked = service.get("/place?lat=48.86&lon=2.35&city=Paris");
+    let place = picked
+        .header("set-cookie")
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap()
+        .to_string();
+    let share = service.request("GET", "/share", &[("Cookie", &place)]);
+    assert_eq!(share.status, 200);
+    let body = share.text();
+    let url = body
+        .split('"')
+        .find(|part| part.contains("/shared?"))
+        .expect("a share URL");
+    let link = &url[url.find("/shared?").unwrap()..];
+    assert!(link.contains("city=Paris"));
+
+    // Anyone opening the link sees Paris, with its map.
+    let shared = service.get(link);
+    assert_eq!(shared.status, 200);
+    assert_eq!(shared.header("x-robots-tag"), Some("noindex"));
+    let page = shared.text();
+    assert!(page.contains("Paris"));
+    let sig = link.rsplit("sig=").next().unwrap();
+    assert!(page.contains("src=\"/map.png?city=Paris&amp;"));
+    assert!(page.contains(&format!("sig={}\"", sig)));
+
+    // Moving the place breaks the signature.
+    let moved = link.replace("city=Paris", "city=Lyon");
+    assert_eq!(service.get(&moved).status, 403);
+    assert_eq!(
+        service
+            .get("/shared?lat=1&lon=2&city=X&exp=9999999999&sig=00")
+            .status,
+        403
+    );
+}
+
+#[test]
+fn api_authentication() {
+    let service = Service::start_with_config(MockApi::Forecast, &[("api_auth", "true")]);
+    let (client, key) = API_CLIENT;
+
+    let unsigned = service.get("/api/trending");
+    assert_eq!(unsigned.status, 401);
+    assert_eq!(unsigned.header("www-authenticate"), Some("HMAC"));
+
+    let signed = api_authorization(client, key, "GET", "/api/trending", "");
+    let resp = service.request("GET", "/api/trending", &[("Authorization", &signed)]);
+    assert_eq!(resp.status, 200);
+
+    // The signature covers the path, and only the client's own key makes it.
+    let other_path = service.request("GET", "/api/fire", &[("Authorization", &signed)]);
+    assert_eq!(other_path.status, 403);
+    let wrong_key = api_authorization(client, "guessed", "GET", "/api/trending", "");
+    let resp = service.request("GET", "/api/trending", &[("Authorization", &wrong_key)]);
+    assert_eq!(resp.status, 403);
+    let unknown = api_authorization("stranger", key, "GET", "/api/trending", "");
+    let resp = service.request("GET", "/api/trending", &[("Authorization", &unknown)]);
+    assert_eq!(resp.status, 403);
+
+    // City search stays open for the location picker, and pages aren't affected.
+    assert_ne!(service.get("/api/geocode?q=London").status, 401);
+    assert_eq!(service.get("/").status, 200);
+}
+
+#[test]
+fn trending_locations() {
+    // Every view is written, so the counts are exact.
+    let service = Service::start_with_config(MockApi::Forecast, &[("trending_sample", "1")]);
+    assert_eq!(service.get("/").status, 200);
+    assert_eq!(service.get("/").status, 200);
+
+    // Each view lands in whichever shard it picked, and the counts add up.
+    let trending = service.get("/api/trending").text();
+    assert!(trending.contains(r#""period_hours":24"#));
+    assert!(trending.contains(r#""geohash":"gcpvj""#));
+    assert!(trending.contains(r#""views":2"#));
+
+    // Places the visitor named themselves aren't counted, so their names
+    // never reach the public list.
+    let picked = service.get("/place?lat=48.86&lon=2.35&city=Anything%20at%20all");
+    let place = picked
+        .header("set-cookie")
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        service.request("GET", "/", &[("Cookie", &place)]).status,
+        200
+    );
+    let trending = service.get("/api/trending").text();
+    assert!(!trending.contains("Anything"), "{}", trending);
+    assert!(trending.contains(r#""views":2"#));
+
+    // Sampled views count for as many as they stand in for.
+    let sampled = Service::start_with_config(MockApi::Forecast, &[("trending_sample", "3")]);
+    for _ in 0..12 {
+        assert_eq!(sampled.get("/").status, 200);
+    }
+    let trending = sampled.get("/api/trending").text();
+    if let Some(views) = trending.split(r#""views":"#).nth(1) {
+        let digits = views.find(|c: char| !c.is_ascii_digit()).unwrap();
+        let views: u64 = views[..digits].parse().unwrap();
+        assert_eq!(views % 3, 0, "{}", trending);
+    }
+}
+
+#[test]
+fn weather_api_budget() {
+    let service = Service::start_with_config(MockApi::Forecast, &[("owm_calls_per_day", "1")]);
+    assert_eq!(service.get("/").status, 200);
+    assert_eq!(service.api_calls(), 1);
+
+    // The day's one call is spent, and nothing was cached to fall back on.
+    let spent = service.get("/api/fire");
+    assert_eq!(spent.status, 503);
+    assert!(spent.header("retry-after").is_some());
+    assert_eq!(service.api_calls(), 1);
+}
+
+#[test]
+fn api_usage_metering() {
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[("api_auth", "true"), ("api_daily_quota", "2")],
+    );
+    let (client, key) = API_CLIENT;
+    let signed = |path: &str| api_authorization(client, key, "GET", path, "");
+
+    let resp = service.request(
+        "GET",
+        "/api/trending",
+        &[("Authorization", &signed("/api/trending"))],
+    );
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.header("x-ratelimit-limit"), Some("2"));
+    assert_eq!(resp.header("x-ratelimit-remaining"), Some("1"));
+    assert!(resp.header("x-ratelimit-reset").is_some());
+
+    // Checking usage doesn't use any of it up.
+    let usage = service.request(
+        "GET",
+        "/api/usage",
+        &[("Authorization", &signed("/api/usage"))],
+    );
+    assert_eq!(usage.status, 200);
+    let body = usage.text();
+    assert!(body.contains(r#""client":"integration""#));
+    assert!(body.contains(r#""requests":1"#));
+    assert!(body.contains(r#""remaining":1"#));
+
+    let auth = signed("/api/trending");
+    assert_eq!(
+        service
+            .request("GET", "/api/trending", &[("Authorization", &auth)])
+            .status,
+        200
+    );
+    let over = service.request("GET", "/api/trending", &[("Authorization", &auth)]);
+    assert_eq!(over.status, 429);
+    assert_eq!(over.header("x-ratelimit-remaining"), Some("0"));
+    assert!(over.header("retry-after").is_some());
+
+    // Without API keys there's nobody to meter.
+    let open = Service::start(MockApi::Forecast);
+    assert_eq!(open.get("/api/usage").status, 404);
+    assert!(open
+        .get("/api/trending")
+        .header("x-ratelimit-limit")
+        .is_none());
+}
+
+#[test]
+fn admin_endpoints() {
+    let service = Service::start_with_config(MockApi::Forecast, &[("privacy_mode", "true")]);
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+
+    assert_eq!(service.get("/admin/config").status, 401);
+    let guessed = service.request(
+        "GET",
+        "/admin/config",
+        &[("Authorization", "Bearer guessed")],
+    );
+    assert_eq!(guessed.status, 401);
+    assert_eq!(guessed.header("www-authenticate"), Some("Bearer"));
+
+    // Secrets are reported as set, never shown.
+    let config = service.request("GET", "/admin/config", &[("Authorization", &bearer)]);
+    assert_eq!(config.status, 200);
+    let body = config.text();
+    assert!(body.contains(r#""privacy_mode":"true""#));
+    assert!(body.contains(r#""canonical_host":null"#));
+    assert!(body.contains(r#""enable_confidence":null"#));
+    assert!(body.contains(r#""share_key":true"#));
+    assert!(!body.contains(ADMIN_TOKEN));
+    assert!(!body.contains(API_CLIENT.1));
+
+    // Purging changes things, so it's a POST, and needs a key.
+    assert_eq!(
+        service
+            .request("GET", "/admin/purge?key=map", &[("Authorization", &bearer)])
+            .status,
+        405
+    );
+    assert_eq!(
+        service
+            .request("POST", "/admin/purge", &[("Authorization", &bearer)])
+            .status,
+        400
+    );
+    let purged = service.request(
+        "POST",
+        "/admin/purge?key=map",
+        &[("Authorization", &bearer)],
+    );
+    assert_eq!(purged.status, 200);
+    assert!(service
+        .api_requests()
+        .iter()
+        .any(|target| target.starts_with("/service/") && target.ends_with("/purge/map")));
+}
+
+#[test]
+fn canary_rollout() {
+    let canary = Service::start_with_config(MockApi::Forecast, &[("canary_percent", "100")]);
+    assert_eq!(canary.get("/").status, 200);
+    let requests = canary.api_requests();
+    assert!(!requests.is_empty());
+    assert!(requests
+        .iter()
+        .all(|target| target.starts_with("/data/3.0/onecall")));
+
+    // Rolling back is a config change.
+    let rolled_back = Service::start_with_config(MockApi::Forecast, &[("canary_percent", "0")]);
+    assert_eq!(rolled_back.get("/").status, 200);
+    assert!(rolled_back
+        .api_requests()
+        .iter()
+        .all(|target| target.starts_with("/data/2.5/onecall")));
+}
+
+#[test]
+fn crawler_fast_path() {
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("default_lat", "48.86"),
+            ("default_lon", "2.35"),
+            ("default_city", "Paris"),
+        ],
+    );
+    let crawler = [(
+        "User-Agent",
+        "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+    )];
+
+    let page = service.request("GET", "/", &crawler);
+    assert_eq!(page.status, 200);
+    assert!(page.text().contains("Paris"));
+    assert!(page.header("cache-control").unwrap().starts_with("public"));
+    assert_eq!(page.header("vary"), Some("User-Agent"));
+    assert!(page.header("set-cookie").is_none());
+
+    // Later crawls, whatever they ask for, reuse the page.
+    let calls = service.api_calls();
+    let again = service.request("GET", "/?mode=snow&units=kelvin", &crawler);
+    assert_eq!(again.status, 200);
+    assert_eq!(again.text(), page.text());
+    assert_eq!(service.api_calls(), calls);
+    // The cached page's inline script keeps running under its own nonce.
+    assert_eq!(
+        again.header("content-security-policy"),
+        page.header("content-security-policy")
+    );
+
+    // Visitors still get their own location.
+    let visitor = service.get("/");
+    assert!(visitor.text().contains("London"));
+    assert!(visitor
+        .header("cache-control")
+        .unwrap()
+        .starts_with("private"));
+
+    // Without a default location, a crawler's page is its own, and isn't
+    // kept for the next one.
+    let anywhere = Service::start(MockApi::Forecast);
+    let picked = anywhere.get("/place?lat=48.86&lon=2.35&city=Paris");
+    let place = picked
+        .header("set-cookie")
+        .and_then(|cookie| cookie.split(';').next())
+        .unwrap()
+        .to_string();
+    let posing = [crawler[0], ("Cookie", &place)];
+    let page = anywhere.request("GET", "/", &posing);
+    assert!(page.text().contains("Paris"));
+    assert!(page.header("cache-control").unwrap().starts_with("private"));
+    let next = anywhere.request("GET", "/", &crawler);
+    assert!(next.text().contains("London"));
+    assert!(!next.text().contains("Paris"));
+}
+
+#[test]
+fn device_aware_layout() {
+    let service = service();
+
+    let desktop = service.request("GET", "/", &[("Sec-CH-UA-Mobile", "?0")]);
+    assert_eq!(
+        desktop.header("accept-ch"),
+        Some("Sec-CH-UA-Mobile, Sec-CH-Viewport-Width")
+    );
+    assert!(desktop.text().contains("<body>"));
+
+    let phone = service.request("GET", "/", &[("Sec-CH-UA-Mobile", "?1")]);
+    let body = phone.text();
+    assert!(body.contains(r#"<body class="mobile">"#));
+    assert!(body.contains("week-list compact"));
+    assert!(!body.contains("fragment/"));
+
+    // A narrow window gets the mobile layout, and so do phones without hints.
+    let narrow = service.request(
+        "GET",
+        "/",
+        &[("Sec-CH-UA-Mobile", "?0"), ("Sec-CH-Viewport-Width", "390")],
+    );
+    assert!(narrow.text().contains(r#"<body class="mobile">"#));
+    let old_phone = service.request(
+        "GET",
+        "/",
+        &[(
+            "User-Agent",
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 15_0 like Mac OS X) Mobile/15E148",
+        )],
+    );
+    assert!(old_phone.text().contains(r#"<body class="mobile">"#));
+}
+
+#[test]
+fn query_sanitization() {
+    let service = service();
+
+    // Campaign tags and other unknown parameters are dropped, not refused.
+    let tagged = service.get("/?utm_source=newsletter&units=imperial");
+    assert_eq!(tagged.status, 200);
+    assert!(!tagged.text().contains("newsletter"));
+
+    for query in [
+        "units=%3Cscript%3E",
+        "units=metric&units=imperial",
+        "lat=51.5%0A&lon=0",
+        "lat=NaN&lon=0",
+        "mode=%ZZ",
+        "skin=two",
+    ] {
+        let resp = service.get(&format!("/?{}", query));
+        assert_eq!(resp.status, 400, "{}", query);
+    }
+
+    // Free text is decoded strictly, and kept to a sensible length.
+    assert_eq!(service.get("/api/geocode?q=%C3%28").status, 400);
+    let long = "a".repeat(300);
+    assert_eq!(service.get(&format!("/api/geocode?q={}", long)).status, 400);
+    assert_ne!(service.get("/api/geocode?q=S%C3%A3o+Paulo").status, 400);
+
+    // Numbers too big for the upstream's limit are capped, not an error.
+    let capped = service.get("/api/geocode?q=Paris&limit=300");
+    assert_eq!(capped.status, 200);
+    assert!(capped.text().contains(r#""name":"Paris""#));
+    assert!(service
+        .api_requests()
+        .iter()
+        .any(|request| request.contains("q=paris&limit=5&")));
+}
+
+#[test]
+fn canonical_links() {
+    let configured = Service::start_with_config(
+        MockApi::Forecast,
+        &[("canonical_host", " Weather.Example.COM ")],
+    );
+    let page = configured.get("/?units=imperial");
+    assert!(page
+        .text()
+        .contains(r#"<link rel="canonical" href="https://weather.example.com/?units=imperial">"#));
+
+    // A host that isn't one is ignored, and the page links to itself instead.
+    let misconfigured = Service::start_with_config(
+        MockApi::Forecast,
+        &[("canonical_host", "evil.example/path")],
+    );
+    let page = misconfigured.get("/").text();
+    assert!(!page.contains("evil.example"));
+    assert!(page.contains(r#"<link rel="canonical" href="https://localhost/">"#));
+}
+
+#[test]
+fn script_nonces() {
+    let service = service();
+
+    let nonce_of = |page: &Response| {
+        let policy = page.header("content-security-policy").unwrap().to_string();
+        let start = policy.find("'nonce-").unwrap() + "'nonce-".len();
+        let len = policy[start..].find('\'').unwrap();
+        policy[start..start + len].to_string()
+    };
+
+    let page = service.get("/");
+    let nonce = nonce_of(&page);
+    assert_eq!(nonce.len(), 24);
+    let body = page.text();
+    assert!(body.contains(&format!(r#"<script nonce="{}">"#, nonce)));
+    assert!(!body.contains("onclick="));
+    assert!(!body.contains("feather.min.js"));
+    assert!(body.contains(r#"<script src="/rum.js" integrity="sha384-"#));
+
+    // Every response gets its own nonce, though the shell is cached.
+    assert_ne!(nonce_of(&service.get("/")), nonce);
+
+    // Fragments carry no scripts of their own.
+    let fragment = service.get("/fragment/current");
+    assert_eq!(fragment.status, 200);
+    assert!(fragment.header("content-security-policy").is_none());
+}
+
+#[test]
+fn provider_attribution() {
+    let service = service();
+
+    let page = service.get("/").text();
+    assert!(page.contains(r#"<p class="attribution">Weather data by <a href="https://openweathermap.org/">OpenWeather</a>"#));
+    assert!(service
+        .get("/amp")
+        .text()
+        .contains(r#"class="attribution""#));
+
+    let fire = service.get("/api/fire");
+    assert_eq!(fire.status, 200);
+    let body = fire.text();
+    assert!(body.contains(r#""fire_season":"#));
+    assert!(body.contains(r#""attribution":{"name":"OpenWeather","url":"https://openweathermap.org/","license":"CC BY-SA 4.0""#));
+}
+
+#[test]
+fn icon_sets() {
+    let feather = service().get("/fragment/daily").text();
+    assert!(feather.contains(r#"<svg class="feather day-icon" width="24" height="24" role="img" aria-label="light rain"><use href="/icons.svg#cloud-rain"></use></svg>"#));
+
+    let emoji = Service::start_with_config(MockApi::Forecast, &[("icon_set", "emoji")]);
+    let daily = emoji.get("/fragment/daily").text();
+    assert!(daily.contains(
+        r#"<span class="day-icon weather-emoji" role="img" aria-label="light rain">🌧️</span>"#
+    ));
+    assert!(!daily.contains("/icons.svg"));
+
+    let openweather = Service::start_with_config(MockApi::Forecast, &[("icon_set", "openweather")]);
+    let current = openweather.get("/fragment/current").text();
+    assert!(current.contains(r#"<img class="weather-icon" src="https://openweathermap.org/img/wn/04d@2x.png" alt="broken clouds""#));
+
+    let sprite = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("icon_set", "sprite"),
+            ("icon_sprite_url", "/static/weather.svg"),
+        ],
+    );
+    let daily = sprite.get("/fragment/daily").text();
+    assert!(daily.contains(r##"<use href="/static/weather.svg#cloud-rain"></use>"##));
+
+    // Browsers won't draw a sprite from another origin, so it isn't used.
+    let elsewhere = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("icon_set", "sprite"),
+            ("icon_sprite_url", "https://cdn.example/weather.svg"),
+        ],
+    );
+    let daily = elsewhere.get("/fragment/daily").text();
+    assert!(daily.contains(r#"<use href="/icons.svg#cloud-rain"></use>"#));
+    assert!(!daily.contains("cdn.example"));
+}
+
+#[test]
+fn ensemble_provider() {
+    let service = Service::start_with_config(MockApi::Forecast, &[("provider", "ensemble")]);
+
+    // 13.6°C from OpenWeather and 12.4°C from Open-Meteo
+    let current = service.get("/fragment/current").text();
+    assert!(current.contains(r#"<h1 class="weather-temp">13°C</h1>"#));
+    assert!(service
+        .api_requests()
+        .iter()
+        .any(|request| request.starts_with("/v1/forecast?")));
+
+    // Saturday's high of 12°C against 19°C brings it up, and it's flagged
+    let daily = service.get("/fragment/daily").text();
+    assert!(daily.contains("<span class=\"day-temp\">14°C</span>\n            <svg"));
+    assert!(daily.contains(r#"class="day-confidence day-confidence-low""#));
+    assert!(daily.contains(r#"class="day-confidence day-confidence-high""#));
+
+    let page = service.get("/").text();
+    assert!(page.contains(r#"blended with <a href="https://open-meteo.com/">Open-Meteo</a>"#));
+    let fire = service.get("/api/fire").text();
+    assert!(fire.contains(r#""blended_with":{"name":"Open-Meteo""#));
+}
+
+#[test]
+fn pressure_trend() {
+    // Readings for London's cell from three and two hours before the
+    // fixture's observation, when the pressure was well up on now.
+    let seeded = Service::start_with_kv(
+        MockApi::Forecast,
+        &[(
+            "pressure:gcpvj",
+            r#"[{"hour":497768,"hpa":1022.0},{"hour":497769,"hpa":1020.5}]"#,
+        )],
+    );
+
+    let conditions = seeded.get("/fragment/conditions").text();
+    assert!(conditions.contains("1017 hPa"));
+    assert!(conditions.contains(r#"title="Falling fast — rain likely">⇊</span>"#));
+
+    let imperial = seeded.get("/fragment/conditions?units=imperial").text();
+    assert!(imperial.contains("30.03 inHg"));
+
+    // With nothing to compare with, there's no arrow.
+    let fresh = service().get("/fragment/conditions").text();
+    assert!(fresh.contains("1017 hPa"));
+    assert!(!fresh.contains("pressure-trend"));
+}
+
+#[test]
+fn condition_details() {
+    let service = service();
+
+    let plain = service.get("/fragment/conditions").text();
+    assert!(!plain.contains("VISIBILITY"));
+    assert!(plain.contains(r#"href="?units=metric&amp;details=on">More details</a>"#));
+
+    let detailed = service.get("/fragment/conditions?details=on").text();
+    assert!(detailed.contains("75% Mostly cloudy"));
+    assert!(detailed.contains("10 km Good visibility"));
+    assert!(detailed.contains(r#"href="?units=metric">Fewer details</a>"#));
+
+    let imperial = service
+        .get("/fragment/conditions?details=on&units=imperial")
+        .text();
+    assert!(imperial.contains("6.2 mi Good visibility"));
+
+    // The whole page passes the parameter on to its conditions card.
+    assert!(service.get("/?details=on").text().contains("Mostly cloudy"));
+}
+
+#[test]
+fn temperature_chart() {
+    let service = service();
+
+    let chart = service.get("/fragment/chart").text();
+    assert!(chart.contains(r#"<svg class="temperature-chart" viewBox="0 0 480 120""#));
+    assert!(chart.contains(r#"<polyline class="chart-line" points="0,100 "#));
+    assert!(chart.contains(">12pm</text>"));
+    assert!(chart.contains(">15°</text>"));
+    assert!(chart.contains(">14°</text>"));
+    assert!(!chart.contains("<script"));
+
+    // Hours are told the way the visitor's language does.
+    let german = service
+        .request("GET", "/fragment/chart", &[("Accept-Language", "de")])
+        .text();
+    assert!(german.contains(">12 Uhr</text>"));
+
+    assert!(service
+        .get("/")
+        .text()
+        .contains(r#"class="temperature-chart""#));
+}
+
+#[test]
+fn sun_path() {
+    let astronomy = service().get("/fragment/astronomy").text();
+    assert!(astronomy.contains(r#"<svg class="sun-path""#));
+    // Just before half way between sunrise and sunset
+    assert!(astronomy.contains(r#"<circle class="sun-path-sun" cx="88.3" cy="10.4" r="6" />"#));
+    assert!(astronomy.contains(r#"d="M10,60 A90,50 0 0 1 88.3,10.4""#));
+}
+
+#[test]
+fn wind_rose() {
+    let rose = service().get("/fragment/wind").text();
+    assert!(rose.contains(r#"aria-label="Mostly from the SW""#));
+    assert!(rose.contains("<title>SW: 2 h</title>"));
+    assert!(rose.contains("<title>NW: 1 h</title>"));
+    assert!(!rose.contains("<title>E:"));
+    // The longest petal reaches the outer ring, due south-west of the centre.
+    assert!(rose.contains(r#"points="50.0,50.0 31.8,85.6 14.4,68.2""#));
+}
+
+#[test]
+fn precipitation_totals() {
+    let service = service();
+
+    let card = service.get("/fragment/precipitation").text();
+    assert!(card.contains("<dt>Next 24 hours</dt><dd>1.9 mm rain</dd>"));
+    assert!(!card.contains("snow</dd>"));
+
+    let api = service.get("/api/precipitation?units=imperial");
+    assert_eq!(api.status, 200);
+    let body = api.text();
+    assert!(body
+        .contains(r#""next_48h":{"rain_mm":1.9,"snow_mm":0.0,"rain":"0.07 in","snow":"0.0 in"}"#));
+    assert!(body.contains(r#""is_wet":true"#));
+    // It's drizzling within the hour.
+    assert!(body.contains(r#""needs_umbrella":true,"umbrella":"yes""#));
+    assert!(body.contains(r#""attribution":{"name":"OpenWeather""#));
+}
+
+#[test]
+fn weekend_outlook() {
+    let service = service();
+
+    let weekend = service.get("/fragment/weekend").text();
+    assert!(weekend.contains("Partly wet, with 5.1 mm in all, 4° to 12°C"));
+    assert!(weekend.contains(
+        r#"<span class="weekend-day">Saturday</span> <span class="weekend-temps">4°–12°</span>"#
+    ));
+    assert!(weekend.contains(r#"<span class="weekend-day">Sunday</span>"#));
+
+    // In Hebrew the weekend is Friday and Saturday, which stay dry.
+    let hebrew = service
+        .request("GET", "/fragment/weekend", &[("Accept-Language", "he")])
+        .text();
+    assert!(hebrew.contains("Dry, 4° to 16°C"));
+    assert!(hebrew.contains(r#"<span class="weekend-temps">9°–16°</span>"#));
+    assert!(!hebrew.contains(r#"<span class="weekend-temps">4°–11°</span>"#));
+}
+
+#[test]
+fn day_parts() {
+    let parts = service().get("/fragment/dayparts").text();
+    // The fixture's six hours run from noon to 5pm in London.
+    assert!(parts.contains(r#"<span class="dayparts-name">This afternoon</span> <span class="dayparts-temps">14°–15°</span> <span class="dayparts-desc">broken clouds</span>"#));
+    assert!(!parts.contains("This evening"));
+}
+
+#[test]
+fn road_conditions() {
+    // A mild October afternoon in London has nothing to warn drivers of.
+    let roads = service().get("/fragment/roads");
+    assert_eq!(roads.status, 200);
+    assert!(!roads.text().contains("roads"));
+
+    let page = service().get("/").text();
+    assert!(!page.contains("Black ice"));
+}
+
+#[test]
+fn display_precision() {
+    let precise = Service::start_with_config(
+        MockApi::Forecast,
+        &[("temp_precision", "1"), ("wind_precision", "0")],
+    );
+    let current = precise.get("/fragment/current").text();
+    assert!(current.contains(r#"<h1 class="weather-temp">13.6°C</h1>"#));
+    let conditions = precise.get("/fragment/conditions").text();
+    assert!(conditions.contains(r#"<span class="value">4"#));
+    assert!(!conditions.contains("4.1"));
+
+    // Whole degrees unless set, rounded rather than cut off
+    let current = service().get("/fragment/current").text();
+    assert!(current.contains(r#"<h1 class="weather-temp">14°C</h1>"#));
+}
+
+#[test]
+fn experiment_weights() {
+    // Weights as big as they can be still split visitors between variants.
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[("experiments", "layout=a:4294967295,b:4294967295")],
+    );
+    let page = service.get("/");
+    assert_eq!(page.status, 200);
+    assert!(matches!(
+        page.header("x-experiments"),
+        Some("layout=a" | "layout=b")
+    ));
+}
+
+#[test]
+fn weather_windows() {
+    let service = service();
+
+    let warm = service.get("/api/windows?min_temp=14&max_pop=20&hours=3");
+    assert_eq!(warm.status, 200);
+    assert!(warm.text().contains(
+        r#"{"windows":[{"start":"2026-10-14T14:00:00+01:00","end":"2026-10-14T18:00:00+01:00","hours":4,"#
+    ));
+
+    // Nothing's that dry, or warm for that long.
+    let dry = service.get("/api/windows?max_pop=5").text();
+    assert!(dry.starts_with(r#"{"windows":[],"#));
+    let long = service.get("/api/windows?min_temp=14&hours=5").text();
+    assert!(long.starts_with(r#"{"windows":[],"#));
+
+    let ics = service.get("/api/windows?min_temp=14&hours=3&format=ics");
+    assert_eq!(
+        ics.header("content-type"),
+        Some("text/calendar; charset=utf-8")
+    );
+    let ics = ics.text();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.contains("DTSTART:20261014T130000Z\r\nDTEND:20261014T170000Z\r\n"));
+
+    assert_eq!(service.get("/api/windows?max_pop=150").status, 400);
+    assert_eq!(service.get("/api/windows?hours=0").status, 400);
+
+    // Temperatures may be below freezing, or fractional, but must be numbers.
+    assert_eq!(service.get("/api/windows?min_temp=-2.5").status, 200);
+    assert_eq!(service.get("/api/windows?min_temp=warm").status, 400);
+    assert_eq!(service.get("/api/windows?min_temp=inf").status, 400);
+}
+
+#[test]
+fn webhook_subscriptions() {
+    let body = r#"{"callback_url":"https://hooks.example.com/weather","lat":51.5,"lon":-0.12,"condition":"temp_above","threshold":30}"#;
+    let json = [("Content-Type", "application/json")];
+    // Without api_auth, there's no telling who registered a webhook.
+    let anonymous = Service::start_with_config(
+        MockApi::Forecast,
+        &[("outbound_hosts", "hooks.example.com")],
+    );
+    assert_eq!(
+        anonymous
+            .request_with_body("POST", "/api/subscriptions", &json, body)
+            .status,
+        403
+    );
+
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("api_auth", "true"),
+            ("outbound_hosts", "hooks.example.com"),
+        ],
+    );
+    let (client, key) = API_CLIENT;
+    let signed =
+        |method: &str, path: &str, body: &str| api_authorization(client, key, method, path, body);
+    let register = |body: &str| {
+        let authorization = signed("POST", "/api/subscriptions", body);
+        let headers = [
+            ("Content-Type", "application/json"),
+            ("Authorization", authorization.as_str()),
+        ];
+        service.request_with_body("POST", "/api/subscriptions", &headers, body)
+    };
+
+    let insecure = register(
+        r#"{"callback_url":"http://hooks.example.com/weather","lat":51.5,"lon":-0.12,"condition":"temp_above","threshold":30}"#,
+    );
+    assert_eq!(insecure.status, 400);
+    let private = register(
+        r#"{"callback_url":"https://127.0.0.1/weather","lat":51.5,"lon":-0.12,"condition":"temp_above","threshold":30}"#,
+    );
+    assert_eq!(private.status, 400);
+    // A public name can point anywhere, so only listed hosts get webhooks.
+    let unlisted = register(
+        r#"{"callback_url":"https://internal.example.net/weather","lat":51.5,"lon":-0.12,"condition":"temp_above","threshold":30}"#,
+    );
+    assert_eq!(unlisted.status, 400);
+    let unknown = register(
+        r#"{"callback_url":"https://hooks.example.com/weather","lat":51.5,"lon":-0.12,"condition":"humid","threshold":30}"#,
+    );
+    assert_eq!(unknown.status, 400);
+
+    // The signature covers the body, so a captured one can't carry another.
+    let authorization = signed("POST", "/api/subscriptions", "{}");
+    let swapped = service.request_with_body(
+        "POST",
+        "/api/subscriptions",
+        &[
+            ("Content-Type", "application/json"),
+            ("Authorization", authorization.as_str()),
+        ],
+        r#"{"callback_url":"https://hooks.example.com/weather","lat":51.5,"lon":-0.12,"condition":"temp_above","threshold":30}"#,
+    );
+    assert_eq!(swapped.status, 403);
+
+    let quiet = register(
+        r#"{"callback_url":"https://hooks.example.com/weather","lat":51.5074,"lon":-0.1278,"condition":"temp_above","threshold":30}"#,
+    );
+    assert_eq!(quiet.status, 201);
+    let body = quiet.text();
+    assert!(body.contains(r#""lat":51.51"#));
+    let id = body
+        .split(r#""id":""#)
+        .nth(1)
+        .unwrap()
+        .split('"')
+        .next()
+        .unwrap();
+    assert_eq!(id.len(), 32);
+
+    // Dispatching is for the scheduler, which has the admin token.
+    assert_eq!(
+        service.request("POST", "/internal/dispatch", &[]).status,
+        401
+    );
+    assert_eq!(service.get("/internal/dispatch").status, 405);
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let dispatch = || service.request("POST", "/internal/dispatch", &[("Authorization", &bearer)]);
+    let nothing = dispatch();
+    assert_eq!(nothing.status, 200);
+    assert!(nothing
+        .text()
+        .contains(r#""webhooks":{"checked":1,"deferred":0,"failed":0,"removed":0,"sent":0}"#));
+    // The next run, minutes later, reuses the forecast.
+    let calls = service.api_calls();
+    assert_eq!(dispatch().status, 200);
+    assert_eq!(service.api_calls(), calls);
+
+    // The callback can't be reached from here, so its webhook fails and is
+    // tried again on the next run.
+    let freezing = register(
+        r#"{"callback_url":"https://hooks.example.com/weather","lat":51.5074,"lon":-0.1278,"condition":"temp_below","threshold":50}"#,
+    );
+    assert_eq!(freezing.status, 201);
+    let failing = r#""webhooks":{"checked":2,"deferred":0,"failed":1,"removed":0,"sent":0}"#;
+    assert!(dispatch().text().contains(failing));
+    assert!(dispatch().text().contains(failing));
+
+    let path = format!("/api/subscriptions?id={}", id);
+    let remove = || {
+        let authorization = signed("DELETE", &path, "");
+        service.request("DELETE", &path, &[("Authorization", &authorization)])
+    };
+    assert_eq!(remove().status, 204);
+    assert_eq!(remove().status, 404);
+    assert!(dispatch()
+        .text()
+        .contains(r#""webhooks":{"checked":1,"deferred":0,"failed":1,"removed":0,"sent":0}"#));
+}
+
+#[test]
+fn push_subscriptions() {
+    let off = service();
+    assert_eq!(off.get("/api/push/key").status, 503);
+
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("vapid_subject", "mailto:ops@example.com"),
+            ("outbound_hosts", "push.example.com"),
+        ],
+    );
+    let key = service.get("/api/push/key");
+    assert_eq!(key.status, 200);
+    assert_eq!(
+        key.text(),
+        format!(r#"{{"public_key":"{}"}}"#, VAPID_PUBLIC_KEY)
+    );
+
+    let json = [("Content-Type", "application/json")];
+    let subscription = r#"{"endpoint":"https://push.example.com/send/abc123","expirationTime":null,"keys":{"p256dh":"BNc","auth":"tBH"}}"#;
+    let subscribe = |method: &str, body: &str| {
+        service.request_with_body(method, "/api/push/subscribe", &json, body)
+    };
+    assert_eq!(
+        subscribe("POST", r#"{"endpoint":"http://push.example.com/send"}"#).status,
+        400
+    );
+    assert_eq!(subscribe("POST", "not a subscription").status, 400);
+    assert_eq!(
+        subscribe("POST", r#"{"endpoint":"https://push.example.net/send"}"#).status,
+        400
+    );
+    assert_eq!(subscribe("POST", subscription).status, 201);
+    // Subscribing again replaces the subscription.
+    assert_eq!(subscribe("POST", subscription).status, 201);
+
+    // The fixture has no alerts, so there's nothing to push.
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let dispatch = service.request("POST", "/internal/dispatch", &[("Authorization", &bearer)]);
+    assert!(dispatch
+        .text()
+        .contains(r#""push":{"checked":1,"deferred":0,"failed":0,"removed":0,"sent":0}"#));
+
+    assert_eq!(subscribe("DELETE", subscription).status, 204);
+    assert_eq!(subscribe("DELETE", subscription).status, 404);
+
+    // A client can only add so many browsers an hour.
+    for n in 0..4 {
+        let other = format!(r#"{{"endpoint":"https://push.example.com/send/{}"}}"#, n);
+        assert_eq!(subscribe("POST", &other).status, 201);
+    }
+    let limited = subscribe(
+        "POST",
+        r#"{"endpoint":"https://push.example.com/send/more"}"#,
+    );
+    assert_eq!(limited.status, 429);
+    assert_eq!(limited.header("retry-after"), Some("3600"));
+
+    // A subscription that was never pushed to lapses after 30 days.
+    let stale = Service::start_with_config_and_kv(
+        MockApi::Forecast,
+        &[
+            ("vapid_subject", "mailto:ops@example.com"),
+            ("outbound_hosts", "push.example.com"),
+        ],
+        &[
+            ("push_subscriptions:0", r#"["0123"]"#),
+            (
+                "push_subscription:0123",
+                r#"{"index":"push_subscriptions:0","endpoint":"https://push.example.com/send/old","lat":51.51,"lon":-0.13,"subscribed":1700000000}"#,
+            ),
+        ],
+    );
+    let dispatch = stale.request("POST", "/internal/dispatch", &[("Authorization", &bearer)]);
+    assert!(dispatch
+        .text()
+        .contains(r#""push":{"checked":1,"deferred":0,"failed":0,"removed":1,"sent":0}"#));
+    let again = stale.request("POST", "/internal/dispatch", &[("Authorization", &bearer)]);
+    assert!(again.text().contains(r#""push":{"checked":0,"#));
+}
+
+#[test]
+fn digest_emails() {
+    let json = [("Content-Type", "application/json")];
+    let sign_up = r#"{"email":"visitor@example.org"}"#;
+    let off = service();
+    assert_eq!(
+        off.request_with_body("POST", "/api/digest", &json, sign_up)
+            .status,
+        503
+    );
+
+    // Sent every run after midnight, to a mail API that can't be reached from here
+    let service = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("mail_api_url", "https://mail.example.com/send"),
+            ("mail_from", "weather@example.com"),
+            ("digest_hour", "0"),
+        ],
+    );
+    let sign = |body: &str| service.request_with_body("POST", "/api/digest", &json, body);
+    assert_eq!(sign(r#"{"email":"not an address"}"#).status, 400);
+    assert_eq!(
+        sign(r#"{"email":"visitor@example.org","units":"kelvin"}"#).status,
+        400
+    );
+    let signed_up = sign(r#"{"email":"visitor@example.org","lat":48.8566,"lon":2.3522}"#);
+    assert_eq!(signed_up.status, 202);
+    let body = signed_up.text();
+    let id = body
+        .split(r#""id":""#)
+        .nth(1)
+        .unwrap()
+        .split('"')
+        .next()
+        .unwrap();
+
+    // One confirmation an hour is plenty for anyone.
+    let again = sign(r#"{"email":"Visitor@example.org"}"#);
+    assert_eq!(again.status, 429);
+
+    // The confirmation couldn't be sent at sign-up, so dispatching tries
+    // again, and sends no digest.
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let dispatch = || {
+        service
+            .request("POST", "/internal/dispatch", &[("Authorization", &bearer)])
+            .text()
+    };
+    let failed = r#""digests":{"checked":1,"deferred":0,"failed":1,"removed":0,"sent":0}"#;
+    assert!(dispatch().contains(failed));
+
+    let confirm = digest_confirmation(id);
+    let forged = format!("{}0", confirm.trim_end_matches(char::is_alphanumeric));
+    assert_eq!(service.get(&forged).status, 403);
+    let confirmed = service.get(&confirm);
+    assert_eq!(confirmed.status, 200);
+    assert!(confirmed.text().starts_with("You're signed up."));
+    assert_eq!(service.get(&confirm).status, 200);
+    // Now it's the digest that can't be sent.
+    assert!(dispatch().contains(failed));
+
+    // Mail apps unsubscribe with a bare POST.
+    let unsubscribe = format!("/api/digest/unsubscribe?id={}", id);
+    assert_eq!(service.get(&unsubscribe).status, 405);
+    assert_eq!(service.request("POST", &unsubscribe, &[]).status, 204);
+    assert_eq!(service.request("POST", &unsubscribe, &[]).status, 404);
+}
+
+#[test]
+fn ntfy_notifications() {
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let dispatch = |service: &Service| {
+        service
+            .request("POST", "/internal/dispatch", &[("Authorization", &bearer)])
+            .text()
+    };
+    assert!(dispatch(&service())
+        .contains(r#""notify":{"checked":0,"deferred":0,"failed":0,"removed":0,"sent":0}"#));
+
+    // Gotify won't take messages without an application token.
+    let gotify = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("notify_url", "https://gotify.example.com/message"),
+            ("notify_service", "gotify"),
+            ("default_lat", "51.5072"),
+            ("default_lon", "-0.1276"),
+        ],
+    );
+    assert!(dispatch(&gotify).contains(r#""notify":{"checked":0,"#));
+
+    // The summary is due after midnight, to a server that can't be reached from here
+    let ntfy = Service::start_with_config(
+        MockApi::Forecast,
+        &[
+            ("notify_url", "https://ntfy.example.com/weather"),
+            ("notify_events", "summary"),
+            ("digest_hour", "0"),
+            ("default_lat", "51.5072"),
+            ("default_lon", "-0.1276"),
+            ("default_city", "London"),
+        ],
+    );
+    assert!(dispatch(&ntfy)
+        .contains(r#""notify":{"checked":1,"deferred":0,"failed":1,"removed":0,"sent":0}"#));
+}
+
+#[test]
+fn home_assistant_sensors() {
+    let service = service();
+
+    let all = service.get("/api/homeassistant");
+    assert_eq!(all.status, 200);
+    assert_eq!(all.header("Cache-Control"), Some("private, max-age=300"));
+    let body = all.text();
+    assert!(body.contains(r#""temp":13.6,"temp_high":15.3"#));
+    assert!(body.contains(r#""temp_unit":"°C""#));
+    assert!(body.contains(r#""wind_speed":4.1,"wind_speed_unit":"m/s""#));
+    assert!(body.contains(r#""condition":"broken clouds""#));
+    assert!(body.contains(r#""attribution":{"#));
+
+    let temp = service.get("/api/sensor/temp?units=imperial").text();
+    assert!(temp.starts_with(r#"{"state":13.6,"#));
+    assert!(temp.contains(r#""unit_of_measurement":"°F""#));
+    assert!(temp.contains(r#""device_class":"temperature""#));
+
+    assert_eq!(service.get("/api/sensor/mood").status, 404);
+}
+
+#[test]
+fn compact_forecast() {
+    let compact = service().get("/api/compact");
+    assert_eq!(compact.status, 200);
+    assert!(compact
+        .header("Cache-Control")
+        .unwrap()
+        .starts_with("private, max-age=900"));
+    let body = compact.text();
+    assert_eq!(
+        body,
+        r#"{"t":14,"h":15,"l":9,"p":10,"i":"04d","s":"OpenWeather"}"#
+    );
+    assert!(body.len() < 300);
+}
+
+#[test]
+fn alerts_only() {
+    let service = service();
+    let alerts = service.get("/api/alerts");
+    assert_eq!(alerts.status, 200);
+    assert_eq!(
+        alerts.header("Cache-Control"),
+        Some("private, max-age=600, stale-while-revalidate=1800, stale-if-error=86400")
+    );
+    assert_eq!(
+        alerts.header("last-modified"),
+        Some("Wed, 14 Oct 2026 11:00:00 GMT")
+    );
+    assert!(alerts.text().starts_with(r#"{"alerts":[],"attribution":"#));
+    let requests = service.api_requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].contains("&exclude=minutely,hourly,daily"));
+
+    // Polls in between come from the cache.
+    let again = service.get("/api/alerts");
+    assert_eq!(again.text(), alerts.text());
+    assert_eq!(again.header("etag"), alerts.header("etag"));
+    assert_eq!(service.api_calls(), 1);
+}
+
+#[test]
+fn conditional_api_requests() {
+    let service = service();
+    let first = service.get("/api/compact");
+    let etag = first.header("etag").unwrap().to_string();
+    let modified = first.header("last-modified").unwrap().to_string();
+    assert_eq!(modified, "Wed, 14 Oct 2026 11:00:00 GMT");
+
+    let cached = service.request("GET", "/api/compact", &[("If-None-Match", &etag)]);
+    assert_eq!(cached.status, 304);
+    assert_eq!(cached.text(), "");
+    assert_eq!(cached.header("etag"), Some(etag.as_str()));
+    let other = service.request("GET", "/api/compact", &[("If-None-Match", "\"stale\"")]);
+    assert_eq!(other.status, 200);
+
+    let since = service.request(
+        "GET",
+        "/api/homeassistant",
+        &[("If-Modified-Since", &modified)],
+    );
+    assert_eq!(since.status, 304);
+    let earlier = service.request(
+        "GET",
+        "/api/homeassistant",
+        &[("If-Modified-Since", "Wed, 14 Oct 2026 10:00:00 GMT")],
+    );
+    assert_eq!(earlier.status, 200);
+}
+
+#[test]
+fn api_errors_are_problem_details() {
+    let service = service();
+
+    let short = service.get("/api/geocode?q=a");
+    assert_eq!(short.status, 400);
+    assert_eq!(
+        short.header("content-type"),
+        Some("application/problem+json")
+    );
+    assert_eq!(
+        short.text(),
+        r#"{"detail":"q must be between 2 and 100 characters","instance":"/api/geocode","status":400,"title":"Bad Request","type":"about:blank"}"#
+    );
+
+    // Errors from outside the handlers are problems too.
+    let missing = service.get("/api/nothing-here");
+    assert_eq!(missing.status, 404);
+    assert!(missing
+        .text()
+        .contains(r#""instance":"/api/nothing-here","status":404,"title":"Not Found""#));
+    let posted = service.request("POST", "/api/compact", &[]);
+    assert_eq!(posted.status, 405);
+    assert_eq!(posted.header("allow"), Some("GET, HEAD, OPTIONS"));
+    assert!(posted
+        .text()
+        .contains(r#""detail":"This method is not allowed""#));
+
+    // Pages for people stay pages.
+    let page = service.get("/nothing-here");
+    assert_eq!(page.status, 404);
+    assert!(page
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+}
+
+#[test]
+fn forecast_ranges() {
+    let service = service();
+
+    let page = service.get("/api/forecast?limit=2");
+    assert_eq!(page.status, 200);
+    let body = page.text();
+    assert_eq!(body.matches(r#""dt":"#).count(), 2);
+    assert!(body.contains(r#""from":"2026-10-14T12:00:00+01:00","to":"2026-10-14T17:59:59+01:00""#));
+    assert!(body.contains(r#""next":"/api/forecast?limit=2&from=1791982800""#));
+    let rest = service.get("/api/forecast?limit=2&from=1791982800").text();
+    assert!(rest.contains(r#"{"time":"2026-10-14T14:00:00+01:00","dt":1791982800,"temp":14.2,"#));
+
+    let daily = service
+        .get("/api/forecast?granularity=daily&from=2026-10-16T00:00:00Z&to=2026-10-17T00:00:00Z")
+        .text();
+    assert_eq!(daily.matches(r#""dt":"#).count(), 2);
+    assert!(daily.contains(r#""temp_min":8.8,"temp_max":16.2"#));
+    assert!(!daily.contains(r#""next""#));
+
+    let outside = service.get("/api/forecast?from=0&to=1000");
+    assert_eq!(outside.status, 400);
+    assert!(outside.text().contains(
+        r#""detail":"The hourly forecast covers 2026-10-14T12:00:00+01:00 to 2026-10-14T17:59:59+01:00""#
+    ));
+    assert_eq!(service.get("/api/forecast?granularity=weekly").status, 400);
+    assert_eq!(
+        service
+            .get("/api/forecast?from=2026-10-15T00:00:00Z&to=2026-10-14T00:00:00Z")
+            .status,
+        400
+    );
+}
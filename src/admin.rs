@@ -0,0 +1,247 @@
+//! Operator endpoints under `/admin/`, for looking after the service without
+//! the Fastly UI.
+//!
+//! Every request needs `Authorization: Bearer <token>`, with the token stored
+//! as `admin_token` in the `weather_secrets` Secret Store. Until one is
+//! stored, the endpoints don't exist and answer 404.
+//!
+//! * `POST /admin/purge?key=<surrogate key>` purges everything cached under
+//!   the key through the Fastly API, using the API token stored as
+//!   `fastly_api_token`. Add `soft=true` to mark it stale instead. Backend
+//!   responses are cached under the name of the module that fetches them,
+//!   such as `map` or `radar`.
+//! * `GET /admin/config` shows the values this instance resolved from the
+//!   `weather_config` and `weather_flags` stores. Secrets are only reported
+//!   as set or not, and API client keys aren't shown at all.
+
+use fastly::http::{header, HeaderValue, Method, StatusCode};
+use fastly::secret_store::SecretStore;
+use fastly::{Error, Request, Response};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    air, assets, aviation, config, geocode, map, marine, openmeteo, problem, radar, signing, snow,
+};
+
+const SECRET_STORE: &str = "weather_secrets";
+const TOKEN_NAME: &str = "admin_token";
+const API_TOKEN_NAME: &str = "fastly_api_token";
+
+const API_BACKEND: &str = "api.fastly.com";
+
+/// The surrogate keys backend responses are cached under, each declared
+/// beside the fetch that uses it
+const SURROGATE_KEYS: &[&str] = &[
+    air::SURROGATE_KEY,
+    assets::SURROGATE_KEY,
+    aviation::SURROGATE_KEY,
+    geocode::SURROGATE_KEY,
+    map::SURROGATE_KEY,
+    marine::SURROGATE_KEY,
+    openmeteo::SURROGATE_KEY,
+    radar::SURROGATE_KEY,
+    snow::SURROGATE_KEY,
+];
+
+/// The `weather_config` values shown by `/admin/config`
+const CONFIG_KEYS: &[&str] = &[
+    "robots_txt",
+    "security_contact",
+    "security_expires",
+    "security_policy",
+    "canonical_host",
+    "alias_hosts",
+    "force_https",
+    "experiments",
+    "owm_calls_per_minute",
+    "owm_calls_per_day",
+    "provider",
+    "default_lat",
+    "default_lon",
+    "default_city",
+    "privacy_mode",
+    "privacy_decimals",
+    "share_ttl_days",
+    "api_auth",
+    "api_daily_quota",
+    "canary_percent",
+    "canary_backend",
+    "canary_api_version",
+    "temp_precision",
+    "wind_precision",
+    "precipitation_precision",
+    "exercise_aqi_caution",
+    "exercise_aqi_avoid",
+    "icon_set",
+    "icon_sprite_url",
+    "day_start_hour",
+    "night_start_hour",
+    "vapid_subject",
+    "digest_hour",
+    "mail_api_url",
+    "mail_from",
+    "notify_url",
+    "notify_service",
+    "notify_units",
+    "notify_events",
+    "outbound_hosts",
+    "trending_sample",
+];
+
+/// The `weather_flags` shown by `/admin/config`
+const FLAGS: &[&str] = &[
+    "use_onecall_v3",
+    "enable_radar",
+    "enable_marine",
+    "enable_exercise",
+    "enable_confidence",
+];
+
+/// Secret Store entries, only ever reported as set or not
+const SECRETS: &[&str] = &[
+    "share_key",
+    TOKEN_NAME,
+    API_TOKEN_NAME,
+    "vapid_private_key",
+    "mail_api_token",
+    "notify_token",
+];
+
+#[derive(Deserialize)]
+struct PurgeParams {
+    key: Option<String>,
+    #[serde(default)]
+    soft: bool,
+}
+
+/// Whether `path` is one of the admin endpoints.
+pub fn is_admin(path: &str) -> bool {
+    path.starts_with("/admin/")
+}
+
+/// Check `req`'s admin token, returning the response to send instead when
+/// it's missing or wrong.
+pub fn authorize(req: &Request) -> Option<Response> {
+    let token = match secret(TOKEN_NAME) {
+        Some(token) => token,
+        None => return Some(problem::response(StatusCode::NOT_FOUND, "Not found")),
+    };
+    let presented = req
+        .get_header_str(header::AUTHORIZATION)
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if signing::constant_time_eq(presented.trim().as_bytes(), &token) {
+        return None;
+    }
+    println!("Rejected admin request for {}", req.get_path());
+    Some(
+        problem::response(StatusCode::UNAUTHORIZED, "Send the admin token")
+            .with_header(header::WWW_AUTHENTICATE, "Bearer"),
+    )
+}
+
+/// The methods each admin endpoint accepts.
+pub fn allowed_methods(path: &str) -> &'static [Method] {
+    match path {
+        "/admin/purge" => &[Method::POST, Method::OPTIONS],
+        _ => &[Method::GET, Method::HEAD, Method::OPTIONS],
+    }
+}
+
+/// Serve `/admin/purge`, purging one surrogate key.
+pub fn purge(req: &Request) -> Result<Response, Error> {
+    let params: PurgeParams = req.get_query()?;
+    let key = match params.key.filter(|key| is_surrogate_key(key)) {
+        Some(key) => key,
+        None => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Name a surrogate key to purge",
+            ))
+        }
+    };
+    let (service_id, api_token) = match (std::env::var("FASTLY_SERVICE_ID"), secret(API_TOKEN_NAME))
+    {
+        (Ok(service_id), Some(api_token)) if !service_id.is_empty() => (service_id, api_token),
+        _ => {
+            return Ok(problem::response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Purging isn't set up",
+            ))
+        }
+    };
+
+    let mut bereq = Request::post(format!(
+        "https://api.fastly.com/service/{}/purge/{}",
+        service_id, key
+    ))
+    .with_header(header::HOST, "api.fastly.com")
+    .with_header(header::ACCEPT, "application/json")
+    .with_header("Fastly-Key", HeaderValue::from_bytes(&api_token)?)
+    .with_pass(true);
+    if params.soft {
+        bereq.set_header("Fastly-Soft-Purge", "1");
+    }
+
+    let beresp = bereq.send(API_BACKEND)?;
+    if !beresp.get_status().is_success() {
+        println!(
+            "Fastly API returned {} purging surrogate key {}",
+            beresp.get_status(),
+            key
+        );
+        return Ok(problem::response(
+            StatusCode::BAD_GATEWAY,
+            "The Fastly API refused the purge",
+        ));
+    }
+    println!("Purged surrogate key {}", key);
+
+    let body = json!({ "purged": key, "soft": params.soft });
+    Ok(Response::from_body(body.to_string())
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "no-store"))
+}
+
+/// Serve `/admin/config`, with the configuration this instance resolved.
+pub fn config() -> Response {
+    let values = |store: &str, keys: &[&str]| {
+        keys.iter()
+            .map(|&key| {
+                let value = config::get_from(store, key).map_or(Value::Null, Value::String);
+                (key.to_string(), value)
+            })
+            .collect::<Map<_, _>>()
+    };
+    let secrets = SECRETS
+        .iter()
+        .map(|&name| (name.to_string(), Value::Bool(secret(name).is_some())))
+        .collect::<Map<_, _>>();
+
+    let body = json!({
+        "service_version": std::env::var("FASTLY_SERVICE_VERSION").ok(),
+        "config": values("weather_config", CONFIG_KEYS),
+        "flags": values("weather_flags", FLAGS),
+        "secrets": secrets,
+        "surrogate_keys": SURROGATE_KEYS,
+    });
+    Response::from_body(body.to_string())
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "no-store")
+}
+
+// Keys go in the API request's path, so keep to characters that need no escaping.
+fn is_surrogate_key(key: &str) -> bool {
+    (1..=256).contains(&key.len())
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-_.:".contains(&byte))
+}
+
+fn secret(name: &str) -> Option<Vec<u8>> {
+    let secret = SecretStore::open(SECRET_STORE).ok()?.get(name)?;
+    Some(secret.plaintext().to_vec()).filter(|value| !value.is_empty())
+}
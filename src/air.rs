@@ -0,0 +1,57 @@
+//! Air quality forecasts from OpenWeatherMap's air pollution API.
+
+use fastly::http::{header, HeaderValue, Method};
+use fastly::{Error, Request};
+use serde::Deserialize;
+
+use crate::location::Location;
+use crate::{get_api_key, BACKEND_NAME};
+
+// The air quality forecast is hourly, but only updated a few times a day.
+const AIR_TTL_SECS: u32 = 3600;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "air";
+
+#[derive(Deserialize)]
+struct AirResponse {
+    list: Vec<AirReport>,
+}
+
+#[derive(Deserialize)]
+struct AirReport {
+    dt: i64,
+    main: AirIndex,
+}
+
+#[derive(Deserialize)]
+struct AirIndex {
+    aqi: u8,
+}
+
+/// The hourly air quality forecast at `location`, as pairs of timestamp and
+/// index from 1 (good) to 5 (very poor). Empty if the API is unavailable.
+pub fn forecast(location: &Location) -> Result<Vec<(i64, u8)>, Error> {
+    let url = format!(
+        "http://api.openweathermap.org/data/2.5/air_pollution/forecast?lat={:.2}&lon={:.2}&appid={}",
+        location.latitude(),
+        location.longitude(),
+        get_api_key()
+    );
+    let mut beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "api.openweathermap.org")
+        .with_ttl(AIR_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(BACKEND_NAME)?;
+    if !beresp.get_status().is_success() {
+        println!("Air pollution API returned {}", beresp.get_status());
+        return Ok(Vec::new());
+    }
+
+    Ok(beresp
+        .take_body_json::<AirResponse>()?
+        .list
+        .into_iter()
+        .map(|report| (report.dt, report.main.aqi))
+        .collect())
+}
@@ -0,0 +1,105 @@
+//! The weather alerts in force, and nothing else, at `/api/alerts`.
+//!
+//! For third parties that poll for severe weather: the forecast is fetched
+//! without its minutely, hourly and daily blocks, leaving only the current
+//! conditions, whose time is what the response's `ETag` and `Last-Modified`
+//! go by. Each location's response is then kept in the edge cache for
+//! [`TTL`], so polls in between cost no weather API call. Agencies issue
+//! alerts hours ahead, so a few minutes out of date is fine.
+//!
+//! Each alert has its `event`, the `sender`, when it's in force from
+//! `start` to `end` (RFC 3339, in the location's time zone), the agency's
+//! `description` and `tags`, and `severe`, whether it's a warning worth
+//! interrupting someone for, as push notifications have it.
+
+use std::time::Duration;
+
+use chrono::{FixedOffset, TimeZone};
+use fastly::cache::simple;
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Response};
+use serde::Serialize;
+
+use crate::location::Location;
+use crate::provider::Attributed;
+use crate::{push, APIResponse};
+
+/// How long a location's alerts are reused
+pub const TTL: Duration = Duration::from_secs(600);
+
+/// The forecast blocks alerts don't need
+pub const EXCLUDE: [&str; 3] = ["minutely", "hourly", "daily"];
+
+const CACHE_CONTROL: &str =
+    "private, max-age=600, stale-while-revalidate=1800, stale-if-error=86400";
+
+/// The `/api/alerts` response
+#[derive(Serialize)]
+struct Alerts<'a> {
+    alerts: Vec<Alert<'a>>,
+}
+
+#[derive(Serialize)]
+struct Alert<'a> {
+    event: &'a str,
+    sender: &'a str,
+    start: String,
+    end: String,
+    severe: bool,
+    description: &'a str,
+    tags: &'a [String],
+}
+
+/// The cached response for `location`, with the time of the forecast it
+/// came from, if there is one.
+pub fn cached(location: &Location) -> Option<(i64, Response)> {
+    let entry = simple::get(cache_key(location)).ok()??.into_string();
+    let (observed, body) = entry.split_once('\n')?;
+    Some((observed.parse().ok()?, respond(body.to_string())))
+}
+
+/// Serve `/api/alerts` for `location` from `api_response`, and keep it for
+/// the next poll.
+pub fn handle(location: &Location, api_response: &APIResponse) -> Result<Response, Error> {
+    let tz = FixedOffset::east_opt(api_response.timezone_offset)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    let rfc3339 = |time: i64| {
+        tz.timestamp_opt(time, 0)
+            .single()
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default()
+    };
+    let alerts = Alerts {
+        alerts: api_response
+            .alerts
+            .iter()
+            .map(|alert| Alert {
+                event: &alert.event,
+                sender: &alert.sender_name,
+                start: rfc3339(alert.start),
+                end: rfc3339(alert.end),
+                severe: push::is_severe(alert),
+                description: &alert.description,
+                tags: &alert.tags,
+            })
+            .collect(),
+    };
+    let body = serde_json::to_string(&Attributed::new(&alerts))?;
+
+    let entry = format!("{}\n{}", api_response.current.dt, body);
+    if let Err(err) = simple::get_or_set(cache_key(location), entry, TTL) {
+        println!("Couldn't cache the alerts: {:?}", err);
+    }
+    Ok(respond(body))
+}
+
+fn respond(body: String) -> Response {
+    Response::from_body(body)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, CACHE_CONTROL)
+}
+
+fn cache_key(location: &Location) -> String {
+    format!("alerts:{}:{}", location.latitude(), location.longitude())
+}
@@ -0,0 +1,168 @@
+//! Heavy static assets, which deployments can leave out of the wasm package
+//! with cargo features to stay under Fastly's size limits:
+//!
+//! * `embedded-images` — the background photo behind every page.
+//! * `themes` — a photo for each season, rather than one all year. Needs
+//!   `embedded-images`.
+//!
+//! Both are on by default. Without one, its route is proxied to the
+//! `assets` backend when the service has one, laid out like `src/static`,
+//! and is a 404 otherwise.
+//!
+//! Each photo has its own `/img/{name}.jpg` route, cached for good, which
+//! the stylesheets point at. `/bg-image.jpg` only picks one and redirects.
+
+use fastly::http::{header, HeaderValue, Method, StatusCode};
+use fastly::{Backend, Request, Response};
+use serde::Deserialize;
+use weather_helpers::Season;
+
+use crate::view;
+
+const ASSETS_BACKEND: &str = "assets";
+
+// Proxied assets only change on deploy, like the embedded ones.
+const ASSETS_TTL_SECS: u32 = 86_400;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "assets";
+
+// A photo is never replaced under the same name, so its route can be cached
+// for a year without revalidating.
+const IMAGE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+// Which photo `/bg-image.jpg` picks can follow the visitor's location, so it
+// stays theirs and short-lived.
+const SELECTION_CACHE_CONTROL: &str = "private, max-age=3600";
+
+#[cfg(feature = "themes")]
+const BACKGROUNDS: &[(Season, &[u8])] = &[
+    (Season::Summer, include_bytes!("static/img/summer.jpg")),
+    (Season::Autumn, include_bytes!("static/img/autumn.jpg")),
+    (Season::Winter, include_bytes!("static/img/winter.jpg")),
+    (Season::Spring, include_bytes!("static/img/spring.jpg")),
+];
+#[cfg(all(feature = "embedded-images", not(feature = "themes")))]
+const BACKGROUNDS: &[(Season, &[u8])] =
+    &[(Season::Summer, include_bytes!("static/img/summer.jpg"))];
+#[cfg(not(feature = "embedded-images"))]
+const BACKGROUNDS: &[(Season, &[u8])] = &[];
+
+// Keep the embedded assets well inside the package limit, with room for the
+// code, whichever features are on.
+const ASSET_BUDGET_BYTES: usize = 640 * 1024;
+const _: () = assert!(
+    embedded_bytes() <= ASSET_BUDGET_BYTES,
+    "embedded assets are over budget"
+);
+
+const fn embedded_bytes() -> usize {
+    let mut total = 0;
+    let mut i = 0;
+    while i < BACKGROUNDS.len() {
+        total += BACKGROUNDS[i].1.len();
+        i += 1;
+    }
+    total
+}
+
+#[derive(Deserialize)]
+struct BackgroundParams {
+    season: Option<String>,
+}
+
+/// Serve `/bg-image.jpg` for `season`, by redirecting to its photo's route.
+pub fn background(season: Season) -> Response {
+    Response::from_status(StatusCode::FOUND)
+        .with_header(
+            header::LOCATION,
+            format!("/img/{}.jpg", season_name(themed(season))),
+        )
+        .with_header(header::CACHE_CONTROL, SELECTION_CACHE_CONTROL)
+}
+
+/// Serve a photo at `/img/{name}.jpg`, or `None` when there's no such photo.
+pub fn image(path: &str) -> Option<Response> {
+    let name = path.strip_prefix("/img/")?.strip_suffix(".jpg")?;
+    let season = season_named(name)?;
+    let resp = match BACKGROUNDS.iter().find(|(embedded, _)| *embedded == season) {
+        Some((_, image)) => Response::from_body(*image)
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::IMAGE_JPEG)
+            .with_header(header::CACHE_CONTROL, IMAGE_CACHE_CONTROL),
+        None => proxy(path),
+    };
+    Some(resp)
+}
+
+/// The season a page asked for in `/bg-image.jpg?season=`, if it named one.
+pub fn requested_season(req: &Request) -> Option<Season> {
+    let params = req.get_query::<BackgroundParams>().ok()?;
+    season_named(&params.season?)
+}
+
+/// The class that gives the current conditions card the photo for `season`,
+/// as the stylesheets name it.
+pub fn background_class(season: Season) -> &'static str {
+    match themed(season) {
+        Season::Summer => "bg-summer",
+        Season::Autumn => "bg-autumn",
+        Season::Winter => "bg-winter",
+        Season::Spring => "bg-spring",
+    }
+}
+
+// Without the seasonal themes, it's summer all year.
+fn themed(season: Season) -> Season {
+    if cfg!(feature = "themes") {
+        season
+    } else {
+        Season::Summer
+    }
+}
+
+// Fetch a left-out asset from the `assets` backend, if there is one.
+fn proxy(path: &str) -> Response {
+    let has_backend = Backend::from_name(ASSETS_BACKEND).is_ok_and(|backend| backend.exists());
+    if !has_backend {
+        return view::not_found(path);
+    }
+
+    let bereq = Request::new(Method::GET, format!("http://{}{}", ASSETS_BACKEND, path))
+        .with_ttl(ASSETS_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY));
+    match bereq.send(ASSETS_BACKEND) {
+        Ok(beresp) if beresp.get_status().is_success() => beresp,
+        Ok(beresp) => {
+            println!(
+                "Assets backend returned {} for {}",
+                beresp.get_status(),
+                path
+            );
+            view::not_found(path)
+        }
+        Err(err) => {
+            println!("Couldn't fetch {} from the assets backend: {}", path, err);
+            view::not_found(path)
+        }
+    }
+}
+
+fn season_name(season: Season) -> &'static str {
+    match season {
+        Season::Summer => "summer",
+        Season::Autumn => "autumn",
+        Season::Winter => "winter",
+        Season::Spring => "spring",
+    }
+}
+
+fn season_named(name: &str) -> Option<Season> {
+    match name {
+        "summer" => Some(Season::Summer),
+        "autumn" => Some(Season::Autumn),
+        "winter" => Some(Season::Winter),
+        "spring" => Some(Season::Spring),
+        _ => None,
+    }
+}
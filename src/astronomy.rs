@@ -0,0 +1,129 @@
+//! The astronomy card for photographers: sunrise and sunset, solar noon,
+//! golden and blue hours, and the moon.
+//!
+//! Sun times are computed locally; moon times and phase come from the daily
+//! forecast. Everything is shown in the location's own time zone. The sun's
+//! path across the sky is drawn as an SVG arc, with the sun placed on it by
+//! how far the day has got between the forecast's sunrise and sunset.
+
+use std::f32::consts::PI;
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+use serde::Serialize;
+use weather_helpers::SolarWindow;
+
+use crate::location::Location;
+use crate::APIResponse;
+
+/// Context for the astronomy fragment
+#[derive(Serialize)]
+pub struct AstronomyCard {
+    solar_noon: String,
+    sunrise: Option<String>,
+    sunset: Option<String>,
+    morning_blue_hour: Option<String>,
+    morning_golden_hour: Option<String>,
+    evening_golden_hour: Option<String>,
+    evening_blue_hour: Option<String>,
+    moonrise: Option<String>,
+    moonset: Option<String>,
+    moon_phase: Option<&'static str>,
+    /// `None` on days the sun doesn't rise or set
+    sun_path: Option<SunPath>,
+}
+
+/// Where the sun is on the arc from sunrise to sunset. The arc itself is
+/// fixed in the template: a half ellipse centred on ([`CENTRE_X`],
+/// [`HORIZON`]) with radii [`RADIUS_X`] and [`RADIUS_Y`].
+#[derive(Serialize)]
+struct SunPath {
+    x: String,
+    y: String,
+    /// Whether the sun is up, or waiting at one end of the arc
+    is_up: bool,
+    /// How much of the arc the sun has covered, for the part drawn solid
+    travelled: String,
+}
+
+const CENTRE_X: f32 = 100.0;
+const HORIZON: f32 = 60.0;
+const RADIUS_X: f32 = 90.0;
+const RADIUS_Y: f32 = 50.0;
+
+impl SunPath {
+    /// The sun's place at unix time `now`, between `sunrise` and `sunset`.
+    fn new(now: i64, sunrise: i64, sunset: i64) -> Option<Self> {
+        if sunrise <= 0 || sunset <= sunrise {
+            return None;
+        }
+        let progress = ((now - sunrise) as f32 / (sunset - sunrise) as f32).clamp(0.0, 1.0);
+        let angle = PI * (1.0 - progress);
+        let (x, y) = (
+            CENTRE_X + RADIUS_X * angle.cos(),
+            HORIZON - RADIUS_Y * angle.sin(),
+        );
+        Some(SunPath {
+            x: format!("{:.1}", x),
+            y: format!("{:.1}", y),
+            is_up: (sunrise..sunset).contains(&now),
+            travelled: format!(
+                "M{},{} A{},{} 0 0 1 {:.1},{:.1}",
+                CENTRE_X - RADIUS_X,
+                HORIZON,
+                RADIUS_X,
+                RADIUS_Y,
+                x,
+                y
+            ),
+        })
+    }
+}
+
+impl AstronomyCard {
+    /// Today's events at `location`, or `None` if the report has no time zone.
+    pub fn new(api_response: &APIResponse, location: &Location) -> Option<Self> {
+        let tz = FixedOffset::east_opt(api_response.timezone_offset)?;
+        let today = api_response.daily.first()?;
+        let date = tz
+            .timestamp_opt(i64::from(today.dt), 0)
+            .single()?
+            .date_naive();
+
+        let events =
+            weather_helpers::solar_events(location.latitude(), location.longitude(), date, tz);
+
+        // OWM reports 0 for a moon that doesn't rise or set that day.
+        let moon_time = |timestamp: i64| {
+            Some(timestamp)
+                .filter(|&timestamp| timestamp > 0)
+                .and_then(|timestamp| tz.timestamp_opt(timestamp, 0).single())
+                .map(|time| format_time(&time))
+        };
+
+        Some(AstronomyCard {
+            solar_noon: format_time(&events.solar_noon),
+            sunrise: events.sunrise.as_ref().map(format_time),
+            sunset: events.sunset.as_ref().map(format_time),
+            morning_blue_hour: events.morning_blue_hour.as_ref().map(format_window),
+            morning_golden_hour: events.morning_golden_hour.as_ref().map(format_window),
+            evening_golden_hour: events.evening_golden_hour.as_ref().map(format_window),
+            evening_blue_hour: events.evening_blue_hour.as_ref().map(format_window),
+            moonrise: moon_time(today.moonrise),
+            moonset: moon_time(today.moonset),
+            moon_phase: today.moon_phase.map(weather_helpers::moon_phase_name),
+            sun_path: SunPath::new(api_response.current.dt, today.sunrise, today.sunset),
+        })
+    }
+}
+
+fn format_time(time: &DateTime<FixedOffset>) -> String {
+    time.format("%H:%M").to_string()
+}
+
+fn format_window(window: &SolarWindow) -> String {
+    format!(
+        "{}–{}",
+        format_time(&window.start),
+        format_time(&window.end)
+    )
+}
@@ -0,0 +1,151 @@
+//! Optional authentication for the JSON API under `/api/`.
+//!
+//! Set `api_auth` to `true` in the `weather_config` store to require it.
+//! Each client then has a key in the `weather_api_keys` Config Store, under
+//! its client id, and signs every request with it:
+//!
+//! ```text
+//! Authorization: HMAC <client id>:<unix time>:<signature>
+//! ```
+//!
+//! The signature is the hex HMAC-SHA256 of the time, the method, the path
+//! with its query string and the hex SHA-256 of the body, one per line. A
+//! `GET` has an empty body, so it signs
+//!
+//! ```text
+//! 1792000000
+//! GET
+//! /api/fire
+//! e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855
+//! ```
+//!
+//! The service drops parameters it doesn't know and sorts the rest by name
+//! before checking, so sign them that way. Signing the body means a captured
+//! request can't carry a different one. Signatures are good for five minutes
+//! either side of the time they carry, which keeps captured requests from
+//! being replayed later.
+//!
+//! Requests with no credentials, or credentials in the wrong shape, get a
+//! 401; unknown clients, bad signatures and stale times get a 403. City
+//! search stays open, since the location picker on our own pages uses it,
+//! as do confirming and unsubscribing from the digest, which are links in
+//! emails, and the push key and subscription, which pages call from the
+//! browser.
+//! Signed requests are metered against each client's quota; see
+//! [`crate::metering`].
+
+use chrono::Utc;
+use fastly::http::{header, StatusCode};
+use fastly::{Request, Response};
+
+use crate::{config, problem, signing};
+
+const KEY_STORE: &str = "weather_api_keys";
+const SCHEME: &str = "HMAC";
+const MAX_SKEW_SECS: i64 = 300;
+
+/// Paths under `/api/` that never need credentials
+const OPEN_PATHS: &[&str] = &[
+    "/api/geocode",
+    "/api/push/key",
+    "/api/push/subscribe",
+    "/api/digest/confirm",
+    "/api/digest/unsubscribe",
+];
+
+/// Whether `path` is part of the JSON API that `api_auth` protects.
+pub fn is_protected(path: &str) -> bool {
+    path.starts_with("/api/") && !OPEN_PATHS.contains(&path)
+}
+
+/// Whether the deployment asks API clients to sign their requests.
+pub fn required() -> bool {
+    config::get("api_auth").as_deref() == Some("true")
+}
+
+/// Who's calling the API
+pub enum Access {
+    /// `api_auth` is off, so anyone can
+    Open,
+    /// The client whose signature checked out
+    Client(String),
+    /// Credentials were missing or wrong, so send this instead
+    Denied(Box<Response>),
+}
+
+/// Check `req`'s credentials, if the deployment asks for them.
+pub fn authorize(req: &mut Request) -> Access {
+    if !required() {
+        return Access::Open;
+    }
+
+    // The signature covers the body, which goes back for the handlers to read
+    // if it checks out.
+    let body = req.take_body_bytes();
+    let credentials = req
+        .get_header_str(header::AUTHORIZATION)
+        .and_then(|value| value.strip_prefix(SCHEME)?.strip_prefix(' '))
+        .and_then(|value| {
+            let mut parts = value.trim().splitn(3, ':');
+            let client = parts.next().filter(|client| is_client_id(client))?;
+            let time = parts.next()?.parse::<i64>().ok()?;
+            Some((client, time, parts.next()?))
+        });
+    let (client, time, signature) = match credentials {
+        Some(credentials) => credentials,
+        None => {
+            return denied(
+                problem::response(StatusCode::UNAUTHORIZED, "Sign the request")
+                    .with_header(header::WWW_AUTHENTICATE, SCHEME),
+            )
+        }
+    };
+
+    if (Utc::now().timestamp() - time).abs() > MAX_SKEW_SECS {
+        return denied(problem::response(
+            StatusCode::FORBIDDEN,
+            "Signature time is too far off",
+        ));
+    }
+
+    let message = format!(
+        "{}\n{}\n{}\n{}",
+        time,
+        req.get_method_str(),
+        path_and_query(req),
+        signing::sha256(&body)
+    );
+
+    // Unknown clients are checked against an empty key, which nothing signs
+    // with, so they take as long to turn away as bad signatures.
+    let key = config::get_from(KEY_STORE, client).unwrap_or_default();
+    if key.is_empty() | !signing::verify(key.as_bytes(), message.as_bytes(), signature) {
+        println!("Rejected API request signed by client {}", client);
+        return denied(problem::response(
+            StatusCode::FORBIDDEN,
+            "Signature doesn't match",
+        ));
+    }
+    let client = client.to_string();
+    req.set_body(body);
+    Access::Client(client)
+}
+
+fn denied(resp: Response) -> Access {
+    Access::Denied(Box::new(resp))
+}
+
+// Client ids are Config Store keys, and end up in the logs.
+fn is_client_id(client: &str) -> bool {
+    (1..=64).contains(&client.len())
+        && client
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+fn path_and_query(req: &Request) -> String {
+    match req.get_query_str() {
+        Some(query) => format!("{}?{}", req.get_path(), query),
+        None => req.get_path().to_string(),
+    }
+}
@@ -0,0 +1,97 @@
+//! Aviation weather at `/metar/{icao}`: the latest METAR and TAF for an
+//! aerodrome, fetched from NOAA's Aviation Weather Center and decoded.
+//!
+//! Reports are issued every 30–60 minutes, so upstream responses are cached
+//! for five minutes.
+
+mod metar;
+mod taf;
+
+use fastly::http::{header, HeaderValue, Method, StatusCode, Url};
+use fastly::{Error, Request, Response};
+use serde::Serialize;
+
+use crate::view::{self, HeadContext};
+pub use metar::Metar;
+pub use taf::Taf;
+
+const AVIATION_BACKEND: &str = "aviationweather.gov";
+const REPORT_TTL_SECS: u32 = 300;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "aviation";
+
+/// Context for the aviation template
+#[derive(Serialize)]
+pub struct AviationContext {
+    station: String,
+    metar: Option<Metar>,
+    taf: Option<Taf>,
+    head: HeadContext,
+}
+
+/// Serve the aviation page, or `None` if `path` isn't `/metar/` followed by an
+/// ICAO code.
+pub fn handle(path: &str) -> Option<Result<Response, Error>> {
+    let station = path.strip_prefix("/metar/")?.to_ascii_uppercase();
+    if !metar::is_station(&station) {
+        return None;
+    }
+
+    Some(report(station))
+}
+
+fn report(station: String) -> Result<Response, Error> {
+    let metar = match fetch("metar", &station)? {
+        Some(raw) => Metar::parse(&raw),
+        None => return Ok(Response::from_status(StatusCode::BAD_GATEWAY)),
+    };
+    let taf = match fetch("taf", &station)? {
+        Some(raw) => Taf::parse(&raw),
+        None => return Ok(Response::from_status(StatusCode::BAD_GATEWAY)),
+    };
+
+    // Unknown stations come back empty rather than as an error.
+    let status = if metar.is_none() && taf.is_none() {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::OK
+    };
+    let context = AviationContext {
+        station,
+        metar,
+        taf,
+        head: HeadContext::new(false),
+    };
+
+    Ok(Response::from_body(view::aviation(&context))
+        .with_status(status)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", REPORT_TTL_SECS),
+        ))
+}
+
+// Fetch the raw text of the latest `product` ("metar" or "taf") for `station`,
+// or `None` if the upstream request failed.
+fn fetch(product: &str, station: &str) -> Result<Option<String>, Error> {
+    let url = Url::parse_with_params(
+        &format!("https://aviationweather.gov/api/data/{}", product),
+        &[("ids", station), ("format", "raw")],
+    )?;
+    let mut beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "aviationweather.gov")
+        .with_ttl(REPORT_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(AVIATION_BACKEND)?;
+
+    if !beresp.get_status().is_success() {
+        return Ok(None);
+    }
+    // TAFs wrap change groups onto new lines; the parsers only care about tokens.
+    // A report that isn't UTF-8 is garbled anyway, so whatever survives will do.
+    let body = beresp.take_body_bytes();
+    let body = String::from_utf8_lossy(&body);
+    Ok(Some(body.split_whitespace().collect::<Vec<_>>().join(" ")))
+}
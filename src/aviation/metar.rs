@@ -0,0 +1,324 @@
+//! METAR (aerodrome routine weather report) parsing.
+//!
+//! Only the body groups pilots read at a glance are decoded; anything
+//! unrecognised, and everything after `RMK`, is kept in the raw text only.
+
+use serde::Serialize;
+
+/// A decoded METAR report
+#[derive(Serialize, Default)]
+pub struct Metar {
+    pub raw: String,
+    pub station: String,
+    pub observed: Option<String>,
+    pub wind: Option<Wind>,
+    pub visibility: Option<String>,
+    pub weather: Vec<String>,
+    pub clouds: Vec<Cloud>,
+    // Kept as text so the template can tell 0°C apart from a missing value.
+    pub temperature: Option<String>,
+    pub dew_point: Option<String>,
+    pub pressure: Option<String>,
+    pub flight_category: Option<&'static str>,
+}
+
+/// Surface wind
+#[derive(Serialize)]
+pub struct Wind {
+    /// Direction in degrees true, or `None` when variable
+    pub direction: Option<u16>,
+    pub speed: u16,
+    pub gust: Option<u16>,
+    pub unit: &'static str,
+}
+
+/// A cloud layer
+#[derive(Serialize)]
+pub struct Cloud {
+    pub cover: String,
+    /// Base in feet above ground level
+    #[serde(skip)]
+    pub base_ft: Option<u32>,
+    /// The base as displayed, e.g. `1200 ft`
+    pub base: Option<String>,
+    pub kind: Option<String>,
+}
+
+impl Metar {
+    /// Parse a raw METAR, such as `METAR EGLL 121250Z 24012KT 9999 FEW040 15/08 Q1016`.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let mut tokens = raw.split_whitespace().peekable();
+
+        if let Some(&"METAR") | Some(&"SPECI") = tokens.peek() {
+            tokens.next();
+        }
+        let station = tokens.next().filter(|t| is_station(t))?.to_string();
+
+        let mut metar = Metar {
+            raw: raw.to_string(),
+            station,
+            ..Default::default()
+        };
+
+        for token in tokens {
+            if token == "RMK" {
+                break;
+            }
+            parse_group(
+                token,
+                &mut metar.observed,
+                &mut metar.wind,
+                &mut metar.visibility,
+                &mut metar.weather,
+                &mut metar.clouds,
+            );
+            if let Some((temperature, dew_point)) = parse_temperatures(token) {
+                metar.temperature = Some(format!("{}°C", temperature));
+                metar.dew_point = dew_point.map(|dew_point| format!("{}°C", dew_point));
+            } else if let Some(pressure) = parse_pressure(token) {
+                metar.pressure = Some(pressure);
+            }
+        }
+
+        metar.flight_category = flight_category(metar.visibility.as_deref(), &metar.clouds);
+        Some(metar)
+    }
+}
+
+/// Whether `token` looks like an ICAO location indicator, such as `KJFK`.
+pub fn is_station(token: &str) -> bool {
+    token.len() == 4
+        && token
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Decode the groups METARs and TAFs share: time, wind, visibility, weather
+/// and clouds. Tokens that aren't one of these are ignored.
+pub fn parse_group(
+    token: &str,
+    time: &mut Option<String>,
+    wind: &mut Option<Wind>,
+    visibility: &mut Option<String>,
+    weather: &mut Vec<String>,
+    clouds: &mut Vec<Cloud>,
+) {
+    if let Some(parsed) = parse_time(token) {
+        *time = Some(parsed);
+    } else if let Some(parsed) = parse_wind(token) {
+        *wind = Some(parsed);
+    } else if let Some(parsed) = parse_visibility(token) {
+        *visibility = Some(parsed);
+    } else if let Some(parsed) = parse_cloud(token) {
+        clouds.push(parsed);
+    } else if is_weather(token) {
+        weather.push(token.to_string());
+    }
+}
+
+// `DDHHMMZ`, e.g. `121250Z` is the 12th at 12:50 UTC.
+fn parse_time(token: &str) -> Option<String> {
+    let digits = token.strip_suffix('Z')?;
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "day {} at {}:{} UTC",
+        &digits[0..2],
+        &digits[2..4],
+        &digits[4..6]
+    ))
+}
+
+// `dddssKT`, `dddssGggKT`, `VRBssKT`, also in `MPS`.
+fn parse_wind(token: &str) -> Option<Wind> {
+    let (body, unit) = match token.strip_suffix("KT") {
+        Some(body) => (body, "kt"),
+        None => (token.strip_suffix("MPS")?, "m/s"),
+    };
+    if body.len() < 5 {
+        return None;
+    }
+
+    // `get` rather than slicing, as a garbled report can put a multi-byte
+    // character across the split.
+    let direction = match body.get(0..3)? {
+        "VRB" => None,
+        digits => Some(digits.parse().ok()?),
+    };
+    let rest = body.get(3..)?;
+    let (speed, gust) = match rest.split_once('G') {
+        Some((speed, gust)) => (speed.parse().ok()?, Some(gust.parse().ok()?)),
+        None => (rest.parse().ok()?, None),
+    };
+
+    Some(Wind {
+        direction,
+        speed,
+        gust,
+        unit,
+    })
+}
+
+// `9999`, `0800`, `10SM`, `P6SM`, `1/2SM` or `CAVOK`.
+fn parse_visibility(token: &str) -> Option<String> {
+    if token == "CAVOK" {
+        return Some(String::from("10 km or more, no cloud below 5000 ft"));
+    }
+    if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+        return Some(match token {
+            "9999" => String::from("10 km or more"),
+            metres => format!("{} m", metres.trim_start_matches('0').max("0")),
+        });
+    }
+    let miles = token.strip_suffix("SM")?;
+    if let Some(miles) = miles.strip_prefix('P') {
+        return Some(format!("more than {} mi", miles));
+    }
+    if !miles.is_empty() && miles.chars().all(|c| c.is_ascii_digit() || c == '/') {
+        return Some(format!("{} mi", miles));
+    }
+    None
+}
+
+// `FEW040`, `BKN012CB`, `VV002`, or a clear-sky code.
+fn parse_cloud(token: &str) -> Option<Cloud> {
+    if matches!(token, "SKC" | "CLR" | "NSC" | "NCD") {
+        return Some(Cloud {
+            cover: String::from("clear"),
+            base_ft: None,
+            base: None,
+            kind: None,
+        });
+    }
+
+    let (cover, rest) = ["FEW", "SCT", "BKN", "OVC", "VV"]
+        .iter()
+        .find_map(|cover| token.strip_prefix(cover).map(|rest| (*cover, rest)))?;
+    let height = rest.get(0..3)?;
+    let kind = &rest[3..];
+    if !kind.is_empty() && kind != "CB" && kind != "TCU" {
+        return None;
+    }
+
+    let base_ft = height.parse::<u32>().ok().map(|hundreds| hundreds * 100);
+    Some(Cloud {
+        cover: String::from(match cover {
+            "FEW" => "few",
+            "SCT" => "scattered",
+            "BKN" => "broken",
+            "OVC" => "overcast",
+            _ => "vertical visibility",
+        }),
+        base_ft,
+        base: base_ft.map(|feet| format!("{} ft", feet)),
+        kind: (!kind.is_empty()).then(|| kind.to_string()),
+    })
+}
+
+// Present weather such as `-RA`, `+TSRA`, `VCSH`, `BR` or `FZFG`.
+fn is_weather(token: &str) -> bool {
+    const CODES: [&str; 28] = [
+        "MI", "PR", "BC", "DR", "BL", "SH", "TS", "FZ", "DZ", "RA", "SN", "SG", "IC", "PL", "GR",
+        "GS", "UP", "BR", "FG", "FU", "VA", "DU", "SA", "HZ", "PO", "SQ", "FC", "SS",
+    ];
+    let codes = token
+        .trim_start_matches(['-', '+'])
+        .trim_start_matches("VC");
+    !codes.is_empty()
+        && codes.len().is_multiple_of(2)
+        && codes
+            .as_bytes()
+            .chunks(2)
+            .all(|code| CODES.iter().any(|known| known.as_bytes() == code))
+}
+
+// `15/08`, `M02/M05`, or `05/` when the dew point is missing.
+fn parse_temperatures(token: &str) -> Option<(i32, Option<i32>)> {
+    let (temperature, dew_point) = token.split_once('/')?;
+    let temperature = parse_signed(temperature)?;
+    let dew_point = if dew_point.is_empty() {
+        None
+    } else {
+        Some(parse_signed(dew_point)?)
+    };
+    Some((temperature, dew_point))
+}
+
+fn parse_signed(value: &str) -> Option<i32> {
+    if value.len() != 2 && value.len() != 3 {
+        return None;
+    }
+    match value.strip_prefix('M') {
+        Some(digits) if digits.len() == 2 => digits.parse::<i32>().ok().map(|t| -t),
+        Some(_) => None,
+        None if value.len() == 2 => value.parse().ok(),
+        None => None,
+    }
+}
+
+// `Q1013` (hectopascals) or `A2992` (inches of mercury).
+fn parse_pressure(token: &str) -> Option<String> {
+    let mut chars = token.chars();
+    let unit = chars.next()?;
+    let digits = chars.as_str();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match unit {
+        'Q' => Some(format!("{} hPa", digits.trim_start_matches('0'))),
+        'A' => Some(format!("{}.{} inHg", &digits[0..2], &digits[2..4])),
+        _ => None,
+    }
+}
+
+/// The FAA flight category for a ceiling and visibility.
+pub fn flight_category(visibility: Option<&str>, clouds: &[Cloud]) -> Option<&'static str> {
+    let visibility_mi = visibility.map(visibility_in_miles)?;
+    // The ceiling is the lowest broken, overcast or obscured layer.
+    let ceiling_ft = clouds
+        .iter()
+        .filter(|cloud| {
+            matches!(
+                cloud.cover.as_str(),
+                "broken" | "overcast" | "vertical visibility"
+            )
+        })
+        .filter_map(|cloud| cloud.base_ft)
+        .min()
+        .unwrap_or(u32::MAX);
+
+    Some(if ceiling_ft < 500 || visibility_mi < 1.0 {
+        "LIFR"
+    } else if ceiling_ft < 1000 || visibility_mi < 3.0 {
+        "IFR"
+    } else if ceiling_ft <= 3000 || visibility_mi <= 5.0 {
+        "MVFR"
+    } else {
+        "VFR"
+    })
+}
+
+// Turn a decoded visibility back into statute miles for the flight category.
+fn visibility_in_miles(visibility: &str) -> f64 {
+    let value = visibility
+        .trim_start_matches("more than ")
+        .split(' ')
+        .next()
+        .unwrap_or_default();
+    let number = match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            numerator.parse::<f64>().unwrap_or(0.0) / denominator.parse::<f64>().unwrap_or(1.0)
+        }
+        None => value.parse::<f64>().unwrap_or(0.0),
+    };
+
+    if visibility.contains(" km") {
+        number / 1.609
+    } else if visibility.ends_with(" m") {
+        number / 1609.0
+    } else {
+        number
+    }
+}
@@ -0,0 +1,158 @@
+//! TAF (terminal aerodrome forecast) parsing.
+//!
+//! A TAF is a base forecast followed by change groups (`FM`, `BECMG`, `TEMPO`
+//! and `PROB30`/`PROB40`). Each group uses the same wind, visibility, weather
+//! and cloud codes as a METAR.
+
+use serde::Serialize;
+
+use super::metar::{self, Cloud, Wind};
+
+/// A decoded TAF
+#[derive(Serialize)]
+pub struct Taf {
+    pub raw: String,
+    pub station: String,
+    pub issued: Option<String>,
+    pub valid: Option<String>,
+    pub periods: Vec<Period>,
+}
+
+/// The base forecast or one of its change groups
+#[derive(Serialize)]
+pub struct Period {
+    pub change: String,
+    pub valid: Option<String>,
+    pub wind: Option<Wind>,
+    pub visibility: Option<String>,
+    pub weather: Vec<String>,
+    pub clouds: Vec<Cloud>,
+    pub flight_category: Option<&'static str>,
+}
+
+impl Period {
+    fn new(change: &str) -> Self {
+        Period {
+            change: change.to_string(),
+            valid: None,
+            wind: None,
+            visibility: None,
+            weather: Vec::new(),
+            clouds: Vec::new(),
+            flight_category: None,
+        }
+    }
+}
+
+impl Taf {
+    /// Parse a raw TAF, such as `TAF KJFK 121130Z 1212/1318 24012KT P6SM FEW250 FM121800 ...`.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let mut tokens = raw
+            .split_whitespace()
+            .skip_while(|token| matches!(*token, "TAF" | "AMD" | "COR"))
+            .peekable();
+        let station = tokens.next().filter(|t| metar::is_station(t))?.to_string();
+
+        let mut taf = Taf {
+            raw: raw.to_string(),
+            station,
+            issued: None,
+            valid: None,
+            periods: Vec::new(),
+        };
+        let mut period = Period::new("Base");
+
+        for token in tokens {
+            if token == "RMK" {
+                break;
+            }
+
+            if let Some(time) = token.strip_prefix("FM") {
+                taf.periods.push(period);
+                period = Period::new("From");
+                period.valid = parse_from(time);
+                continue;
+            }
+            if let Some(change) = match token {
+                "BECMG" => Some("Becoming"),
+                "TEMPO" => Some("Temporarily"),
+                "PROB30" => Some("30% chance"),
+                "PROB40" => Some("40% chance"),
+                _ => None,
+            } {
+                // `PROB30 TEMPO` is a single group.
+                if token == "TEMPO" && period.change.ends_with("chance") && period.valid.is_none() {
+                    period.change.push_str(", temporarily");
+                } else {
+                    taf.periods.push(period);
+                    period = Period::new(change);
+                }
+                continue;
+            }
+            if let Some(valid) = parse_validity(token) {
+                if taf.valid.is_none() {
+                    taf.valid = Some(valid);
+                } else {
+                    period.valid = Some(valid);
+                }
+                continue;
+            }
+
+            let mut issued = None;
+            metar::parse_group(
+                token,
+                &mut issued,
+                &mut period.wind,
+                &mut period.visibility,
+                &mut period.weather,
+                &mut period.clouds,
+            );
+            if issued.is_some() {
+                taf.issued = issued;
+            }
+        }
+        taf.periods.push(period);
+
+        for period in &mut taf.periods {
+            period.flight_category =
+                metar::flight_category(period.visibility.as_deref(), &period.clouds);
+        }
+        Some(taf)
+    }
+}
+
+// `DDHH/DDHH`, e.g. `1212/1318` is the 12th at 12:00 until the 13th at 18:00 UTC.
+fn parse_validity(token: &str) -> Option<String> {
+    let (from, until) = token.split_once('/')?;
+    if from.len() != 4 || until.len() != 4 {
+        return None;
+    }
+    if !from
+        .chars()
+        .chain(until.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    Some(format!(
+        "day {} {}:00 to day {} {}:00 UTC",
+        &from[0..2],
+        &from[2..4],
+        &until[0..2],
+        &until[2..4]
+    ))
+}
+
+// The `DDHHMM` after `FM`.
+fn parse_from(time: &str) -> Option<String> {
+    if time.len() != 6 || !time.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "day {} {}:{} UTC",
+        &time[0..2],
+        &time[2..4],
+        &time[4..6]
+    ))
+}
@@ -0,0 +1,114 @@
+//! A fast path for search engine crawlers and other bots.
+//!
+//! Crawlers fetch the page far more often than anyone reads what they index,
+//! and geolocating a data centre tells them nothing. So requests for `/` from
+//! a known crawler get one generic page: the configured default location, in
+//! the standard layout, with no experiments or cookies. It's rendered once
+//! and kept in the edge cache for [`PAGE_TTL`], so repeat crawls cost neither
+//! a geolocation lookup nor a weather API call. Its inline script keeps the
+//! nonce it was rendered with, which is stored alongside it.
+//!
+//! The page is only ever rendered for the default location, never for a
+//! place taken from the request, so a client posing as a crawler can't put
+//! its own text in it. Without a default location, crawlers are served like
+//! any other visitor, and nothing is cached or marked public.
+
+use std::time::Duration;
+
+use fastly::cache::simple;
+use fastly::http::{header, StatusCode};
+use fastly::{Request, Response};
+use serde::Deserialize;
+
+use crate::csp;
+
+/// How long a rendered crawler page is reused
+pub const PAGE_TTL: Duration = Duration::from_secs(3 * 3600);
+
+/// Lowercase User-Agent fragments of well-known crawlers and link previewers
+const CRAWLERS: &[&str] = &[
+    "googlebot",
+    "bingbot",
+    "slurp",
+    "duckduckbot",
+    "baiduspider",
+    "yandex",
+    "applebot",
+    "facebookexternalhit",
+    "twitterbot",
+    "linkedinbot",
+    "slackbot",
+    "discordbot",
+    "petalbot",
+    "semrushbot",
+    "ahrefsbot",
+    "gptbot",
+    "ccbot",
+    // Most others say so somewhere
+    "bot/",
+    "crawler",
+    "spider",
+];
+
+const UNITS: &[&str] = &["metric", "imperial", "standard"];
+
+#[derive(Deserialize)]
+struct UnitsParam {
+    units: Option<String>,
+}
+
+/// Whether `req` comes from a crawler, going by its User-Agent.
+pub fn is_crawler(req: &Request) -> bool {
+    req.get_header_str(header::USER_AGENT).is_some_and(|agent| {
+        let agent = agent.to_ascii_lowercase();
+        CRAWLERS.iter().any(|crawler| agent.contains(crawler))
+    })
+}
+
+/// The units of the crawler page for `req`. Unknown units get the metric
+/// page, so made-up values can't each cost an API call.
+pub fn units(req: &Request) -> &'static str {
+    let units = req
+        .get_query::<UnitsParam>()
+        .ok()
+        .and_then(|param| param.units);
+    UNITS
+        .iter()
+        .find(|&&known| units.as_deref() == Some(known))
+        .unwrap_or(&UNITS[0])
+}
+
+/// The cached crawler page for `req`, if there is one.
+pub fn cached_page(req: &Request) -> Option<Response> {
+    let body = simple::get(cache_key(req)).ok()??.into_string();
+    let (nonce, page) = body.split_once('\n')?;
+    let mut resp = Response::from_body(page)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8);
+    csp::tag(&mut resp, nonce);
+    tag(&mut resp);
+    Some(resp)
+}
+
+/// Keep a freshly rendered crawler page, and the nonce it was rendered
+/// with, for the next crawl.
+pub fn store_page(req: &Request, nonce: &str, page: &str) {
+    let entry = format!("{}\n{}", nonce, page);
+    if let Err(err) = simple::get_or_set(cache_key(req), entry, PAGE_TTL) {
+        println!("Couldn't cache the crawler page: {:?}", err);
+    }
+}
+
+/// Mark a crawler page as the same for every crawler, and fine to keep.
+pub fn tag(resp: &mut Response) {
+    resp.set_header(
+        header::CACHE_CONTROL,
+        format!("public, max-age={}", PAGE_TTL.as_secs()),
+    );
+    // Visitors get their own location, so caches mustn't hand them this page.
+    resp.set_header(header::VARY, "User-Agent");
+}
+
+fn cache_key(req: &Request) -> String {
+    format!("crawler_page:{}", units(req))
+}
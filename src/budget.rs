@@ -0,0 +1,161 @@
+//! Keeping OpenWeatherMap calls inside the free tier.
+//!
+//! Calls in the last minute are counted by the `owm_calls` edge rate
+//! counter, which increments atomically. Calls today are counted in the KV
+//! Store under `owm_calls:YYYYMMDD`, split into shards so concurrent requests
+//! rarely overwrite each other's increments; the daily budget leaves headroom
+//! for the few that are lost. Each POP keeps the summed daily count for
+//! [`TODAY_TTL`] rather than reading every shard on every fetch, and sums
+//! them again after a call of its own. Rate counters
+//! are kept per POP, so the per-minute count is the busiest POP's share rather
+//! than the service's.
+//!
+//! Once either count passes [`CONSERVE_AT`] of its budget, responses are
+//! cached for longer so repeat visitors don't cost a call. When a budget is
+//! spent, only cached responses are served until the minute or day is over.
+//! The budgets come from `owm_calls_per_minute` and `owm_calls_per_day` in
+//! the `weather_config` store.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Timelike, Utc};
+use fastly::cache::simple::{self, get_or_set_with, CacheEntry};
+use fastly::erl::{CounterDuration, RateCounter};
+
+use crate::config;
+use crate::kv::{self, ShardedCount};
+
+// The free tier's limits: 60 calls a minute, 1,000 a day on One Call 3.0.
+const DEFAULT_PER_MINUTE: u64 = 60;
+const DEFAULT_PER_DAY: u64 = 1_000;
+
+/// The share of a budget that can be spent before conserving calls
+const CONSERVE_AT: f64 = 0.9;
+
+const RATE_COUNTER: &str = "owm_calls";
+/// The rate counter entry, as there's only the one thing to count
+const ENTRY: &str = "onecall";
+
+/// How long each POP reuses the day's count. A day's budget is far bigger
+/// than the calls a POP can make in that time.
+const TODAY_TTL: Duration = Duration::from_secs(30);
+
+/// How freely the weather API can be called right now
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Normal,
+    /// Nearly out of calls: prefer cached responses, and cache them for longer
+    Conserve,
+    /// Out of calls: serve only cached responses, for this many more seconds
+    Exhausted {
+        retry_secs: u32,
+    },
+}
+
+/// Today's call counts, against the configured budgets
+pub struct Budget {
+    now: DateTime<Utc>,
+    today: u64,
+    in_minute: u64,
+    per_minute: u64,
+    per_day: u64,
+}
+
+impl Budget {
+    /// Load today's counts.
+    pub fn load() -> Self {
+        let now = Utc::now();
+        let in_minute = RateCounter::open(RATE_COUNTER)
+            .lookup_count(ENTRY, CounterDuration::SixtySecs)
+            .map_or(0, u64::from);
+        Budget::with_counts(
+            now,
+            calls_today(&day_key(&now)),
+            in_minute,
+            limit("owm_calls_per_day", DEFAULT_PER_DAY),
+            limit("owm_calls_per_minute", DEFAULT_PER_MINUTE),
+        )
+    }
+
+    /// A budget of `per_day` and `per_minute` calls at `now`, with `today`
+    /// and `in_minute` of them made.
+    pub fn with_counts(
+        now: DateTime<Utc>,
+        today: u64,
+        in_minute: u64,
+        per_day: u64,
+        per_minute: u64,
+    ) -> Self {
+        Budget {
+            now,
+            today,
+            in_minute,
+            per_minute,
+            per_day,
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        if self.today >= self.per_day {
+            let midnight = 86_400 - self.now.num_seconds_from_midnight();
+            Mode::Exhausted {
+                retry_secs: midnight,
+            }
+        } else if self.in_minute >= self.per_minute {
+            Mode::Exhausted {
+                retry_secs: 60 - self.now.second(),
+            }
+        } else if self.today as f64 >= self.per_day as f64 * CONSERVE_AT
+            || self.in_minute as f64 >= self.per_minute as f64 * CONSERVE_AT
+        {
+            Mode::Conserve
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// Count a call to the weather API.
+    pub fn record_call(&mut self) {
+        self.in_minute += 1;
+        self.today += 1;
+        if let Err(err) = RateCounter::open(RATE_COUNTER).increment(ENTRY, 1) {
+            println!("Couldn't count a weather API call this minute: {:?}", err);
+        }
+        let key = day_key(&self.now);
+        if !kv::increment(&key) {
+            println!("Couldn't record a weather API call against the budget");
+        }
+        // Other POPs' calls can wait, but this one's should count straight away.
+        let _ = simple::purge(total_key(&key));
+    }
+}
+
+fn day_key(now: &DateTime<Utc>) -> String {
+    format!("owm_calls:{}", now.format("%Y%m%d"))
+}
+
+fn total_key(key: &str) -> String {
+    format!("{}:total", key)
+}
+
+// The calls counted under `key`, from this POP's cache if it summed them lately.
+fn calls_today(key: &str) -> u64 {
+    let cached = get_or_set_with(total_key(key), || {
+        Ok(CacheEntry {
+            value: ShardedCount::load(key).total().to_string().into(),
+            ttl: TODAY_TTL,
+        })
+    });
+    match cached {
+        Ok(Some(total)) => total.into_string().parse().unwrap_or_default(),
+        _ => ShardedCount::load(key).total(),
+    }
+}
+
+// A budget from the config store, ignoring values that aren't positive numbers.
+fn limit(key: &str, default: u64) -> u64 {
+    config::get(key)
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&limit| limit > 0)
+        .unwrap_or(default)
+}
@@ -0,0 +1,86 @@
+//! Canary rollouts of a new upstream for the One Call forecast.
+//!
+//! `canary_percent` in the `weather_config` store sends that share of
+//! clients, picked by a hash of their IP like the feature flags, to the
+//! canary instead of the usual API. The canary is One Call
+//! `canary_api_version` (`3.0` unless set) on the `canary_backend` backend,
+//! which defaults to OpenWeatherMap itself; any backend on the service that
+//! speaks the One Call API will do. Setting `canary_percent` to `0`, or
+//! removing it, rolls every client back at once.
+//!
+//! Each forecast fetch logs which upstream served it, so the two can be
+//! compared in `fastly log-tail`.
+
+use fastly::Backend;
+use serde_json::json;
+
+use crate::config;
+use crate::flags::Flags;
+
+/// The One Call versions there are
+const VERSIONS: &[&str] = &["2.5", "3.0"];
+
+const DEFAULT_CANARY_VERSION: &str = "3.0";
+
+/// Where a client's One Call requests go
+pub struct Upstream {
+    pub backend: String,
+    pub version: String,
+    pub is_canary: bool,
+}
+
+impl Upstream {
+    /// The usual upstream, or the canary if the client is in its slice.
+    /// `backend` and `version` are the usual ones.
+    pub fn choose(flags: &Flags, backend: &str, version: &str) -> Self {
+        let primary = Upstream {
+            backend: backend.to_string(),
+            version: version.to_string(),
+            is_canary: false,
+        };
+        let percent = match config::get("canary_percent")
+            .and_then(|percent| percent.trim().trim_end_matches('%').parse::<u32>().ok())
+        {
+            Some(percent) if percent > 0 => percent.min(100),
+            _ => return primary,
+        };
+        if !flags.in_rollout("canary", percent) {
+            return primary;
+        }
+
+        let canary_backend = config::get("canary_backend")
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|| backend.to_string());
+        if Backend::from_name(&canary_backend).map_or(true, |backend| !backend.exists()) {
+            println!(
+                "Canary backend {} doesn't exist, using {}",
+                canary_backend, backend
+            );
+            return primary;
+        }
+        let version = config::get("canary_api_version")
+            .map(|version| version.trim().to_string())
+            .filter(|version| VERSIONS.contains(&version.as_str()))
+            .unwrap_or_else(|| DEFAULT_CANARY_VERSION.to_string());
+
+        Upstream {
+            backend: canary_backend,
+            version,
+            is_canary: true,
+        }
+    }
+
+    /// Log which upstream served a forecast.
+    pub fn log(&self) {
+        let route = if self.is_canary { "canary" } else { "primary" };
+        println!(
+            "{}",
+            json!({
+                "event": "upstream",
+                "route": route,
+                "backend": self.backend,
+                "version": self.version,
+            })
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! The hourly temperature chart: the next 48 hours as an inline SVG line,
+//! drawn here so the page needs no charting script.
+//!
+//! The fragment template draws the SVG from the coordinates worked out here.
+//! Its colours are CSS custom properties (`--chart-line`, `--chart-fill` and
+//! `--chart-text`), so stylesheets can theme it, and the hour labels are in
+//! the visitor's language.
+
+use chrono::{FixedOffset, TimeZone, Timelike};
+use serde::Serialize;
+use weather_helpers::Locale;
+
+use crate::precision::Precision;
+use crate::APIResponse;
+
+const HOURS: usize = 48;
+
+// The drawing's size in SVG units. It scales to fit the card.
+const WIDTH: f32 = 480.0;
+const HEIGHT: f32 = 120.0;
+// Room for the hour labels under the line, and the extremes above it
+const TOP: f32 = 16.0;
+const BOTTOM: f32 = 100.0;
+
+// Hours between labels along the bottom, which fall on the local clock's
+// midnight, 6am, noon and 6pm
+const LABEL_EVERY: u32 = 6;
+
+/// Context for the chart fragment
+#[derive(Serialize)]
+pub struct TemperatureChart {
+    width: u32,
+    height: u32,
+    /// `x,y` pairs for the line's `points`
+    line: String,
+    /// The line closed along the bottom, for the shaded area underneath
+    area: String,
+    labels: Vec<Label>,
+    high: Label,
+    low: Label,
+}
+
+/// Text placed on the chart
+#[derive(Serialize)]
+struct Label {
+    x: String,
+    y: String,
+    text: String,
+}
+
+impl TemperatureChart {
+    /// The chart of the next 48 hours in `api_response`, labelled in
+    /// `locale`. `None` without at least two hours to draw a line between.
+    pub fn new(api_response: &APIResponse, locale: Locale) -> Option<Self> {
+        let tz = FixedOffset::east_opt(api_response.timezone_offset)?;
+        let precision = Precision::configured();
+        let hours = &api_response.hourly[..api_response.hourly.len().min(HOURS)];
+        if hours.len() < 2 {
+            return None;
+        }
+
+        let temps: Vec<f32> = hours.iter().map(|hour| hour.temp).collect();
+        let (low, high) = temps
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), &temp| {
+                (low.min(temp), high.max(temp))
+            });
+        // A flat day still gets some height, so the line sits mid-chart.
+        let range = (high - low).max(1.0);
+        let step = WIDTH / (hours.len() - 1) as f32;
+        let x = |i: usize| i as f32 * step;
+        let y = |temp: f32| BOTTOM - (temp - low) / range * (BOTTOM - TOP);
+
+        let line = temps
+            .iter()
+            .enumerate()
+            .map(|(i, &temp)| format!("{},{}", round(x(i)), round(y(temp))))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let area = format!("0,{} {} {},{}", BOTTOM, line, WIDTH, BOTTOM);
+
+        let labels = hours
+            .iter()
+            .enumerate()
+            .filter_map(|(i, hour)| {
+                let local = tz.timestamp_opt(hour.dt, 0).single()?;
+                (local.hour() % LABEL_EVERY == 0).then(|| Label {
+                    x: round(clamp_x(x(i))),
+                    y: round(HEIGHT - 4.0),
+                    text: locale.format_hour(local.hour()),
+                })
+            })
+            .collect();
+
+        // The extremes are labelled just above where the line reaches them.
+        let extreme = |temp: f32| {
+            let i = temps.iter().position(|&t| t == temp).unwrap_or_default();
+            Label {
+                x: round(clamp_x(x(i))),
+                y: round(y(temp) - 5.0),
+                text: format!("{}°", precision.temp(locale, temp)),
+            }
+        };
+
+        Some(TemperatureChart {
+            width: WIDTH as u32,
+            height: HEIGHT as u32,
+            line,
+            area,
+            labels,
+            high: extreme(high),
+            low: extreme(low),
+        })
+    }
+}
+
+// Labels are centred on their hour, so keep them clear of the chart's edges.
+fn clamp_x(x: f32) -> f32 {
+    x.clamp(16.0, WIDTH - 16.0)
+}
+
+// One decimal place is plenty at this size, and keeps the markup short.
+fn round(value: f32) -> String {
+    format!("{}", (value * 10.0).round() / 10.0)
+}
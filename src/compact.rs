@@ -0,0 +1,57 @@
+//! A tiny forecast at `/api/compact`, for watch faces and other clients that
+//! can only hold a few hundred bytes.
+//!
+//! Keys are one letter: `t` is the temperature now, `h` and `l` today's high
+//! and low, all whole degrees in the requested units, `p` the chance of
+//! precipitation in the next hour in percent, `i` the OpenWeather icon code
+//! and `s` who the forecast is from. Anything the forecast lacks is left
+//! out. The forecast changes hourly and watches poll often, so it may be
+//! cached for a quarter of an hour and served stale for longer.
+
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Response};
+use serde::Serialize;
+
+use crate::{provider, APIResponse};
+
+/// The `/api/compact` response
+#[derive(Serialize)]
+struct Compact<'a> {
+    t: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    h: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    l: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    i: Option<&'a str>,
+    s: &'static str,
+}
+
+/// Serve `/api/compact`.
+pub fn handle(api_response: &APIResponse) -> Result<Response, Error> {
+    let today = api_response.daily.first();
+    let compact = Compact {
+        t: weather_helpers::round_temperature(api_response.current.temp),
+        h: today.map(|today| weather_helpers::round_temperature(today.temp.max)),
+        l: today.map(|today| weather_helpers::round_temperature(today.temp.min)),
+        p: api_response
+            .hourly
+            .first()
+            .map(|hour| (hour.pop * 100.0).round() as u32),
+        i: api_response
+            .current
+            .weather
+            .first()
+            .map(|weather| weather.icon.as_str()),
+        s: provider::current().name,
+    };
+    Ok(Response::from_body(serde_json::to_string(&compact)?)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(
+            header::CACHE_CONTROL,
+            "private, max-age=900, stale-while-revalidate=3600, stale-if-error=86400",
+        ))
+}
@@ -0,0 +1,101 @@
+//! Edge-side page composition.
+//!
+//! The main page is a shell that only depends on the visitor's preferences,
+//! so it's rendered once and kept in Fastly's cache. The per-location parts
+//! are `<esi:include src="..." />` tags in the shell, which are swapped for
+//! freshly rendered fragments on every request.
+
+use std::io::{self, Write};
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+use fastly::cache::simple::{get_or_set_with, CacheEntry};
+
+// The shell only changes on deploy, so this just bounds how long a stale one survives.
+#[cfg(target_arch = "wasm32")]
+const SHELL_TTL: Duration = Duration::from_secs(86_400);
+
+const INCLUDE_OPEN: &str = "<esi:include";
+const INCLUDE_CLOSE: &str = "/>";
+
+/// Get the shell stored under `key`, rendering and caching it on a miss.
+#[cfg(target_arch = "wasm32")]
+pub fn cached_shell(key: &str, render: impl FnOnce() -> String) -> String {
+    // Keys are scoped to the service version, which every deploy bumps, so
+    // a deploy never serves an old shell. Local servers have no version.
+    let version = std::env::var("FASTLY_SERVICE_VERSION").unwrap_or_default();
+    let key = format!("shell:{}:{}", version, key);
+    let mut render = Some(render);
+    let mut rendered = None;
+
+    let cached = get_or_set_with(key, || {
+        let shell = render.take().map(|render| render()).unwrap_or_default();
+        rendered = Some(shell.clone());
+        Ok(CacheEntry {
+            value: shell.into(),
+            ttl: SHELL_TTL,
+        })
+    });
+
+    match cached {
+        Ok(Some(body)) => body.into_string(),
+        // If the cache is unavailable, fall back to the copy rendered above,
+        // or render one now if the lookup failed before getting that far.
+        _ => rendered
+            .or_else(|| render.map(|render| render()))
+            .unwrap_or_default(),
+    }
+}
+
+/// Render the shell. Built for the host, as for the benchmarks and fuzz
+/// targets, there's no cache to keep it in.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn cached_shell(_key: &str, render: impl FnOnce() -> String) -> String {
+    render()
+}
+
+/// Replace every `<esi:include src="..." />` in `shell` with the fragment
+/// `resolve` returns for its path, writing the page to `out` as it goes.
+/// Unknown includes are dropped. The shell before each include is flushed
+/// before the fragment renders, so a streaming client can start on it.
+pub fn compose_to(
+    shell: &str,
+    resolve: impl Fn(&str) -> Option<String>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut rest = shell;
+
+    while let Some(start) = rest.find(INCLUDE_OPEN) {
+        out.write_all(&rest.as_bytes()[..start])?;
+        out.flush()?;
+        let tag = &rest[start..];
+        let end = match tag.find(INCLUDE_CLOSE) {
+            Some(end) => end + INCLUDE_CLOSE.len(),
+            // An unterminated tag isn't an include, so leave it alone.
+            None => {
+                rest = tag;
+                break;
+            }
+        };
+
+        if let Some(fragment) = include_src(&tag[..end]).and_then(|src| resolve(path_of(src))) {
+            out.write_all(fragment.as_bytes())?;
+        }
+        rest = &tag[end..];
+    }
+    out.write_all(rest.as_bytes())?;
+    out.flush()
+}
+
+// Pull the `src` attribute out of an include tag.
+fn include_src(tag: &str) -> Option<&str> {
+    let start = tag.find("src=\"")? + "src=\"".len();
+    let len = tag[start..].find('"')?;
+    Some(&tag[start..start + len])
+}
+
+// Fragments share the page's query string, so only the path picks one.
+fn path_of(src: &str) -> &str {
+    src.split('?').next().unwrap_or(src)
+}
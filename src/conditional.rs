@@ -0,0 +1,59 @@
+//! Conditional GETs for the forecast endpoints of the JSON API, so clients
+//! polling for changes only download a forecast when it's new.
+//!
+//! Each response carries an `ETag`, a hash of its body, and a
+//! `Last-Modified` from when the current conditions were observed. A
+//! request with a matching `If-None-Match`, or without one and with an
+//! `If-Modified-Since` no earlier than the observation, gets a 304 with no
+//! body. Only successful responses are tagged.
+
+use chrono::{DateTime, TimeZone, Utc};
+use fastly::http::{header, StatusCode};
+use fastly::{Request, Response};
+use sha2::{Digest, Sha256};
+
+// Bytes of the body's hash in the tag, which is plenty to tell forecasts apart.
+const TAG_BYTES: usize = 12;
+
+/// `resp` to `req`, tagged with its hash and `observed`, the Unix time the
+/// forecast's current conditions are from, or a 304 if the client has it.
+pub fn respond(req: &Request, observed: i64, mut resp: Response) -> Response {
+    if resp.get_status() != StatusCode::OK {
+        return resp;
+    }
+    let body = resp.take_body_bytes();
+    let etag = format!(
+        "\"{}\"",
+        Sha256::digest(&body)[..TAG_BYTES]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    );
+    let modified = Utc.timestamp_opt(observed, 0).single();
+    resp.set_header(header::ETAG, &etag);
+    if let Some(modified) = modified {
+        resp.set_header(
+            header::LAST_MODIFIED,
+            modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        );
+    }
+
+    let unchanged = match req.get_header_str(header::IF_NONE_MATCH) {
+        Some(tags) => tags.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }),
+        None => req
+            .get_header_str(header::IF_MODIFIED_SINCE)
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .zip(modified)
+            .is_some_and(|(since, modified)| since >= modified),
+    };
+    if unchanged {
+        resp.set_status(StatusCode::NOT_MODIFIED);
+        resp.remove_header(header::CONTENT_TYPE);
+        return resp;
+    }
+    resp.set_body(body);
+    resp
+}
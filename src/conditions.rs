@@ -0,0 +1,86 @@
+//! What the current readings mean in plain words: how strong the sun is and
+//! how long it takes to burn, how the air feels, and how much sky and
+//! distance can be seen. How hot it feels is `weather_helpers::heat_index`.
+
+/// The WHO exposure category for a UV index.
+pub fn uv_risk(uvi: f32) -> &'static str {
+    if uvi < 3.0 {
+        "Low"
+    } else if uvi < 6.0 {
+        "Moderate"
+    } else if uvi < 8.0 {
+        "High"
+    } else if uvi < 11.0 {
+        "Very high"
+    } else {
+        "Extreme"
+    }
+}
+
+/// Roughly how many minutes of unprotected sun it takes to burn, for a UV index
+/// and a Fitzpatrick skin type from 1 (always burns) to 6 (never burns). This
+/// is the time to one minimal erythemal dose, which varies a lot from person to
+/// person, so treat it as an upper bound. `None` when the UV index is too low
+/// to burn or the skin type isn't 1 to 6.
+pub fn safe_exposure_minutes(uvi: f32, skin_type: u8) -> Option<u32> {
+    // Minimal erythemal dose in J/m² for each skin type
+    let med = match skin_type {
+        1 => 200.0,
+        2 => 250.0,
+        3 => 300.0,
+        4 => 450.0,
+        5 => 600.0,
+        6 => 1000.0,
+        _ => return None,
+    };
+    if uvi < 1.0 {
+        return None;
+    }
+
+    // Each point of UV index is 25 mW/m² of erythemally weighted irradiance.
+    Some((med / (uvi * 0.025) / 60.0).floor() as u32)
+}
+
+/// How the air feels for a dew point in °C. The dew point, unlike relative
+/// humidity, doesn't swing with the temperature through the day, so it's the
+/// better guide to how sticky it is.
+pub fn comfort_level(dew_point: f32) -> &'static str {
+    if dew_point < 10.0 {
+        "Dry"
+    } else if dew_point < 16.0 {
+        "Comfortable"
+    } else if dew_point < 21.0 {
+        "Muggy"
+    } else {
+        "Oppressive"
+    }
+}
+
+/// How much of the sky is covered, for a cloud cover in %.
+pub fn cloud_cover(clouds: f32) -> &'static str {
+    if clouds <= 10.0 {
+        "Clear"
+    } else if clouds <= 30.0 {
+        "Mostly clear"
+    } else if clouds <= 60.0 {
+        "Partly cloudy"
+    } else if clouds <= 90.0 {
+        "Mostly cloudy"
+    } else {
+        "Overcast"
+    }
+}
+
+/// How far you can see, for a visibility in metres. The API tops out at 10km,
+/// so anything from there on is good.
+pub fn visibility(metres: f32) -> &'static str {
+    if metres < 1000.0 {
+        "Fog"
+    } else if metres < 4000.0 {
+        "Poor visibility"
+    } else if metres < 10_000.0 {
+        "Moderate visibility"
+    } else {
+        "Good visibility"
+    }
+}
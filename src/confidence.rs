@@ -0,0 +1,97 @@
+//! How far to trust each day's forecast.
+//!
+//! OpenWeather doesn't say how sure it is, so each day is compared with
+//! Open-Meteo's forecast for the same date: the further apart their highs,
+//! lows and chances of rain, the less confident the forecast. Shown on the
+//! daily forecast with the `enable_confidence` flag. Days Open-Meteo doesn't
+//! cover, or all of them when it can't be reached, go without.
+
+use chrono::NaiveDate;
+
+use crate::location::Location;
+use crate::openmeteo::{self, Day};
+use crate::APIResponse;
+
+/// The confidence in each day of a forecast
+pub struct DailyConfidence {
+    /// By the day's `dt` in the forecast
+    days: Vec<(i32, Confidence)>,
+}
+
+impl DailyConfidence {
+    /// Rate the days of a forecast fetched in `units` at `location`.
+    pub fn fetch(
+        location: &Location,
+        api_response: &APIResponse,
+        units: &str,
+    ) -> Result<Self, fastly::Error> {
+        Ok(DailyConfidence::compare(
+            api_response,
+            units,
+            &openmeteo::daily(location)?,
+        ))
+    }
+
+    /// Rate the days of a forecast fetched in `units` against `others`.
+    pub fn compare(api_response: &APIResponse, units: &str, others: &[Day]) -> Self {
+        let celsius = |temp: f32| weather_helpers::to_celsius(temp, units);
+        let date = |dt: i32| -> NaiveDate {
+            weather_helpers::local_time(dt.into(), api_response.timezone_offset).date_naive()
+        };
+        DailyConfidence {
+            days: api_response
+                .daily
+                .iter()
+                .filter_map(|daily| {
+                    let other = others.iter().find(|other| other.date == date(daily.dt))?;
+                    let temp_gap = (celsius(daily.temp.max) - other.max)
+                        .abs()
+                        .max((celsius(daily.temp.min) - other.min).abs());
+                    let pop_gap = daily.pop.zip(other.pop).map(|(pop, other)| pop - other);
+                    Some((daily.dt, forecast_confidence(temp_gap, pop_gap)))
+                })
+                .collect(),
+        }
+    }
+
+    /// The confidence in the day at `dt`, if it could be rated.
+    pub fn on(&self, dt: i32) -> Option<Confidence> {
+        self.days
+            .iter()
+            .find(|(day, _)| *day == dt)
+            .map(|(_, confidence)| *confidence)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+impl Confidence {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Confidence::High => "High confidence",
+            Confidence::Medium => "Medium confidence",
+            Confidence::Low => "Low confidence",
+        }
+    }
+}
+
+/// How far to trust a day's forecast, from how much two independent forecasts
+/// for it disagree: the larger of the gaps between their highs and their lows
+/// in °C, and the gap between their chances of rain from 0 to 1, if both give
+/// one. Models that agree are likelier to be right.
+pub fn forecast_confidence(temp_gap: f32, pop_gap: Option<f32>) -> Confidence {
+    let temp_gap = temp_gap.abs();
+    let pop_gap = pop_gap.map_or(0.0, f32::abs);
+    if temp_gap >= 4.0 || pop_gap >= 0.4 {
+        Confidence::Low
+    } else if temp_gap >= 2.0 || pop_gap >= 0.2 {
+        Confidence::Medium
+    } else {
+        Confidence::High
+    }
+}
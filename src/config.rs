@@ -0,0 +1,51 @@
+//! Service configuration read from Config Stores.
+//!
+//! Every value is optional: preview deployments run without any resources
+//! linked, so callers fall back to sensible defaults when a key (or the whole
+//! store) is missing.
+//!
+//! Values are looked up once per instance and remembered, missing ones
+//! included, so asking again costs no hostcalls. Built for the host, as for
+//! the benchmarks and fuzz targets, there are no stores, so nothing is set.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[cfg(target_arch = "wasm32")]
+use fastly::ConfigStore;
+
+const STORE_NAME: &str = "weather_config";
+
+thread_local! {
+    static VALUES: RefCell<HashMap<(String, String), Option<String>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Look up a value in the `weather_config` store, returning `None` if it isn't set.
+pub fn get(key: &str) -> Option<String> {
+    get_from(STORE_NAME, key)
+}
+
+/// Look up a value in the named Config Store, returning `None` if it isn't set.
+pub fn get_from(store: &str, key: &str) -> Option<String> {
+    let cache_key = (store.to_string(), key.to_string());
+    if let Some(value) = VALUES.with(|values| values.borrow().get(&cache_key).cloned()) {
+        return value;
+    }
+
+    let value = lookup(store, key).filter(|value| !value.trim().is_empty());
+    VALUES.with(|values| values.borrow_mut().insert(cache_key, value.clone()));
+    value
+}
+
+#[cfg(target_arch = "wasm32")]
+fn lookup(store: &str, key: &str) -> Option<String> {
+    ConfigStore::try_open(store)
+        .ok()
+        .and_then(|store| store.get(key))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn lookup(_store: &str, _key: &str) -> Option<String> {
+    None
+}
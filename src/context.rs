@@ -0,0 +1,102 @@
+//! What's known about a request before its handler runs.
+//!
+//! A [`ReqCtx`] is built once per request, after the query string is
+//! sanitized, and handed to whichever route answers it. It has the visitor's
+//! preferences, the feature flags and the timings, and locates the visitor
+//! when a route asks, so no route works any of that out for itself.
+//!
+//! The widget and the background image can be given `lat` and `lon`, and
+//! optionally `city`, by whoever embeds them. Those coordinates stand in for
+//! locating the visitor, so those requests make no geolocation lookup.
+
+use chrono::Utc;
+use fastly::{Error, Request};
+use serde::Deserialize;
+
+use crate::flags::Flags;
+use crate::location::Location;
+use crate::timing::Timings;
+
+#[derive(Deserialize)]
+struct CoordinateParams {
+    lat: Option<f64>,
+    lon: Option<f64>,
+    city: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UnitsParam {
+    units: Option<String>,
+}
+
+/// A request's context, built once and handed to the routes that need it
+pub struct ReqCtx {
+    /// Fastly's id for the request, which ties its log lines together
+    pub request_id: String,
+    pub flags: Flags,
+    pub timings: Timings,
+    /// The units asked for with `?units=`, metric otherwise
+    pub units: String,
+    /// Whether the visitor sent `Save-Data: on`
+    pub save_data: bool,
+    /// When the request arrived, in Unix time
+    pub now: i64,
+    /// The place the request named itself, on routes that take one
+    given: Option<(f64, f64, String)>,
+}
+
+impl ReqCtx {
+    pub fn new(req: &Request) -> Self {
+        let units = req
+            .get_query::<UnitsParam>()
+            .ok()
+            .and_then(|param| param.units)
+            .unwrap_or_else(|| String::from("metric"));
+        let given = match req.get_path() {
+            "/widget" | "/bg-image.jpg" => given_place(req),
+            _ => None,
+        };
+        ReqCtx {
+            request_id: req.get_client_request_id().unwrap_or_default().to_string(),
+            flags: Flags::for_request(req),
+            timings: Timings::new(),
+            units,
+            save_data: req
+                .get_header_str("Save-Data")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("on")),
+            now: Utc::now().timestamp(),
+            given,
+        }
+    }
+
+    /// Whether the request named its own place, rather than being answered
+    /// for wherever the visitor is.
+    pub fn has_coordinates(&self) -> bool {
+        self.given.is_some()
+    }
+
+    /// Where to answer the request for: the place it named, or else the
+    /// client's location as [`Location::for_client`] finds it, timed as
+    /// `geo`. Named places are coarsened for privacy mode just as the
+    /// visitor's own are.
+    pub fn location(&mut self, req: &Request) -> Result<Option<Location>, Error> {
+        match &self.given {
+            Some((lat, lon, city)) => {
+                let mut location = Location::at(*lat, *lon, city.clone());
+                location.coarsen();
+                Ok(Some(location))
+            }
+            None => self.timings.time("geo", || Location::for_client(req)),
+        }
+    }
+}
+
+// The `lat` and `lon` in the query string, when both are there and in range.
+fn given_place(req: &Request) -> Option<(f64, f64, String)> {
+    let params = req.get_query::<CoordinateParams>().ok()?;
+    let (lat, lon) = (params.lat?, params.lon?);
+    if lat.abs() > 90.0 || lon.abs() > 180.0 {
+        return None;
+    }
+    Some((lat, lon, params.city.unwrap_or_default()))
+}
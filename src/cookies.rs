@@ -0,0 +1,13 @@
+//! Minimal request cookie parsing.
+
+use fastly::http::header;
+use fastly::Request;
+
+/// Get the value of the cookie called `name`, if the client sent one.
+pub fn get<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.get_header_str(header::COOKIE)?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
@@ -0,0 +1,64 @@
+//! Content Security Policy for the weather page.
+//!
+//! The page runs the scripts it loads from this service and one inline
+//! script, which wires up the buttons. The inline script
+//! carries a nonce that's new on every response, and the
+//! `Content-Security-Policy` header only lets scripts with that nonce, or
+//! from our own origin, run. Markup that gets into the page some other way,
+//! including inline event handlers, is blocked. The page's script tags also
+//! carry Subresource Integrity hashes, so a tampered copy falls flat too.
+//!
+//! The policy only restricts scripts, plugins and `<base>`; styles and
+//! images still come from wherever the page says.
+
+use fastly::Response;
+use sha2::{Digest, Sha384};
+
+use crate::shared;
+
+const HEADER: &str = "Content-Security-Policy";
+
+const NONCE_BYTES: usize = 16;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A fresh nonce for one response.
+pub fn nonce() -> String {
+    base64(&shared::random_bytes::<NONCE_BYTES>())
+}
+
+/// Send the policy for a page rendered with `nonce`.
+pub fn tag(resp: &mut Response, nonce: &str) {
+    resp.set_header(
+        HEADER,
+        format!(
+            "script-src 'self' 'nonce-{}'; object-src 'none'; base-uri 'none'",
+            nonce
+        ),
+    );
+}
+
+/// The Subresource Integrity value for `script`, for its tag's `integrity`
+/// attribute.
+pub fn integrity(script: &str) -> String {
+    format!("sha384-{}", base64(&Sha384::digest(script)))
+}
+
+// Standard, padded base64, which is what both nonces and SRI hashes use.
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
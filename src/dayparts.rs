@@ -0,0 +1,165 @@
+//! The forecast by part of the day: morning, afternoon, evening and night,
+//! as most weather apps lay out the next day or so.
+//!
+//! The next 24 hours of the hourly forecast are split up on the location's
+//! clock, each part with its low, high and the conditions seen most often.
+//! Morning runs from `day_start_hour` (6 unless set) to noon and the
+//! afternoon to 6pm; the evening ends, and the night starts, at
+//! `night_start_hour` (22 unless set). Both are read from the
+//! `weather_config` store, and ignored if they'd leave a part empty.
+
+use chrono::Timelike;
+use serde::Serialize;
+
+use weather_helpers::Locale;
+
+use crate::iconset::{self, Icon};
+use crate::precision::Precision;
+use crate::{config, APIResponse};
+
+const HOURS: usize = 24;
+
+const DEFAULT_DAY_START: u32 = 6;
+const DEFAULT_NIGHT_START: u32 = 22;
+const NOON: u32 = 12;
+const EVENING: u32 = 18;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl Part {
+    fn name(&self, is_today: bool) -> &'static str {
+        match (self, is_today) {
+            (Part::Morning, true) => "This morning",
+            (Part::Afternoon, true) => "This afternoon",
+            (Part::Evening, true) => "This evening",
+            (Part::Night, true) => "Tonight",
+            (Part::Morning, false) => "Tomorrow morning",
+            (Part::Afternoon, false) => "Tomorrow afternoon",
+            (Part::Evening, false) => "Tomorrow evening",
+            (Part::Night, false) => "Tomorrow night",
+        }
+    }
+}
+
+/// Context for the day parts fragment
+#[derive(Serialize)]
+pub struct DayParts {
+    parts: Vec<DayPart>,
+}
+
+/// One part of the day
+#[derive(Serialize)]
+struct DayPart {
+    name: &'static str,
+    low: String,
+    high: String,
+    icon: Icon,
+    description: String,
+}
+
+/// Where the day's parts begin, from the configuration
+struct Boundaries {
+    day_start: u32,
+    night_start: u32,
+}
+
+impl Boundaries {
+    fn configured() -> Self {
+        let hour = |key: &str, range: std::ops::Range<u32>, default: u32| {
+            config::get(key)
+                .and_then(|hour| hour.trim().parse::<u32>().ok())
+                .filter(|hour| range.contains(hour))
+                .unwrap_or(default)
+        };
+        Boundaries {
+            day_start: hour("day_start_hour", 0..NOON, DEFAULT_DAY_START),
+            night_start: hour("night_start_hour", EVENING + 1..24, DEFAULT_NIGHT_START),
+        }
+    }
+
+    fn part(&self, hour: u32) -> Part {
+        if (self.day_start..NOON).contains(&hour) {
+            Part::Morning
+        } else if (NOON..EVENING).contains(&hour) {
+            Part::Afternoon
+        } else if (EVENING..self.night_start).contains(&hour) {
+            Part::Evening
+        } else {
+            Part::Night
+        }
+    }
+}
+
+impl DayParts {
+    /// The parts of the next 24 hours in `api_response`, starting with the
+    /// one under way. Parts starting after midnight are tomorrow's, so the
+    /// small hours are still tonight. `None` without an hourly forecast.
+    pub fn new(api_response: &APIResponse, locale: Locale) -> Option<Self> {
+        let boundaries = Boundaries::configured();
+        let precision = Precision::configured();
+        let icons = iconset::configured();
+        let offset = api_response.timezone_offset;
+        let local = |dt: i64| weather_helpers::local_time(dt, offset);
+        let today = local(api_response.current.dt).date_naive();
+
+        // Runs of consecutive hours in the same part of the day
+        let mut runs: Vec<(Part, bool, Vec<_>)> = Vec::new();
+        for hour in api_response.hourly.iter().take(HOURS) {
+            let time = local(hour.dt);
+            let part = boundaries.part(time.hour());
+            match runs.last_mut() {
+                Some((last, _, hours)) if *last == part => hours.push(hour),
+                _ => runs.push((part, time.date_naive() == today, vec![hour])),
+            }
+        }
+        if runs.is_empty() {
+            return None;
+        }
+
+        let parts = runs
+            .into_iter()
+            .map(|(part, is_today, hours)| {
+                let low = hours.iter().map(|hour| hour.temp).fold(f32::MAX, f32::min);
+                let high = hours.iter().map(|hour| hour.temp).fold(f32::MIN, f32::max);
+
+                // The conditions reported for the most hours, the earliest on a tie
+                let conditions: Vec<_> = hours
+                    .iter()
+                    .filter_map(|hour| hour.weather.first())
+                    .collect();
+                let dominant = conditions.iter().max_by_key(|weather| {
+                    let count = conditions
+                        .iter()
+                        .filter(|other| other.icon == weather.icon)
+                        .count();
+                    let first = conditions
+                        .iter()
+                        .position(|other| other.icon == weather.icon);
+                    (count, std::cmp::Reverse(first))
+                });
+
+                DayPart {
+                    name: part.name(is_today),
+                    low: precision.temp(locale, low),
+                    high: precision.temp(locale, high),
+                    icon: icons.icon(
+                        dominant.map_or(0, |weather| weather.id),
+                        dominant.map_or("", |weather| weather.icon.as_str()),
+                        dominant.map_or("", |weather| weather.description.as_str()),
+                    ),
+                    description: dominant
+                        .map(|weather| weather.description.clone())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Some(DayParts { parts })
+    }
+}
@@ -0,0 +1,48 @@
+//! Which layout suits the visitor's device, going by Client Hints.
+//!
+//! Chromium sends `Sec-CH-UA-Mobile` with every request. Once a response
+//! has asked for it with `Accept-CH`, it sends `Sec-CH-Viewport-Width` too,
+//! and a narrow viewport gets the mobile layout even on a desktop browser.
+//! Browsers without Client Hints are judged by the `Mobi` token that mobile
+//! User-Agents carry.
+
+use fastly::http::header;
+use fastly::{Request, Response};
+
+use crate::view::Device;
+
+/// The hints pages ask for, and vary on
+const HINTS: &str = "Sec-CH-UA-Mobile, Sec-CH-Viewport-Width";
+
+// Narrower than this, the two-column layout doesn't fit.
+const MOBILE_MAX_WIDTH: u32 = 640;
+
+/// The device behind `req`.
+pub fn detect(req: &Request) -> Device {
+    let width = req
+        .get_header_str("Sec-CH-Viewport-Width")
+        .or_else(|| req.get_header_str("Viewport-Width"))
+        .and_then(|width| width.trim().parse::<u32>().ok());
+    if width.is_some_and(|width| width < MOBILE_MAX_WIDTH) {
+        return Device::Mobile;
+    }
+
+    match req.get_header_str("Sec-CH-UA-Mobile").map(str::trim) {
+        Some("?1") => Device::Mobile,
+        Some(_) => Device::Desktop,
+        None => match req.get_header_str(header::USER_AGENT) {
+            Some(agent) if agent.contains("Mobi") => Device::Mobile,
+            _ => Device::Desktop,
+        },
+    }
+}
+
+/// Ask for the hints on `resp`, and say the page depends on them.
+pub fn tag(resp: &mut Response) {
+    resp.set_header("Accept-CH", HINTS);
+    let vary = match resp.get_header_str(header::VARY) {
+        Some(vary) => format!("{}, {}, User-Agent", vary, HINTS),
+        None => format!("{}, User-Agent", HINTS),
+    };
+    resp.set_header(header::VARY, vary);
+}
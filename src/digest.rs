@@ -0,0 +1,781 @@
+//! Morning forecast emails, for visitors who sign up at `/api/digest`.
+//!
+//! `POST /api/digest` with `{"email": "..."}` signs an address up for the
+//! forecast where the visitor is, as the page has it, or at `lat` and `lon`
+//! if the body gives them, to two decimal places. `units` picks the units,
+//! metric unless given. The 202 holds an `id` that unsubscribes, with
+//! `DELETE /api/digest?id=<id>`; every email carries it in a one-click
+//! `List-Unsubscribe` header too, which mail apps `POST` to
+//! `/api/digest/unsubscribe?id=<id>`.
+//!
+//! Nothing but a confirmation is sent until the address's owner says so. The
+//! confirmation email links to `/api/digest/confirm` with the id, signed
+//! like share links under `share_key`, and the sign-up stays pending until
+//! that link is opened. Pending sign-ups are forgotten after
+//! [`CONFIRM_TTL_SECS`], and an address is sent at most one confirmation
+//! an hour. Confirming replaces any earlier sign-up for the same address.
+//! Pending sign-ups are capped at [`MAX_PENDING`], apart from the
+//! [`MAX_SUBSCRIBERS`] confirmed ones, so made-up addresses can't crowd out
+//! real ones, and a client may sign up [`MAX_SIGN_UPS_PER_CLIENT`] addresses
+//! an hour.
+//!
+//! Like webhooks ([`crate::subscriptions`]), each sign-up is its own
+//! document, listed in a [`kv::Index`] of pending or of confirmed ones, so
+//! sign-ups, unsubscribes and dispatch runs only write what they change.
+//! Each address's sign-ups are noted under the hash of it.
+//!
+//! Each run of [`crate::dispatch`] sends the confirmations that couldn't be
+//! sent at sign-up, and checks up to [`MAX_CHECKS`] confirmed subscribers,
+//! carrying on where the last run stopped, emailing each one for whom it's
+//! past `digest_hour` (7 unless set) where they are and who hasn't had the
+//! day's email yet. It's the page's template context rendered as plain text,
+//! with day names in the language their browser asked for when they signed
+//! up.
+//!
+//! Emails go to the transactional mail API at `mail_api_url`, from
+//! `mail_from`, both in `weather_config`, with the token stored as
+//! `mail_api_token` in the `weather_secrets` Secret Store as a bearer token.
+//! Each is a JSON `POST`:
+//!
+//! ```json
+//! {"from": "weather@example.com", "to": "visitor@example.org",
+//!  "subject": "...", "text": "...", "headers": {"List-Unsubscribe": "..."}}
+//! ```
+//!
+//! which most such APIs take as it is, or a small relay can translate.
+//! Without all three, and `share_key`, sign-ups are refused and nothing is
+//! sent.
+
+use std::io::Read;
+
+use chrono::{DateTime, FixedOffset, Timelike, Utc};
+use fastly::http::{header, Method, StatusCode, Url};
+use fastly::secret_store::SecretStore;
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use weather_helpers::Locale;
+
+use crate::dispatch::{Forecasts, Report};
+use crate::experiments::Assignments;
+use crate::location::Location;
+use crate::outbound::{self, Outcome};
+use crate::view::{self, Device, PageOptions, Place, View};
+use crate::{
+    config, dayparts, kv, precipitation, problem, redirect, share, shared, signing, APIResponse,
+};
+
+/// How many confirmed addresses the service keeps
+pub const MAX_SUBSCRIBERS: usize = 500;
+
+/// How many sign-ups may be waiting to be confirmed at once
+pub const MAX_PENDING: usize = 50;
+
+/// How many addresses one client may sign up an hour
+pub const MAX_SIGN_UPS_PER_CLIENT: usize = 3;
+
+/// How many confirmed subscribers a dispatch run checks
+pub const MAX_CHECKS: usize = 200;
+
+/// How long a sign-up waits to be confirmed
+pub const CONFIRM_TTL_SECS: i64 = 2 * 24 * 3600;
+
+// Signing up again sooner doesn't send another confirmation.
+const RESEND_SECS: i64 = 3600;
+
+const CONFIRMED: kv::Index = kv::Index::new("digest_subscriptions");
+const PENDING: kv::Index = kv::Index::new("digest_pending");
+const CURSOR_KEY: &str = "digest_subscriptions_cursor";
+const RECORD_PREFIX: &str = "digest_subscriber:";
+const ADDRESS_PREFIX: &str = "digest_address:";
+const CLIENTS: kv::ClientLimit =
+    kv::ClientLimit::new("digest_client:", MAX_SIGN_UPS_PER_CLIENT, RESEND_SECS);
+const SECRET_STORE: &str = "weather_secrets";
+const SECRET_NAME: &str = "mail_api_token";
+const DEFAULT_DIGEST_HOUR: u32 = 7;
+const UNITS: &[&str] = &["metric", "imperial", "standard"];
+
+// A sign-up is an address and a few numbers.
+const MAX_BODY_BYTES: u64 = 2048;
+const MAX_EMAIL_LEN: usize = 254;
+
+/// The body of a sign-up
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignUp {
+    email: String,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    units: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfirmParams {
+    id: String,
+    sig: String,
+}
+
+/// A signed-up address
+#[derive(Deserialize, Serialize)]
+struct Subscriber {
+    id: String,
+    /// The shard of the pending or confirmed list that lists it
+    index: String,
+    email: String,
+    lat: f64,
+    lon: f64,
+    city: String,
+    units: String,
+    /// The code of the locale the email is written in
+    lang: String,
+    /// The page the email links to for the full forecast
+    page_url: String,
+    unsubscribe_url: String,
+    /// Seconds east of UTC where they are, once a forecast has said
+    #[serde(default)]
+    timezone_offset: Option<i32>,
+    /// The local date of the last email, as `YYYY-MM-DD`
+    #[serde(default)]
+    last_sent: Option<String>,
+    /// When they signed up, in Unix seconds
+    #[serde(default)]
+    signed_up: i64,
+    /// Whether they've opened the confirmation link, and so get digests
+    #[serde(default)]
+    confirmed: bool,
+    /// The link that confirms the sign-up, until it's been emailed
+    #[serde(default)]
+    confirm_url: Option<String>,
+}
+
+/// The sign-ups for one address, by id, so signing up again finds them
+#[derive(Default, Deserialize, Serialize)]
+struct Address {
+    pending: Option<String>,
+    confirmed: Option<String>,
+}
+
+impl Address {
+    fn forget(&mut self, id: &str) {
+        if self.pending.as_deref() == Some(id) {
+            self.pending = None;
+        }
+        if self.confirmed.as_deref() == Some(id) {
+            self.confirmed = None;
+        }
+    }
+}
+
+/// The mail API and who emails are from
+struct Mailer {
+    url: Url,
+    from: String,
+    token: String,
+}
+
+impl Mailer {
+    fn load() -> Option<Self> {
+        let url = config::get("mail_api_url")
+            .and_then(|url| Url::parse(url.trim()).ok())
+            .filter(outbound::is_sendable)?;
+        let from = config::get("mail_from")
+            .map(|from| from.trim().to_string())
+            .filter(|from| is_email(from))?;
+        let secret = SecretStore::open(SECRET_STORE).ok()?.get(SECRET_NAME)?;
+        let token = String::from_utf8(secret.plaintext().to_vec()).ok()?;
+        Some(Mailer {
+            url,
+            from,
+            token: token.trim().to_string(),
+        })
+        .filter(|mailer| !mailer.token.is_empty())
+    }
+
+    fn send(&self, subscriber: &Subscriber, subject: &str, text: &str) -> Outcome {
+        let body = json!({
+            "from": self.from,
+            "to": subscriber.email,
+            "subject": subject,
+            "text": text,
+            "headers": {
+                "List-Unsubscribe": format!("<{}>", subscriber.unsubscribe_url),
+                "List-Unsubscribe-Post": "List-Unsubscribe=One-Click",
+            },
+        });
+        outbound::send(
+            Request::post(self.url.clone())
+                .with_header(header::AUTHORIZATION, format!("Bearer {}", self.token))
+                .with_content_type(fastly::mime::APPLICATION_JSON)
+                .with_body(body.to_string()),
+        )
+    }
+}
+
+/// Serve `/api/digest`: sign an address up, or remove one.
+pub fn handle(req: &mut Request) -> Result<Response, Error> {
+    if req.get_method() == Method::DELETE {
+        return unsubscribe(req);
+    }
+
+    let mut body = Vec::new();
+    let read = req
+        .take_body()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body);
+    if read.is_err() || body.len() as u64 > MAX_BODY_BYTES {
+        return Ok(problem::response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "The body is too big",
+        ));
+    }
+    let sign_up = match serde_json::from_slice::<SignUp>(&body) {
+        Ok(sign_up) => sign_up,
+        Err(_) => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Send the email address as JSON",
+            ))
+        }
+    };
+    if !is_email(&sign_up.email) {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "That isn't an email address",
+        ));
+    }
+    let units = sign_up
+        .units
+        .clone()
+        .unwrap_or_else(|| String::from("metric"));
+    if !UNITS.contains(&units.as_str()) {
+        return Ok(problem::response(StatusCode::BAD_REQUEST, "Unknown units"));
+    }
+    let (mailer, key) = match (Mailer::load(), share::secret_key()) {
+        (Some(mailer), Some(key)) => (mailer, key),
+        _ => return Ok(unavailable()),
+    };
+
+    let location = match (sign_up.lat, sign_up.lon) {
+        (Some(lat), Some(lon))
+            if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) =>
+        {
+            Location::at(lat, lon, String::new())
+        }
+        (None, None) => match Location::for_client(req)? {
+            Some(location) => location,
+            None => {
+                return Ok(problem::response(
+                    StatusCode::BAD_REQUEST,
+                    "Choose a location, or send lat and lon",
+                ))
+            }
+        },
+        _ => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "lat or lon is out of range",
+            ))
+        }
+    };
+    let locale = Locale::negotiate(
+        req.get_header_str(header::ACCEPT_LANGUAGE),
+        location.country_code(),
+    );
+
+    // Signing up again, say from somewhere new, replaces the old sign-up
+    // once it's confirmed. Until then there's one pending sign-up an address.
+    let now = Utc::now().timestamp();
+    let earlier = load_address(&sign_up.email)
+        .pending
+        .as_deref()
+        .and_then(load);
+    if earlier
+        .as_ref()
+        .is_some_and(|earlier| now - earlier.signed_up < RESEND_SECS)
+    {
+        return Ok(too_many("A confirmation was sent to that address lately"));
+    }
+    let pending = PENDING
+        .count()
+        .saturating_sub(usize::from(earlier.is_some()));
+    if pending >= MAX_PENDING {
+        return Ok(problem::response(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "Too many sign-ups are waiting to be confirmed; try again later",
+        ));
+    }
+    if CONFIRMED.count() >= MAX_SUBSCRIBERS {
+        return Ok(problem::response(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "There are too many sign-ups",
+        ));
+    }
+    if !CLIENTS.note(req, &key, now) {
+        return Ok(too_many(
+            "There have been too many sign-ups from your network lately",
+        ));
+    }
+
+    let id = shared::new_id();
+    let unsubscribe_url = format!(
+        "{}?id={}",
+        redirect::canonical_url(req, "/api/digest/unsubscribe", "metric"),
+        id
+    );
+    let confirm_url = format!(
+        "{}?id={}&sig={}",
+        redirect::canonical_url(req, "/api/digest/confirm", "metric"),
+        id,
+        signing::sign(&key, confirm_message(&id).as_bytes())
+    );
+    let mut subscriber = Subscriber {
+        id: id.clone(),
+        index: PENDING.random_shard(),
+        email: sign_up.email,
+        lat: shared::round_coord(location.latitude()),
+        lon: shared::round_coord(location.longitude()),
+        city: location.city().to_string(),
+        page_url: redirect::canonical_url(req, "/", &units),
+        units,
+        lang: locale.code().to_string(),
+        unsubscribe_url,
+        timezone_offset: None,
+        last_sent: None,
+        signed_up: now,
+        confirmed: false,
+        confirm_url: Some(confirm_url),
+    };
+    // If it can't be sent now, the next dispatch run tries again.
+    if let Outcome::Failed(err) = send_confirmation(&mailer, &mut subscriber) {
+        println!("Couldn't send a digest confirmation: {}", err);
+    }
+    if !save(&subscriber) || !PENDING.add(&subscriber.index, &id) {
+        return Ok(unavailable());
+    }
+    if let Some(earlier) = earlier {
+        remove(&earlier);
+    }
+    let mut address = load_address(&subscriber.email);
+    address.pending = Some(id.clone());
+    save_address(&subscriber.email, &address);
+    println!(
+        "Signed up an address for the digest, {} waiting to be confirmed",
+        pending + 1
+    );
+
+    Ok(Response::from_body(json!({ "id": id }).to_string())
+        .with_status(StatusCode::ACCEPTED)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "no-store"))
+}
+
+/// Serve `/api/digest/unsubscribe`, and `DELETE /api/digest`.
+pub fn unsubscribe(req: &Request) -> Result<Response, Error> {
+    let params: IdParams = req.get_query()?;
+    let id = params.id.unwrap_or_default();
+    let subscriber = match Some(id.as_str()).filter(|id| is_id(id)).and_then(load) {
+        Some(subscriber) => subscriber,
+        None => {
+            return Ok(problem::response(
+                StatusCode::NOT_FOUND,
+                "There's no such sign-up",
+            ))
+        }
+    };
+    if !remove(&subscriber) {
+        return Ok(unavailable());
+    }
+    Ok(
+        Response::from_status(StatusCode::NO_CONTENT)
+            .with_header(header::CACHE_CONTROL, "no-store"),
+    )
+}
+
+/// Serve `/api/digest/confirm`, the link in the confirmation email.
+pub fn confirm(req: &Request) -> Result<Response, Error> {
+    let params = match req.get_query::<ConfirmParams>() {
+        Ok(params) => params,
+        Err(_) => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "The link is missing its id or signature",
+            ))
+        }
+    };
+    let key = match share::secret_key() {
+        Some(key) => key,
+        None => return Ok(unavailable()),
+    };
+    if !is_id(&params.id)
+        || !signing::verify(&key, confirm_message(&params.id).as_bytes(), &params.sig)
+    {
+        return Ok(problem::response(
+            StatusCode::FORBIDDEN,
+            "The link isn't one we sent",
+        ));
+    }
+
+    let now = Utc::now().timestamp();
+    let mut subscriber = match load(&params.id)
+        .filter(|subscriber| subscriber.confirmed || now - subscriber.signed_up < CONFIRM_TTL_SECS)
+    {
+        Some(subscriber) if subscriber.confirmed => return Ok(confirmed()),
+        Some(subscriber) => subscriber,
+        None => {
+            return Ok(problem::response(
+                StatusCode::NOT_FOUND,
+                "That sign-up has expired; sign up again",
+            ))
+        }
+    };
+    let earlier = load_address(&subscriber.email)
+        .confirmed
+        .as_deref()
+        .and_then(load);
+    if earlier.is_none() && CONFIRMED.count() >= MAX_SUBSCRIBERS {
+        return Ok(problem::response(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "There are too many sign-ups; try again later",
+        ));
+    }
+
+    // Listed as confirmed before it's taken off the pending list, so a
+    // failure part way leaves it listed somewhere.
+    let pending_index = std::mem::replace(&mut subscriber.index, CONFIRMED.random_shard());
+    subscriber.confirmed = true;
+    subscriber.confirm_url = None;
+    if !save(&subscriber) || !CONFIRMED.add(&subscriber.index, &subscriber.id) {
+        return Ok(unavailable());
+    }
+    PENDING.remove(&[(&pending_index, &subscriber.id)]);
+    if let Some(earlier) = earlier {
+        remove(&earlier);
+    }
+    let mut address = load_address(&subscriber.email);
+    address.forget(&subscriber.id);
+    address.confirmed = Some(subscriber.id.clone());
+    save_address(&subscriber.email, &address);
+    Ok(confirmed())
+}
+
+/// Email the next [`MAX_CHECKS`] subscribers if it's their morning, and send
+/// the confirmations that couldn't be sent at sign-up.
+pub fn dispatch(forecasts: &mut Forecasts) -> Report {
+    let pending = PENDING.entries();
+    let listed = CONFIRMED.entries();
+    let mut report = Report::default();
+    if pending.is_empty() && listed.is_empty() {
+        return report;
+    }
+    let mailer = match Mailer::load() {
+        Some(mailer) => mailer,
+        None => {
+            println!("Mail isn't set up, so no digests were sent");
+            return report;
+        }
+    };
+    let page = kv::next_page(&listed, CURSOR_KEY, MAX_CHECKS);
+    report.checked = pending.len() + page.len();
+    let hour = digest_hour();
+    let now = Utc::now().timestamp();
+
+    // Ids to take out of the pending list, because their sign-up has gone
+    let mut gone = Vec::new();
+    for (index, id) in &pending {
+        let mut subscriber = match load(id) {
+            Some(subscriber) => subscriber,
+            None => {
+                gone.push((index, id));
+                continue;
+            }
+        };
+        // Sign-ups nobody confirmed are forgotten.
+        if now - subscriber.signed_up >= CONFIRM_TTL_SECS {
+            if remove(&subscriber) {
+                report.removed += 1;
+            }
+            continue;
+        }
+        if subscriber.confirm_url.is_none() || report.is_full() {
+            continue;
+        }
+        match send_confirmation(&mailer, &mut subscriber) {
+            Outcome::Sent => {
+                report.sent += 1;
+                save_after_dispatch(&subscriber);
+            }
+            Outcome::Gone => report.failed += 1,
+            Outcome::Failed(err) => {
+                println!("Couldn't send a digest confirmation: {}", err);
+                report.failed += 1;
+            }
+        }
+    }
+    PENDING.remove(&gone);
+
+    // And from the confirmed list
+    let mut gone = Vec::new();
+    for (index, id) in page {
+        let mut subscriber = match load(id) {
+            Some(subscriber) => subscriber,
+            None => {
+                gone.push((index, id));
+                continue;
+            }
+        };
+        // Until a forecast has said where they are in the day, fetch one to find out.
+        if let Some(offset) = subscriber.timezone_offset {
+            if !is_due(&subscriber, offset, hour) {
+                continue;
+            }
+        }
+        if report.is_full() {
+            report.deferred += 1;
+            continue;
+        }
+        let api_response = match forecasts.fetch(subscriber.lat, subscriber.lon, &subscriber.units)
+        {
+            Some(api_response) => api_response,
+            None => {
+                report.deferred += 1;
+                continue;
+            }
+        };
+        let mut changed = false;
+        if subscriber.timezone_offset != Some(api_response.timezone_offset) {
+            subscriber.timezone_offset = Some(api_response.timezone_offset);
+            changed = true;
+        }
+        if is_due(&subscriber, api_response.timezone_offset, hour) {
+            let local =
+                weather_helpers::local_time(Utc::now().timestamp(), api_response.timezone_offset);
+            let email = render(&subscriber, api_response, local);
+            // The first line is the subject.
+            let (subject, text) = email.split_once('\n').unwrap_or((&email, ""));
+            match mailer.send(&subscriber, subject, text.trim_start()) {
+                Outcome::Sent => {
+                    report.sent += 1;
+                    subscriber.last_sent = Some(local.format("%Y-%m-%d").to_string());
+                    changed = true;
+                }
+                // A 404 from the mail API says nothing about the address.
+                Outcome::Gone => report.failed += 1,
+                Outcome::Failed(err) => {
+                    println!("Couldn't send a digest: {}", err);
+                    report.failed += 1;
+                }
+            }
+        }
+        if changed {
+            save_after_dispatch(&subscriber);
+        }
+    }
+    CONFIRMED.remove(&gone);
+    report
+}
+
+// Email `subscriber` the link that confirms their sign-up. Once it's sent,
+// the link is forgotten, so it isn't sent again.
+fn send_confirmation(mailer: &Mailer, subscriber: &mut Subscriber) -> Outcome {
+    let confirm_url = match &subscriber.confirm_url {
+        Some(confirm_url) => confirm_url,
+        None => return Outcome::Sent,
+    };
+    let text = format!(
+        "Someone, hopefully you, asked for the morning forecast for {} to be \
+         emailed to this address. Open this link to start getting it:\n\n{}\n\n\
+         If it wasn't you, ignore this email and you won't hear from us again.",
+        place_name(&subscriber.city),
+        confirm_url
+    );
+    let outcome = mailer.send(subscriber, "Confirm your morning forecast", &text);
+    if let Outcome::Sent = outcome {
+        subscriber.confirm_url = None;
+    }
+    outcome
+}
+
+fn place_name(city: &str) -> &str {
+    if city.is_empty() {
+        "your area"
+    } else {
+        city
+    }
+}
+
+// What's signed in a confirmation link. Share links are signed under the
+// same key, but their messages always start with a latitude.
+fn confirm_message(id: &str) -> String {
+    format!("digest-confirm\n{}", id)
+}
+
+fn confirmed() -> Response {
+    Response::from_body("You're signed up. The forecast arrives each morning.\n")
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_PLAIN_UTF_8)
+        .with_header(header::CACHE_CONTROL, "no-store")
+}
+
+/// The local hour after which the day's digest goes out, from `digest_hour`.
+pub fn digest_hour() -> u32 {
+    config::get("digest_hour")
+        .and_then(|hour| hour.trim().parse::<u32>().ok())
+        .filter(|hour| *hour < 24)
+        .unwrap_or(DEFAULT_DIGEST_HOUR)
+}
+
+// Whether it's past the digest hour where `subscriber` is, and they haven't
+// had today's email.
+fn is_due(subscriber: &Subscriber, offset: i32, hour: u32) -> bool {
+    let local = weather_helpers::local_time(Utc::now().timestamp(), offset);
+    local.hour() >= hour
+        && subscriber.last_sent.as_deref() != Some(local.format("%Y-%m-%d").to_string().as_str())
+}
+
+// The email for `subscriber`, subject first.
+fn render(
+    subscriber: &Subscriber,
+    api_response: APIResponse,
+    local: DateTime<FixedOffset>,
+) -> String {
+    let experiments = Assignments::default();
+    let place = Place {
+        city: subscriber.city.clone(),
+        latitude: subscriber.lat,
+        longitude: subscriber.lon,
+        is_default: false,
+        map_url: None,
+    };
+    let locale = Locale::negotiate(Some(&subscriber.lang), "");
+    let day_parts = dayparts::DayParts::new(&api_response, locale);
+    let precipitation = precipitation::PrecipitationTotals::new(&api_response, &subscriber.units);
+    view::generate_view(
+        api_response,
+        &place,
+        local,
+        PageOptions {
+            units: &subscriber.units,
+            view: View::Digest,
+            experiments: &experiments,
+            canonical_url: &subscriber.page_url,
+            show_radar: false,
+            marine: None,
+            fire: None,
+            astronomy: None,
+            garden: None,
+            exercise: None,
+            laundry: None,
+            roads: None,
+            confidence: None,
+            chart: None,
+            wind_rose: None,
+            precipitation,
+            weekend: None,
+            day_parts,
+            pressure_trend: None,
+            show_details: false,
+            skin_type: None,
+            locale,
+            device: Device::Desktop,
+            nonce: "",
+            save_data: false,
+        },
+    )
+}
+
+// Enough to catch typos; the mail API has the last word.
+fn is_email(email: &str) -> bool {
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    email.len() <= MAX_EMAIL_LEN
+        && !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email
+            .chars()
+            .any(|char| char.is_whitespace() || char.is_control() || "<>\",;()[]\\".contains(char))
+}
+
+fn load(id: &str) -> Option<Subscriber> {
+    kv::get_json(&record_key(id))
+}
+
+fn save(subscriber: &Subscriber) -> bool {
+    kv::put_json(&record_key(&subscriber.id), subscriber)
+}
+
+fn save_after_dispatch(subscriber: &Subscriber) {
+    if !save(subscriber) {
+        println!("Couldn't save a digest sign-up after dispatching");
+    }
+}
+
+// Delete `subscriber`, take it off its list and forget it in its address's
+// sign-ups, returning whether it was deleted.
+fn remove(subscriber: &Subscriber) -> bool {
+    if !kv::delete(&record_key(&subscriber.id)) {
+        return false;
+    }
+    let listed_in = if subscriber.confirmed {
+        &CONFIRMED
+    } else {
+        &PENDING
+    };
+    listed_in.remove(&[(&subscriber.index, &subscriber.id)]);
+    let mut address = load_address(&subscriber.email);
+    address.forget(&subscriber.id);
+    save_address(&subscriber.email, &address);
+    true
+}
+
+fn load_address(email: &str) -> Address {
+    kv::get_json(&address_key(email)).unwrap_or_default()
+}
+
+fn save_address(email: &str, address: &Address) {
+    let key = address_key(email);
+    let saved = if address.pending.is_none() && address.confirmed.is_none() {
+        kv::delete(&key)
+    } else {
+        kv::put_json(&key, address)
+    };
+    if !saved {
+        println!("Couldn't save an address's digest sign-ups");
+    }
+}
+
+// Addresses are keyed by their hash, lowercased, so the keys don't show them
+// and any case finds the same sign-ups.
+fn address_key(email: &str) -> String {
+    format!(
+        "{}{}",
+        ADDRESS_PREFIX,
+        signing::sha256(email.to_ascii_lowercase().as_bytes())
+    )
+}
+
+fn record_key(id: &str) -> String {
+    format!("{}{}", RECORD_PREFIX, id)
+}
+
+// Ids are hex, so there's no reaching other keys through them.
+fn is_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn too_many(detail: &str) -> Response {
+    problem::response(StatusCode::TOO_MANY_REQUESTS, detail)
+        .with_header(header::RETRY_AFTER, RESEND_SECS.to_string())
+}
+
+fn unavailable() -> Response {
+    problem::response(StatusCode::SERVICE_UNAVAILABLE, "The digest isn't set up")
+}
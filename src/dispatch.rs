@@ -0,0 +1,130 @@
+//! `POST /internal/dispatch`, which sends everything clients have asked to
+//! be sent: webhooks ([`crate::subscriptions`]), Web Push ([`crate::push`]),
+//! morning emails ([`crate::digest`]) and ntfy or Gotify notifications
+//! ([`crate::notify`]).
+//!
+//! It needs the admin token, and is meant to be called by a scheduler every
+//! few minutes. Each channel only sends what has changed since the last
+//! run, and subscriptions at the same place share one forecast. The response
+//! counts what each channel did.
+//!
+//! Forecasts are kept in the edge cache for [`FORECAST_TTL`], so runs a few
+//! minutes apart share them rather than calling the weather API each time.
+//! A run calls it for at most [`MAX_CALLS`] places, and each channel makes
+//! at most [`MAX_SENDS`] sends; whatever's left over is deferred to the next
+//! run, which finds the forecasts fetched this time already cached.
+
+use std::time::Duration;
+
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Response};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::flags::Flags;
+use crate::location::Location;
+use crate::timing::Timings;
+use crate::{digest, fetch_weather_reusing, notify, push, subscriptions, APIResponse, Reuse};
+
+/// How long a run's forecasts are reused by later runs. One Call's current
+/// conditions only change every ten minutes or so.
+pub const FORECAST_TTL: Duration = Duration::from_secs(600);
+
+/// How many places a run may call the weather API for
+pub const MAX_CALLS: usize = 25;
+
+/// How many sends each channel may make in a run
+pub const MAX_SENDS: usize = 50;
+
+/// What one channel did in a run
+#[derive(Default, Serialize)]
+pub struct Report {
+    /// Subscriptions there were to check
+    pub checked: usize,
+    pub sent: usize,
+    /// Sends that will be tried again next run
+    pub failed: usize,
+    /// Subscriptions dropped because their receiver has gone
+    pub removed: usize,
+    /// Subscriptions left for the next run, for want of a forecast or of
+    /// sends to spare in this one
+    pub deferred: usize,
+}
+
+impl Report {
+    /// Whether the channel has made all the sends it may this run.
+    pub fn is_full(&self) -> bool {
+        self.sent + self.failed >= MAX_SENDS
+    }
+}
+
+/// The metric forecasts fetched during a run, by place
+pub struct Forecasts<'a> {
+    flags: &'a Flags,
+    timings: &'a mut Timings,
+    fetched: Vec<((f64, f64), Option<APIResponse>)>,
+    calls: usize,
+}
+
+impl<'a> Forecasts<'a> {
+    /// The metric forecast at `lat`, `lon`, fetching it the first time it's
+    /// asked for. `None` if it couldn't be fetched, which is logged once, or
+    /// the run has made all its calls and it isn't cached.
+    pub fn at(&mut self, lat: f64, lon: f64) -> Option<&APIResponse> {
+        let index = match self.fetched.iter().position(|(at, _)| *at == (lat, lon)) {
+            Some(index) => index,
+            None => {
+                let forecast = self.fetch(lat, lon, "metric");
+                self.fetched.push(((lat, lon), forecast));
+                self.fetched.len() - 1
+            }
+        };
+        self.fetched[index].1.as_ref()
+    }
+
+    /// A forecast of its own at `lat`, `lon` in `units`, for channels that
+    /// render one. These aren't shared within the run, but are cached.
+    pub fn fetch(&mut self, lat: f64, lon: f64, units: &str) -> Option<APIResponse> {
+        let location = Location::at(lat, lon, String::new());
+        if let Some(api_response) = self.fetch_reusing(&location, units, Reuse::CachedOnly) {
+            return Some(api_response);
+        }
+        if self.calls >= MAX_CALLS {
+            return None;
+        }
+        self.calls += 1;
+        self.fetch_reusing(&location, units, Reuse::Within(FORECAST_TTL))
+    }
+
+    fn fetch_reusing(
+        &mut self,
+        location: &Location,
+        units: &str,
+        reuse: Reuse,
+    ) -> Option<APIResponse> {
+        fetch_weather_reusing(location, units, &[], self.flags, self.timings, reuse)
+            .map_err(|err| println!("Couldn't fetch the forecast to dispatch: {}", err))
+            .ok()
+            .flatten()
+    }
+}
+
+/// Serve `/internal/dispatch`.
+pub fn handle(flags: &Flags, timings: &mut Timings) -> Result<Response, Error> {
+    let mut forecasts = Forecasts {
+        flags,
+        timings,
+        fetched: Vec::new(),
+        calls: 0,
+    };
+    let body = json!({
+        "webhooks": subscriptions::dispatch(&mut forecasts),
+        "push": push::dispatch(&mut forecasts),
+        "digests": digest::dispatch(&mut forecasts),
+        "notify": notify::dispatch(&mut forecasts),
+    });
+    Ok(Response::from_body(body.to_string())
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "no-store"))
+}
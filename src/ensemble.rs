@@ -0,0 +1,68 @@
+//! The `ensemble` provider: OpenWeather and Open-Meteo, blended.
+//!
+//! With `provider` set to `ensemble` in the `weather_config` store, every
+//! forecast is fetched from both at once and the two averaged: the current
+//! temperature, each hour's temperature and chance of rain, and each day's
+//! high, low and chance of rain. Everything else, and any hour or day
+//! Open-Meteo doesn't cover, is OpenWeather's alone, as is the whole
+//! forecast when Open-Meteo can't be reached.
+//!
+//! Days the two disagree on by a lot are logged, and the daily forecast rates
+//! every day by how far they agreed, as the `enable_confidence` flag does.
+//! Pages and API responses credit both.
+
+use crate::confidence::{Confidence, DailyConfidence};
+use crate::openmeteo::Forecast;
+use crate::{config, APIResponse};
+
+/// Whether the `provider` config value asks for the ensemble.
+pub fn enabled() -> bool {
+    config::get("provider").is_some_and(|provider| provider.trim().eq_ignore_ascii_case("ensemble"))
+}
+
+/// Average Open-Meteo's forecast into one fetched from OpenWeather in
+/// `units`, and rate each day by how far the two agreed.
+pub fn blend(api_response: &mut APIResponse, other: &Forecast, units: &str) {
+    let from_celsius = |temp: f32| weather_helpers::from_celsius(temp, units);
+    let mean = |a: f32, b: f32| (a + b) / 2.0;
+    let offset = api_response.timezone_offset;
+
+    let confidence = DailyConfidence::compare(api_response, units, &other.daily);
+    for daily in &api_response.daily {
+        if confidence.on(daily.dt) == Some(Confidence::Low) {
+            println!(
+                "OpenWeather and Open-Meteo disagree on the forecast for {}",
+                weather_helpers::local_time(daily.dt.into(), offset).date_naive()
+            );
+        }
+    }
+
+    if let Some(temp) = other.current {
+        api_response.current.temp = mean(api_response.current.temp, from_celsius(temp));
+    }
+    for hour in &mut api_response.hourly {
+        if let Some(other) = other.hourly.iter().find(|other| other.dt == hour.dt) {
+            hour.temp = mean(hour.temp, from_celsius(other.temp));
+            if let Some(pop) = other.pop {
+                hour.pop = mean(hour.pop, pop);
+            }
+        }
+    }
+    for daily in &mut api_response.daily {
+        let date = weather_helpers::local_time(daily.dt.into(), offset).date_naive();
+        if let Some(other) = other.daily.iter().find(|other| other.date == date) {
+            let max = mean(daily.temp.max, from_celsius(other.max));
+            let min = mean(daily.temp.min, from_celsius(other.min));
+            // The daytime temperature moves with the high and low
+            daily.temp.day += mean(max - daily.temp.max, min - daily.temp.min);
+            daily.temp.max = max;
+            daily.temp.min = min;
+            daily.pop = match (daily.pop, other.pop) {
+                (Some(pop), Some(other)) => Some(mean(pop, other)),
+                (pop, other) => pop.or(other),
+            };
+        }
+    }
+
+    api_response.confidence = Some(confidence);
+}
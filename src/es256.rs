@@ -0,0 +1,29 @@
+//! ES256 (ECDSA over NIST P-256 with SHA-256), for signing VAPID tokens.
+//!
+//! Only what VAPID needs is here: public keys from private ones, and
+//! signatures. Nonces are derived from the key and message as RFC 6979
+//! describes, so signing needs no randomness from the host.
+
+use std::convert::TryInto;
+
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+
+/// The uncompressed public key for the private key `d`, as VAPID and the
+/// Push API want it, or `None` if `d` isn't a valid key.
+pub fn public_key(d: &[u8; 32]) -> Option<[u8; 65]> {
+    let point = signing_key(d)?.verifying_key().to_encoded_point(false);
+    point.as_bytes().try_into().ok()
+}
+
+/// The ES256 signature of `message` under the private key `d`: `r` then
+/// `s`, 32 bytes each, as JWS has it. `None` if `d` isn't a valid key.
+pub fn sign(d: &[u8; 32], message: &[u8]) -> Option<[u8; 64]> {
+    let signature: Signature = signing_key(d)?.sign(message);
+    Some(signature.to_bytes().into())
+}
+
+// A private key must be from 1 to n - 1.
+fn signing_key(d: &[u8; 32]) -> Option<SigningKey> {
+    SigningKey::from_bytes(d.into()).ok()
+}
@@ -0,0 +1,201 @@
+//! The outdoor exercise card: the best hours today for a run or a ride,
+//! scored from the hourly forecast and air quality.
+//!
+//! When the air quality index for the next hour reaches
+//! `exercise_aqi_caution` (4, poor, unless set) the card warns to keep it
+//! gentle, and from `exercise_aqi_avoid` (5, very poor) it says to exercise
+//! indoors. Both are read from the `weather_config` store, on the 1 to 5
+//! scale of the air pollution API. Hours from `exercise_aqi_avoid` are never
+//! suggested as windows, however good the weather.
+
+use chrono::{FixedOffset, TimeZone, Timelike};
+use serde::Serialize;
+
+use crate::{config, windows, APIResponse};
+
+const DEFAULT_AQI_CAUTION: u8 = 4;
+const DEFAULT_AQI_AVOID: u8 = 5;
+
+// Hours scoring at least this are worth heading out for.
+const GOOD_SCORE: u8 = 70;
+const MAX_WINDOWS: usize = 2;
+
+// Nobody wants the 3am slot, however cool it is.
+const FIRST_HOUR: u32 = 6;
+const LAST_HOUR: u32 = 21;
+
+/// Context for the exercise fragment
+#[derive(Serialize)]
+pub struct ExerciseCard {
+    windows: Vec<String>,
+    score_now: u8,
+    /// A warning about the air in the next hour, if it's bad enough
+    air: Option<AirWarning>,
+}
+
+#[derive(Serialize)]
+struct AirWarning {
+    label: &'static str,
+    /// `caution` or `avoid`, for styling
+    level: &'static str,
+}
+
+/// The air quality indices to warn from, from the configuration
+struct AirThresholds {
+    caution: u8,
+    avoid: u8,
+}
+
+impl AirThresholds {
+    fn configured() -> Self {
+        let index = |key: &str, default: u8| {
+            config::get(key)
+                .and_then(|index| index.trim().parse::<u8>().ok())
+                .filter(|index| (1..=5).contains(index))
+                .unwrap_or(default)
+        };
+        AirThresholds {
+            caution: index("exercise_aqi_caution", DEFAULT_AQI_CAUTION),
+            avoid: index("exercise_aqi_avoid", DEFAULT_AQI_AVOID),
+        }
+    }
+
+    fn advice(&self, aqi: u8) -> Option<AirAdvice> {
+        air_advice(aqi, self.caution, self.avoid)
+    }
+}
+
+impl ExerciseCard {
+    /// The best exercise windows over the next day, from a report fetched in
+    /// `units` and the hourly air quality forecast.
+    pub fn new(api_response: &APIResponse, units: &str, air_quality: &[(i64, u8)]) -> Option<Self> {
+        let tz = FixedOffset::east_opt(api_response.timezone_offset)?;
+        let thresholds = AirThresholds::configured();
+        let aqi_at = |dt: i64| {
+            air_quality
+                .iter()
+                .find(|(time, _)| *time == dt)
+                .map(|(_, aqi)| *aqi)
+        };
+
+        let hours: Vec<(i64, u8)> = api_response
+            .hourly
+            .iter()
+            .take(24)
+            .filter_map(|hour| {
+                // Night hours score zero rather than being skipped, so windows
+                // can't run on through the night.
+                let local = tz.timestamp_opt(hour.dt, 0).single()?;
+                if !(FIRST_HOUR..=LAST_HOUR).contains(&local.hour()) {
+                    return Some((hour.dt, 0));
+                }
+                let aqi = aqi_at(hour.dt);
+                if aqi.and_then(|aqi| thresholds.advice(aqi)) == Some(AirAdvice::Avoid) {
+                    return Some((hour.dt, 0));
+                }
+                let wind_speed = match units {
+                    "imperial" => hour.wind_speed / 2.237,
+                    _ => hour.wind_speed,
+                };
+                let score = exercise_score(
+                    weather_helpers::to_celsius(hour.temp, units),
+                    hour.humidity,
+                    wind_speed,
+                    hour.pop,
+                    aqi,
+                );
+                Some((hour.dt, score))
+            })
+            .collect();
+
+        let scores: Vec<u8> = hours.iter().map(|(_, score)| *score).collect();
+        let windows = windows::best_windows(&scores, GOOD_SCORE)
+            .into_iter()
+            .take(MAX_WINDOWS)
+            .filter_map(|window| {
+                let start = tz.timestamp_opt(hours[window.start].0, 0).single()?;
+                // Windows run to the end of their last hour.
+                let end = tz
+                    .timestamp_opt(hours[window.end - 1].0 + 3600, 0)
+                    .single()?;
+                Some(format!("{}–{}", start.format("%H:%M"), end.format("%H:%M")))
+            })
+            .collect();
+
+        let air = api_response
+            .hourly
+            .first()
+            .and_then(|hour| aqi_at(hour.dt))
+            .and_then(|aqi| thresholds.advice(aqi))
+            .map(|advice| AirWarning {
+                label: advice.label(),
+                level: match advice {
+                    AirAdvice::Caution => "caution",
+                    AirAdvice::Avoid => "avoid",
+                },
+            });
+
+        Some(ExerciseCard {
+            windows,
+            score_now: *scores.first()?,
+            air,
+        })
+    }
+}
+
+/// Score an hour for running or cycling from 0 (stay in) to 100 (perfect),
+/// from the temperature in °C, relative humidity in %, wind speed in m/s, the
+/// probability of precipitation from 0 to 1, and the air quality index from
+/// 1 (good) to 5 (very poor) if known.
+pub fn exercise_score(temp: f32, humidity: f32, wind_speed: f32, pop: f32, aqi: Option<u8>) -> u8 {
+    let mut score = 100.0;
+
+    // 10–18°C is comfortable for most; heat hurts more than cold.
+    if temp < 10.0 {
+        score -= (10.0 - temp) * 3.0;
+    } else if temp > 18.0 {
+        score -= (temp - 18.0) * 5.0;
+    }
+    // Humidity only matters once it stops sweat from cooling you.
+    let humid_from = if temp > 20.0 { 60.0 } else { 75.0 };
+    score -= (humidity - humid_from).max(0.0);
+    score -= (wind_speed - 5.0).max(0.0) * 4.0;
+    score -= pop.clamp(0.0, 1.0) * 60.0;
+    score -= match aqi {
+        Some(3) => 10.0,
+        Some(4) => 30.0,
+        Some(5) => 60.0,
+        _ => 0.0,
+    };
+
+    score.clamp(0.0, 100.0).round() as u8
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AirAdvice {
+    /// Take it easy, especially with asthma or a heart condition
+    Caution,
+    Avoid,
+}
+
+impl AirAdvice {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AirAdvice::Caution => "Poor air quality: keep it gentle outdoors",
+            AirAdvice::Avoid => "Very poor air quality: exercise indoors",
+        }
+    }
+}
+
+/// Whether to warn against exercising outdoors at an air quality index from
+/// 1 (good) to 5 (very poor), given the indices from which to advise caution
+/// and to advise staying in.
+pub fn air_advice(aqi: u8, caution: u8, avoid: u8) -> Option<AirAdvice> {
+    if aqi >= avoid {
+        Some(AirAdvice::Avoid)
+    } else if aqi >= caution {
+        Some(AirAdvice::Caution)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,156 @@
+//! Template experiments (A/B tests).
+//!
+//! Experiments are configured with the `experiments` config value, a
+//! semicolon-separated list of `name=variant:weight,variant:weight` entries:
+//!
+//! ```text
+//! forecast_layout=cards:50,compact:50
+//! ```
+//!
+//! Visitors get a random id in the `weather_vid` cookie, and the hash of that
+//! id and the experiment name picks their variant, so assignments stay stable
+//! across visits without storing anything at the edge.
+
+use chrono::Utc;
+use fastly::http::header;
+use fastly::{Request, Response};
+
+use crate::{config, cookies};
+
+const VISITOR_COOKIE: &str = "weather_vid";
+
+// Keep visitors in the same buckets for a year.
+const VISITOR_COOKIE_MAX_AGE: u32 = 31_536_000;
+
+/// The variants a visitor has been assigned for this request. The default
+/// is in no experiments at all.
+#[derive(Default)]
+pub struct Assignments {
+    visitor_id: String,
+    is_new_visitor: bool,
+    variants: Vec<(String, String)>,
+}
+
+impl Assignments {
+    /// Assign the visitor behind `req` to a variant of every configured experiment.
+    pub fn for_request(req: &Request) -> Self {
+        let (visitor_id, is_new_visitor) = match cookies::get(req, VISITOR_COOKIE) {
+            Some(id) if !id.is_empty() => (id.to_string(), false),
+            _ => (new_visitor_id(req), true),
+        };
+
+        let variants = config::get("experiments")
+            .map(|experiments| {
+                experiments
+                    .split(';')
+                    .filter_map(|experiment| choose_variant(&visitor_id, experiment))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Assignments {
+            visitor_id,
+            is_new_visitor,
+            variants,
+        }
+    }
+
+    /// The variant assigned for `experiment`, if it is running.
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        self.variants
+            .iter()
+            .find(|(name, _)| name == experiment)
+            .map(|(_, variant)| variant.as_str())
+    }
+
+    /// Tag a response with the assigned variants for analytics, and hand out
+    /// the visitor cookie if this is the first visit.
+    pub fn tag(&self, resp: &mut Response) {
+        if !self.variants.is_empty() {
+            let tag = self
+                .variants
+                .iter()
+                .map(|(name, variant)| format!("{}={}", name, variant))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("Experiments for visitor {}: {}", self.visitor_id, tag);
+            resp.set_header("X-Experiments", tag);
+        }
+
+        if self.is_new_visitor {
+            resp.append_header(
+                header::SET_COOKIE,
+                format!(
+                    "{}={}; Max-Age={}; Path=/; Secure; HttpOnly; SameSite=Lax",
+                    VISITOR_COOKIE, self.visitor_id, VISITOR_COOKIE_MAX_AGE
+                ),
+            );
+        }
+    }
+}
+
+/// Hash a visitor and a salt (such as an experiment name) into a bucket from 0 to 99.
+pub fn bucket(visitor_id: &str, salt: &str) -> u32 {
+    fnv1a(&[salt, ":", visitor_id]) % 100
+}
+
+// FNV-1a rather than the standard library hasher, whose output is allowed to
+// change between Rust releases and would reshuffle every visitor.
+fn fnv1a(parts: &[&str]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in parts.iter().flat_map(|part| part.bytes()) {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+// Parse one `name=variant:weight,...` entry and pick the visitor's variant.
+fn choose_variant(visitor_id: &str, experiment: &str) -> Option<(String, String)> {
+    let (name, variants) = experiment.trim().split_once('=')?;
+    // Weights are at most u32::MAX each, so their total and the scaled
+    // bucket below fit in a u64 for any number of variants a config holds.
+    let variants: Vec<(&str, u64)> = variants
+        .split(',')
+        .filter_map(|variant| {
+            let (variant, weight) = variant.trim().split_once(':')?;
+            let weight: u32 = weight.trim().parse().ok()?;
+            Some((variant.trim(), u64::from(weight)))
+        })
+        .collect();
+
+    // Scale the 0-99 bucket onto the total weight, so weights needn't add up to 100.
+    let total: u64 = variants.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut point = u64::from(bucket(visitor_id, name.trim())) * total / 100;
+    for (variant, weight) in variants {
+        if point < weight {
+            return Some((name.trim().to_string(), variant.to_string()));
+        }
+        point -= weight;
+    }
+    None
+}
+
+// Visitor ids only need to be unique, so reuse Fastly's per-request id.
+fn new_visitor_id(req: &Request) -> String {
+    match req.get_client_request_id() {
+        Some(id) => id.to_string(),
+        None => {
+            let now = Utc::now();
+            // Nanoseconds run out in 2262, and microseconds will do after that.
+            let time = now
+                .timestamp_nanos_opt()
+                .unwrap_or_else(|| now.timestamp_micros());
+            format!(
+                "{:08x}",
+                fnv1a(&[
+                    &format!("{:?}", req.get_client_ip_addr()),
+                    &time.to_string()
+                ])
+            )
+        }
+    }
+}
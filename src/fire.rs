@@ -0,0 +1,121 @@
+//! Fire danger, from the Fosberg Fire Weather Index of the current
+//! temperature, humidity and wind, damped by today's forecast rain.
+//!
+//! The card only appears on the page during the local fire season, but
+//! `/api/fire` always answers so rural users can keep an eye on it.
+
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+use serde::Serialize;
+use weather_helpers::Season;
+
+use crate::location::Location;
+use crate::provider::Attributed;
+use crate::APIResponse;
+
+/// The fire danger card and `/api/fire` response
+#[derive(Serialize)]
+pub struct FireCard {
+    index: u8,
+    level: &'static str,
+    pub fire_season: bool,
+}
+
+impl FireCard {
+    /// The fire danger at `location`, from a report fetched in `units`.
+    pub fn new(api_response: &APIResponse, units: &str, location: &Location) -> Self {
+        let current = &api_response.current;
+
+        // The index works in °C and m/s, whatever units the report came in.
+        let temp = weather_helpers::to_celsius(current.temp, units);
+        let wind_speed = match units {
+            "imperial" => current.wind_speed / 2.237,
+            _ => current.wind_speed,
+        };
+        let rain = api_response
+            .daily
+            .first()
+            .and_then(|today| today.rain)
+            .unwrap_or(0.0);
+
+        let index = fire_weather_index(temp, current.humidity, wind_speed, rain);
+        let local = weather_helpers::local_time(current.dt, api_response.timezone_offset);
+        let season = weather_helpers::get_season(location.latitude(), local);
+
+        FireCard {
+            index: index.round() as u8,
+            level: fire_danger(index).label(),
+            fire_season: matches!(season, Season::Summer | Season::Autumn),
+        }
+    }
+}
+
+/// Serve `/api/fire` for the visitor's location.
+pub fn handle(card: &FireCard) -> Result<Response, fastly::Error> {
+    Ok(
+        Response::from_body(serde_json::to_string(&Attributed::new(card))?)
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_header(header::CACHE_CONTROL, "private, max-age=300"),
+    )
+}
+
+/// The danger bands of the fire weather index
+pub enum FireDanger {
+    Low,
+    Moderate,
+    High,
+    VeryHigh,
+    Extreme,
+}
+
+impl FireDanger {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FireDanger::Low => "Low",
+            FireDanger::Moderate => "Moderate",
+            FireDanger::High => "High",
+            FireDanger::VeryHigh => "Very high",
+            FireDanger::Extreme => "Extreme",
+        }
+    }
+}
+
+/// Fosberg Fire Weather Index, from 0 to 100, for a temperature in °C, relative
+/// humidity in %, wind speed in m/s and the day's rain in mm. Rain isn't part of
+/// the original index, so soaked fuels just get the index scaled down.
+pub fn fire_weather_index(temp: f32, humidity: f32, wind_speed: f32, rain: f32) -> f32 {
+    let t = temp * 9.0 / 5.0 + 32.0;
+    let h = humidity.clamp(0.0, 100.0);
+    let u = wind_speed.max(0.0) * 2.237;
+
+    // Equilibrium moisture content of dead fuels
+    let m = if h < 10.0 {
+        0.03229 + 0.281073 * h - 0.000578 * h * t
+    } else if h <= 50.0 {
+        2.22749 + 0.160107 * h - 0.01478 * t
+    } else {
+        21.0606 + 0.005565 * h * h - 0.00035 * h * t - 0.483199 * h
+    };
+    let r = m / 30.0;
+    let eta = 1.0 - 2.0 * r + 1.5 * r * r - 0.5 * r * r * r;
+    let index = eta * (1.0 + u * u).sqrt() / 0.3002;
+
+    let damping = 1.0 / (1.0 + rain.max(0.0) / 2.5);
+    (index * damping).clamp(0.0, 100.0)
+}
+
+/// The danger band for a fire weather index
+pub fn fire_danger(index: f32) -> FireDanger {
+    if index < 20.0 {
+        FireDanger::Low
+    } else if index < 40.0 {
+        FireDanger::Moderate
+    } else if index < 60.0 {
+        FireDanger::High
+    } else if index < 80.0 {
+        FireDanger::VeryHigh
+    } else {
+        FireDanger::Extreme
+    }
+}
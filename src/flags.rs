@@ -0,0 +1,73 @@
+//! Feature flags read from the `weather_flags` Config Store.
+//!
+//! Each key is a flag name, and its value is either a boolean (`true`/`false`)
+//! or a rollout percentage such as `25`. Percentages are evaluated against a
+//! hash of the client IP, so a given client sees the same answer on every
+//! request. Missing or unparseable flags are off.
+//!
+//! Known flags:
+//!
+//! * `use_onecall_v3` — fetch from One Call API 3.0 instead of 2.5.
+//! * `enable_radar` — show the precipitation radar map below the forecast.
+//! * `enable_marine` — allow `?mode=marine` to show waves, sea temperature and tides.
+//! * `enable_exercise` — show the best run and ride windows, which fetches air quality too.
+//! * `enable_confidence` — rate each day's forecast against Open-Meteo's, which it fetches.
+
+use std::net::IpAddr;
+
+use fastly::Request;
+
+use crate::{config, experiments};
+
+const STORE_NAME: &str = "weather_flags";
+
+/// Flags evaluated for a single client.
+pub struct Flags {
+    client: String,
+}
+
+impl Flags {
+    /// Evaluate flags for the client that sent `req`.
+    pub fn for_request(req: &Request) -> Self {
+        Flags {
+            client: req
+                .get_client_ip_addr()
+                .as_ref()
+                .map(IpAddr::to_string)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Evaluate flags for `client`, such as an IP address.
+    pub fn for_client(client: &str) -> Self {
+        Flags {
+            client: client.to_string(),
+        }
+    }
+
+    /// Whether `flag` is on for this client.
+    pub fn enabled(&self, flag: &str) -> bool {
+        match config::get_from(STORE_NAME, flag) {
+            Some(value) => self.is_on(flag, &value),
+            None => false,
+        }
+    }
+
+    /// Whether `flag` is on for this client when it's set to `value`.
+    pub fn is_on(&self, flag: &str, value: &str) -> bool {
+        match value.trim() {
+            "true" | "on" => true,
+            "false" | "off" => false,
+            percent => match percent.trim_end_matches('%').parse::<u32>() {
+                Ok(percent) => self.in_rollout(flag, percent),
+                Err(_) => false,
+            },
+        }
+    }
+
+    /// Whether this client is in the first `percent` of clients for
+    /// `rollout`. Each rollout slices clients its own way.
+    pub fn in_rollout(&self, rollout: &str, percent: u32) -> bool {
+        experiments::bucket(&self.client, rollout) < percent
+    }
+}
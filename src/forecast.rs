@@ -0,0 +1,287 @@
+//! The hourly or daily forecast over a time range, at `/api/forecast`.
+//!
+//! * `granularity`: `hourly`, the default, or `daily`
+//! * `from` and `to`: where the range starts and ends, as Unix times or
+//!   RFC 3339, both inclusive. Each defaults to the end of what the
+//!   forecast covers, and a range outside that is a 400 saying what it is.
+//! * `limit`: the most entries to return. When there are more, `next` is
+//!   the URL of the page after.
+//!
+//! Entries are timed in the location's time zone, so they read as local.
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+use fastly::http::{header, StatusCode, Url};
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::provider::Attributed;
+use crate::{problem, APIResponse};
+
+const HOUR_SECS: i64 = 3600;
+const DAY_SECS: i64 = 86_400;
+
+#[derive(Deserialize)]
+struct RangeParams {
+    granularity: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Which forecast block a range is over
+#[derive(Clone, Copy, PartialEq)]
+pub enum Granularity {
+    Hourly,
+    Daily,
+}
+
+impl Granularity {
+    /// The forecast block this range doesn't need fetched.
+    pub fn unused_block(self) -> &'static str {
+        match self {
+            Granularity::Hourly => "daily",
+            Granularity::Daily => "hourly",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Granularity::Hourly => "hourly",
+            Granularity::Daily => "daily",
+        }
+    }
+
+    fn step(self) -> i64 {
+        match self {
+            Granularity::Hourly => HOUR_SECS,
+            Granularity::Daily => DAY_SECS,
+        }
+    }
+}
+
+/// The range a client asked for
+pub struct Range {
+    pub granularity: Granularity,
+    /// Unix times, when given
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<usize>,
+}
+
+/// The `/api/forecast` response
+#[derive(Serialize)]
+struct ForecastRange {
+    granularity: &'static str,
+    /// RFC 3339, where the returned entries start and the range ends
+    from: String,
+    to: String,
+    entries: Vec<Entry>,
+    /// The URL of the rest of the range, if `limit` cut it short
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+/// An hour or a day of the forecast
+#[derive(Serialize)]
+struct Entry {
+    /// When it starts, RFC 3339
+    time: String,
+    /// The same, as a Unix time
+    dt: i64,
+    /// The temperature for hours; `temp_min` and `temp_max` for days
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temp: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temp_min: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temp_max: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    humidity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wind_speed: Option<f32>,
+    /// The chance of precipitation in percent, for hours
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pop: Option<u32>,
+    /// Rain and snow in millimetres, for days that have any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rain: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snow: Option<f32>,
+    description: String,
+}
+
+impl Range {
+    /// The range in `req`'s query string, or the 400 to send when it doesn't
+    /// make sense.
+    pub fn from_request(req: &Request) -> Result<Self, Box<Response>> {
+        let params: RangeParams = req
+            .get_query()
+            .map_err(|_| error("limit must be a number"))?;
+        let granularity = match params.granularity.as_deref() {
+            None | Some("hourly") => Granularity::Hourly,
+            Some("daily") => Granularity::Daily,
+            Some(_) => return Err(error("granularity must be hourly or daily")),
+        };
+        let time = |value: Option<String>, name: &str| match value {
+            None => Ok(None),
+            Some(value) => parse_time(&value)
+                .map(Some)
+                .ok_or_else(|| error(&format!("{} must be a Unix time or RFC 3339", name))),
+        };
+        let from = time(params.from, "from")?;
+        let to = time(params.to, "to")?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(error("from must not be after to"));
+            }
+        }
+        if params.limit == Some(0) {
+            return Err(error("limit must be at least 1"));
+        }
+        Ok(Range {
+            granularity,
+            from,
+            to,
+            limit: params.limit,
+        })
+    }
+}
+
+/// Serve `/api/forecast` for `range` of the forecast in `api_response`.
+pub fn handle(req: &Request, range: &Range, api_response: &APIResponse) -> Result<Response, Error> {
+    let tz = FixedOffset::east_opt(api_response.timezone_offset)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    let rfc3339 = |time: i64| {
+        tz.timestamp_opt(time, 0)
+            .single()
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default()
+    };
+    let step = range.granularity.step();
+
+    let entries: Vec<Entry> = match range.granularity {
+        Granularity::Hourly => api_response
+            .hourly
+            .iter()
+            .map(|hour| Entry {
+                time: rfc3339(hour.dt),
+                dt: hour.dt,
+                temp: Some(hour.temp),
+                temp_min: None,
+                temp_max: None,
+                humidity: Some(hour.humidity),
+                wind_speed: Some(hour.wind_speed),
+                pop: Some((hour.pop * 100.0).round() as u32),
+                rain: None,
+                snow: None,
+                description: hour
+                    .weather
+                    .first()
+                    .map(|weather| weather.description.clone())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        Granularity::Daily => api_response
+            .daily
+            .iter()
+            .map(|day| Entry {
+                time: rfc3339(day.dt.into()),
+                dt: day.dt.into(),
+                temp: None,
+                temp_min: Some(day.temp.min),
+                temp_max: Some(day.temp.max),
+                humidity: None,
+                wind_speed: None,
+                pop: None,
+                rain: day.rain,
+                snow: day.snow,
+                description: day
+                    .weather
+                    .first()
+                    .map(|weather| weather.description.clone())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+    };
+
+    // What the forecast covers: from the first entry to the end of the last.
+    let (first, last) = match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => (first.dt, last.dt + step - 1),
+        _ => {
+            return Ok(problem::response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The forecast has no entries at that granularity",
+            ))
+        }
+    };
+    let from = range.from.unwrap_or(first);
+    let to = range.to.unwrap_or(last);
+    if to < first || from > last {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "The {} forecast covers {} to {}",
+                range.granularity.name(),
+                rfc3339(first),
+                rfc3339(last)
+            ),
+        ));
+    }
+
+    // Entries whose hour or day overlaps the range
+    let mut entries: Vec<Entry> = entries
+        .into_iter()
+        .filter(|entry| entry.dt + step > from && entry.dt <= to)
+        .collect();
+    let mut next = None;
+    if let Some(limit) = range.limit {
+        if entries.len() > limit {
+            let rest = entries.split_off(limit);
+            next = Some(next_url(req, rest[0].dt));
+        }
+    }
+
+    let body = ForecastRange {
+        granularity: range.granularity.name(),
+        from: rfc3339(from.max(first)),
+        to: rfc3339(to.min(last)),
+        entries,
+        next,
+    };
+    Ok(
+        Response::from_body(serde_json::to_string(&Attributed::new(&body))?)
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_header(header::CACHE_CONTROL, "private, max-age=300"),
+    )
+}
+
+// This request's path and query with `from` moved on to `from`.
+fn next_url(req: &Request, from: i64) -> String {
+    let mut url = Url::parse("https://weather.invalid/").unwrap();
+    url.set_path(req.get_path());
+    {
+        let mut query = url.query_pairs_mut();
+        for (name, value) in req.get_url().query_pairs() {
+            if name != "from" {
+                query.append_pair(&name, &value);
+            }
+        }
+        query.append_pair("from", &from.to_string());
+    }
+    format!("{}?{}", url.path(), url.query().unwrap_or_default())
+}
+
+// A Unix time, or an RFC 3339 date and time.
+fn parse_time(value: &str) -> Option<i64> {
+    if value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return value.parse().ok();
+    }
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.timestamp())
+}
+
+fn error(message: &str) -> Box<Response> {
+    Box::new(problem::response(StatusCode::BAD_REQUEST, message))
+}
@@ -0,0 +1,114 @@
+//! The garden card for `?mode=garden`: frost warnings from the overnight
+//! lows, and growing degree days from the daily highs and lows.
+
+use chrono::Datelike;
+use serde::Serialize;
+use weather_helpers::Locale;
+
+use crate::APIResponse;
+
+// Most temperate crops start growing at 10°C (50°F).
+const GDD_BASE_CELSIUS: f32 = 10.0;
+
+/// Context for the garden fragment
+#[derive(Serialize)]
+pub struct GardenCard {
+    frost_tonight: Option<String>,
+    frost_nights: Vec<String>,
+    gdd_today: String,
+    gdd_week: String,
+    gdd_base: &'static str,
+}
+
+impl GardenCard {
+    /// The garden advisory from a report fetched in `units`, with the frost
+    /// nights named in `locale`.
+    pub fn new(api_response: &APIResponse, units: &str, locale: Locale) -> Option<Self> {
+        let is_metric = units == "metric";
+        let degree = if is_metric { "°C" } else { "°F" };
+
+        // Each day's low comes just before dawn, so tonight's low is tomorrow's.
+        let tonight = api_response.daily.get(1)?;
+        let frost_tonight =
+            frost_risk(weather_helpers::to_celsius(tonight.temp.min, units)).map(|risk| {
+                format!(
+                    "{}: protect plants tonight, low of {}{}",
+                    risk.label(),
+                    weather_helpers::round_temperature(tonight.temp.min),
+                    degree
+                )
+            });
+        let frost_nights = api_response
+            .daily
+            .iter()
+            .skip(2)
+            .filter(|daily| {
+                frost_risk(weather_helpers::to_celsius(daily.temp.min, units)).is_some()
+            })
+            .map(|daily| {
+                let local =
+                    weather_helpers::local_time(daily.dt.into(), api_response.timezone_offset);
+                locale.weekday_short(local.weekday()).to_string()
+            })
+            .collect();
+
+        // Degree days are computed in °C, then scaled for Fahrenheit readers.
+        let gdd: Vec<f32> = api_response
+            .daily
+            .iter()
+            .map(|daily| {
+                growing_degree_days(
+                    weather_helpers::to_celsius(daily.temp.max, units),
+                    weather_helpers::to_celsius(daily.temp.min, units),
+                    GDD_BASE_CELSIUS,
+                ) * if is_metric { 1.0 } else { 1.8 }
+            })
+            .collect();
+
+        Some(GardenCard {
+            frost_tonight,
+            frost_nights,
+            gdd_today: format!("{:.0}", gdd.first()?),
+            gdd_week: format!("{:.0}", gdd.iter().sum::<f32>()),
+            gdd_base: if is_metric { "10°C" } else { "50°F" },
+        })
+    }
+}
+
+/// How hard a night's frost is
+pub enum FrostRisk {
+    /// Air above freezing, but cold enough for frost on the ground
+    GroundFrost,
+    Frost,
+    HardFrost,
+}
+
+impl FrostRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrostRisk::GroundFrost => "Ground frost likely",
+            FrostRisk::Frost => "Frost",
+            FrostRisk::HardFrost => "Hard frost",
+        }
+    }
+}
+
+/// Frost risk for an overnight low in °C. Ground and leaves cool below the air
+/// temperature on clear nights, so tender plants are at risk from around 3°C.
+pub fn frost_risk(low: f32) -> Option<FrostRisk> {
+    if low <= -2.0 {
+        Some(FrostRisk::HardFrost)
+    } else if low <= 0.0 {
+        Some(FrostRisk::Frost)
+    } else if low <= 3.0 {
+        Some(FrostRisk::GroundFrost)
+    } else {
+        None
+    }
+}
+
+/// Growing degree days for one day, from its high and low in °C and the base
+/// temperature the crop starts growing at (10°C for most).
+pub fn growing_degree_days(high: f32, low: f32, base: f32) -> f32 {
+    ((high + low) / 2.0 - base).max(0.0)
+}
@@ -0,0 +1,93 @@
+//! City search at `/api/geocode?q=par`, proxied from OpenWeatherMap's direct
+//! geocoding API.
+//!
+//! Place names don't move, so both the upstream response and ours are cached
+//! for a day, which keeps the search box fast and cheap on API quota.
+
+use fastly::http::{header, HeaderValue, Method, StatusCode, Url};
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{get_api_key, problem, BACKEND_NAME};
+
+const CACHE_TTL_SECS: u32 = 86_400;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "geocode";
+
+const MAX_QUERY_LEN: usize = 100;
+// The upstream API returns at most five matches.
+const MAX_LIMIT: u64 = 5;
+
+#[derive(Deserialize)]
+struct GeocodeParams {
+    q: Option<String>,
+    /// Wide enough for any number the query string sanitizer lets through
+    limit: Option<u64>,
+}
+
+/// A candidate place, as returned by the API and passed on to clients
+#[derive(Deserialize, Serialize)]
+struct Place {
+    name: String,
+    country: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    lat: f64,
+    lon: f64,
+}
+
+/// Serve `/api/geocode`, returning up to `limit` places matching `q`.
+pub fn handle(req: &Request) -> Result<Response, Error> {
+    let params: GeocodeParams = match req.get_query() {
+        Ok(params) => params,
+        Err(_) => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "The query string couldn't be read",
+            ))
+        }
+    };
+    let query = params.q.unwrap_or_default();
+    let query = query.trim();
+
+    // Very short queries match half the planet, so wait for a couple of characters.
+    if query.chars().count() < 2 || query.len() > MAX_QUERY_LEN {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "q must be between 2 and 100 characters",
+        ));
+    }
+    let limit = params.limit.unwrap_or(MAX_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Lowercase the query so "Paris" and "paris" share a cache entry upstream.
+    let url = Url::parse_with_params(
+        "http://api.openweathermap.org/geo/1.0/direct",
+        &[
+            ("q", query.to_lowercase()),
+            ("limit", limit.to_string()),
+            ("appid", get_api_key()),
+        ],
+    )?;
+    let mut beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "api.openweathermap.org")
+        .with_ttl(CACHE_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(BACKEND_NAME)?;
+
+    if !beresp.get_status().is_success() {
+        return Ok(problem::response(
+            StatusCode::BAD_GATEWAY,
+            "geocoding is temporarily unavailable",
+        ));
+    }
+    let places = beresp.take_body_json::<Vec<Place>>()?;
+
+    Ok(Response::from_body(serde_json::to_string(&places)?)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", CACHE_TTL_SECS),
+        ))
+}
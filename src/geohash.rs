@@ -0,0 +1,43 @@
+//! Geohash encoding, used to group nearby visitors without storing coordinates.
+
+const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Encode a coordinate as a geohash of `precision` characters.
+///
+/// Five characters is a cell of roughly 5km × 5km, which is about the
+/// resolution of a city-level forecast.
+pub fn encode(latitude: f64, longitude: f64, precision: usize) -> String {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lon_range = (-180.0, 180.0);
+    let mut hash = String::with_capacity(precision);
+    let mut even_bit = true;
+    let mut bit = 0;
+    let mut index = 0;
+
+    while hash.len() < precision {
+        // Bits alternate between longitude and latitude, starting with longitude.
+        let (range, value) = if even_bit {
+            (&mut lon_range, longitude)
+        } else {
+            (&mut lat_range, latitude)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        if value >= mid {
+            index = index * 2 + 1;
+            range.0 = mid;
+        } else {
+            index *= 2;
+            range.1 = mid;
+        }
+        even_bit = !even_bit;
+
+        bit += 1;
+        if bit == 5 {
+            hash.push(BASE32[index] as char);
+            bit = 0;
+            index = 0;
+        }
+    }
+
+    hash
+}
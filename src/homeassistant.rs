@@ -0,0 +1,190 @@
+//! Readings for home automation, in the shapes Home Assistant's REST sensor
+//! reads well.
+//!
+//! `/api/homeassistant` has every reading as a top-level key, with its unit
+//! alongside as `<key>_unit`, so one REST resource can feed a sensor per
+//! reading with `value_template: "{{ value_json.temp }}"`. `/api/sensor/<key>`
+//! has just one, as `state` with its `unit_of_measurement` and
+//! `device_class`, for setups with a resource per sensor. Both take `units`,
+//! as the page does.
+
+use chrono::{FixedOffset, TimeZone};
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Response};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::provider::Attributed;
+use crate::APIResponse;
+
+/// One reading
+struct Sensor {
+    key: &'static str,
+    value: Value,
+    unit: Option<&'static str>,
+    /// How Home Assistant should treat it, where it has a class for it
+    device_class: Option<&'static str>,
+}
+
+/// The `/api/sensor/<key>` response
+#[derive(Serialize)]
+struct SensorState {
+    state: Value,
+    unit_of_measurement: Option<&'static str>,
+    device_class: Option<&'static str>,
+    /// When the reading was taken, RFC 3339 in the location's time zone
+    updated: String,
+}
+
+/// Serve `/api/homeassistant`.
+pub fn handle(api_response: &APIResponse, units: &str) -> Result<Response, Error> {
+    let mut readings = Map::new();
+    for sensor in sensors(api_response, units) {
+        if let Some(unit) = sensor.unit {
+            readings.insert(format!("{}_unit", sensor.key), Value::from(unit));
+        }
+        readings.insert(sensor.key.to_string(), sensor.value);
+    }
+    readings.insert(String::from("updated"), Value::from(updated(api_response)));
+    respond(&readings)
+}
+
+/// Serve `/api/sensor/<key>`, or `None` if there's no such sensor.
+pub fn sensor(
+    key: &str,
+    api_response: &APIResponse,
+    units: &str,
+) -> Option<Result<Response, Error>> {
+    let sensor = sensors(api_response, units)
+        .into_iter()
+        .find(|sensor| sensor.key == key)?;
+    Some(respond(&SensorState {
+        state: sensor.value,
+        unit_of_measurement: sensor.unit,
+        device_class: sensor.device_class,
+        updated: updated(api_response),
+    }))
+}
+
+// Every reading there is in `api_response`. Optional ones are null when the
+// forecast doesn't have them, so templates can tell.
+fn sensors(api_response: &APIResponse, units: &str) -> Vec<Sensor> {
+    let (temp_unit, speed_unit) = match units {
+        "imperial" => ("°F", "mph"),
+        "standard" => ("K", "m/s"),
+        _ => ("°C", "m/s"),
+    };
+    let current = &api_response.current;
+    let today = api_response.daily.first();
+    let next_hour = api_response.hourly.first();
+    let sensor = |key, value: Value, unit, device_class| Sensor {
+        key,
+        value,
+        unit,
+        device_class,
+    };
+    vec![
+        sensor(
+            "temp",
+            round(current.temp).into(),
+            Some(temp_unit),
+            Some("temperature"),
+        ),
+        sensor(
+            "temp_high",
+            today.map(|today| round(today.temp.max)).into(),
+            Some(temp_unit),
+            Some("temperature"),
+        ),
+        sensor(
+            "temp_low",
+            today.map(|today| round(today.temp.min)).into(),
+            Some(temp_unit),
+            Some("temperature"),
+        ),
+        sensor(
+            "dew_point",
+            current.dew_point.map(round).into(),
+            Some(temp_unit),
+            Some("temperature"),
+        ),
+        sensor(
+            "humidity",
+            whole(current.humidity).into(),
+            Some("%"),
+            Some("humidity"),
+        ),
+        sensor(
+            "pressure",
+            current.pressure.map(whole).into(),
+            Some("hPa"),
+            Some("atmospheric_pressure"),
+        ),
+        sensor(
+            "wind_speed",
+            round(current.wind_speed).into(),
+            Some(speed_unit),
+            Some("wind_speed"),
+        ),
+        sensor("clouds", current.clouds.map(whole).into(), Some("%"), None),
+        sensor(
+            "visibility",
+            current.visibility.map(whole).into(),
+            Some("m"),
+            Some("distance"),
+        ),
+        sensor("uv_index", round(current.uvi).into(), None, None),
+        sensor(
+            "precipitation_probability",
+            next_hour.map(|hour| whole(hour.pop * 100.0)).into(),
+            Some("%"),
+            None,
+        ),
+        sensor(
+            "condition",
+            current
+                .weather
+                .first()
+                .map(|weather| weather.description.clone())
+                .into(),
+            None,
+            None,
+        ),
+        sensor(
+            "icon",
+            current
+                .weather
+                .first()
+                .map(|weather| weather.icon.clone())
+                .into(),
+            None,
+            None,
+        ),
+    ]
+}
+
+// Readings to a tenth, as the API has them, without float noise
+fn round(value: f32) -> f64 {
+    weather_helpers::round_to(value, 1)
+}
+
+fn whole(value: f32) -> i64 {
+    value.round() as i64
+}
+
+fn updated(api_response: &APIResponse) -> String {
+    FixedOffset::east_opt(api_response.timezone_offset)
+        .and_then(|tz| tz.timestamp_opt(api_response.current.dt, 0).single())
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn respond<T: Serialize>(body: &T) -> Result<Response, Error> {
+    Ok(
+        Response::from_body(serde_json::to_string(&Attributed::new(body))?)
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            // Home Assistant polls every few minutes; the forecast changes hourly.
+            .with_header(header::CACHE_CONTROL, "private, max-age=300"),
+    )
+}
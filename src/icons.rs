@@ -0,0 +1,86 @@
+//! Favicons, touch icons and the page's icon sprite.
+
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+
+// Embedded icons rarely change, so let browsers and the edge hold on to them for a month.
+const STATIC_ICON_CACHE_CONTROL: &str = "public, max-age=2592000";
+
+// The dynamic favicon tracks the current weather, so keep it roughly as fresh as the page.
+const DYNAMIC_ICON_CACHE_CONTROL: &str = "private, max-age=600";
+
+/// Returns true if `path` is one of the embedded icon routes.
+pub fn is_icon(path: &str) -> bool {
+    icon_for_path(path).is_some()
+}
+
+/// Serve an embedded icon with long cache lifetimes.
+pub fn serve(path: &str) -> Response {
+    match icon_for_path(path) {
+        Some((content_type, bytes)) => Response::from_body(bytes)
+            .with_status(StatusCode::OK)
+            .with_header(header::CONTENT_TYPE, content_type)
+            .with_header(header::CACHE_CONTROL, STATIC_ICON_CACHE_CONTROL),
+        None => Response::from_status(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Generate an SVG favicon showing the current temperature, coloured by condition.
+pub fn dynamic_favicon(temp: i32, condition: u32, owm_icon: &str) -> Response {
+    Response::from_body(favicon_svg(temp, condition, owm_icon))
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::IMAGE_SVG)
+        .with_header(header::CACHE_CONTROL, DYNAMIC_ICON_CACHE_CONTROL)
+}
+
+/// The same favicon as a `data:` URI, for pages to link without the browser
+/// asking for it, and the weather being fetched again to draw it.
+pub fn favicon_data_uri(temp: i32, condition: u32, owm_icon: &str) -> String {
+    let mut uri = String::from("data:image/svg+xml,");
+    for byte in favicon_svg(temp, condition, owm_icon).bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn favicon_svg(temp: i32, condition: u32, owm_icon: &str) -> String {
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 64"><rect width="64" height="64" rx="14" fill="{}"/><text x="32" y="43" font-family="Montserrat,sans-serif" font-size="{}" font-weight="700" fill="#fff" text-anchor="middle">{}°</text></svg>"##,
+        condition_colour(weather_helpers::get_feather_weather_icon(
+            condition, owm_icon
+        )),
+        if temp.abs() >= 100 { 22 } else { 30 },
+        temp
+    )
+}
+
+fn icon_for_path(path: &str) -> Option<(&'static str, &'static [u8])> {
+    match path {
+        "/favicon.ico" => Some(("image/x-icon", include_bytes!("static/icons/favicon.ico"))),
+        "/apple-touch-icon.png" | "/apple-touch-icon-precomposed.png" => Some((
+            "image/png",
+            include_bytes!("static/icons/apple-touch-icon.png"),
+        )),
+        "/icon-192.png" => Some(("image/png", include_bytes!("static/icons/icon-192.png"))),
+        // The Feather icons the templates draw with `<use>`
+        "/icons.svg" => Some(("image/svg+xml", include_bytes!("static/icons.svg"))),
+        _ => None,
+    }
+}
+
+// Pick a background for the favicon from the feather icon name.
+fn condition_colour(feather_icon: &str) -> &'static str {
+    match feather_icon {
+        "sun" => "#f5a623",
+        "cloud-rain" | "cloud-drizzle" => "#3d7edb",
+        "cloud-lightning" => "#5b3fc4",
+        "cloud-snow" => "#8fb8de",
+        "cloud" | "align-center" | "wind" => "#7a8594",
+        "alert-triangle" => "#b5452a",
+        _ => "#5151E5",
+    }
+}
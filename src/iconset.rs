@@ -0,0 +1,163 @@
+//! The icons drawn for the weather conditions.
+//!
+//! Each [`IconSet`] turns an OpenWeather condition into an [`Icon`], which
+//! the `weather_icon` template draws. The `icon_set` value in the
+//! `weather_config` store picks one:
+//!
+//! * `feather`, the default: Feather icons, from the embedded sprite
+//! * `emoji`: emoji, which need no script or images at all
+//! * `openweather`: OpenWeather's own PNG icons, loaded from their site
+//! * `sprite`: symbols from the SVG sprite at `icon_sprite_url`, with the
+//!   Feather names as their ids, for custom artwork
+//!
+//! Browsers only draw `<use>` references to the page's own origin, so the
+//! sprite must be served from this host, and `icon_sprite_url` is a path such
+//! as `/static/weather.svg`. Unknown values, and `sprite` without a usable
+//! sprite, fall back to Feather.
+
+use serde::Serialize;
+
+use crate::config;
+
+/// A way of drawing the weather conditions
+pub trait IconSet {
+    /// The icon for an OpenWeather condition code and icon, such as 500 and
+    /// "10d", with `label` as its text alternative.
+    fn icon(&self, condition: u32, icon: &str, label: &str) -> Icon;
+}
+
+/// A condition's icon, as the `weather_icon` template draws it. Exactly one
+/// of `feather`, `emoji`, `src` and `sprite` is set.
+#[derive(Serialize, Default)]
+pub struct Icon {
+    /// A Feather icon name
+    feather: Option<&'static str>,
+    emoji: Option<&'static str>,
+    /// An image URL
+    src: Option<String>,
+    /// A reference to a symbol in a sprite, for `<use>`
+    sprite: Option<String>,
+    label: String,
+    /// The class the icon is styled by, if any
+    class: Option<&'static str>,
+}
+
+impl Icon {
+    /// Style the icon with `class`.
+    pub fn with_class(self, class: &'static str) -> Self {
+        Icon {
+            class: Some(class),
+            ..self
+        }
+    }
+}
+
+/// The icon set chosen in the configuration.
+pub fn configured() -> Box<dyn IconSet> {
+    let set = config::get("icon_set").unwrap_or_default();
+    match set.trim() {
+        "emoji" => Box::new(Emoji),
+        "openweather" => Box::new(OpenWeather),
+        "sprite" => match config::get("icon_sprite_url").filter(|url| !url.trim().is_empty()) {
+            Some(url) if is_same_origin(url.trim()) => Box::new(Sprite {
+                url: url.trim().to_string(),
+            }),
+            Some(url) => {
+                println!(
+                    "The icon sprite {} isn't a path on this host, so Feather icons are drawn",
+                    url.trim()
+                );
+                Box::new(Feather)
+            }
+            None => Box::new(Feather),
+        },
+        _ => Box::new(Feather),
+    }
+}
+
+// Whether a URL is a path on the page's own host, rather than an absolute or
+// protocol-relative URL that could point anywhere.
+fn is_same_origin(url: &str) -> bool {
+    url.starts_with('/') && !url.starts_with("//") && !url.starts_with("/\\")
+}
+
+/// Feather icons, drawn from the sprite served at `/icons.svg`
+pub struct Feather;
+
+impl IconSet for Feather {
+    fn icon(&self, condition: u32, icon: &str, label: &str) -> Icon {
+        Icon {
+            feather: Some(weather_helpers::get_feather_weather_icon(condition, icon)),
+            label: label.to_string(),
+            ..Icon::default()
+        }
+    }
+}
+
+/// Emoji, one for each Feather icon
+pub struct Emoji;
+
+impl IconSet for Emoji {
+    fn icon(&self, condition: u32, icon: &str, label: &str) -> Icon {
+        let emoji = match (
+            condition,
+            weather_helpers::get_feather_weather_icon(condition, icon),
+        ) {
+            (781, _) => "🌪️",
+            (_, "moon") => "🌙",
+            (_, "cloud") => "☁️",
+            (_, "cloud-drizzle") => "🌦️",
+            (_, "cloud-rain") => "🌧️",
+            (_, "cloud-lightning") => "⛈️",
+            (_, "cloud-snow") => "🌨️",
+            (_, "align-center") => "🌫️",
+            (_, "wind") => "💨",
+            (_, "alert-triangle") => "⚠️",
+            _ => "☀️",
+        };
+        Icon {
+            emoji: Some(emoji),
+            label: label.to_string(),
+            ..Icon::default()
+        }
+    }
+}
+
+/// OpenWeather's PNG icons, by their icon code
+pub struct OpenWeather;
+
+impl IconSet for OpenWeather {
+    fn icon(&self, _condition: u32, icon: &str, label: &str) -> Icon {
+        // Only ever a code such as "10d", since it goes into a URL
+        let icon = icon.trim().trim_matches('"');
+        let code = if icon.len() == 3 && icon.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            icon
+        } else {
+            "01d"
+        };
+        Icon {
+            src: Some(format!("https://openweathermap.org/img/wn/{}@2x.png", code)),
+            label: label.to_string(),
+            ..Icon::default()
+        }
+    }
+}
+
+/// Symbols from an SVG sprite on this host, with Feather names as their ids
+pub struct Sprite {
+    url: String,
+}
+
+impl IconSet for Sprite {
+    fn icon(&self, condition: u32, icon: &str, label: &str) -> Icon {
+        Icon {
+            sprite: Some(format!(
+                "{}#{}",
+                self.url,
+                weather_helpers::get_feather_weather_icon(condition, icon)
+            )),
+            label: label.to_string(),
+            ..Icon::default()
+        }
+    }
+}
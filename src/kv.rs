@@ -0,0 +1,269 @@
+//! JSON documents in the `weather_kv` KV Store.
+//!
+//! Like the Config Stores, the KV Store may not be linked (preview
+//! deployments, local development), so reads come back empty and writes are
+//! skipped rather than failing the request. Built for the host, as for the
+//! tests and benchmarks, there's no store at all.
+//!
+//! The store can't increment a key atomically, so a document that every
+//! request updates is split into [`SHARDS`] documents, `<key>:0` to
+//! `<key>:7`. Each write updates one picked at random, so concurrent requests
+//! mostly update different documents rather than overwriting each other, and
+//! reads combine them all.
+//!
+//! Nor can it list keys, so records kept one per document, where concurrent
+//! writes to different records can't overwrite each other, are found again
+//! through an [`Index`] of their ids, sharded the same way.
+
+use std::net::IpAddr;
+
+#[cfg(target_arch = "wasm32")]
+use fastly::KVStore;
+use fastly::Request;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{shared, signing};
+
+#[cfg(target_arch = "wasm32")]
+const STORE_NAME: &str = "weather_kv";
+
+/// How many documents a busy key is split into
+pub const SHARDS: usize = 8;
+
+/// Read and deserialize the document stored under `key`.
+pub fn get_json<T: DeserializeOwned>(key: &str) -> Option<T> {
+    serde_json::from_slice(&lookup(key)?).ok()
+}
+
+/// Serialize `value` and store it under `key`, returning whether it was written.
+pub fn put_json<T: Serialize>(key: &str, value: &T) -> bool {
+    match serde_json::to_vec(value) {
+        Ok(body) => insert(key, body),
+        Err(_) => false,
+    }
+}
+
+/// Delete the document stored under `key`, returning whether it was deleted.
+pub fn delete(key: &str) -> bool {
+    remove(key)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn open() -> Option<KVStore> {
+    KVStore::open(STORE_NAME).ok().flatten()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn lookup(key: &str) -> Option<Vec<u8>> {
+    open()?.lookup_bytes(key).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn insert(key: &str, body: Vec<u8>) -> bool {
+    open().is_some_and(|mut store| store.insert(key, body).is_ok())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn remove(key: &str) -> bool {
+    open().is_some_and(|store| store.delete(key).is_ok())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn lookup(_key: &str) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn insert(_key: &str, _body: Vec<u8>) -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn remove(_key: &str) -> bool {
+    false
+}
+
+/// A count kept in `key`'s shards, each holding a plain number
+pub struct ShardedCount {
+    key: String,
+    shards: [u64; SHARDS],
+}
+
+impl ShardedCount {
+    /// Read every shard of the count under `key`.
+    pub fn load(key: &str) -> Self {
+        let mut shards = [0; SHARDS];
+        for (count, shard) in shards.iter_mut().zip(shard_keys(key)) {
+            *count = get_json(&shard).unwrap_or_default();
+        }
+        ShardedCount {
+            key: key.to_string(),
+            shards,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.shards.iter().sum()
+    }
+
+    /// Add one to a shard picked at random, returning whether it was written.
+    pub fn increment(&mut self) -> bool {
+        let shard = random_index();
+        self.shards[shard] += 1;
+        put_json(&shard_key(&self.key, shard), &self.shards[shard])
+    }
+}
+
+/// The ids of records kept one per document, listed in `key`'s shards. A
+/// record is added to a shard picked at random and remembers which, so
+/// concurrent additions rarely write the same shard and removals only
+/// rewrite the shards that list what's removed.
+pub struct Index {
+    key: &'static str,
+}
+
+impl Index {
+    pub const fn new(key: &'static str) -> Self {
+        Index { key }
+    }
+
+    /// Every listed id, with the shard that lists it.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        shard_keys(self.key)
+            .flat_map(|shard| {
+                load_ids(&shard)
+                    .into_iter()
+                    .map(move |id| (shard.clone(), id))
+            })
+            .collect()
+    }
+
+    /// How many ids are listed.
+    pub fn count(&self) -> usize {
+        shard_keys(self.key)
+            .map(|shard| load_ids(&shard).len())
+            .sum()
+    }
+
+    /// A shard picked at random, for a new record to be listed in.
+    pub fn random_shard(&self) -> String {
+        random_shard(self.key)
+    }
+
+    /// List `id` in `shard`, returning whether it was written. Write the
+    /// record first, so the index never lists one that isn't there.
+    pub fn add(&self, shard: &str, id: &str) -> bool {
+        let mut ids = load_ids(shard);
+        if !ids.iter().any(|listed| listed == id) {
+            ids.push(id.to_string());
+        }
+        put_json(shard, &ids)
+    }
+
+    /// Take `entries`, as (shard, id), out of the shards that list them.
+    pub fn remove(&self, entries: &[(&String, &String)]) {
+        let mut shards: Vec<&String> = entries.iter().map(|(shard, _)| *shard).collect();
+        shards.sort();
+        shards.dedup();
+        for shard in shards {
+            let mut ids = load_ids(shard);
+            ids.retain(|id| !entries.contains(&(shard, id)));
+            if !put_json(shard, &ids) {
+                println!("Couldn't update the index {}", shard);
+            }
+        }
+    }
+}
+
+/// A cap on how many times one client may do something in a while, such as
+/// sign up. Clients are counted by IPv4 address or IPv6 /64, under its HMAC
+/// with a secret key, so the store never holds an IP address.
+pub struct ClientLimit {
+    prefix: &'static str,
+    max: usize,
+    window_secs: i64,
+}
+
+impl ClientLimit {
+    /// At most `max` times in `window_secs`, noted under `prefix` and the
+    /// client's HMAC.
+    pub const fn new(prefix: &'static str, max: usize, window_secs: i64) -> Self {
+        ClientLimit {
+            prefix,
+            max,
+            window_secs,
+        }
+    }
+
+    /// Whether the client sending `req` may go again at `now`, noting that
+    /// it has if so.
+    pub fn note(&self, req: &Request, key: &[u8], now: i64) -> bool {
+        let network = match req.get_client_ip_addr() {
+            Some(IpAddr::V4(ip)) => ip.to_string(),
+            Some(IpAddr::V6(ip)) => {
+                let [a, b, c, d, ..] = ip.segments();
+                format!("{:x}:{:x}:{:x}:{:x}::/64", a, b, c, d)
+            }
+            None => return true,
+        };
+        let key = format!("{}{}", self.prefix, signing::sign(key, network.as_bytes()));
+        let mut times: Vec<i64> = get_json(&key).unwrap_or_default();
+        times.retain(|at| now - at < self.window_secs);
+        if times.len() >= self.max {
+            return false;
+        }
+        times.push(now);
+        put_json(&key, &times);
+        true
+    }
+}
+
+fn load_ids(shard: &str) -> Vec<String> {
+    get_json(shard).unwrap_or_default()
+}
+
+/// Up to `max` of `entries`, carrying on from where the last page under
+/// `cursor_key` stopped and wrapping round to the start.
+pub fn next_page<'a, T>(entries: &'a [T], cursor_key: &str, max: usize) -> Vec<&'a T> {
+    let cursor = match entries.len() {
+        0 => 0,
+        len => get_json::<usize>(cursor_key).unwrap_or_default() % len,
+    };
+    if entries.len() > max {
+        put_json(cursor_key, &(cursor + max));
+    }
+    entries
+        .iter()
+        .cycle()
+        .skip(cursor)
+        .take(entries.len().min(max))
+        .collect()
+}
+
+/// Add one to a shard of the count under `key` picked at random, without
+/// reading the others, returning whether it was written.
+pub fn increment(key: &str) -> bool {
+    let shard = random_shard(key);
+    let count: u64 = get_json(&shard).unwrap_or_default();
+    put_json(&shard, &(count + 1))
+}
+
+/// The key of one of `key`'s shards, picked at random.
+pub fn random_shard(key: &str) -> String {
+    shard_key(key, random_index())
+}
+
+/// The keys of all of `key`'s shards.
+pub fn shard_keys(key: &str) -> impl Iterator<Item = String> + '_ {
+    (0..SHARDS).map(move |shard| shard_key(key, shard))
+}
+
+fn shard_key(key: &str, shard: usize) -> String {
+    format!("{}:{}", key, shard)
+}
+
+fn random_index() -> usize {
+    let [byte] = shared::random_bytes::<1>();
+    byte as usize % SHARDS
+}
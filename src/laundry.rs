@@ -0,0 +1,104 @@
+//! The laundry card: the best daylight hours over the next two days to get
+//! washing dry on the line.
+
+use chrono::{FixedOffset, TimeZone, Timelike};
+use serde::Serialize;
+
+use crate::{windows, APIResponse};
+
+// Hours scoring at least this will dry a load.
+const GOOD_SCORE: u8 = 60;
+const MAX_WINDOWS: usize = 3;
+
+// Dew settles overnight, so only daylight hours count.
+const FIRST_HOUR: u32 = 7;
+const LAST_HOUR: u32 = 19;
+
+/// Context for the laundry fragment
+#[derive(Serialize)]
+pub struct LaundryCard {
+    windows: Vec<String>,
+}
+
+impl LaundryCard {
+    /// The best drying windows over the next 48 hours, from a report fetched
+    /// in `units`.
+    pub fn new(api_response: &APIResponse, units: &str) -> Option<Self> {
+        let tz = FixedOffset::east_opt(api_response.timezone_offset)?;
+        if api_response.hourly.is_empty() {
+            return None;
+        }
+
+        let scores: Vec<u8> = api_response
+            .hourly
+            .iter()
+            .take(48)
+            .map(|hour| {
+                let daylight = tz
+                    .timestamp_opt(hour.dt, 0)
+                    .single()
+                    .is_some_and(|local| (FIRST_HOUR..=LAST_HOUR).contains(&local.hour()));
+                if !daylight {
+                    return 0;
+                }
+                let wind_speed = match units {
+                    "imperial" => hour.wind_speed / 2.237,
+                    _ => hour.wind_speed,
+                };
+                drying_score(
+                    weather_helpers::to_celsius(hour.temp, units),
+                    hour.humidity,
+                    wind_speed,
+                    hour.pop,
+                )
+            })
+            .collect();
+
+        let windows = windows::best_windows(&scores, GOOD_SCORE)
+            .into_iter()
+            // A single hour isn't enough to dry anything.
+            .filter(|window| window.len() >= 2)
+            .take(MAX_WINDOWS)
+            .filter_map(|window| {
+                let start = tz
+                    .timestamp_opt(api_response.hourly[window.start].dt, 0)
+                    .single()?;
+                let end = tz
+                    .timestamp_opt(api_response.hourly[window.end - 1].dt + 3600, 0)
+                    .single()?;
+                Some(format!(
+                    "{} {}–{}",
+                    start.format("%a"),
+                    start.format("%H:%M"),
+                    end.format("%H:%M")
+                ))
+            })
+            .collect();
+
+        Some(LaundryCard { windows })
+    }
+}
+
+/// Score how well washing dries on the line in an hour, from 0 (don't bother)
+/// to 100 (dry in no time), from the temperature in °C, relative humidity in %,
+/// wind speed in m/s and the probability of precipitation from 0 to 1. Drying
+/// is mostly driven by how thirsty the air is, so the score is built on the
+/// vapour pressure deficit, helped along by a breeze.
+pub fn drying_score(temp: f32, humidity: f32, wind_speed: f32, pop: f32) -> u8 {
+    // Saturation vapour pressure in kPa (Tetens)
+    let saturation = 0.6108 * (17.27 * temp / (temp + 237.3)).exp();
+    let deficit = saturation * (1.0 - humidity.clamp(0.0, 100.0) / 100.0);
+
+    let mut score = (deficit / 1.5).min(1.0) * 65.0;
+    // A breeze helps, but a gale takes the pegs with it.
+    score += match wind_speed.max(0.0) {
+        w if w <= 6.0 => w / 6.0 * 25.0,
+        w => (25.0 - (w - 6.0) * 5.0).max(0.0),
+    };
+    if temp > 15.0 {
+        score += 10.0;
+    }
+    score *= 1.0 - pop.clamp(0.0, 1.0);
+
+    score.clamp(0.0, 100.0).round() as u8
+}
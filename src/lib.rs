@@ -0,0 +1,1284 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use fastly::cache::simple::{self, get_or_set_with, CacheEntry, CacheError};
+use fastly::{
+    http::{header, Method, StatusCode},
+    Error, Request, Response,
+};
+
+mod admin;
+mod air;
+mod alerts;
+mod assets;
+pub mod astronomy;
+mod auth;
+pub mod aviation;
+mod bots;
+pub mod budget;
+mod canary;
+pub mod chart;
+mod compact;
+mod compose;
+mod conditional;
+pub mod conditions;
+pub mod confidence;
+mod config;
+mod context;
+mod cookies;
+mod csp;
+pub mod dayparts;
+mod device;
+mod digest;
+mod dispatch;
+mod ensemble;
+pub mod es256;
+pub mod exercise;
+pub mod experiments;
+pub mod fire;
+pub mod flags;
+mod forecast;
+pub mod garden;
+mod geocode;
+mod geohash;
+mod homeassistant;
+mod icons;
+mod iconset;
+mod kv;
+pub mod laundry;
+pub mod location;
+mod map;
+mod marine;
+mod metering;
+mod mock;
+mod notify;
+pub mod openmeteo;
+mod outbound;
+mod policy;
+pub mod precipitation;
+mod precision;
+mod pressure;
+mod privacy;
+pub mod problem;
+mod provider;
+mod push;
+mod radar;
+mod redirect;
+pub mod roads;
+mod rum;
+mod sanitize;
+mod share;
+mod shared;
+pub mod signing;
+mod snow;
+mod subscriptions;
+pub mod templates;
+mod timing;
+mod trending;
+mod umbrella;
+pub mod view;
+pub mod weekend;
+pub mod wind;
+pub mod windows;
+
+use budget::Budget;
+use canary::Upstream;
+use context::ReqCtx;
+use experiments::Assignments;
+use flags::Flags;
+use location::Location;
+use metering::Usage;
+use timing::Timings;
+use view::{Device, Fragment, Layout, PageOptions, Place, View};
+
+// Define a constant for the backend name, as shown in your Fastly service:
+const BACKEND_NAME: &str = "api.openweathermap.org";
+
+/// The RUM beacon script, served at `/rum.js`
+const RUM_JS: &str = include_str!("static/rum.js");
+
+// How long to keep weather API responses while the call budget is nearly spent.
+const CONSERVE_TTL: Duration = Duration::from_secs(1800);
+
+/// How [`fetch_weather_reusing`] may answer with a response fetched before
+#[derive(Clone, Copy)]
+enum Reuse {
+    /// Only while the call budget says to save calls
+    WhenConserving,
+    /// Any from the last `Duration`, or else call the API and keep it that long
+    Within(Duration),
+    /// Only one that's already cached, never calling the API
+    CachedOnly,
+}
+
+/// The page's query string. Public, like [`normalize_query`] and
+/// [`sanitize_query`], for the fuzz targets.
+#[derive(Deserialize)]
+pub struct QueryParams {
+    mode: Option<String>,
+    /// Fitzpatrick skin type, 1 to 6, for the UV exposure estimate
+    skin: Option<String>,
+    /// `on` to show cloud cover and visibility on the conditions card
+    details: Option<String>,
+    /// `gmi` for the page as Gemtext
+    format: Option<String>,
+}
+
+impl QueryParams {
+    /// The skin type, if it's a number.
+    pub fn skin_type(&self) -> Option<u8> {
+        self.skin.as_deref().and_then(|skin| skin.parse().ok())
+    }
+
+    /// Whether the conditions card shows the extra details.
+    pub fn shows_details(&self) -> bool {
+        self.details.as_deref() == Some("on")
+    }
+}
+
+pub use redirect::normalize_query;
+pub use sanitize::sanitize_query;
+
+/// How the body of the composed page gets to the client
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    /// Rendered in full, then returned as a response
+    Buffered,
+    /// Sent as it's rendered, after the headers
+    Streamed,
+}
+
+/// Handle a request from a visitor, rendering the whole response before
+/// returning it.
+pub fn handle_request(req: Request) -> Result<Response, Error> {
+    Ok(respond(req, Delivery::Buffered)?.expect("buffered responses are returned"))
+}
+
+/// Handle a request from a visitor and send the response, streaming the
+/// composed page so the top of it arrives while the rest renders. This is
+/// the whole application: the binary's entry point just calls it.
+pub fn serve(req: Request) -> Result<(), Error> {
+    if let Some(resp) = respond(req, Delivery::Streamed)? {
+        resp.send_to_client();
+    }
+    Ok(())
+}
+
+// The response, or `None` if the page has already been streamed.
+fn respond(req: Request, delivery: Delivery) -> Result<Option<Response>, Error> {
+    let path = req.get_path().to_string();
+    // HEAD gets the same headers as GET, with the body dropped, so it needs the whole body.
+    let head = req.get_method() == Method::HEAD;
+    let delivery = if head { Delivery::Buffered } else { delivery };
+
+    let mut resp = route(req, delivery);
+    // API clients get their errors as problem details, whichever step failed
+    if problem::is_api(&path) {
+        resp = match resp {
+            Ok(resp) => Ok(resp.map(|resp| problem::finish(&path, resp))),
+            Err(err) => {
+                println!("Couldn't serve {}: {}", path, err);
+                Ok(Some(problem::internal(&path)))
+            }
+        };
+    }
+
+    if head {
+        let mut resp = resp?.expect("buffered responses are returned");
+        let length = resp.take_body_bytes().len();
+        return Ok(Some(
+            resp.with_header(header::CONTENT_LENGTH, length.to_string()),
+        ));
+    }
+    resp
+}
+
+// Check the request is one we serve, then hand it to its handler.
+fn route(mut req: Request, delivery: Delivery) -> Result<Option<Response>, Error> {
+    // Send visitors on non-canonical hosts or plain HTTP to the canonical HTTPS URL.
+    if let Some(redirect) = redirect::canonical_redirect(&req) {
+        return Ok(Some(redirect));
+    }
+
+    // Handlers only see the query parameters they know, in the shape they expect
+    if let Some(rejection) = sanitize_query(&mut req) {
+        return Ok(Some(rejection));
+    }
+
+    // Equivalent query strings should look the same to every handler
+    normalize_query(&mut req);
+
+    let allowed = allowed_methods(req.get_path());
+    let allow_header = allowed
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Answer preflight and capability checks without running the handler.
+    if req.get_method() == Method::OPTIONS {
+        return Ok(Some(
+            Response::from_status(StatusCode::NO_CONTENT).with_header(header::ALLOW, allow_header),
+        ));
+    }
+
+    // Return early if the route doesn't support the request method.
+    if !allowed.contains(req.get_method()) {
+        return Ok(Some(
+            Response::from_status(StatusCode::METHOD_NOT_ALLOWED)
+                .with_header(header::ALLOW, allow_header)
+                .with_body_text_plain("This method is not allowed"),
+        ));
+    }
+
+    handle(req, delivery).or_else(upstream_unavailable)
+}
+
+/// The weather API refused the request: out of quota (429) or a rejected key (401).
+#[derive(Debug)]
+struct Unavailable {
+    status: StatusCode,
+    retry_after: Option<String>,
+}
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "weather API returned {}", self.status)
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+/// Show a friendly page, rather than a 500, for errors caused by the weather
+/// API turning us away.
+fn upstream_unavailable(err: Error) -> Result<Option<Response>, Error> {
+    match err.downcast::<Unavailable>() {
+        Ok(unavailable) => {
+            println!("{}, showing the unavailable page", unavailable);
+            Ok(Some(view::unavailable(unavailable.retry_after.as_deref())))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The methods each route accepts.
+fn allowed_methods(path: &str) -> &'static [Method] {
+    match path {
+        "/rum" => &[Method::POST, Method::OPTIONS],
+        "/api/subscriptions" | "/api/push/subscribe" | "/api/digest" => {
+            &[Method::POST, Method::DELETE, Method::OPTIONS]
+        }
+        "/internal/dispatch" | "/api/digest/unsubscribe" => &[Method::POST, Method::OPTIONS],
+        path if admin::is_admin(path) => admin::allowed_methods(path),
+        // Every other route, including the 404 fallback, is read-only.
+        _ => &[Method::GET, Method::HEAD, Method::OPTIONS],
+    }
+}
+
+/// Route a request to its handler.
+fn handle(mut req: Request, delivery: Delivery) -> Result<Option<Response>, Error> {
+    // Performance beacons are the only requests with a body
+    if req.get_path() == "/rum" {
+        // Visitors who asked not to be tracked aren't measured either
+        if privacy::do_not_track(&req) {
+            return Ok(Some(Response::from_status(StatusCode::NO_CONTENT)));
+        }
+        return Ok(Some(rum::collect(req)));
+    }
+
+    // Operator endpoints need the admin token
+    if admin::is_admin(req.get_path()) {
+        if let Some(resp) = admin::authorize(&req) {
+            return Ok(Some(resp));
+        }
+    }
+
+    // The JSON API can be closed to all but known clients, who are metered
+    let mut usage = None;
+    let mut client = None;
+    if auth::is_protected(req.get_path()) {
+        match auth::authorize(&mut req) {
+            auth::Access::Open => {}
+            auth::Access::Client(signed_by) => {
+                let mut client_usage = Usage::load(&signed_by);
+                if req.get_path() == "/api/usage" {
+                    return Ok(Some(client_usage.report()));
+                }
+                if client_usage.is_exhausted() {
+                    return Ok(Some(client_usage.over_quota()));
+                }
+                client_usage.record();
+                usage = Some(client_usage);
+                client = Some(signed_by);
+            }
+            auth::Access::Denied(resp) => return Ok(Some(*resp)),
+        }
+    }
+
+    let mut ctx = ReqCtx::new(&req);
+
+    let mut resp = match req.get_path() {
+        path if path == "/"
+            || path == "/amp"
+            || path == "/m"
+            || path == "/shared"
+            || Layout::from_path(path).is_some()
+            || Fragment::from_path(path).is_some() =>
+        {
+            // Crawlers all get the same page, rendered once in a while for the
+            // default location. Without one, they're served like anyone else,
+            // so nothing a client sends ends up in the shared page.
+            let crawler_location = (req.get_path() == "/" && bots::is_crawler(&req))
+                .then(Location::configured_default)
+                .flatten();
+            let crawler = crawler_location.is_some();
+            if crawler {
+                if let Some(resp) = bots::cached_page(&req) {
+                    return Ok(Some(resp));
+                }
+            }
+
+            // Get the end user's location, or the one pinned by a share link.
+            // Crawlers get the default location.
+            let shared = req.get_path() == "/shared";
+            let location = if shared {
+                match share::verify(&req) {
+                    share::Shared::Valid(location) => location,
+                    share::Shared::Expired => return Ok(Some(view::share_unusable(true))),
+                    share::Shared::Invalid => return Ok(Some(view::share_unusable(false))),
+                }
+            } else if let Some(location) = crawler_location {
+                location
+            } else {
+                match ctx.location(&req)? {
+                    Some(location) => location,
+                    // Visitors who turned IP geolocation off choose where they are
+                    None => {
+                        let mut resp = view::location_picker();
+                        location::remember_choice(&req, &mut resp);
+                        return Ok(Some(resp));
+                    }
+                }
+            };
+            // Log output helps you debug issues when developing your service.
+            // Run `fastly log-tail` to see this output live as you make requests.
+            println!("Requesting weather for {}", privacy::describe(&location));
+
+            // Fetch the query string and parse it into the `QueryParams` type
+            let mut query: QueryParams = req.get_query()?;
+
+            // The crawler page is the standard one, in units there are cached
+            // pages for
+            if crawler {
+                ctx.units = bots::units(&req).to_string();
+                query.mode = None;
+                query.skin = None;
+                query.details = None;
+                query.format = None;
+            }
+            let units = ctx.units.as_str();
+
+            // Leave out the forecast blocks this response won't show
+            let exclude = unused_forecast_blocks(req.get_path(), query.mode.as_deref());
+            let mut api_response =
+                fetch_weather(&location, units, &exclude, &ctx.flags, &mut ctx.timings)?;
+
+            // The local time where the weather is, rather than at the edge
+            let local =
+                weather_helpers::local_time(api_response.current.dt, api_response.timezone_offset);
+
+            // Marine conditions for coastal visitors who ask for them
+            let marine =
+                if query.mode.as_deref() == Some("marine") && ctx.flags.enabled("enable_marine") {
+                    // Inland, or with the marine API down, there's just no card.
+                    ctx.timings
+                        .time("marine", || marine::fetch(&location, units))
+                        .map_err(|err| println!("Couldn't fetch the marine forecast: {:?}", err))
+                        .ok()
+                        .flatten()
+                } else {
+                    None
+                };
+
+            // Fire danger only matters on the page during fire season
+            let fire = Some(fire::FireCard::new(&api_response, units, &location))
+                .filter(|card| card.fire_season);
+
+            let astronomy = astronomy::AstronomyCard::new(&api_response, &location);
+
+            // Phones get the compact layout; crawlers all get the full one
+            let device = if crawler {
+                Device::Desktop
+            } else {
+                device::detect(&req)
+            };
+
+            // The best hours for a run, which needs the air quality forecast too.
+            // The mobile layout leaves the card out, so it needn't be fetched.
+            let exercise = if ctx.flags.enabled("enable_exercise")
+                && !api_response.hourly.is_empty()
+                && (device == Device::Desktop || Fragment::from_path(req.get_path()).is_some())
+            {
+                // The card is optional, so the page goes without it rather
+                // than failing with the air quality API.
+                ctx.timings
+                    .time("air", || air::forecast(&location))
+                    .map_err(|err| println!("Couldn't fetch the air quality forecast: {:?}", err))
+                    .ok()
+                    .and_then(|air_quality| {
+                        exercise::ExerciseCard::new(&api_response, units, &air_quality)
+                    })
+            } else {
+                None
+            };
+
+            let laundry = laundry::LaundryCard::new(&api_response, units);
+
+            // How sure each day's forecast is, going by a second opinion. Only
+            // the daily forecast card shows it.
+            let confidence = match api_response.confidence.take() {
+                // The ensemble has already compared them
+                Some(confidence) => Some(confidence),
+                None if ctx.flags.enabled("enable_confidence")
+                    && api_response.daily.len() > 3
+                    && (req.get_path() == "/"
+                        || matches!(
+                            Fragment::from_path(req.get_path()),
+                            Some(Fragment::Daily)
+                        )) =>
+                {
+                    // Without a second opinion the days just go unrated.
+                    ctx.timings
+                        .time("confidence", || {
+                            confidence::DailyConfidence::fetch(&location, &api_response, units)
+                        })
+                        .map_err(|err| println!("Couldn't rate the forecast days: {:?}", err))
+                        .ok()
+                }
+                None => None,
+            };
+
+            // Black ice warnings for drivers, all winter and whenever there's a risk
+            let roads = roads::RoadCard::new(&api_response, units, &location);
+
+            // Which way the pressure is heading, going by earlier page views
+            let pressure_trend = pressure::track(&api_response, &location);
+
+            // Render the AMP variant for publishers embedding the page, the
+            // minimal one for feature phones, Gemtext for smolweb gateways, the
+            // widget, kiosk or print layout, or a single fragment of the
+            // composed page
+            let view = match req.get_path() {
+                "/amp" => View::Amp,
+                "/m" => View::Minimal,
+                "/" if query.format.as_deref() == Some("gmi") => View::Gemtext,
+                "/" if query.mode.as_deref() == Some("snow") => {
+                    View::Snow(ctx.timings.time("snow", || snow::fetch(&location, units))?)
+                }
+                path => match (Fragment::from_path(path), Layout::from_path(path)) {
+                    (Some(fragment), _) => View::Fragment(fragment),
+                    (None, Some(layout)) => View::Layout(layout),
+                    (None, None) => View::Standard,
+                },
+            };
+
+            // Count full page views towards the trending locations, leaving out
+            // visitors shown the default location for want of their own,
+            // widgets showing wherever the embedding site asked for, and places
+            // named by the client, in a picked place or a share link, whose
+            // names would be shown to everyone
+            if Fragment::from_path(req.get_path()).is_none()
+                && !location.is_default()
+                && !location.is_picked()
+                && !shared
+                && !ctx.has_coordinates()
+                && !crawler
+                && !privacy::do_not_track(&req)
+            {
+                trending::record_view(&location);
+            }
+
+            // Bucket the visitor into any running template experiments
+            let experiments = if crawler {
+                Assignments::default()
+            } else {
+                Assignments::for_request(&req)
+            };
+
+            // Every variant points search engines at the full page
+            let canonical_url = redirect::canonical_url(&req, "/", units);
+
+            // Name days and months in the visitor's language
+            let locale = weather_helpers::Locale::negotiate(
+                req.get_header_str(header::ACCEPT_LANGUAGE)
+                    .filter(|_| !crawler),
+                location.country_code(),
+            );
+
+            // The next two days' temperatures, drawn for the desktop page
+            let chart = chart::TemperatureChart::new(&api_response, locale);
+            let wind_rose = wind::WindRose::new(&api_response);
+            let precipitation = precipitation::PrecipitationTotals::new(&api_response, units);
+            let weekend = weekend::WeekendCard::new(&api_response, units, locale);
+            let day_parts = dayparts::DayParts::new(&api_response, locale);
+
+            // Frost warnings and degree days for gardeners who ask for them
+            let garden = if query.mode.as_deref() == Some("garden") {
+                garden::GardenCard::new(&api_response, units, locale)
+            } else {
+                None
+            };
+
+            // An optional skin type tailors the UV exposure estimate
+            let skin_type = query.skin_type();
+
+            let mut resp = Response::new().with_status(StatusCode::OK);
+            if matches!(view, View::Gemtext) {
+                resp.set_header(header::CONTENT_TYPE, "text/gemini; charset=utf-8");
+            } else {
+                resp.set_content_type(fastly::mime::TEXT_HTML_UTF_8);
+            }
+            experiments.tag(&mut resp);
+            location::remember_choice(&req, &mut resp);
+
+            // Pages depend on the visitor's IP location, so shared caches must never
+            // store them. The experiment cookie picks the layout, the
+            // language follows Accept-Language, and Save-Data drops the
+            // background photo.
+            resp.set_header(header::CACHE_CONTROL, "private, max-age=300");
+            resp.set_header(header::VARY, "Cookie, Accept-Language, Save-Data");
+            if crawler {
+                bots::tag(&mut resp);
+            } else {
+                device::tag(&mut resp);
+            }
+
+            // Only the inline script with this response's nonce may run
+            let nonce = csp::nonce();
+            if matches!(view, View::Standard | View::Layout(_)) {
+                csp::tag(&mut resp, &nonce);
+            }
+
+            let streamed =
+                delivery == Delivery::Streamed && matches!(view, View::Standard) && !crawler;
+            let mut place = Place::from(&location);
+            // Recipients of a share link see the map of the shared place, not their own
+            if shared {
+                place.map_url = Some(format!(
+                    "/map.png?{}",
+                    req.get_query_str().unwrap_or_default()
+                ));
+                resp.set_header("X-Robots-Tag", "noindex");
+            }
+            let options = PageOptions {
+                units,
+                view,
+                experiments: &experiments,
+                canonical_url: &canonical_url,
+                show_radar: ctx.flags.enabled("enable_radar"),
+                marine,
+                fire,
+                astronomy,
+                garden,
+                exercise,
+                laundry,
+                roads,
+                confidence,
+                chart,
+                wind_rose,
+                precipitation,
+                weekend,
+                day_parts,
+                pressure_trend,
+                show_details: query.shows_details(),
+                skin_type,
+                locale,
+                device,
+                nonce: &nonce,
+                save_data: ctx.save_data,
+            };
+
+            // Send the headers now and the composed page as it renders. The
+            // fetches are done, so nothing left can fail with another status.
+            if streamed {
+                ctx.timings.add_header(&mut resp);
+                let mut body = resp.stream_to_client();
+                let written = ctx.timings.time("render", || {
+                    view::write_view(api_response, &place, local, options, &mut body)
+                });
+                match written {
+                    Ok(()) => body.finish()?,
+                    Err(err) => println!("Streaming the page failed: {}", err),
+                }
+                ctx.timings.log(req.get_path(), &ctx.request_id);
+                return Ok(None);
+            }
+
+            let body_response = ctx.timings.time("render", || {
+                view::generate_view(api_response, &place, local, options)
+            });
+            if crawler {
+                bots::store_page(&req, &nonce, &body_response);
+            }
+            resp.with_body(body_response)
+        }
+        // Point at the background photo for the season
+        "/bg-image.jpg" => {
+            // Pages name the season they picked, so there's no locating the visitor again
+            let season = match assets::requested_season(&req) {
+                Some(season) => season,
+                None => match ctx.location(&req)? {
+                    Some(location) => {
+                        let local = weather_helpers::local_time(ctx.now, location.utc_offset());
+                        weather_helpers::get_season(location.latitude(), local)
+                    }
+                    // Without a location there's no season, so use the all-year image
+                    None => weather_helpers::Season::Summer,
+                },
+            };
+            assets::background(season)
+        }
+        // The background photos themselves
+        path if path.starts_with("/img/") => match assets::image(path) {
+            Some(resp) => resp,
+            None => view::not_found(path),
+        },
+
+        // Map of the detected location
+        "/map.png" => {
+            let location = if share::is_shared(&req) {
+                match share::verify(&req) {
+                    share::Shared::Valid(location) => Some(location),
+                    _ => None,
+                }
+            } else {
+                ctx.location(&req)?
+            };
+            match location {
+                Some(location) => map::handle(&location)?,
+                None => view::not_found(req.get_path()),
+            }
+        }
+
+        // Favicon showing the current temperature and condition
+        "/favicon.svg" => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(icons::serve("/favicon.ico"))),
+            };
+            let exclude = ["minutely", "hourly", "daily", "alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+
+            let weather = api_response.current.weather.first();
+            icons::dynamic_favicon(
+                weather_helpers::round_temperature(api_response.current.temp),
+                weather.map_or(0, |weather| weather.id),
+                weather.map_or("", |weather| weather.icon.as_str()),
+            )
+        }
+
+        // Latest METAR and TAF for an aerodrome
+        path if path.starts_with("/metar/") => match aviation::handle(path) {
+            Some(resp) => resp?,
+            None => view::not_found(path),
+        },
+
+        // Precipitation radar tiles for the map section
+        path if path.starts_with("/radar/") => match radar::handle(path) {
+            Some(resp) => resp?,
+            None => view::not_found(path),
+        },
+
+        // Serve embedded favicons and touch icons
+        path if icons::is_icon(path) => icons::serve(path),
+
+        // City search for the location picker, and where it sends the choice
+        "/api/geocode" => geocode::handle(&req)?,
+        "/place" => location::pick(&req),
+
+        // Signed clients see their own usage above; without keys there's none
+        "/api/usage" => metering::unmetered(),
+
+        // Most viewed locations over the last day
+        "/api/trending" => trending::handle(&req),
+
+        // Fire danger for the visitor's location, in or out of season
+        "/api/fire" => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            let exclude = ["minutely", "hourly", "alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            conditional::respond(
+                &req,
+                api_response.current.dt,
+                fire::handle(&fire::FireCard::new(&api_response, &ctx.units, &location))?,
+            )
+        }
+
+        // How much rain and snow the next two days bring
+        "/api/precipitation" => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            // The umbrella call reads the minutely forecast and today's sunset.
+            let exclude = ["alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            let resp = match precipitation::PrecipitationTotals::new(&api_response, &ctx.units) {
+                Some(totals) => precipitation::handle(&totals)?,
+                None => view::not_found(req.get_path()),
+            };
+            conditional::respond(&req, api_response.current.dt, resp)
+        }
+
+        // Runs of hours that meet the client's conditions
+        "/api/windows" => {
+            let constraints = match windows::Constraints::from_request(&req) {
+                Ok(constraints) => constraints,
+                Err(resp) => return Ok(Some(*resp)),
+            };
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            let exclude = ["minutely", "daily", "alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            conditional::respond(
+                &req,
+                api_response.current.dt,
+                windows::handle(&constraints, &api_response)?,
+            )
+        }
+
+        // The hourly or daily forecast over the range the client asks for
+        "/api/forecast" => {
+            let range = match forecast::Range::from_request(&req) {
+                Ok(range) => range,
+                Err(resp) => return Ok(Some(*resp)),
+            };
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            let exclude = ["minutely", "alerts", range.granularity.unused_block()];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            conditional::respond(
+                &req,
+                api_response.current.dt,
+                forecast::handle(&req, &range, &api_response)?,
+            )
+        }
+
+        // Just the alerts, cheap to poll
+        "/api/alerts" => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            match alerts::cached(&location) {
+                Some((observed, resp)) => conditional::respond(&req, observed, resp),
+                None => {
+                    let api_response = fetch_weather(
+                        &location,
+                        "metric",
+                        &alerts::EXCLUDE,
+                        &ctx.flags,
+                        &mut ctx.timings,
+                    )?;
+                    conditional::respond(
+                        &req,
+                        api_response.current.dt,
+                        alerts::handle(&location, &api_response)?,
+                    )
+                }
+            }
+        }
+
+        // A few bytes of forecast for watch faces
+        "/api/compact" => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            let exclude = ["minutely", "alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            conditional::respond(
+                &req,
+                api_response.current.dt,
+                compact::handle(&api_response)?,
+            )
+        }
+
+        // Current readings for Home Assistant, all together or one at a time
+        path if path == "/api/homeassistant" || path.starts_with("/api/sensor/") => {
+            let location = match ctx.location(&req)? {
+                Some(location) => location,
+                None => return Ok(Some(view::not_found(req.get_path()))),
+            };
+            let exclude = ["minutely", "alerts"];
+            let api_response = fetch_weather(
+                &location,
+                &ctx.units,
+                &exclude,
+                &ctx.flags,
+                &mut ctx.timings,
+            )?;
+            let resp = match path.strip_prefix("/api/sensor/") {
+                Some(key) => match homeassistant::sensor(key, &api_response, &ctx.units) {
+                    Some(resp) => resp?,
+                    None => view::not_found(path),
+                },
+                None => homeassistant::handle(&api_response, &ctx.units)?,
+            };
+            conditional::respond(&req, api_response.current.dt, resp)
+        }
+
+        // Webhooks for when the weather crosses a client's threshold
+        "/api/subscriptions" => subscriptions::handle(&mut req, client.as_deref())?,
+
+        // Web Push when severe weather alerts are issued for the visitor's location
+        "/api/push/key" => push::key(),
+        "/api/push/subscribe" => push::subscribe(&mut req)?,
+
+        // Morning forecast emails, and their one-click unsubscribe
+        "/api/digest" => digest::handle(&mut req)?,
+        "/api/digest/unsubscribe" => digest::unsubscribe(&req)?,
+        "/api/digest/confirm" => digest::confirm(&req)?,
+
+        // Sends what the webhooks, push subscriptions and digests are waiting for
+        "/internal/dispatch" => match admin::authorize(&req) {
+            Some(resp) => resp,
+            None => dispatch::handle(&ctx.flags, &mut ctx.timings)?,
+        },
+
+        // Parse the templates without rendering anything, to warm an instance
+        "/warmup" => {
+            templates::warm_up();
+            Response::from_status(StatusCode::NO_CONTENT)
+                .with_header(header::CACHE_CONTROL, "no-store")
+        }
+
+        // Signed links to the forecast for the visitor's location
+        "/share" => share::handle(&req)?,
+
+        // What privacy mode does, and whether it's on
+        "/privacy" => privacy::handle(),
+
+        // What the JSON API offers
+        "/docs" => view::api_docs(),
+
+        // Cache purges and the resolved configuration, for operators
+        "/admin/purge" => admin::purge(&req)?,
+        "/admin/config" => admin::config(),
+
+        // Crawl rules and security contact details
+        "/robots.txt" => policy::robots_txt(),
+        "/.well-known/security.txt" => policy::security_txt(),
+
+        // Serve static CSS and JS files
+        "/style.css" => Response::from_body(include_str!("static/style.css"))
+            .with_content_type(fastly::mime::TEXT_CSS),
+        "/style-rtl.css" => Response::from_body(include_str!("static/style-rtl.css"))
+            .with_content_type(fastly::mime::TEXT_CSS),
+        "/rum.js" => Response::from_body(RUM_JS).with_content_type(fastly::mime::TEXT_JAVASCRIPT),
+
+        // Catch all other requests and return a 404.
+        path => view::not_found(path),
+    };
+
+    if let Some(usage) = usage {
+        usage.tag(&mut resp);
+    }
+    ctx.timings
+        .report(req.get_path(), &ctx.request_id, &mut resp);
+    Ok(Some(resp))
+}
+
+/// Struct representing API response
+#[derive(Deserialize)]
+pub struct APIResponse {
+    /// Seconds east of UTC at the requested location
+    #[serde(default)]
+    timezone_offset: i32,
+    current: CurrentReport,
+    /// The forecast blocks are empty when excluded from the request
+    #[serde(default)]
+    daily: Vec<DailyReport>,
+    #[serde(default)]
+    minutely: Vec<MinutelyReport>,
+    #[serde(default)]
+    hourly: Vec<HourlyReport>,
+    /// Absent when there are none
+    #[serde(default)]
+    alerts: Vec<AlertReport>,
+    /// How far the `ensemble` provider's sources agreed on each day
+    #[serde(skip)]
+    confidence: Option<confidence::DailyConfidence>,
+}
+
+/// Struct representing a single response entry
+#[derive(Deserialize)]
+struct CurrentReport {
+    /// Unix time of the observation
+    dt: i64,
+    temp: f32,
+    wind_speed: f32,
+    humidity: f32,
+    /// Sea-level pressure in hPa
+    #[serde(default)]
+    pressure: Option<f32>,
+    #[serde(default)]
+    dew_point: Option<f32>,
+    /// Cloud cover in %
+    #[serde(default)]
+    clouds: Option<f32>,
+    /// Visibility in metres, up to 10km
+    #[serde(default)]
+    visibility: Option<f32>,
+    #[serde(default)]
+    uvi: f32,
+    weather: Vec<WeatherReport>,
+    /// What fell in the last hour, when anything did
+    #[serde(default)]
+    rain: Option<HourlyPrecipitation>,
+    #[serde(default)]
+    snow: Option<HourlyPrecipitation>,
+}
+
+/// Struct representing a single day's weather
+#[derive(Deserialize)]
+struct DailyReport {
+    dt: i32,
+    temp: Temperatures,
+    weather: Vec<WeatherReport>,
+    /// Snowfall in millimetres, only present on snowy days
+    #[serde(default)]
+    snow: Option<f32>,
+    /// Rainfall in millimetres, only present on rainy days
+    #[serde(default)]
+    rain: Option<f32>,
+    /// The chance of rain from 0 to 1
+    #[serde(default)]
+    pop: Option<f32>,
+    #[serde(default)]
+    moonrise: i64,
+    #[serde(default)]
+    moonset: i64,
+    #[serde(default)]
+    moon_phase: Option<f32>,
+    /// Unix times, 0 on days the sun doesn't rise or set, as with the moon
+    #[serde(default)]
+    sunrise: i64,
+    #[serde(default)]
+    sunset: i64,
+}
+
+/// Struct representing a single weather report
+#[derive(Deserialize)]
+struct WeatherReport {
+    /// The condition code, such as 600 for light snow; 0 if it's left out
+    #[serde(default)]
+    id: u32,
+    description: String,
+    icon: String,
+}
+
+/// Struct representing the rain or snow over an hour
+#[derive(Deserialize)]
+struct HourlyPrecipitation {
+    #[serde(rename = "1h")]
+    one_hour: f32,
+}
+
+/// Struct representing a single hour's forecast
+#[derive(Deserialize)]
+struct HourlyReport {
+    dt: i64,
+    temp: f32,
+    #[serde(default)]
+    dew_point: Option<f32>,
+    humidity: f32,
+    wind_speed: f32,
+    /// The direction the wind blows from, in degrees
+    #[serde(default)]
+    wind_deg: Option<f32>,
+    /// Probability of precipitation, from 0 to 1
+    #[serde(default)]
+    pop: f32,
+    /// Empty in the forecasts of some providers
+    #[serde(default)]
+    weather: Vec<WeatherReport>,
+    /// Only present for hours with rain, as is `snow`
+    #[serde(default)]
+    rain: Option<HourlyPrecipitation>,
+    #[serde(default)]
+    snow: Option<HourlyPrecipitation>,
+}
+
+/// Struct representing precipitation data
+#[derive(Deserialize)]
+struct MinutelyReport {
+    /// Millimetres an hour, of rain or snow
+    precipitation: f32,
+}
+
+/// Struct representing a weather warning from a national agency
+#[derive(Deserialize)]
+struct AlertReport {
+    /// The agency that issued it
+    #[serde(default)]
+    sender_name: String,
+    event: String,
+    /// Unix times the alert is in force from and until
+    start: i64,
+    #[serde(default)]
+    end: i64,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Struct representing a set of temperatures
+#[derive(Deserialize)]
+struct Temperatures {
+    day: f32,
+    #[serde(default)]
+    min: f32,
+    #[serde(default)]
+    max: f32,
+}
+
+/// Fetch the weather report for a location from the backend.
+fn fetch_weather(
+    location: &Location,
+    units: &str,
+    exclude: &[&str],
+    flags: &Flags,
+    timings: &mut Timings,
+) -> Result<APIResponse, Error> {
+    fetch_weather_reusing(
+        location,
+        units,
+        exclude,
+        flags,
+        timings,
+        Reuse::WhenConserving,
+    )?
+    .ok_or_else(|| Error::msg("no weather report was fetched"))
+}
+
+/// Fetch the weather report for a location, or take one fetched before as
+/// `reuse` allows. `None` if only a cached one would do and there isn't one.
+fn fetch_weather_reusing(
+    location: &Location,
+    units: &str,
+    exclude: &[&str],
+    flags: &Flags,
+    timings: &mut Timings,
+    reuse: Reuse,
+) -> Result<Option<APIResponse>, Error> {
+    if mock::enabled() {
+        let body = mock::onecall(units, exclude)?;
+        return Ok(Some(
+            timings.time("parse", || serde_json::from_slice::<APIResponse>(&body))?,
+        ));
+    }
+
+    // The ensemble asks Open-Meteo at the same time
+    let other = if ensemble::enabled() {
+        openmeteo::request(location)
+            .map_err(|err| println!("Couldn't ask Open-Meteo for a forecast: {:?}", err))
+            .ok()
+    } else {
+        None
+    };
+
+    // One Call 3.0 returns the same shape, but needs its own subscription
+    let version = if flags.enabled("use_onecall_v3") {
+        "3.0"
+    } else {
+        "2.5"
+    };
+    // A slice of clients may be trying out another upstream
+    let upstream = Upstream::choose(flags, BACKEND_NAME, version);
+    upstream.log();
+
+    // Build the API request
+    let url = format!(
+        "http://{}/data/{}/onecall?lat={}&lon={}&appid={}&units={}&exclude={}",
+        upstream.backend,
+        upstream.version,
+        location.latitude(),
+        location.longitude(),
+        get_api_key(),
+        units,
+        exclude.join(",")
+    );
+    // Cached copies are keyed on everything in the request but the API key
+    let cache_key = format!(
+        "owm:{}:{}:{}:{}:{}:{}",
+        upstream.backend,
+        upstream.version,
+        location.latitude(),
+        location.longitude(),
+        units,
+        exclude.join(",")
+    );
+
+    let mut budget = Budget::load();
+    let ttl = match (budget.mode(), reuse) {
+        (_, Reuse::CachedOnly) | (budget::Mode::Exhausted { .. }, _) => None,
+        (budget::Mode::Normal, Reuse::WhenConserving) => Some(Duration::ZERO),
+        (budget::Mode::Normal, Reuse::Within(ttl)) => Some(ttl),
+        (budget::Mode::Conserve, Reuse::WhenConserving) => Some(CONSERVE_TTL),
+        (budget::Mode::Conserve, Reuse::Within(ttl)) => Some(ttl.max(CONSERVE_TTL)),
+    };
+    let body = match ttl {
+        Some(Duration::ZERO) => call_weather_api(&url, &upstream.backend, &mut budget, timings)?,
+        Some(ttl) => {
+            let cached = get_or_set_with(cache_key, || {
+                let body = call_weather_api(&url, &upstream.backend, &mut budget, timings)?;
+                Ok(CacheEntry {
+                    value: body.into(),
+                    ttl,
+                })
+            });
+            match cached {
+                Ok(Some(body)) => body.into_bytes(),
+                Err(CacheError::GetOrSet(err)) => return Err(err),
+                // Without the cache there's nothing to save, so just call the API.
+                _ => call_weather_api(&url, &upstream.backend, &mut budget, timings)?,
+            }
+        }
+        None => match simple::get(cache_key) {
+            Ok(Some(body)) => body.into_bytes(),
+            _ => match budget.mode() {
+                budget::Mode::Exhausted { retry_secs } => {
+                    println!("Weather API budget spent, and nothing cached for this location");
+                    return Err(Unavailable {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        retry_after: Some(retry_secs.to_string()),
+                    }
+                    .into());
+                }
+                _ => return Ok(None),
+            },
+        },
+    };
+
+    // Get the response body into an APIResponse
+    let mut api_response =
+        timings.time("parse", || serde_json::from_slice::<APIResponse>(&body))?;
+    if let Some(pending) = other {
+        match timings.time("openmeteo", || openmeteo::forecast(pending)) {
+            Ok(forecast) => ensemble::blend(&mut api_response, &forecast, units),
+            Err(err) => println!("Couldn't fetch the Open-Meteo forecast: {:?}", err),
+        }
+    }
+    Ok(Some(api_response))
+}
+
+/// Call the weather API, bypassing the cache, and count the call against the budget.
+fn call_weather_api(
+    url: &str,
+    backend: &str,
+    budget: &mut Budget,
+    timings: &mut Timings,
+) -> Result<Vec<u8>, Error> {
+    let mut bereq = Request::new(Method::GET, url).with_pass(true);
+    // Canary backends set their own host
+    if backend == BACKEND_NAME {
+        bereq.set_header(header::HOST, "api.openweathermap.org");
+    }
+
+    // Send the request to the backend
+    let mut beresp = timings.time("fetch", || bereq.send(backend).map_err(Error::from))?;
+    budget.record_call();
+
+    let status = beresp.get_status();
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::UNAUTHORIZED {
+        return Err(Unavailable {
+            status,
+            retry_after: beresp
+                .get_header_str(header::RETRY_AFTER)
+                .map(str::to_string),
+        }
+        .into());
+    }
+    Ok(beresp.take_body_bytes())
+}
+
+/// The One Call blocks that the page at `path` doesn't show, for the API's
+/// `exclude` parameter. The page doesn't show alerts.
+pub fn unused_forecast_blocks(path: &str, mode: Option<&str>) -> Vec<&'static str> {
+    let (minutely, hourly) = match Fragment::from_path(path) {
+        Some(fragment) => (fragment.shows_minutely(), fragment.shows_hourly()),
+        // The snow report only has the current conditions and the daily forecast
+        None if path == "/" && mode == Some("snow") => (false, false),
+        // AMP and minimal pages have no laundry or exercise cards
+        None if path == "/amp" || path == "/m" => (true, false),
+        None => (true, true),
+    };
+
+    let mut exclude = vec!["alerts"];
+    if !minutely {
+        exclude.push("minutely");
+    }
+    if !hourly {
+        exclude.push("hourly");
+    }
+    exclude
+}
+
+fn get_api_key() -> String {
+    // match ConfigStore::open("weather_auth").get("key") {
+    //     Some(key) => key,
+    //     None => panic!("No OpenWeatherMap API key!"),
+    // }
+
+    // The GitHub Action that generates preview URLs does not
+    // currently support resources, so we are hard-coding the token
+    // for now. I don't mind this token being leaked, it's free.
+    "380fdb5dcee55cf704461bbba3b617bd".into()
+}
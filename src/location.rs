@@ -0,0 +1,342 @@
+//! Where the visitor is, so we know whose weather to show.
+//!
+//! The client IP's geolocation usually answers that. Corporate proxies and
+//! VPN exits often have no usable geo data, though, so `default_lat`,
+//! `default_lon` and `default_city` in the `weather_config` store name a
+//! location to show instead. Pages for it carry a banner asking the visitor
+//! to search for their own city.
+//!
+//! Visitors can also keep their IP address out of it. `?geo=off` turns IP
+//! geolocation off, and the `weather_geo` cookie remembers that; the page is
+//! then a location picker until they choose a place, which is kept in the
+//! `weather_place` cookie. `?geo=on` opts back in, forgetting both.
+//!
+//! A page view asks where the visitor is several times over, for the page
+//! and then for its background image, map and favicon. Each IP prefix's
+//! location (its /24, or /48 for IPv6) is kept in the edge cache for
+//! [`GEO_TTL`], so those follow-up requests reuse the page's lookup. Built
+//! for the host, as for the tests and benchmarks, there's no geolocation or
+//! cache, so clients are never located by IP.
+
+#[cfg(target_arch = "wasm32")]
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[cfg(target_arch = "wasm32")]
+use fastly::cache::simple::{get_or_set_with, CacheEntry};
+#[cfg(target_arch = "wasm32")]
+use fastly::geo::{geo_lookup, Geo};
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, cookies, privacy};
+
+const GEO_COOKIE: &str = "weather_geo";
+const PLACE_COOKIE: &str = "weather_place";
+const COOKIE_MAX_AGE: u32 = 31_536_000;
+
+// Longer than any real place name, short enough to keep the cookie small.
+const MAX_CITY_LEN: usize = 100;
+
+/// How long an IP prefix's location is reused
+pub const GEO_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Deserialize)]
+struct GeoParams {
+    geo: Option<String>,
+}
+
+/// A place picked from the location picker, as sent to `/place` and kept in
+/// the place cookie
+#[derive(Deserialize, Serialize)]
+struct PickedPlace {
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    city: String,
+}
+
+impl PickedPlace {
+    fn is_valid(&self) -> bool {
+        self.lat.abs() <= 90.0 && self.lon.abs() <= 180.0 && self.city.len() <= MAX_CITY_LEN
+    }
+}
+
+/// The location a request is answered for
+#[derive(Deserialize, Serialize)]
+pub struct Location {
+    latitude: f64,
+    longitude: f64,
+    city: String,
+    country_code: String,
+    country_name: String,
+    utc_offset: i32,
+    is_default: bool,
+    is_picked: bool,
+}
+
+impl Location {
+    /// Locate the client: the place they picked, or their IP's location if
+    /// they allow it, falling back to the configured default location.
+    /// `None` means they've turned IP geolocation off and not picked a place.
+    pub fn for_client(req: &Request) -> Result<Option<Self>, Error> {
+        let location = match geo_choice(req).as_deref() {
+            Some("off") => return Ok(None),
+            // Opting back in means using the IP location, even if a place was picked.
+            Some("on") => Location::geolocate(req),
+            _ => match Location::picked(req) {
+                Some(location) => Some(location),
+                None if cookies::get(req, GEO_COOKIE) == Some("off") => return Ok(None),
+                None => Location::geolocate(req),
+            },
+        };
+
+        if let Some(mut location) = location {
+            location.coarsen();
+            return Ok(Some(location));
+        }
+
+        let default = Location::configured_default();
+        if default.is_none() {
+            println!("Couldn't locate the client, and no default location is configured");
+        }
+        default
+            .map(Some)
+            .ok_or_else(|| Error::msg("client location unavailable"))
+    }
+
+    // The client IP's location, if it has usable geo data, as last looked
+    // up for its prefix. Prefixes without any are remembered too.
+    #[cfg(target_arch = "wasm32")]
+    fn geolocate(req: &Request) -> Option<Self> {
+        let ip = req.get_client_ip_addr()?;
+        let lookup = || {
+            geo_lookup(ip)
+                .filter(is_usable)
+                .map(|geo| Location::from(&geo))
+        };
+        let mut located = None;
+        let cached = get_or_set_with(geo_cache_key(ip), || {
+            let location = lookup();
+            let value = serde_json::to_string(&location)?;
+            located = Some(location);
+            Ok(CacheEntry {
+                value: value.into(),
+                ttl: GEO_TTL,
+            })
+        });
+
+        match cached {
+            Ok(Some(entry)) => match located {
+                // Looked up just now, so there's nothing to parse
+                Some(location) => location,
+                None => serde_json::from_str(&entry.into_string()).unwrap_or_else(|_| lookup()),
+            },
+            // Without the cache, look it up every time
+            _ => located.unwrap_or_else(lookup),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn geolocate(_req: &Request) -> Option<Self> {
+        None
+    }
+
+    // The place in the place cookie, if there's a valid one.
+    fn picked(req: &Request) -> Option<Self> {
+        let place = serde_urlencoded::from_str::<PickedPlace>(cookies::get(req, PLACE_COOKIE)?)
+            .ok()
+            .filter(PickedPlace::is_valid)?;
+
+        Some(Location {
+            is_picked: true,
+            ..Location::at(place.lat, place.lon, place.city)
+        })
+    }
+
+    /// The location from `default_lat`, `default_lon` and `default_city`, if
+    /// the coordinates are set and valid.
+    pub fn configured_default() -> Option<Self> {
+        let coordinate = |key: &str, limit: f64| {
+            config::get(key)
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.abs() <= limit)
+        };
+        let latitude = coordinate("default_lat", 90.0)?;
+        let longitude = coordinate("default_lon", 180.0)?;
+
+        let city = config::get("default_city").unwrap_or_default();
+        Some(Location {
+            is_default: true,
+            ..Location::at(latitude, longitude, city)
+        })
+    }
+
+    /// A named place, without the country and time zone that geolocation gives.
+    pub fn at(latitude: f64, longitude: f64, city: String) -> Self {
+        Location {
+            latitude,
+            longitude,
+            city,
+            country_code: String::new(),
+            country_name: String::new(),
+            utc_offset: 0,
+            is_default: false,
+            is_picked: false,
+        }
+    }
+
+    /// Truncate the coordinates as far as privacy mode asks, if it's on.
+    pub fn coarsen(&mut self) {
+        if let Some(decimals) = privacy::coordinate_decimals() {
+            self.truncate_coordinates(decimals);
+        }
+    }
+
+    // Drop all but `decimals` decimal places, rounding towards zero.
+    fn truncate_coordinates(&mut self, decimals: u32) {
+        let scale = 10f64.powi(decimals as i32);
+        self.latitude = (self.latitude * scale).trunc() / scale;
+        self.longitude = (self.longitude * scale).trunc() / scale;
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    pub fn city(&self) -> &str {
+        &self.city
+    }
+
+    /// The ISO 3166-1 alpha-2 code, empty for the default location
+    pub fn country_code(&self) -> &str {
+        &self.country_code
+    }
+
+    pub fn country_name(&self) -> &str {
+        &self.country_name
+    }
+
+    /// Seconds east of UTC, or 0 when it isn't known
+    pub fn utc_offset(&self) -> i32 {
+        self.utc_offset
+    }
+
+    /// Whether this is the configured default, because the client couldn't
+    /// be located
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    /// Whether the client picked this place, and named it, themselves
+    pub fn is_picked(&self) -> bool {
+        self.is_picked
+    }
+}
+
+// The cache key for the prefix `ip` is in: its /24, or its /48 for IPv6.
+#[cfg(target_arch = "wasm32")]
+fn geo_cache_key(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("geo:{}.{}.{}.0/24", a, b, c)
+        }
+        IpAddr::V6(ip) => {
+            let [a, b, c, ..] = ip.segments();
+            format!("geo:{:x}:{:x}:{:x}::/48", a, b, c)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<&Geo> for Location {
+    fn from(geo: &Geo) -> Self {
+        Location {
+            latitude: geo.latitude(),
+            longitude: geo.longitude(),
+            city: geo.city().to_string(),
+            country_code: geo.country_code().to_string(),
+            country_name: geo.country_name().to_string(),
+            utc_offset: geo.utc_offset().map_or(0, |offset| offset.whole_seconds()),
+            is_default: false,
+            is_picked: false,
+        }
+    }
+}
+
+/// Remember a `?geo=` choice on `resp`: off until further notice, or back
+/// on, forgetting any picked place.
+pub fn remember_choice(req: &Request, resp: &mut Response) {
+    match geo_choice(req).as_deref() {
+        Some("off") => resp.append_header(header::SET_COOKIE, cookie(GEO_COOKIE, "off")),
+        Some("on") => {
+            resp.append_header(header::SET_COOKIE, expired_cookie(GEO_COOKIE));
+            resp.append_header(header::SET_COOKIE, expired_cookie(PLACE_COOKIE));
+        }
+        _ => {}
+    }
+}
+
+/// Serve `/place`, where the location picker sends the chosen place:
+/// remember it and go back to the forecast. Only the site's own pages may
+/// send visitors here, so another site can't move them somewhere else.
+pub fn pick(req: &Request) -> Response {
+    if is_cross_site(req) {
+        return Response::from_status(StatusCode::FORBIDDEN)
+            .with_body_text_plain("Pick a place from the forecast page\n");
+    }
+    let place = match req.get_query::<PickedPlace>() {
+        Ok(place) if place.is_valid() => place,
+        _ => {
+            return Response::from_status(StatusCode::BAD_REQUEST)
+                .with_body_text_plain("Pick a place with lat, lon and city\n")
+        }
+    };
+    // Serializing a struct of numbers and a string can't fail.
+    let value = serde_urlencoded::to_string(&place).unwrap();
+
+    Response::from_status(StatusCode::SEE_OTHER)
+        .with_header(header::LOCATION, "/")
+        .with_header(header::CACHE_CONTROL, "no-store")
+        .with_header(header::SET_COOKIE, cookie(PLACE_COOKIE, &value))
+}
+
+// Whether the browser says another site sent the request. Those that don't
+// send `Sec-Fetch-Site` at all, and visitors typing the URL, are let through.
+fn is_cross_site(req: &Request) -> bool {
+    match req.get_header_str("Sec-Fetch-Site") {
+        Some(site) => !matches!(site.trim(), "same-origin" | "none"),
+        None => false,
+    }
+}
+
+// The `geo` query parameter, if there's one.
+fn geo_choice(req: &Request) -> Option<String> {
+    req.get_query::<GeoParams>().ok()?.geo
+}
+
+fn cookie(name: &str, value: &str) -> String {
+    format!(
+        "{}={}; Max-Age={}; Path=/; Secure; HttpOnly; SameSite=Lax",
+        name, value, COOKIE_MAX_AGE
+    )
+}
+
+fn expired_cookie(name: &str) -> String {
+    format!(
+        "{}=; Max-Age=0; Path=/; Secure; HttpOnly; SameSite=Lax",
+        name
+    )
+}
+
+// Addresses without geo data can come back at 0°N 0°E, which is open sea.
+#[cfg(target_arch = "wasm32")]
+fn is_usable(geo: &Geo) -> bool {
+    geo.latitude() != 0.0 || geo.longitude() != 0.0
+}
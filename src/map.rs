@@ -0,0 +1,50 @@
+//! A static map of the detected location, served at `/map.png` so visitors can
+//! check the geolocation is right.
+//!
+//! Upstream requests use coordinates rounded to two decimals (about 1km), so
+//! visitors in the same area share a cached image and the map provider never
+//! sees precise locations.
+
+use fastly::http::{header, HeaderValue, Method, StatusCode};
+use fastly::{Error, Request, Response};
+
+use crate::location::Location;
+
+const MAP_BACKEND: &str = "staticmap.openstreetmap.de";
+
+// Streets don't move, so cache the rendered maps for a week.
+const MAP_TTL_SECS: u32 = 604_800;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "map";
+
+const ZOOM: u8 = 11;
+const SIZE: &str = "250x80";
+
+/// Fetch the map image for `location`.
+pub fn handle(location: &Location) -> Result<Response, Error> {
+    let latitude = format!("{:.2}", location.latitude());
+    let longitude = format!("{:.2}", location.longitude());
+    let url = format!(
+        "https://staticmap.openstreetmap.de/staticmap.php?center={lat},{lon}&zoom={}&size={}&markers={lat},{lon},red-pushpin",
+        ZOOM,
+        SIZE,
+        lat = latitude,
+        lon = longitude,
+    );
+
+    let beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "staticmap.openstreetmap.de")
+        .with_ttl(MAP_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(MAP_BACKEND)?;
+
+    if !beresp.get_status().is_success() {
+        return Ok(Response::from_status(StatusCode::BAD_GATEWAY));
+    }
+
+    // Each visitor gets the map for their own location, so only browsers may cache it.
+    Ok(beresp
+        .with_content_type(fastly::mime::IMAGE_PNG)
+        .with_header(header::CACHE_CONTROL, "private, max-age=86400"))
+}
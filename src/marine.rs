@@ -0,0 +1,174 @@
+//! Marine conditions for `?mode=marine`: waves, swell, sea temperature and
+//! tide times from the Open-Meteo marine API.
+//!
+//! The API answers with empty values for points away from the sea, in which
+//! case no marine card is shown. Tide times are the turning points of the
+//! hourly sea level forecast, so they're only accurate to the hour.
+
+use chrono::{FixedOffset, TimeZone};
+use fastly::http::{header, HeaderValue, Method, Url};
+use fastly::{Error, Request};
+use serde::{Deserialize, Serialize};
+
+use crate::location::Location;
+
+const MARINE_BACKEND: &str = "marine-api.open-meteo.com";
+
+// The marine model runs hourly.
+const MARINE_TTL_SECS: u32 = 1800;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "marine";
+
+// Enough upcoming tides to cover the rest of the day.
+const MAX_TIDES: usize = 4;
+
+#[derive(Deserialize)]
+struct MarineResponse {
+    utc_offset_seconds: i32,
+    current: CurrentMarine,
+    hourly: HourlyMarine,
+}
+
+#[derive(Deserialize)]
+struct CurrentMarine {
+    time: i64,
+    wave_height: Option<f32>,
+    wave_period: Option<f32>,
+    swell_wave_height: Option<f32>,
+    swell_wave_direction: Option<f32>,
+    sea_surface_temperature: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct HourlyMarine {
+    time: Vec<i64>,
+    sea_level_height_msl: Vec<Option<f32>>,
+}
+
+/// The marine card shown on coastal pages
+#[derive(Serialize)]
+pub struct MarineReport {
+    wave_height: String,
+    wave_period: Option<String>,
+    swell: Option<String>,
+    water_temp: Option<String>,
+    tides: Vec<Tide>,
+    length_unit: &'static str,
+}
+
+/// An upcoming high or low tide
+#[derive(Serialize)]
+struct Tide {
+    kind: &'static str,
+    time: String,
+}
+
+/// Fetch the marine report for `location`, or `None` if it's inland or the
+/// API is unavailable.
+pub fn fetch(location: &Location, units: &str) -> Result<Option<MarineReport>, Error> {
+    let imperial = units == "imperial";
+    let url = Url::parse_with_params(
+        "https://marine-api.open-meteo.com/v1/marine",
+        &[
+            ("latitude", format!("{:.2}", location.latitude())),
+            ("longitude", format!("{:.2}", location.longitude())),
+            (
+                "current",
+                String::from("wave_height,wave_period,swell_wave_height,swell_wave_direction,sea_surface_temperature"),
+            ),
+            ("hourly", String::from("sea_level_height_msl")),
+            ("forecast_days", String::from("2")),
+            ("timezone", String::from("auto")),
+            ("timeformat", String::from("unixtime")),
+            (
+                "length_unit",
+                String::from(if imperial { "imperial" } else { "metric" }),
+            ),
+            (
+                "temperature_unit",
+                String::from(if imperial { "fahrenheit" } else { "celsius" }),
+            ),
+        ],
+    )?;
+
+    let mut beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "marine-api.open-meteo.com")
+        .with_ttl(MARINE_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(MARINE_BACKEND)?;
+    if !beresp.get_status().is_success() {
+        println!("Marine API returned {}", beresp.get_status());
+        return Ok(None);
+    }
+    let marine = beresp.take_body_json::<MarineResponse>()?;
+
+    let current = &marine.current;
+    let wave_height = match current.wave_height {
+        Some(height) => height,
+        None => return Ok(None),
+    };
+    let length_unit = if imperial { "ft" } else { "m" };
+
+    Ok(Some(MarineReport {
+        wave_height: format!("{:.1}", wave_height),
+        wave_period: current.wave_period.map(|period| format!("{:.0}", period)),
+        swell: current
+            .swell_wave_height
+            .map(|height| match current.swell_wave_direction {
+                Some(direction) => format!("{:.1} {} from {:.0}°", height, length_unit, direction),
+                None => format!("{:.1} {}", height, length_unit),
+            }),
+        water_temp: current
+            .sea_surface_temperature
+            .map(|temp| weather_helpers::round_temperature(temp).to_string()),
+        tides: tides(&marine),
+        length_unit,
+    }))
+}
+
+// Find the next high and low tides, where the hourly sea level changes direction.
+fn tides(marine: &MarineResponse) -> Vec<Tide> {
+    let offset = match FixedOffset::east_opt(marine.utc_offset_seconds) {
+        Some(offset) => offset,
+        None => return Vec::new(),
+    };
+    let hourly = &marine.hourly;
+
+    let mut tides = Vec::new();
+    for i in 1..hourly.sea_level_height_msl.len().saturating_sub(1) {
+        let (before, level, after) = match (
+            hourly.sea_level_height_msl[i - 1],
+            hourly.sea_level_height_msl[i],
+            hourly.sea_level_height_msl[i + 1],
+        ) {
+            (Some(before), Some(level), Some(after)) => (before, level, after),
+            _ => continue,
+        };
+        let kind = if level > before && level >= after {
+            "High"
+        } else if level < before && level <= after {
+            "Low"
+        } else {
+            continue;
+        };
+
+        let timestamp = match hourly.time.get(i) {
+            Some(&timestamp) => timestamp,
+            None => break,
+        };
+        if timestamp < marine.current.time {
+            continue;
+        }
+        if let Some(time) = offset.timestamp_opt(timestamp, 0).single() {
+            tides.push(Tide {
+                kind,
+                time: time.format("%a %H:%M").to_string(),
+            });
+        }
+        if tides.len() == MAX_TIDES {
+            break;
+        }
+    }
+    tides
+}
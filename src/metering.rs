@@ -0,0 +1,108 @@
+//! Usage metering for signed API clients.
+//!
+//! Each client's requests are counted in one KV document per UTC day
+//! (`api_usage:<client>:YYYYMMDD`), against a daily quota from
+//! `api_daily_quota` in the `weather_config` store. Like the call budget's
+//! daily count, it's split into shards so concurrent requests rarely
+//! overwrite each other's increments.
+//!
+//! Metered responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+//! `X-RateLimit-Reset` (seconds until the count starts again). Once the quota
+//! is spent, requests get a 429 until midnight UTC. `/api/usage` shows a
+//! client its own count, without adding to it.
+
+use chrono::{DateTime, Timelike, Utc};
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+use serde_json::json;
+
+use crate::kv::ShardedCount;
+use crate::{config, problem};
+
+const DEFAULT_DAILY_QUOTA: u64 = 10_000;
+
+/// A client's usage today, against its quota
+pub struct Usage {
+    client: String,
+    now: DateTime<Utc>,
+    /// Requests the client has made today
+    requests: ShardedCount,
+    quota: u64,
+}
+
+impl Usage {
+    /// Load `client`'s count for today.
+    pub fn load(client: &str) -> Self {
+        let now = Utc::now();
+        Usage {
+            client: client.to_string(),
+            requests: ShardedCount::load(&key(client, &now)),
+            now,
+            quota: config::get("api_daily_quota")
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|&quota| quota > 0)
+                .unwrap_or(DEFAULT_DAILY_QUOTA),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.requests.total() >= self.quota
+    }
+
+    /// Count a request against the quota.
+    pub fn record(&mut self) {
+        if !self.requests.increment() {
+            println!("Couldn't record API usage for client {}", self.client);
+        }
+    }
+
+    /// Add the `X-RateLimit-*` headers to `resp`.
+    pub fn tag(&self, resp: &mut Response) {
+        resp.set_header("X-RateLimit-Limit", self.quota.to_string());
+        resp.set_header("X-RateLimit-Remaining", self.remaining().to_string());
+        resp.set_header("X-RateLimit-Reset", self.reset_secs().to_string());
+    }
+
+    /// The response for a client that's spent its quota.
+    pub fn over_quota(&self) -> Response {
+        let mut resp = problem::response(StatusCode::TOO_MANY_REQUESTS, "Daily quota used up")
+            .with_header(header::RETRY_AFTER, self.reset_secs().to_string());
+        self.tag(&mut resp);
+        resp
+    }
+
+    /// Serve `/api/usage`.
+    pub fn report(&self) -> Response {
+        let body = json!({
+            "client": self.client,
+            "day": self.now.format("%Y-%m-%d").to_string(),
+            "requests": self.requests.total(),
+            "limit": self.quota,
+            "remaining": self.remaining(),
+            "reset_secs": self.reset_secs(),
+        });
+        let mut resp = Response::from_body(body.to_string())
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_header(header::CACHE_CONTROL, "private, no-store");
+        self.tag(&mut resp);
+        resp
+    }
+
+    fn remaining(&self) -> u64 {
+        self.quota.saturating_sub(self.requests.total())
+    }
+
+    fn reset_secs(&self) -> u32 {
+        86_400 - self.now.num_seconds_from_midnight()
+    }
+}
+
+/// Serve `/api/usage` when there are no API keys, so nobody to meter.
+pub fn unmetered() -> Response {
+    problem::response(StatusCode::NOT_FOUND, "API keys aren't in use")
+}
+
+fn key(client: &str, now: &DateTime<Utc>) -> String {
+    format!("api_usage:{}:{}", client, now.format("%Y%m%d"))
+}
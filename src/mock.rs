@@ -0,0 +1,75 @@
+//! Canned weather for local development and demos.
+//!
+//! Setting `provider` to `mock` in the `weather_config` store answers every
+//! One Call request with the fixture forecast in `tests/fixtures`, so
+//! `fastly compute serve` works without an API key and every page renders
+//! the same way each time. Only the One Call forecast is mocked: the marine,
+//! snow, aviation and map cards still call their own APIs.
+
+use serde_json::{Map, Value};
+
+use crate::config;
+
+const ONECALL: &str = include_str!("../tests/fixtures/onecall.json");
+
+// The fixture is metric, so these keys are converted for other units.
+const TEMPERATURE_KEYS: [&str; 9] = [
+    "temp",
+    "feels_like",
+    "dew_point",
+    "day",
+    "min",
+    "max",
+    "night",
+    "eve",
+    "morn",
+];
+const SPEED_KEYS: [&str; 2] = ["wind_speed", "wind_gust"];
+
+/// Whether the `provider` config value asks for the mock.
+pub fn enabled() -> bool {
+    config::get("provider").is_some_and(|provider| provider.trim().eq_ignore_ascii_case("mock"))
+}
+
+/// The fixture forecast in `units`, without the `exclude`d blocks, shaped
+/// like the body of a One Call response.
+pub fn onecall(units: &str, exclude: &[&str]) -> Result<Vec<u8>, serde_json::Error> {
+    let mut response: Value = serde_json::from_str(ONECALL)?;
+    if let Value::Object(blocks) = &mut response {
+        blocks.retain(|block, _| !exclude.contains(&block.as_str()));
+        convert(blocks, units);
+    }
+    serde_json::to_vec(&response)
+}
+
+// Convert every metric reading in `object`, and the objects inside it, to `units`.
+fn convert(object: &mut Map<String, Value>, units: &str) {
+    for (key, value) in object.iter_mut() {
+        match value {
+            Value::Object(inner) => convert(inner, units),
+            Value::Array(items) => {
+                for item in items {
+                    if let Value::Object(inner) = item {
+                        convert(inner, units);
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let metric = number.as_f64().unwrap_or_default();
+                let converted = if TEMPERATURE_KEYS.contains(&key.as_str()) {
+                    match units {
+                        "imperial" => metric * 9.0 / 5.0 + 32.0,
+                        "standard" => metric + 273.15,
+                        _ => continue,
+                    }
+                } else if SPEED_KEYS.contains(&key.as_str()) && units == "imperial" {
+                    metric * 2.237
+                } else {
+                    continue;
+                };
+                *value = Value::from((converted * 100.0).round() / 100.0);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Notifications to a self-hosted ntfy or Gotify server, for the service's
+//! own place.
+//!
+//! Set `notify_url` in `weather_config` to an ntfy topic, such as
+//! `https://ntfy.example.com/weather`, or to a Gotify server's `/message`
+//! endpoint with `notify_service` set to `gotify`. The token goes in the
+//! `weather_secrets` Secret Store as `notify_token`: Gotify needs an
+//! application token, and ntfy takes an access token for topics that
+//! aren't open. The server must be reachable over HTTPS by name, as with
+//! any [`crate::outbound`] send.
+//!
+//! Notifications are about the default location (`default_lat`,
+//! `default_lon`, `default_city`), in `notify_units`, metric unless set.
+//! Each run of [`crate::dispatch`] sends a one-line summary of the day once
+//! it's past `digest_hour` there, and a notification for each new severe
+//! alert, as [`crate::push`] has them. `notify_events` limits this to
+//! `summary` or `alerts`. Without a URL and a location, nothing is sent.
+
+use chrono::{Timelike, Utc};
+use fastly::http::Url;
+use fastly::secret_store::SecretStore;
+use fastly::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::dispatch::{Forecasts, Report};
+use crate::location::Location;
+use crate::outbound::{self, Outcome};
+use crate::{config, digest, kv, push, APIResponse, AlertReport};
+
+const KV_KEY: &str = "notify_state";
+const SECRET_STORE: &str = "weather_secrets";
+const SECRET_NAME: &str = "notify_token";
+const UNITS: &[&str] = &["metric", "imperial", "standard"];
+
+/// Which server `notify_url` is
+#[derive(Clone, Copy, PartialEq)]
+enum Service {
+    Ntfy,
+    Gotify,
+}
+
+/// What's been sent so far
+#[derive(Default, Deserialize, Serialize)]
+struct State {
+    /// The local date of the last summary, as `YYYY-MM-DD`
+    #[serde(default)]
+    last_summary: Option<String>,
+    /// The severe alerts in force at the last dispatch, which have been sent
+    #[serde(default)]
+    alerted: Vec<String>,
+}
+
+/// A notification to send
+struct Notification {
+    title: String,
+    message: String,
+    /// Alerts are urgent, summaries aren't
+    urgent: bool,
+}
+
+/// The server, and what to tell it about
+struct Notifier {
+    url: Url,
+    service: Service,
+    token: Option<String>,
+    location: Location,
+    units: String,
+    summary: bool,
+    alerts: bool,
+}
+
+impl Notifier {
+    fn load() -> Option<Self> {
+        let url = config::get("notify_url")
+            .and_then(|url| Url::parse(url.trim()).ok())
+            .filter(outbound::is_sendable)?;
+        let service = match config::get("notify_service").as_deref().map(str::trim) {
+            None | Some("ntfy") => Service::Ntfy,
+            Some("gotify") => Service::Gotify,
+            Some(other) => {
+                println!(
+                    "Unknown notify_service {:?}, so nothing was notified",
+                    other
+                );
+                return None;
+            }
+        };
+        let token = SecretStore::open(SECRET_STORE)
+            .ok()
+            .and_then(|store| store.get(SECRET_NAME))
+            .and_then(|secret| String::from_utf8(secret.plaintext().to_vec()).ok())
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty());
+        if service == Service::Gotify && token.is_none() {
+            println!("Gotify needs an application token as notify_token");
+            return None;
+        }
+        let units = config::get("notify_units")
+            .map(|units| units.trim().to_string())
+            .filter(|units| UNITS.contains(&units.as_str()))
+            .unwrap_or_else(|| String::from("metric"));
+        let events = config::get("notify_events").unwrap_or_default();
+        let wants = |event: &str| {
+            events.trim().is_empty() || events.split(',').any(|wanted| wanted.trim() == event)
+        };
+        Some(Notifier {
+            url,
+            service,
+            token,
+            location: Location::configured_default()?,
+            summary: wants("summary"),
+            alerts: wants("alerts"),
+            units,
+        })
+    }
+
+    fn send(&self, notification: &Notification) -> Outcome {
+        let req = match self.service {
+            // ntfy takes the message as the body, and the rest as headers.
+            Service::Ntfy => {
+                let req = Request::post(self.url.clone())
+                    .with_header("Title", &notification.title)
+                    .with_header("Priority", if notification.urgent { "5" } else { "3" })
+                    .with_header(
+                        "Tags",
+                        if notification.urgent {
+                            "warning"
+                        } else {
+                            "partly_sunny"
+                        },
+                    )
+                    .with_body_text_plain(&notification.message);
+                match &self.token {
+                    Some(token) => req.with_header(
+                        fastly::http::header::AUTHORIZATION,
+                        format!("Bearer {}", token),
+                    ),
+                    None => req,
+                }
+            }
+            Service::Gotify => Request::post(self.url.clone())
+                .with_header("X-Gotify-Key", self.token.as_deref().unwrap_or_default())
+                .with_content_type(fastly::mime::APPLICATION_JSON)
+                .with_body(
+                    json!({
+                        "title": notification.title,
+                        "message": notification.message,
+                        "priority": if notification.urgent { 8 } else { 4 },
+                    })
+                    .to_string(),
+                ),
+        };
+        outbound::send(req)
+    }
+}
+
+/// Notify the configured server of the day's summary and new alerts.
+pub fn dispatch(forecasts: &mut Forecasts) -> Report {
+    let mut report = Report::default();
+    let notifier = match Notifier::load() {
+        Some(notifier) => notifier,
+        None => return report,
+    };
+    report.checked = 1;
+    let api_response = match forecasts.fetch(
+        notifier.location.latitude(),
+        notifier.location.longitude(),
+        &notifier.units,
+    ) {
+        Some(api_response) => api_response,
+        None => {
+            report.deferred += 1;
+            return report;
+        }
+    };
+    let mut state: State = kv::get_json(KV_KEY).unwrap_or_default();
+    let mut changed = false;
+    let local = weather_helpers::local_time(Utc::now().timestamp(), api_response.timezone_offset);
+    let today = local.format("%Y-%m-%d").to_string();
+
+    let mut notifications = Vec::new();
+    if notifier.summary
+        && local.hour() >= digest::digest_hour()
+        && state.last_summary.as_deref() != Some(today.as_str())
+    {
+        notifications.push((
+            None,
+            summary(notifier.location.city(), &notifier.units, &api_response),
+        ));
+    }
+    if notifier.alerts {
+        let severe: Vec<_> = api_response
+            .alerts
+            .iter()
+            .filter(|alert| push::is_severe(alert))
+            .collect();
+        for alert in &severe {
+            if !state.alerted.contains(&push::alert_id(alert)) {
+                notifications.push((
+                    Some(push::alert_id(alert)),
+                    alert_notification(notifier.location.city(), alert, &api_response),
+                ));
+            }
+        }
+        // Forget alerts that are over, so they're sent again if reissued
+        let before = state.alerted.len();
+        state
+            .alerted
+            .retain(|id| severe.iter().any(|alert| push::alert_id(alert) == *id));
+        changed |= state.alerted.len() != before;
+    }
+
+    for (alert, notification) in notifications {
+        match notifier.send(&notification) {
+            Outcome::Sent => {
+                report.sent += 1;
+                match alert {
+                    Some(alert) => state.alerted.push(alert),
+                    None => state.last_summary = Some(today.clone()),
+                }
+                changed = true;
+            }
+            // A missing topic is the server's setup, not something to give up on.
+            Outcome::Gone => report.failed += 1,
+            Outcome::Failed(err) => {
+                println!("Couldn't notify {}: {}", notifier.url, err);
+                report.failed += 1;
+            }
+        }
+    }
+
+    if changed && !kv::put_json(KV_KEY, &state) {
+        println!("Couldn't save what was notified");
+    }
+    report
+}
+
+// A line about the day, such as "Broken clouds, 13°C now, 9° to 15° today".
+fn summary(city: &str, units: &str, api_response: &APIResponse) -> Notification {
+    let symbol = match units {
+        "imperial" => "°F",
+        "standard" => "K",
+        _ => "°C",
+    };
+    let description = api_response
+        .current
+        .weather
+        .first()
+        .map(|weather| weather.description.as_str())
+        .unwrap_or_default();
+    let mut message = format!(
+        "{}{}, {}{} now",
+        description.get(..1).unwrap_or_default().to_uppercase(),
+        description.get(1..).unwrap_or_default(),
+        weather_helpers::round_temperature(api_response.current.temp),
+        symbol
+    );
+    if let Some(today) = api_response.daily.first() {
+        message.push_str(&format!(
+            ", {}° to {}° today",
+            weather_helpers::round_temperature(today.temp.min),
+            weather_helpers::round_temperature(today.temp.max)
+        ));
+    }
+    Notification {
+        title: if city.is_empty() {
+            String::from("Today's weather")
+        } else {
+            format!("Today's weather in {}", city)
+        },
+        message,
+        urgent: false,
+    }
+}
+
+fn alert_notification(city: &str, alert: &AlertReport, api_response: &APIResponse) -> Notification {
+    let starts = weather_helpers::local_time(alert.start, api_response.timezone_offset);
+    let mut message = format!("From {}", starts.format("%A %H:%M"));
+    if !city.is_empty() {
+        message.push_str(&format!(" in {}", city));
+    }
+    Notification {
+        title: alert.event.clone(),
+        message,
+        urgent: true,
+    }
+}
@@ -0,0 +1,167 @@
+//! Forecasts from the Open-Meteo forecast API.
+//!
+//! A second opinion on OpenWeather's, from different models, for rating how
+//! far each day's forecast can be trusted and for the `ensemble` provider.
+//! Temperatures are always in °C, whatever units the page is in, and days
+//! come in the location's own time zone, as OpenWeather's do. The request is
+//! sent without waiting, so it can go out alongside OpenWeather's.
+
+use chrono::NaiveDate;
+use fastly::http::request::PendingRequest;
+use fastly::http::{header, HeaderValue, Method, Url};
+use fastly::{Error, Request};
+use serde::Deserialize;
+
+use crate::location::Location;
+
+const BACKEND: &str = "api.open-meteo.com";
+const TTL_SECS: u32 = 1800;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "openmeteo";
+
+// As far ahead as One Call goes
+const FORECAST_DAYS: &str = "8";
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+    utc_offset_seconds: i32,
+    current: CurrentForecast,
+    hourly: HourlyForecast,
+    daily: DailyForecast,
+}
+
+#[derive(Deserialize)]
+struct CurrentForecast {
+    temperature_2m: Option<f32>,
+}
+
+/// Each hour's readings, as a list per variable
+#[derive(Deserialize)]
+struct HourlyForecast {
+    time: Vec<i64>,
+    temperature_2m: Vec<Option<f32>>,
+    /// In %
+    precipitation_probability: Vec<Option<f32>>,
+}
+
+/// Each day's readings, as a list per variable
+#[derive(Deserialize)]
+struct DailyForecast {
+    /// Unix times of each local midnight
+    time: Vec<i64>,
+    temperature_2m_max: Vec<Option<f32>>,
+    temperature_2m_min: Vec<Option<f32>>,
+    /// In %, and missing for days too far ahead to give one
+    precipitation_probability_max: Vec<Option<f32>>,
+}
+
+/// Open-Meteo's forecast for a location
+pub struct Forecast {
+    /// The temperature now in °C
+    pub current: Option<f32>,
+    pub hourly: Vec<Hour>,
+    pub daily: Vec<Day>,
+}
+
+/// One hour of Open-Meteo's forecast
+pub struct Hour {
+    /// Unix time of the start of the hour
+    pub dt: i64,
+    /// In °C
+    pub temp: f32,
+    /// The chance of rain from 0 to 1
+    pub pop: Option<f32>,
+}
+
+/// One day of Open-Meteo's forecast
+pub struct Day {
+    pub date: NaiveDate,
+    /// The high and low in °C
+    pub max: f32,
+    pub min: f32,
+    /// The chance of rain from 0 to 1
+    pub pop: Option<f32>,
+}
+
+/// Send the request for the forecast at `location`, to collect with
+/// [`forecast`].
+pub fn request(location: &Location) -> Result<PendingRequest, Error> {
+    let url = Url::parse_with_params(
+        "https://api.open-meteo.com/v1/forecast",
+        &[
+            ("latitude", format!("{:.2}", location.latitude())),
+            ("longitude", format!("{:.2}", location.longitude())),
+            ("current", String::from("temperature_2m")),
+            (
+                "hourly",
+                String::from("temperature_2m,precipitation_probability"),
+            ),
+            (
+                "daily",
+                String::from("temperature_2m_max,temperature_2m_min,precipitation_probability_max"),
+            ),
+            ("forecast_days", String::from(FORECAST_DAYS)),
+            ("timezone", String::from("auto")),
+            ("timeformat", String::from("unixtime")),
+        ],
+    )?;
+    Ok(Request::new(Method::GET, url)
+        .with_header(header::HOST, "api.open-meteo.com")
+        .with_ttl(TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send_async(BACKEND)?)
+}
+
+/// Wait for the forecast sent for with [`request`]. An upstream failure
+/// leaves it empty rather than failing the page.
+pub fn forecast(pending: PendingRequest) -> Result<Forecast, Error> {
+    let mut beresp = pending.wait()?;
+    if !beresp.get_status().is_success() {
+        println!("Open-Meteo forecast API returned {}", beresp.get_status());
+        return Ok(Forecast {
+            current: None,
+            hourly: Vec::new(),
+            daily: Vec::new(),
+        });
+    }
+    let response = beresp.take_body_json::<ForecastResponse>()?;
+    let percent = |percent: Option<&Option<f32>>| percent.copied().flatten().map(|p| p / 100.0);
+
+    let hourly = &response.hourly;
+    let daily = &response.daily;
+    Ok(Forecast {
+        current: response.current.temperature_2m,
+        hourly: hourly
+            .time
+            .iter()
+            .enumerate()
+            .filter_map(|(i, dt)| {
+                Some(Hour {
+                    dt: *dt,
+                    temp: (*hourly.temperature_2m.get(i)?)?,
+                    pop: percent(hourly.precipitation_probability.get(i)),
+                })
+            })
+            .collect(),
+        daily: daily
+            .time
+            .iter()
+            .enumerate()
+            .filter_map(|(i, midnight)| {
+                Some(Day {
+                    date: weather_helpers::local_time(*midnight, response.utc_offset_seconds)
+                        .date_naive(),
+                    max: (*daily.temperature_2m_max.get(i)?)?,
+                    min: (*daily.temperature_2m_min.get(i)?)?,
+                    pop: percent(daily.precipitation_probability_max.get(i)),
+                })
+            })
+            .collect(),
+    })
+}
+
+/// The daily forecast at `location`.
+pub fn daily(location: &Location) -> Result<Vec<Day>, Error> {
+    Ok(forecast(request(location)?)?.daily)
+}
@@ -0,0 +1,110 @@
+//! Backends for hosts that are only known at runtime, such as the callbacks
+//! clients register for webhooks.
+//!
+//! Each host gets a dynamic backend the first time an instance sends to it,
+//! over TLS with its certificate checked, and later sends reuse it. The
+//! service must have dynamic backends enabled; until it does, creating one
+//! fails and the send is reported as failed.
+//!
+//! A Compute instance can't resolve names itself, so there's no telling
+//! whether a visitor's host points back into a private network. URLs that
+//! visitors give us are only sent to the hosts in `outbound_hosts`, a
+//! comma-separated list in the `weather_config` store, or the built-in
+//! ones a feature passes to [`is_allowed`]. Each entry allows that host
+//! and its subdomains.
+
+use std::time::Duration;
+
+use fastly::http::{StatusCode, Url};
+use fastly::{Backend, Error, Request};
+
+use crate::config;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const FIRST_BYTE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How a send went
+pub enum Outcome {
+    Sent,
+    /// The receiver says it's gone for good (404 or 410), so stop sending
+    Gone,
+    Failed(Error),
+}
+
+/// Whether `url` is somewhere we're willing to send to: HTTPS, to a named
+/// host that isn't this machine, without credentials in it. That's enough
+/// for URLs the operator configures, but a name can still resolve to a
+/// private address, so check visitors' URLs with [`is_allowed`].
+pub fn is_sendable(url: &Url) -> bool {
+    url.scheme() == "https"
+        && url.username().is_empty()
+        && url.password().is_none()
+        // IP literals have no domain, so they're refused here
+        && host(url).is_some_and(|host| host.contains('.') && !host.ends_with(".localhost"))
+}
+
+/// Whether a visitor's `url` is sendable and on one of the `built_in`
+/// hosts or those in `outbound_hosts`.
+pub fn is_allowed(url: &Url, built_in: &[&str]) -> bool {
+    let host = match host(url) {
+        Some(host) if is_sendable(url) => host,
+        _ => return false,
+    };
+    let configured = config::get("outbound_hosts").unwrap_or_default();
+    built_in
+        .iter()
+        .copied()
+        .chain(configured.split(','))
+        .map(|entry| entry.trim().trim_matches('.').to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            host == entry
+                || host
+                    .strip_suffix(entry.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+}
+
+// `url`'s host name, lowercased and without a trailing dot.
+fn host(url: &Url) -> Option<String> {
+    url.domain()
+        .map(|host| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// The backend for `url`'s host, creating it if this instance hasn't yet.
+pub fn backend(url: &Url) -> Result<Backend, Error> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::msg("the URL has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let name = format!("outbound_{}_{}", host, port);
+    if let Ok(backend) = Backend::from_name(&name) {
+        if backend.exists() {
+            return Ok(backend);
+        }
+    }
+    Ok(Backend::builder(&name, format!("{}:{}", host, port))
+        .override_host(host)
+        .enable_ssl()
+        .sni_hostname(host)
+        .check_certificate(host)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .first_byte_timeout(FIRST_BYTE_TIMEOUT)
+        .finish()?)
+}
+
+/// Send `req` to its URL's host.
+pub fn send(req: Request) -> Outcome {
+    let backend = match backend(req.get_url()) {
+        Ok(backend) => backend,
+        Err(err) => return Outcome::Failed(err),
+    };
+    match req.with_pass(true).send(backend) {
+        Ok(beresp) if beresp.get_status().is_success() => Outcome::Sent,
+        Ok(beresp) if [StatusCode::NOT_FOUND, StatusCode::GONE].contains(&beresp.get_status()) => {
+            Outcome::Gone
+        }
+        Ok(beresp) => Outcome::Failed(Error::msg(format!("it returned {}", beresp.get_status()))),
+        Err(err) => Outcome::Failed(err.into()),
+    }
+}
@@ -0,0 +1,64 @@
+//! Crawler and security policy files: `/robots.txt` and `/.well-known/security.txt`.
+
+use chrono::{Duration, SecondsFormat, Utc};
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+
+use crate::{config, view};
+
+// Allow everything unless the operator configures otherwise.
+const DEFAULT_ROBOTS_TXT: &str = "User-agent: *\nAllow: /\n";
+
+const POLICY_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Serve `/robots.txt` using the `robots_txt` config value as crawl rules.
+pub fn robots_txt() -> Response {
+    let mut body = config::get("robots_txt").unwrap_or_else(|| DEFAULT_ROBOTS_TXT.into());
+    if !body.ends_with('\n') {
+        body.push('\n');
+    }
+
+    text_response(body)
+}
+
+/// Serve `/.well-known/security.txt` as described by RFC 9116.
+///
+/// `security_contact` is required (a comma-separated list of addresses or
+/// URLs), so the route 404s until one is configured. `security_expires` and
+/// `security_policy` are optional.
+pub fn security_txt() -> Response {
+    let contacts = match config::get("security_contact") {
+        Some(contacts) => contacts,
+        None => return view::not_found("/.well-known/security.txt"),
+    };
+
+    let mut body = String::new();
+    for contact in contacts.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        // Bare email addresses need a scheme to be valid contact URIs.
+        if contact.contains(':') {
+            body.push_str(&format!("Contact: {}\n", contact));
+        } else {
+            body.push_str(&format!("Contact: mailto:{}\n", contact));
+        }
+    }
+
+    // The RFC requires an expiry, so default to a year from now when unset.
+    let expires = config::get("security_expires").unwrap_or_else(|| {
+        (Utc::now() + Duration::days(365)).to_rfc3339_opts(SecondsFormat::Secs, true)
+    });
+    body.push_str(&format!("Expires: {}\n", expires));
+
+    if let Some(policy) = config::get("security_policy") {
+        body.push_str(&format!("Policy: {}\n", policy));
+    }
+    body.push_str("Preferred-Languages: en\n");
+
+    text_response(body)
+}
+
+fn text_response(body: String) -> Response {
+    Response::from_body(body)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_PLAIN_UTF_8)
+        .with_header(header::CACHE_CONTROL, POLICY_CACHE_CONTROL)
+}
@@ -0,0 +1,103 @@
+//! How much rain and snow is coming: the hourly forecast's amounts summed
+//! over the next 24 and 48 hours.
+//!
+//! The totals are on the precipitation card and at `/api/precipitation`.
+//! The API reports amounts in millimetres whatever the units, so the JSON
+//! has those alongside the amounts formatted for the page. It also answers
+//! whether today needs an umbrella; see [`crate::umbrella`].
+
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+use serde::Serialize;
+
+use crate::provider::Attributed;
+use crate::umbrella::Umbrella;
+use crate::{snow, APIResponse};
+
+/// The precipitation card and `/api/precipitation` response
+#[derive(Serialize)]
+pub struct PrecipitationTotals {
+    next_24h: Totals,
+    next_48h: Totals,
+    /// Whether any rain or snow is forecast in the next 48 hours
+    pub is_wet: bool,
+    needs_umbrella: bool,
+    umbrella: Umbrella,
+}
+
+/// What falls over one stretch of the forecast
+#[derive(Serialize)]
+struct Totals {
+    rain_mm: f32,
+    snow_mm: f32,
+    /// The amounts in the page's units, such as "4.2 mm" or "0.17 in"
+    rain: String,
+    snow: String,
+}
+
+impl PrecipitationTotals {
+    /// The totals of the hourly forecast in `api_response`, formatted for
+    /// `units`. `None` without an hourly forecast to add up.
+    pub fn new(api_response: &APIResponse, units: &str) -> Option<Self> {
+        if api_response.hourly.is_empty() {
+            return None;
+        }
+        let next_24h = Totals::over(api_response, 24, units);
+        let next_48h = Totals::over(api_response, 48, units);
+        let umbrella = Umbrella::today(api_response);
+        Some(PrecipitationTotals {
+            is_wet: next_48h.rain_mm > 0.0 || next_48h.snow_mm > 0.0,
+            next_24h,
+            next_48h,
+            needs_umbrella: umbrella.is_needed(),
+            umbrella,
+        })
+    }
+}
+
+impl Totals {
+    fn over(api_response: &APIResponse, hours: usize, units: &str) -> Self {
+        let (rain, snow) =
+            api_response
+                .hourly
+                .iter()
+                .take(hours)
+                .fold((0.0, 0.0), |(rain, snow), hour| {
+                    (
+                        rain + hour.rain.as_ref().map_or(0.0, |rain| rain.one_hour),
+                        snow + hour.snow.as_ref().map_or(0.0, |snow| snow.one_hour),
+                    )
+                });
+        Totals {
+            rain_mm: round(rain),
+            snow_mm: round(snow),
+            rain: format_rainfall(rain, units),
+            snow: snow::format_snowfall(snow, units),
+        }
+    }
+}
+
+/// Format a rainfall for display: millimetres, or inches for imperial
+/// units, which need another decimal place to show a shower at all.
+pub fn format_rainfall(millimetres: f32, units: &str) -> String {
+    if units == "imperial" {
+        format!("{:.2} in", millimetres / 25.4)
+    } else {
+        format!("{:.1} mm", millimetres)
+    }
+}
+
+/// Serve `/api/precipitation` for the visitor's location.
+pub fn handle(totals: &PrecipitationTotals) -> Result<Response, fastly::Error> {
+    Ok(
+        Response::from_body(serde_json::to_string(&Attributed::new(totals))?)
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_header(header::CACHE_CONTROL, "private, max-age=300"),
+    )
+}
+
+// Hundredths of a millimetre are already below what a gauge can tell.
+fn round(millimetres: f32) -> f32 {
+    (millimetres * 100.0).round() / 100.0
+}
@@ -0,0 +1,65 @@
+//! How precisely readings are shown.
+//!
+//! Every temperature, wind speed and rainfall on the pages goes through
+//! [`Precision`], which rounds it to the number of decimals set in the
+//! `weather_config` store and writes it the way the visitor's locale does:
+//!
+//! * `temp_precision`: 0 for whole degrees, the default, or 1
+//! * `wind_precision`: 0 to 2, 1 unless set
+//! * `precipitation_precision`: 0 to 2, 1 unless set
+//!
+//! Values are rounded, never truncated, so -0.6° is -1° and -0.4° is 0°.
+//! Out-of-range settings are ignored.
+
+use weather_helpers::Locale;
+
+use crate::config;
+
+const DEFAULT_TEMP: usize = 0;
+const DEFAULT_WIND: usize = 1;
+const DEFAULT_PRECIPITATION: usize = 1;
+
+/// The decimals each kind of reading is shown with
+#[derive(Clone, Copy)]
+pub struct Precision {
+    temp: usize,
+    wind: usize,
+    precipitation: usize,
+}
+
+impl Precision {
+    /// The precision set in the configuration.
+    pub fn configured() -> Self {
+        let decimals = |key: &str, most: usize, default: usize| {
+            config::get(key)
+                .and_then(|decimals| decimals.trim().parse::<usize>().ok())
+                .filter(|decimals| *decimals <= most)
+                .unwrap_or(default)
+        };
+        Precision {
+            temp: decimals("temp_precision", 1, DEFAULT_TEMP),
+            wind: decimals("wind_precision", 2, DEFAULT_WIND),
+            precipitation: decimals("precipitation_precision", 2, DEFAULT_PRECIPITATION),
+        }
+    }
+
+    /// A temperature without its unit, such as "14".
+    pub fn temp(&self, locale: Locale, value: f32) -> String {
+        locale.format_number(value, self.temp)
+    }
+
+    /// A temperature in `units` with its unit, such as "14°C".
+    pub fn temp_label(&self, locale: Locale, value: f32, units: &str) -> String {
+        locale.format_temperature(value, self.temp, units)
+    }
+
+    /// A wind speed without its unit.
+    pub fn wind(&self, locale: Locale, value: f32) -> String {
+        locale.format_number(value, self.wind)
+    }
+
+    /// An amount of rain or snow without its unit.
+    pub fn precipitation(&self, locale: Locale, value: f32) -> String {
+        locale.format_number(value, self.precipitation)
+    }
+}
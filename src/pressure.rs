@@ -0,0 +1,151 @@
+//! Air pressure and which way it's heading, for the conditions card.
+//!
+//! The API only reports the pressure now, but the trend says more about the
+//! weather to come. So page views record the reading for their geohash cell
+//! once an hour, in `pressure:<geohash>` in the KV Store, and the card
+//! compares it with the reading about three hours earlier. The outlook is the
+//! barometer's rule of thumb: falling fast means rain is likely.
+//!
+//! Like the trending counts, concurrent views can overwrite each other's
+//! readings, which only ever costs an hour's reading.
+
+use serde::{Deserialize, Serialize};
+
+use crate::location::Location;
+use crate::{geohash, kv, APIResponse};
+
+// The same cells as the trending locations, about 5km across.
+const GEOHASH_PRECISION: usize = 5;
+
+// Enough hourly readings to find one from three hours ago
+const MAX_READINGS: usize = 6;
+
+// The trend compares readings this many hours apart, give or take one.
+const TREND_HOURS: i64 = 3;
+
+// Changes over three hours, in hPa, that count as rising or falling, and as
+// doing so fast.
+const CHANGE_HPA: f32 = 1.0;
+const FAST_CHANGE_HPA: f32 = 3.5;
+
+const INHG_PER_HPA: f32 = 0.029_53;
+
+/// One hourly reading for a cell
+#[derive(Serialize, Deserialize)]
+struct Reading {
+    /// Hours since the Unix epoch
+    hour: i64,
+    hpa: f32,
+}
+
+/// Which way the pressure is heading
+#[derive(Clone, Copy)]
+pub enum Trend {
+    RisingFast,
+    Rising,
+    Steady,
+    Falling,
+    FallingFast,
+}
+
+impl Trend {
+    /// The trend of a change of `change_hpa` over three hours.
+    fn from_change(change_hpa: f32) -> Self {
+        match change_hpa {
+            change if change >= FAST_CHANGE_HPA => Trend::RisingFast,
+            change if change >= CHANGE_HPA => Trend::Rising,
+            change if change <= -FAST_CHANGE_HPA => Trend::FallingFast,
+            change if change <= -CHANGE_HPA => Trend::Falling,
+            _ => Trend::Steady,
+        }
+    }
+
+    fn arrow(self) -> &'static str {
+        match self {
+            Trend::RisingFast => "⇈",
+            Trend::Rising => "↑",
+            Trend::Steady => "→",
+            Trend::Falling => "↓",
+            Trend::FallingFast => "⇊",
+        }
+    }
+
+    /// What the trend says about the weather to come.
+    fn outlook(self) -> &'static str {
+        match self {
+            Trend::RisingFast => "Rising fast — clearing, but windy",
+            Trend::Rising => "Rising — fair weather ahead",
+            Trend::Steady => "Steady — little change",
+            Trend::Falling => "Falling — turning unsettled",
+            Trend::FallingFast => "Falling fast — rain likely",
+        }
+    }
+}
+
+/// Context for the pressure line of the conditions card
+#[derive(Serialize)]
+pub struct Pressure {
+    value: String,
+    unit: &'static str,
+    /// `None` until there's a reading from a few hours ago to compare with
+    trend: Option<TrendContext>,
+}
+
+#[derive(Serialize)]
+struct TrendContext {
+    arrow: &'static str,
+    outlook: &'static str,
+}
+
+/// Record the pressure in `api_response` for `location`, returning its trend
+/// if there's an earlier reading to compare with.
+pub fn track(api_response: &APIResponse, location: &Location) -> Option<Trend> {
+    let hpa = api_response.current.pressure?;
+    let hour = api_response.current.dt.div_euclid(3600);
+    let key = format!(
+        "pressure:{}",
+        geohash::encode(location.latitude(), location.longitude(), GEOHASH_PRECISION)
+    );
+
+    let mut readings: Vec<Reading> = kv::get_json(&key).unwrap_or_default();
+    let trend = trend(&readings, hour, hpa);
+    // One reading an hour is plenty, and spares the store a write per view.
+    if readings.last().is_none_or(|last| last.hour < hour) {
+        readings.push(Reading { hour, hpa });
+        let excess = readings.len().saturating_sub(MAX_READINGS);
+        readings.drain(..excess);
+        kv::put_json(&key, &readings);
+    }
+    trend
+}
+
+impl Pressure {
+    /// The reading `hpa`, heading `trend`, shown in `units`.
+    pub fn new(hpa: f32, trend: Option<Trend>, units: &str) -> Self {
+        let (value, unit) = match units {
+            "imperial" => (format!("{:.2}", hpa * INHG_PER_HPA), "inHg"),
+            _ => (format!("{:.0}", hpa), "hPa"),
+        };
+        Pressure {
+            value,
+            unit,
+            trend: trend.map(|trend| TrendContext {
+                arrow: trend.arrow(),
+                outlook: trend.outlook(),
+            }),
+        }
+    }
+}
+
+// Compare `hpa` at `hour` with the reading closest to three hours before,
+// scaled to three hours if it's an hour either side.
+fn trend(readings: &[Reading], hour: i64, hpa: f32) -> Option<Trend> {
+    let earlier = readings
+        .iter()
+        .filter(|reading| (TREND_HOURS - 1..=TREND_HOURS + 1).contains(&(hour - reading.hour)))
+        .min_by_key(|reading| (hour - reading.hour - TREND_HOURS).abs())?;
+    let hours = (hour - earlier.hour) as f32;
+    Some(Trend::from_change(
+        (hpa - earlier.hpa) * TREND_HOURS as f32 / hours,
+    ))
+}
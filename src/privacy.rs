@@ -0,0 +1,100 @@
+//! Privacy mode, for deployments that want to know as little as possible
+//! about their visitors. Set `privacy_mode` to `true` in the `weather_config`
+//! store to turn it on. Then:
+//!
+//! * Visitors' coordinates are truncated to `privacy_decimals` places (1 or
+//!   2, default 1) as soon as they're located, so OpenWeatherMap and the
+//!   other upstream APIs never see anything finer than about 11km. So are
+//!   the coordinates widgets, background photos and share links name.
+//! * Logs name the visitor's area by geohash and country, never by IP
+//!   address or coordinates.
+//! * Requests sent with `DNT: 1` aren't counted anywhere: no trending views
+//!   and no RUM beacons.
+//!
+//! `/privacy` explains this to visitors, saying which parts are switched on.
+
+use fastly::http::{header, StatusCode};
+use fastly::{Request, Response};
+use serde::Serialize;
+
+use crate::location::Location;
+use crate::view::{self, HeadContext};
+use crate::{config, geohash};
+
+const DEFAULT_DECIMALS: u32 = 1;
+
+// Four characters is a cell of about 39km by 20km, plenty for reading logs.
+const LOG_GEOHASH_PRECISION: usize = 4;
+
+/// Context for the `/privacy` page
+#[derive(Serialize)]
+pub struct PrivacyContext {
+    head: HeadContext,
+    enabled: bool,
+    /// How finely coordinates are kept, in words
+    precision: &'static str,
+}
+
+/// Whether privacy mode is switched on.
+pub fn enabled() -> bool {
+    config::get("privacy_mode").as_deref() == Some("true")
+}
+
+/// How many decimal places to keep in visitors' coordinates, or `None` to
+/// keep them all.
+pub fn coordinate_decimals() -> Option<u32> {
+    if !enabled() {
+        return None;
+    }
+    let decimals = config::get("privacy_decimals")
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|decimals| (1..=2).contains(decimals))
+        .unwrap_or(DEFAULT_DECIMALS);
+    Some(decimals)
+}
+
+/// Whether `req` shouldn't be counted, because the visitor sent `DNT: 1` and
+/// privacy mode is on.
+pub fn do_not_track(req: &Request) -> bool {
+    req.get_header_str("DNT").map(str::trim) == Some("1") && enabled()
+}
+
+/// How to name `location` in the logs.
+pub fn describe(location: &Location) -> String {
+    if enabled() {
+        format!(
+            "area {} ({})",
+            geohash::encode(
+                location.latitude(),
+                location.longitude(),
+                LOG_GEOHASH_PRECISION
+            ),
+            location.country_code()
+        )
+    } else {
+        format!(
+            "{}, {} ({}, {})",
+            location.latitude(),
+            location.longitude(),
+            location.city(),
+            location.country_name()
+        )
+    }
+}
+
+/// Serve `/privacy`.
+pub fn handle() -> Response {
+    let context = PrivacyContext {
+        head: HeadContext::new(false),
+        enabled: enabled(),
+        precision: match coordinate_decimals() {
+            Some(2) => "two decimal places, about 1km",
+            _ => "one decimal place, about 11km",
+        },
+    };
+
+    Response::from_body(view::privacy(&context))
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(header::CACHE_CONTROL, "public, max-age=3600")
+}
@@ -0,0 +1,82 @@
+//! Errors from the JSON API, as RFC 7807 problem details.
+//!
+//! Handlers say what went wrong with [`response`], which gives the status's
+//! reason as the `title` and the message as the `detail`. Every response on
+//! an API path then goes through [`finish`], which adds the path as
+//! `instance`, and turns the errors the rest of the service sends as HTML
+//! or plain text, such as 404s, 405s, rejected query strings and failed
+//! fetches, into problems too. None of them have a `type` of their own, so
+//! it's `about:blank`, which says the status is all there is to know.
+
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+use serde_json::{json, Value};
+
+use crate::admin;
+
+/// The media type of problem details
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// Whether `path` is part of the API, whose errors are problems.
+pub fn is_api(path: &str) -> bool {
+    path.starts_with("/api/") || path.starts_with("/internal/") || admin::is_admin(path)
+}
+
+/// A problem with `status`, described by `detail`.
+pub fn response(status: StatusCode, detail: &str) -> Response {
+    Response::from_body(body(status, detail).to_string())
+        .with_status(status)
+        .with_header(header::CONTENT_TYPE, CONTENT_TYPE)
+        .with_header(header::CACHE_CONTROL, "no-store")
+}
+
+/// `resp` to a request for `path`, with any error in it as a problem.
+/// Headers such as `Allow` and `Retry-After` are kept.
+pub fn finish(path: &str, mut resp: Response) -> Response {
+    let status = resp.get_status();
+    if !is_api(path) || !(status.is_client_error() || status.is_server_error()) {
+        return resp;
+    }
+
+    let mut problem = if resp.get_header_str(header::CONTENT_TYPE) == Some(CONTENT_TYPE) {
+        resp.take_body_json::<Value>()
+            .unwrap_or_else(|_| body(status, ""))
+    } else {
+        // Plain-text errors say what's wrong; HTML ones are pages for people.
+        let is_text = resp
+            .get_content_type()
+            .is_some_and(|mime| mime.essence_str() == "text/plain");
+        let text = resp.take_body_str();
+        body(status, if is_text { text.trim() } else { "" })
+    };
+    problem["instance"] = Value::from(path);
+
+    resp.set_header(header::CONTENT_TYPE, CONTENT_TYPE);
+    resp.set_header(header::CACHE_CONTROL, "no-store");
+    resp.set_body(problem.to_string());
+    resp
+}
+
+/// The response for a handler that failed on `path`, where the visitor
+/// would otherwise get the platform's bare 500.
+pub fn internal(path: &str) -> Response {
+    finish(
+        path,
+        response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Something went wrong; try again later",
+        ),
+    )
+}
+
+fn body(status: StatusCode, detail: &str) -> Value {
+    let mut problem = json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or("Error"),
+        "status": status.as_u16(),
+    });
+    if !detail.is_empty() {
+        problem["detail"] = Value::from(detail);
+    }
+    problem
+}
@@ -0,0 +1,75 @@
+//! Where forecasts come from, and the credit their terms ask for.
+//!
+//! Pages showing a forecast name its source in the footer, and API responses
+//! carrying one include it as `attribution`. Open data sources such as
+//! Open-Meteo and MET Norway make that a condition of use, so each provider
+//! brings its own [`Provider`] entry with the name, link and license to show.
+//! The `ensemble` provider blends in Open-Meteo's forecast, so it credits
+//! both.
+
+use serde::Serialize;
+
+use crate::ensemble;
+
+/// A source of forecasts, as credited on the page and in the API
+#[derive(Serialize)]
+pub struct Provider {
+    pub name: &'static str,
+    /// Where the credit links to
+    pub url: &'static str,
+    /// The license the data is published under
+    pub license: &'static str,
+    pub license_url: &'static str,
+    /// The provider whose forecast is blended in, if any
+    pub blended_with: Option<&'static Provider>,
+}
+
+pub const OPENWEATHER: Provider = Provider {
+    name: "OpenWeather",
+    url: "https://openweathermap.org/",
+    license: "CC BY-SA 4.0",
+    license_url: "https://creativecommons.org/licenses/by-sa/4.0/",
+    blended_with: None,
+};
+
+pub const OPEN_METEO: Provider = Provider {
+    name: "Open-Meteo",
+    url: "https://open-meteo.com/",
+    license: "CC BY 4.0",
+    license_url: "https://creativecommons.org/licenses/by/4.0/",
+    blended_with: None,
+};
+
+/// OpenWeather's forecast averaged with Open-Meteo's
+pub const ENSEMBLE: Provider = Provider {
+    blended_with: Some(&OPEN_METEO),
+    ..OPENWEATHER
+};
+
+/// The provider forecasts come from. The mock replays an OpenWeather
+/// forecast, so it's credited the same way.
+pub fn current() -> &'static Provider {
+    if ensemble::enabled() {
+        &ENSEMBLE
+    } else {
+        &OPENWEATHER
+    }
+}
+
+/// An API response body, with its source credited alongside the data
+#[derive(Serialize)]
+pub struct Attributed<'a, T> {
+    #[serde(flatten)]
+    pub data: &'a T,
+    pub attribution: &'static Provider,
+}
+
+impl<'a, T: Serialize> Attributed<'a, T> {
+    /// Credit the current provider for `data`.
+    pub fn new(data: &'a T) -> Self {
+        Attributed {
+            data,
+            attribution: current(),
+        }
+    }
+}
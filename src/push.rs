@@ -0,0 +1,479 @@
+//! Web Push when severe weather alerts are issued for a subscriber's place.
+//!
+//! Pages subscribe with the Push API, giving the key from `GET
+//! /api/push/key` as the `applicationServerKey`, then `POST` the
+//! `PushSubscription` as JSON to `/api/push/subscribe`. It's kept for the
+//! visitor's location, to two decimal places; `DELETE` with the same body
+//! unsubscribes. Endpoints must be on one of the major browsers' push
+//! services, or a host in `outbound_hosts`.
+//!
+//! Each run of [`crate::dispatch`] pushes to subscribers whose place has a
+//! severe alert they haven't been pushed yet: warnings and emergencies, but
+//! not advisories, watches or yellow warnings. Pushes carry no payload,
+//! which would have to be encrypted for each subscriber, so the service
+//! worker that gets one should fetch the forecast to show. Subscriptions
+//! the push service says have expired are removed. A run checks up to
+//! [`MAX_CHECKS`] subscribers, carrying on where the last one stopped.
+//!
+//! Anyone can subscribe, so a client may add [`MAX_SUBSCRIBES_PER_CLIENT`]
+//! browsers an hour, and a subscription that has never been pushed to is
+//! dropped [`UNPROVEN_TTL_SECS`] after it was made, or last renewed, so
+//! made-up endpoints at quiet places don't keep their places for good.
+//! Pages should subscribe again now and then to stay subscribed.
+//!
+//! Like webhooks ([`crate::subscriptions`]), each subscriber is its own
+//! document, under `push_subscription:<hash of the endpoint>`, listed in a
+//! [`kv::Index`], so concurrent subscribes and dispatch runs only write the
+//! subscribers they change.
+//!
+//! Pushes are signed with VAPID: store the private key, base64url as
+//! `web-push generate-vapid-keys` prints it, as `vapid_private_key` in the
+//! `weather_secrets` Secret Store, and a `mailto:` or `https:` contact as
+//! `vapid_subject` in `weather_config`. Without both, push is off.
+
+use std::convert::TryInto;
+use std::io::Read;
+
+use chrono::Utc;
+use fastly::http::{header, Method, StatusCode, Url};
+use fastly::secret_store::SecretStore;
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::dispatch::{Forecasts, Report};
+use crate::location::Location;
+use crate::outbound::{self, Outcome};
+use crate::{config, es256, kv, problem, shared, signing, APIResponse, AlertReport};
+
+/// How many subscriptions the service keeps, across all visitors
+pub const MAX_SUBSCRIBERS: usize = 1000;
+
+/// How many subscribers a dispatch run checks
+pub const MAX_CHECKS: usize = 200;
+
+/// How many browsers one client may subscribe an hour
+pub const MAX_SUBSCRIBES_PER_CLIENT: usize = 5;
+
+/// How long a subscription that has never been pushed to is kept
+pub const UNPROVEN_TTL_SECS: i64 = 30 * 24 * 3600;
+
+const CLIENT_WINDOW_SECS: i64 = 3600;
+const CLIENTS: kv::ClientLimit = kv::ClientLimit::new(
+    "push_client:",
+    MAX_SUBSCRIBES_PER_CLIENT,
+    CLIENT_WINDOW_SECS,
+);
+
+const INDEX: kv::Index = kv::Index::new("push_subscriptions");
+const CURSOR_KEY: &str = "push_subscriptions_cursor";
+const RECORD_PREFIX: &str = "push_subscription:";
+const SECRET_STORE: &str = "weather_secrets";
+const SECRET_NAME: &str = "vapid_private_key";
+
+// Push services allow tokens for up to a day.
+const TOKEN_LIFETIME_SECS: i64 = 12 * 3600;
+// An alert pushed a day late is still worth seeing.
+const PUSH_TTL_SECS: u32 = 24 * 3600;
+
+// The push services of Chrome, Firefox, Edge and Safari. Others can be
+// allowed with `outbound_hosts`.
+const PUSH_SERVICES: &[&str] = &[
+    "fcm.googleapis.com",
+    "push.services.mozilla.com",
+    "notify.windows.com",
+    "push.apple.com",
+];
+
+// Subscriptions are an endpoint URL and two short keys.
+const MAX_BODY_BYTES: u64 = 4096;
+const MAX_ENDPOINT_LEN: usize = 1024;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A `PushSubscription`, as the browser serializes it
+#[derive(Deserialize)]
+struct SubscriptionBody {
+    endpoint: String,
+    #[serde(default)]
+    keys: Option<Keys>,
+}
+
+/// The subscriber's keys, for encrypting payloads to them
+#[derive(Deserialize, Serialize)]
+struct Keys {
+    p256dh: String,
+    auth: String,
+}
+
+/// A subscribed browser
+#[derive(Deserialize, Serialize)]
+struct Subscriber {
+    /// The index shard that lists it
+    index: String,
+    endpoint: String,
+    #[serde(default)]
+    keys: Option<Keys>,
+    lat: f64,
+    lon: f64,
+    /// The severe alerts in force at the last dispatch, which have been pushed
+    #[serde(default)]
+    pushed: Vec<String>,
+    /// When it was subscribed, or last subscribed again
+    #[serde(default)]
+    subscribed: i64,
+    /// Whether a push to it has ever gone through
+    #[serde(default)]
+    delivered: bool,
+}
+
+/// The VAPID key and contact, and the tokens signed with them this run
+struct Vapid {
+    private_key: [u8; 32],
+    public_key: String,
+    subject: String,
+    /// By push service origin
+    tokens: Vec<(String, String)>,
+}
+
+impl Vapid {
+    fn load() -> Option<Self> {
+        let secret = SecretStore::open(SECRET_STORE).ok()?.get(SECRET_NAME)?;
+        let private_key = base64url_decode(std::str::from_utf8(&secret.plaintext()).ok()?.trim())?;
+        let private_key: [u8; 32] = private_key.as_slice().try_into().ok()?;
+        let public_key = base64url(&es256::public_key(&private_key)?);
+        let subject = config::get("vapid_subject")
+            .map(|subject| subject.trim().to_string())
+            .filter(|subject| subject.starts_with("mailto:") || subject.starts_with("https:"))?;
+        Some(Vapid {
+            private_key,
+            public_key,
+            subject,
+            tokens: Vec::new(),
+        })
+    }
+
+    /// The `Authorization` header for pushes to `endpoint`.
+    fn authorization(&mut self, endpoint: &Url) -> String {
+        let audience = endpoint.origin().ascii_serialization();
+        let token = match self.tokens.iter().find(|(origin, _)| *origin == audience) {
+            Some((_, token)) => token.clone(),
+            None => {
+                let header = base64url(br#"{"typ":"JWT","alg":"ES256"}"#);
+                let claims = json!({
+                    "aud": audience,
+                    "exp": Utc::now().timestamp() + TOKEN_LIFETIME_SECS,
+                    "sub": self.subject,
+                });
+                let signed = format!("{}.{}", header, base64url(claims.to_string().as_bytes()));
+                let signature = es256::sign(&self.private_key, signed.as_bytes())
+                    .expect("the key was checked when it was loaded");
+                let token = format!("{}.{}", signed, base64url(&signature));
+                self.tokens.push((audience, token.clone()));
+                token
+            }
+        };
+        format!("vapid t={}, k={}", token, self.public_key)
+    }
+}
+
+/// Serve `/api/push/key`, with the key pages subscribe with.
+pub fn key() -> Response {
+    match Vapid::load() {
+        Some(vapid) => Response::from_body(json!({ "public_key": vapid.public_key }).to_string())
+            .with_status(StatusCode::OK)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_header(header::CACHE_CONTROL, "public, max-age=3600"),
+        None => unavailable(),
+    }
+}
+
+/// Serve `/api/push/subscribe`: subscribe a browser, or unsubscribe it.
+pub fn subscribe(req: &mut Request) -> Result<Response, Error> {
+    let mut body = Vec::new();
+    let read = req
+        .take_body()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body);
+    if read.is_err() || body.len() as u64 > MAX_BODY_BYTES {
+        return Ok(problem::response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "The body is too big",
+        ));
+    }
+    let subscription = match serde_json::from_slice::<SubscriptionBody>(&body) {
+        Ok(subscription) => subscription,
+        Err(_) => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Send the PushSubscription as JSON",
+            ))
+        }
+    };
+    let id = subscriber_id(&subscription.endpoint);
+    let existing = load(&id);
+
+    if req.get_method() == Method::DELETE {
+        let subscriber = match existing {
+            Some(subscriber) => subscriber,
+            None => {
+                return Ok(problem::response(
+                    StatusCode::NOT_FOUND,
+                    "That browser isn't subscribed",
+                ))
+            }
+        };
+        if !kv::delete(&record_key(&id)) {
+            return Ok(unavailable());
+        }
+        INDEX.remove(&[(&subscriber.index, &id)]);
+        return Ok(stored(StatusCode::NO_CONTENT));
+    }
+
+    let endpoint = Url::parse(&subscription.endpoint).ok();
+    if subscription.endpoint.len() > MAX_ENDPOINT_LEN
+        || !endpoint
+            .as_ref()
+            .is_some_and(|endpoint| outbound::is_allowed(endpoint, PUSH_SERVICES))
+    {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "endpoint must be an HTTPS URL on a browser's push service",
+        ));
+    }
+    let vapid = match Vapid::load() {
+        Some(vapid) => vapid,
+        None => return Ok(unavailable()),
+    };
+    let location = match Location::for_client(req)? {
+        Some(location) => location,
+        None => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Choose a location to be alerted about",
+            ))
+        }
+    };
+
+    // Subscribing again, say from somewhere new, replaces the old subscription.
+    let now = Utc::now().timestamp();
+    let (index, delivered) = match existing {
+        Some(subscriber) => (subscriber.index, subscriber.delivered),
+        None if INDEX.count() >= MAX_SUBSCRIBERS => {
+            return Ok(problem::response(
+                StatusCode::INSUFFICIENT_STORAGE,
+                "There are too many subscriptions",
+            ))
+        }
+        None if !CLIENTS.note(req, &vapid.private_key, now) => {
+            return Ok(problem::response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "There have been too many subscriptions from your network lately",
+            )
+            .with_header(header::RETRY_AFTER, CLIENT_WINDOW_SECS.to_string()))
+        }
+        None => (INDEX.random_shard(), false),
+    };
+    let subscriber = Subscriber {
+        index,
+        endpoint: subscription.endpoint,
+        keys: subscription.keys,
+        lat: shared::round_coord(location.latitude()),
+        lon: shared::round_coord(location.longitude()),
+        pushed: Vec::new(),
+        subscribed: now,
+        delivered,
+    };
+    if !save(&id, &subscriber) || !INDEX.add(&subscriber.index, &id) {
+        return Ok(unavailable());
+    }
+    Ok(stored(StatusCode::CREATED))
+}
+
+/// Push to the next [`MAX_CHECKS`] subscribers, if they have new severe
+/// alerts.
+pub fn dispatch(forecasts: &mut Forecasts) -> Report {
+    let listed = INDEX.entries();
+    let mut report = Report::default();
+    if listed.is_empty() {
+        return report;
+    }
+    let mut vapid = match Vapid::load() {
+        Some(vapid) => vapid,
+        None => {
+            println!("Push isn't set up, so nothing was pushed");
+            return report;
+        }
+    };
+    let page = kv::next_page(&listed, CURSOR_KEY, MAX_CHECKS);
+    report.checked = page.len();
+    let now = Utc::now().timestamp();
+    // Ids to take out of the index, because their subscriber has gone
+    let mut gone = Vec::new();
+
+    for (index, id) in page {
+        let mut subscriber = match load(id) {
+            Some(subscriber) => subscriber,
+            None => {
+                gone.push((index, id));
+                continue;
+            }
+        };
+        if !subscriber.delivered {
+            // Subscriptions from before they were dated start counting now.
+            if subscriber.subscribed == 0 {
+                subscriber.subscribed = now;
+                save_after_dispatch(id, &subscriber);
+            } else if now - subscriber.subscribed > UNPROVEN_TTL_SECS {
+                report.removed += 1;
+                kv::delete(&record_key(id));
+                gone.push((index, id));
+                continue;
+            }
+        }
+        let severe = match forecasts.at(subscriber.lat, subscriber.lon) {
+            Some(api_response) => severe_alerts(api_response),
+            None => {
+                report.deferred += 1;
+                continue;
+            }
+        };
+        if severe.iter().all(|alert| subscriber.pushed.contains(alert)) {
+            // Forget alerts that are over, so they're pushed if reissued
+            if subscriber.pushed != severe {
+                subscriber.pushed = severe;
+                save_after_dispatch(id, &subscriber);
+            }
+            continue;
+        }
+        if report.is_full() {
+            report.deferred += 1;
+            continue;
+        }
+
+        // Endpoints are checked when they're subscribed, and again in case
+        // outbound_hosts has changed since.
+        let endpoint = match Url::parse(&subscriber.endpoint) {
+            Ok(endpoint) if outbound::is_allowed(&endpoint, PUSH_SERVICES) => endpoint,
+            _ => {
+                kv::delete(&record_key(id));
+                gone.push((index, id));
+                continue;
+            }
+        };
+        let authorization = vapid.authorization(&endpoint);
+        let push = Request::post(endpoint)
+            .with_header(header::AUTHORIZATION, authorization)
+            .with_header("TTL", PUSH_TTL_SECS.to_string())
+            .with_header("Urgency", "high")
+            // A newer push replaces one that hasn't been delivered yet
+            .with_header("Topic", "weather-alerts")
+            .with_header(header::CONTENT_LENGTH, "0");
+        match outbound::send(push) {
+            Outcome::Sent => {
+                report.sent += 1;
+                subscriber.pushed = severe;
+                subscriber.delivered = true;
+                save_after_dispatch(id, &subscriber);
+            }
+            Outcome::Gone => {
+                report.removed += 1;
+                kv::delete(&record_key(id));
+                gone.push((index, id));
+            }
+            Outcome::Failed(err) => {
+                println!("Couldn't push to a subscriber: {}", err);
+                report.failed += 1;
+            }
+        }
+    }
+
+    INDEX.remove(&gone);
+    report
+}
+
+// The severe alerts in `api_response`, by id.
+fn severe_alerts(api_response: &APIResponse) -> Vec<String> {
+    api_response
+        .alerts
+        .iter()
+        .filter(|alert| is_severe(alert))
+        .map(alert_id)
+        .collect()
+}
+
+/// Whether `alert` is worth interrupting someone for: a warning or an
+/// emergency, but not a yellow warning.
+pub fn is_severe(alert: &AlertReport) -> bool {
+    let event = alert.event.to_ascii_lowercase();
+    (event.contains("warning") || event.contains("emergency")) && !event.starts_with("yellow")
+}
+
+/// A name for `alert` that stays the same while it's in force: its event
+/// and start.
+pub fn alert_id(alert: &AlertReport) -> String {
+    format!("{}@{}", alert.event, alert.start)
+}
+
+fn load(id: &str) -> Option<Subscriber> {
+    kv::get_json(&record_key(id))
+}
+
+fn save(id: &str, subscriber: &Subscriber) -> bool {
+    kv::put_json(&record_key(id), subscriber)
+}
+
+fn save_after_dispatch(id: &str, subscriber: &Subscriber) {
+    if !save(id, subscriber) {
+        println!("Couldn't save a push subscription after dispatching");
+    }
+}
+
+// A response saying the subscription was stored, or removed.
+fn stored(status: StatusCode) -> Response {
+    Response::from_status(status).with_header(header::CACHE_CONTROL, "no-store")
+}
+
+// Subscribers are keyed by their endpoint's hash, which is fixed-length hex
+// whatever the endpoint is, and doesn't show it in the key.
+fn subscriber_id(endpoint: &str) -> String {
+    signing::sha256(endpoint.as_bytes())
+}
+
+fn record_key(id: &str) -> String {
+    format!("{}{}", RECORD_PREFIX, id)
+}
+
+// Unpadded base64url, which JWTs and the Push API use.
+fn base64url(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64url_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+    for char in text.trim_end_matches('=').bytes() {
+        let value = BASE64URL.iter().position(|&known| known == char)? as u32;
+        n = n << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((n >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn unavailable() -> Response {
+    problem::response(StatusCode::SERVICE_UNAVAILABLE, "Push isn't set up")
+}
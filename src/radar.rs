@@ -0,0 +1,106 @@
+//! Precipitation radar tiles, proxied from OpenWeatherMap's tile API.
+//!
+//! Tiles are served at `/radar/{z}/{x}/{y}.png` and cached at the edge for a
+//! few minutes, so neighbouring visitors share them and the API key never
+//! reaches the browser.
+
+use fastly::http::{header, HeaderValue, Method, StatusCode};
+use fastly::{Error, Request, Response};
+use serde::Serialize;
+
+use crate::get_api_key;
+
+const TILE_BACKEND: &str = "tile.openweathermap.org";
+
+// Radar frames update every ten minutes or so.
+const TILE_TTL_SECS: u32 = 600;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "radar";
+
+// Beyond this zoom the radar data is just upscaled.
+const MAX_ZOOM: u32 = 10;
+
+/// Zoom level used for the map section on the page, about 40km per tile.
+pub const PAGE_ZOOM: u32 = 8;
+
+/// A 3×3 grid of tiles around a location, with a marker for the location itself
+#[derive(Serialize)]
+pub struct RadarMap {
+    tiles: Vec<String>,
+    marker_left: String,
+    marker_top: String,
+}
+
+impl RadarMap {
+    /// The tiles surrounding `latitude`, `longitude` at `zoom`.
+    pub fn around(latitude: f64, longitude: f64, zoom: u32) -> Self {
+        let n = f64::from(1u32 << zoom);
+        let lat = latitude.to_radians();
+        let x = (longitude + 180.0) / 360.0 * n;
+        let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0 * n;
+
+        let (center_x, center_y) = (x.floor() as i64, y.floor() as i64);
+        let max = (1i64 << zoom) - 1;
+        let mut tiles = Vec::with_capacity(9);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // Wrap around the antimeridian, but not over the poles.
+                let tile_x = (center_x + dx).rem_euclid(max + 1);
+                let tile_y = (center_y + dy).clamp(0, max);
+                tiles.push(format!("/radar/{}/{}/{}.png", zoom, tile_x, tile_y));
+            }
+        }
+
+        // The marker sits inside the centre tile, which is the middle third of the grid.
+        RadarMap {
+            tiles,
+            marker_left: format!("{:.2}%", (1.0 + x.fract()) / 3.0 * 100.0),
+            marker_top: format!("{:.2}%", (1.0 + y.fract()) / 3.0 * 100.0),
+        }
+    }
+}
+
+/// Serve a radar tile, or `None` if `path` isn't a valid tile path.
+pub fn handle(path: &str) -> Option<Result<Response, Error>> {
+    let (z, x, y) = parse_tile_path(path)?;
+
+    let url = format!(
+        "https://tile.openweathermap.org/map/precipitation_new/{}/{}/{}.png?appid={}",
+        z,
+        x,
+        y,
+        get_api_key()
+    );
+    let bereq = Request::new(Method::GET, url)
+        .with_header(header::HOST, "tile.openweathermap.org")
+        .with_ttl(TILE_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY));
+
+    Some(bereq.send(TILE_BACKEND).map_err(Error::from).map(|beresp| {
+        if !beresp.get_status().is_success() {
+            return Response::from_status(StatusCode::BAD_GATEWAY);
+        }
+        beresp
+            .with_header(
+                header::CACHE_CONTROL,
+                format!("public, max-age={}", TILE_TTL_SECS),
+            )
+            .with_content_type(fastly::mime::IMAGE_PNG)
+    }))
+}
+
+// Parse `/radar/{z}/{x}/{y}.png`, rejecting coordinates outside the tile grid.
+fn parse_tile_path(path: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = path
+        .strip_prefix("/radar/")?
+        .strip_suffix(".png")?
+        .split('/');
+    let z: u32 = parts.next()?.parse().ok()?;
+    let x: u32 = parts.next()?.parse().ok()?;
+    let y: u32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || z > MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+        return None;
+    }
+    Some((z, x, y))
+}
@@ -0,0 +1,108 @@
+//! URL canonicalization, applied before routing: canonical-host and HTTPS
+//! redirects, and query string normalization.
+//!
+//! Both are opt-in so local development under `fastly compute serve` keeps
+//! working over plain HTTP on `127.0.0.1`:
+//!
+//! * `canonical_host` — the hostname every request should end up on.
+//! * `alias_hosts` — comma-separated hostnames redirected to `canonical_host`.
+//! * `force_https` — set to `true` to redirect plaintext requests to HTTPS.
+
+use fastly::http::{header, StatusCode, Url};
+use fastly::{Request, Response};
+
+use crate::config;
+
+/// Returns a 301 to the canonical HTTPS URL if the request isn't already on it.
+pub fn canonical_redirect(req: &Request) -> Option<Response> {
+    let host = req.get_url().host_str()?.to_ascii_lowercase();
+    let canonical_host = canonical_host();
+
+    let is_alias = match config::get("alias_hosts") {
+        Some(aliases) => aliases
+            .split(',')
+            .any(|alias| alias.trim().eq_ignore_ascii_case(&host)),
+        None => false,
+    };
+    let force_https = config::get("force_https").as_deref() == Some("true");
+    // Only ask about TLS when it matters: the SDK panics on plaintext
+    // requests under some versions of Viceroy.
+    let is_plaintext = || req.get_tls_protocol().is_none();
+
+    let target_host = match canonical_host {
+        Some(canonical) if is_alias && canonical != host => canonical,
+        _ if force_https && is_plaintext() => host,
+        _ => return None,
+    };
+    // The request's own host comes from the client, so check it too.
+    if !is_hostname(&target_host) {
+        return None;
+    }
+
+    // Preserve the path and query string of the original request.
+    let mut location = format!("https://{}{}", target_host, req.get_path());
+    if let Some(query) = req.get_query_str() {
+        location.push('?');
+        location.push_str(query);
+    }
+
+    Some(
+        Response::from_status(StatusCode::MOVED_PERMANENTLY)
+            .with_header(header::LOCATION, location)
+            .with_header(header::CACHE_CONTROL, "public, max-age=3600"),
+    )
+}
+
+// The configured `canonical_host`, if it's set to a plain hostname. Anything
+// else would send visitors, or search engines, somewhere we don't mean.
+fn canonical_host() -> Option<String> {
+    config::get("canonical_host")
+        .map(|host| host.trim().to_ascii_lowercase())
+        .filter(|host| is_hostname(host))
+}
+
+fn is_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.')
+}
+
+/// Sort the query string by parameter name, so equivalent URLs such as
+/// `?b=2&a=1` and `?a=1&b=2` share cache keys and canonical links.
+pub fn normalize_query(req: &mut Request) {
+    let query = match req.get_query_str() {
+        Some(query) => query,
+        None => return,
+    };
+
+    // Sort the raw `key=value` pairs so values are kept exactly as sent.
+    let mut pairs: Vec<&str> = query.split('&').filter(|pair| !pair.is_empty()).collect();
+    pairs.sort_by_key(|pair| pair.split('=').next().unwrap_or(pair));
+    let normalized = pairs.join("&");
+
+    if normalized.is_empty() {
+        req.remove_query();
+    } else if normalized != query {
+        req.set_query_str(normalized);
+    }
+}
+
+/// The canonical URL of the page at `path` for the given units.
+///
+/// Only parameters that change the page are kept, and defaults are left out.
+pub fn canonical_url(req: &Request, path: &str, units: &str) -> String {
+    let host = canonical_host()
+        .or_else(|| req.get_url().host_str().map(str::to_string))
+        .unwrap_or_default();
+
+    let mut url = match Url::parse(&format!("https://{}", host)) {
+        Ok(url) => url,
+        Err(_) => return path.to_string(),
+    };
+    url.set_path(path);
+    if units != "metric" {
+        url.query_pairs_mut().append_pair("units", units);
+    }
+    url.to_string()
+}
@@ -0,0 +1,149 @@
+//! The road conditions card: a black ice warning for drivers.
+//!
+//! The risk now, from the current conditions and whatever's falling, and
+//! over the next 12 hours of the hourly forecast, each hour counting the
+//! rain and snow of the three before it as still on the road. In winter the
+//! card is always at the top of the page, saying so when the roads should
+//! be clear; the rest of the year it only appears when there's a risk.
+
+use serde::Serialize;
+use weather_helpers::Season;
+
+use crate::location::Location;
+use crate::APIResponse;
+
+const HOURS: usize = 12;
+// How long rain or snow stays on the road to freeze
+const WET_HOURS: usize = 3;
+
+/// Context for the roads fragment
+#[derive(Serialize)]
+pub struct RoadCard {
+    /// `None` when the roads should be clear
+    risk: Option<Risk>,
+}
+
+#[derive(Serialize)]
+struct Risk {
+    label: &'static str,
+    /// `likely` or `possible`, for styling
+    level: &'static str,
+    /// The local time it starts, or `None` if it's already icy
+    from: Option<String>,
+}
+
+impl RoadCard {
+    /// The black ice risk at `location`, from a report fetched in `units`.
+    /// `None` outside winter when there's no risk.
+    pub fn new(api_response: &APIResponse, units: &str, location: &Location) -> Option<Self> {
+        let current = &api_response.current;
+        let celsius = |temp: f32| weather_helpers::to_celsius(temp, units);
+        let local = |dt: i64| weather_helpers::local_time(dt, api_response.timezone_offset);
+
+        // What's fallen in the last hour, and what's falling now
+        let falling = api_response
+            .minutely
+            .iter()
+            .map(|minute| minute.precipitation / 60.0)
+            .sum::<f32>();
+        let fallen = [&current.rain, &current.snow]
+            .iter()
+            .filter_map(|precipitation| precipitation.as_ref())
+            .map(|precipitation| precipitation.one_hour)
+            .sum::<f32>();
+        let now = black_ice_risk(
+            celsius(current.temp),
+            current.dew_point.map(celsius),
+            fallen + falling,
+        );
+
+        let hours = &api_response.hourly[..api_response.hourly.len().min(HOURS)];
+        let amounts: Vec<f32> = hours
+            .iter()
+            .map(|hour| {
+                [&hour.rain, &hour.snow]
+                    .iter()
+                    .filter_map(|precipitation| precipitation.as_ref())
+                    .map(|precipitation| precipitation.one_hour)
+                    .sum()
+            })
+            .collect();
+        let ahead = hours
+            .iter()
+            .enumerate()
+            .filter(|(_, hour)| hour.dt > current.dt)
+            .filter_map(|(i, hour)| {
+                let mut recent = amounts[i.saturating_sub(WET_HOURS)..=i].iter().sum::<f32>();
+                if i < WET_HOURS {
+                    recent += fallen + falling;
+                }
+                black_ice_risk(celsius(hour.temp), hour.dew_point.map(celsius), recent)
+                    .map(|risk| (risk, hour.dt))
+            })
+            // The worst risk, from when it starts
+            .max_by_key(|(risk, dt)| (*risk, std::cmp::Reverse(*dt)));
+
+        let risk = match (now, ahead) {
+            (Some(now), Some((ahead, _))) if now >= ahead => Some((now, None)),
+            (_, Some((ahead, dt))) => Some((ahead, Some(dt))),
+            (Some(now), None) => Some((now, None)),
+            (None, None) => None,
+        };
+        let winter =
+            weather_helpers::get_season(location.latitude(), local(current.dt)) == Season::Winter;
+        if risk.is_none() && !winter {
+            return None;
+        }
+
+        Some(RoadCard {
+            risk: risk.map(|(risk, from)| Risk {
+                label: risk.label(),
+                level: match risk {
+                    IceRisk::Likely => "likely",
+                    IceRisk::Possible => "possible",
+                },
+                from: from.map(|dt| local(dt).format("%H:%M").to_string()),
+            }),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum IceRisk {
+    /// Wet or damp roads close to freezing
+    Possible,
+    Likely,
+}
+
+impl IceRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IceRisk::Possible => "Black ice possible",
+            IceRisk::Likely => "Black ice likely",
+        }
+    }
+}
+
+/// Black ice risk on roads, from the air temperature and dew point in °C and
+/// the rain or snow in mm over the last few hours. Road surfaces cool below
+/// the air on clear nights, so they can freeze with the air at 3°C. They
+/// need water to ice over: what's fallen, or frost where the air is close to
+/// saturated below freezing.
+pub fn black_ice_risk(
+    temp: f32,
+    dew_point: Option<f32>,
+    recent_precipitation: f32,
+) -> Option<IceRisk> {
+    if temp > 3.0 {
+        return None;
+    }
+    let wet = recent_precipitation >= 0.1;
+    let frosting = dew_point.is_some_and(|dew_point| dew_point <= 0.0 && temp - dew_point <= 2.0);
+    if !wet && !frosting {
+        None
+    } else if temp <= 0.0 {
+        Some(IceRisk::Likely)
+    } else {
+        Some(IceRisk::Possible)
+    }
+}
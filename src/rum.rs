@@ -0,0 +1,73 @@
+//! Real User Monitoring beacons posted to `/rum` by the page snippet.
+//!
+//! Beacons are checked for shape and plausible values, then forwarded as a
+//! JSON line to the `rum` log endpoint. Anything else gets a 400, and nothing
+//! from the beacon is ever echoed back.
+
+use std::io::{Read, Write};
+
+use fastly::http::StatusCode;
+use fastly::log::Endpoint;
+use fastly::{Request, Response};
+use serde::{Deserialize, Serialize};
+
+const LOG_ENDPOINT: &str = "rum";
+
+// A beacon is a handful of numbers, so anything bigger isn't one.
+const MAX_BEACON_BYTES: u64 = 2048;
+
+// Metrics above a minute are almost certainly backgrounded tabs or clock skew.
+const MAX_METRIC_MS: f64 = 60_000.0;
+
+/// A performance beacon sent by the page.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Beacon {
+    /// Path of the page that was measured.
+    page: String,
+    /// Time to first byte.
+    ttfb: Option<f64>,
+    /// `DOMContentLoaded` event end.
+    dcl: Option<f64>,
+    /// `load` event end.
+    load: Option<f64>,
+    /// Largest Contentful Paint.
+    lcp: Option<f64>,
+}
+
+impl Beacon {
+    fn is_valid(&self) -> bool {
+        let metrics = [self.ttfb, self.dcl, self.load, self.lcp];
+        self.page.starts_with('/')
+            && self.page.len() <= 256
+            && metrics.iter().any(Option::is_some)
+            && metrics
+                .iter()
+                .flatten()
+                .all(|ms| ms.is_finite() && (0.0..=MAX_METRIC_MS).contains(ms))
+    }
+}
+
+/// Validate a beacon and forward it to the log endpoint.
+pub fn collect(mut req: Request) -> Response {
+    let mut body = Vec::new();
+    let read = req
+        .take_body()
+        .take(MAX_BEACON_BYTES + 1)
+        .read_to_end(&mut body);
+    if read.is_err() || body.len() as u64 > MAX_BEACON_BYTES {
+        return Response::from_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let beacon = match serde_json::from_slice::<Beacon>(&body) {
+        Ok(beacon) if beacon.is_valid() => beacon,
+        _ => return Response::from_status(StatusCode::BAD_REQUEST),
+    };
+
+    // Losing a beacon is fine, so don't fail the request if logging does.
+    if let Ok(mut endpoint) = Endpoint::try_from_name(LOG_ENDPOINT) {
+        let _ = writeln!(endpoint, "{}", serde_json::to_string(&beacon).unwrap());
+    }
+
+    Response::from_status(StatusCode::NO_CONTENT)
+}
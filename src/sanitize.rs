@@ -0,0 +1,144 @@
+//! Query string sanitization, applied before routing.
+//!
+//! Handlers only see parameters they know about, each in the shape it
+//! should have, so nothing odd can be reflected into a page or spliced into
+//! a backend URL. Unknown parameters, such as campaign tags, are dropped.
+//! Known ones are percent-decoded strictly, checked against what they may
+//! hold and refused with a 400 when they don't fit, as are repeated
+//! parameters and malformed escapes. What's left is kept as sent, so
+//! signatures over it still check out.
+
+use fastly::http::StatusCode;
+use fastly::{Request, Response};
+
+/// Longest value, once decoded, that any parameter may have
+const MAX_VALUE_LEN: usize = 256;
+
+/// Whether a decoded value is fit for its parameter
+type Check = fn(&str) -> bool;
+
+/// Every parameter a route reads, and what its decoded value may hold
+const PARAMS: &[(&str, Check)] = &[
+    ("units", is_word),
+    ("mode", is_word),
+    ("skin", is_number),
+    ("details", is_word),
+    ("geo", is_word),
+    ("lat", is_coordinate),
+    ("lon", is_coordinate),
+    ("city", is_text),
+    ("exp", is_number),
+    ("sig", is_hex),
+    ("q", is_text),
+    ("limit", is_number),
+    ("key", is_token),
+    ("soft", is_word),
+    ("min_temp", is_temperature),
+    ("max_pop", is_number),
+    ("hours", is_number),
+    ("format", is_word),
+    ("id", is_hex),
+    ("granularity", is_word),
+    ("from", is_time),
+    ("to", is_time),
+    ("season", is_word),
+];
+
+/// Drop unknown parameters from `req`'s query string, returning a 400 to
+/// send instead if a known one is malformed.
+pub fn sanitize_query(req: &mut Request) -> Option<Response> {
+    let query = req.get_query_str()?.to_string();
+
+    let mut kept: Vec<&str> = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let check = match PARAMS.iter().find(|(known, _)| *known == name) {
+            Some((_, check)) => check,
+            None => continue,
+        };
+        if seen.contains(&name) {
+            return Some(bad_request(&format!("{} is given more than once", name)));
+        }
+        seen.push(name);
+
+        match percent_decode(value) {
+            Some(value) if value.chars().count() <= MAX_VALUE_LEN && check(&value) => {
+                kept.push(pair)
+            }
+            _ => return Some(bad_request(&format!("{} isn't valid", name))),
+        }
+    }
+
+    let sanitized = kept.join("&");
+    if sanitized.is_empty() {
+        req.remove_query();
+    } else if sanitized != query {
+        req.set_query_str(sanitized);
+    }
+    None
+}
+
+/// Decode a form-encoded value, or `None` if it has a broken escape or
+/// isn't UTF-8 once decoded.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        bytes.push(match byte {
+            b'+' => b' ',
+            b'%' => {
+                let high = (input.next()? as char).to_digit(16)?;
+                let low = (input.next()? as char).to_digit(16)?;
+                (high * 16 + low) as u8
+            }
+            byte => byte,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn is_word(value: &str) -> bool {
+    (1..=16).contains(&value.len()) && value.bytes().all(|byte| byte.is_ascii_alphabetic())
+}
+
+fn is_number(value: &str) -> bool {
+    (1..=12).contains(&value.len()) && value.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn is_coordinate(value: &str) -> bool {
+    (1..=24).contains(&value.len()) && value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+// A temperature in any of the units, which may be negative or fractional.
+fn is_temperature(value: &str) -> bool {
+    (1..=12).contains(&value.len()) && value.parse::<f32>().is_ok_and(f32::is_finite)
+}
+
+// Unix times and RFC 3339, which the handler parses properly.
+fn is_time(value: &str) -> bool {
+    (1..=35).contains(&value.len())
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || b"-:.+TZtz".contains(&byte))
+}
+
+fn is_hex(value: &str) -> bool {
+    (1..=128).contains(&value.len()) && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn is_token(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || b"-_.:".contains(&byte))
+}
+
+// Free text, such as place names, can hold anything but control characters.
+fn is_text(value: &str) -> bool {
+    !value.chars().any(char::is_control)
+}
+
+fn bad_request(message: &str) -> Response {
+    Response::from_status(StatusCode::BAD_REQUEST)
+        .with_body_text_plain(&format!("Bad query string: {}\n", message))
+}
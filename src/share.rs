@@ -0,0 +1,143 @@
+//! Share links: `/share` hands out a link to the forecast for the visitor's
+//! location, which anyone can open at `/shared` until it expires.
+//!
+//! A link carries the place (`lat`, `lon` and `city`) and its expiry (`exp`,
+//! in Unix seconds), signed with HMAC-SHA256 under `share_key` from the
+//! `weather_secrets` Secret Store. Recipients see the forecast for exactly
+//! that place, wherever they are, but can't move it or keep it past the
+//! expiry. Links last `share_ttl_days` from the `weather_config` store, a
+//! week by default. Until a key is stored, `/share` answers 503.
+
+use chrono::{Duration, SecondsFormat, TimeZone, Utc};
+use fastly::http::{header, StatusCode, Url};
+use fastly::secret_store::SecretStore;
+use fastly::{Error, Request, Response};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::location::Location;
+use crate::{config, problem, redirect, signing};
+
+const SECRET_STORE: &str = "weather_secrets";
+const SECRET_NAME: &str = "share_key";
+
+const DEFAULT_TTL_DAYS: i64 = 7;
+// Nobody needs a forecast link for longer than a season.
+const MAX_TTL_DAYS: i64 = 90;
+
+/// The query string of a share link. Coordinates are kept as sent, so the
+/// signature covers exactly the text in the link.
+#[derive(Deserialize)]
+struct SharedParams {
+    lat: String,
+    lon: String,
+    city: String,
+    exp: i64,
+    sig: String,
+}
+
+/// What a share link turned out to be
+pub enum Shared {
+    Valid(Location),
+    Expired,
+    /// Unsigned, tampered with, or signed with a key we no longer have
+    Invalid,
+}
+
+/// Serve `/share`, with a link to the forecast for the visitor's location.
+pub fn handle(req: &Request) -> Result<Response, Error> {
+    let key = match secret_key() {
+        Some(key) => key,
+        None => {
+            return Ok(problem::response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Sharing isn't set up",
+            ))
+        }
+    };
+    let location = match Location::for_client(req)? {
+        Some(location) => location,
+        None => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Pick a place to share first",
+            ))
+        }
+    };
+
+    let ttl_days = config::get("share_ttl_days")
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|days| (1..=MAX_TTL_DAYS).contains(days))
+        .unwrap_or(DEFAULT_TTL_DAYS);
+    let expires = Utc::now() + Duration::days(ttl_days);
+
+    let lat = location.latitude().to_string();
+    let lon = location.longitude().to_string();
+    let exp = expires.timestamp();
+    let sig = signing::sign(&key, message(&lat, &lon, location.city(), exp).as_bytes());
+
+    let mut url = Url::parse(&redirect::canonical_url(req, "/shared", "metric"))?;
+    url.query_pairs_mut()
+        .append_pair("lat", &lat)
+        .append_pair("lon", &lon)
+        .append_pair("city", location.city())
+        .append_pair("exp", &exp.to_string())
+        .append_pair("sig", &sig);
+
+    let body = json!({
+        "url": url.as_str(),
+        "expires": expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+    });
+    Ok(Response::from_body(body.to_string())
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "private, no-store"))
+}
+
+/// Check the share link in `req`'s query string.
+pub fn verify(req: &Request) -> Shared {
+    let params = match req.get_query::<SharedParams>() {
+        Ok(params) => params,
+        Err(_) => return Shared::Invalid,
+    };
+    let signed = secret_key().is_some_and(|key| {
+        let message = message(&params.lat, &params.lon, &params.city, params.exp);
+        signing::verify(&key, message.as_bytes(), &params.sig)
+    });
+    if !signed {
+        return Shared::Invalid;
+    }
+    if Utc.timestamp_opt(params.exp, 0).single() < Some(Utc::now()) {
+        return Shared::Expired;
+    }
+
+    match (params.lat.parse::<f64>(), params.lon.parse::<f64>()) {
+        // Links signed before privacy mode was on may be finer than it allows.
+        (Ok(lat), Ok(lon)) => {
+            let mut location = Location::at(lat, lon, params.city);
+            location.coarsen();
+            Shared::Valid(location)
+        }
+        _ => Shared::Invalid,
+    }
+}
+
+/// Whether `req` carries a share link signature, for routes that also serve
+/// the visitor's own location.
+pub fn is_shared(req: &Request) -> bool {
+    req.get_query_str()
+        .is_some_and(|query| query.split('&').any(|pair| pair.starts_with("sig=")))
+}
+
+// What's signed: every field of the link but the signature, one per line.
+// Only the city can hold a newline, and it's never the first or last line.
+fn message(lat: &str, lon: &str, city: &str, exp: i64) -> String {
+    format!("{}\n{}\n{}\n{}", lat, lon, city, exp)
+}
+
+/// The key share links are signed under, which also signs digest
+/// confirmation links.
+pub fn secret_key() -> Option<Vec<u8>> {
+    let secret = SecretStore::open(SECRET_STORE).ok()?.get(SECRET_NAME)?;
+    Some(secret.plaintext().to_vec()).filter(|key| !key.is_empty())
+}
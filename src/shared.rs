@@ -0,0 +1,44 @@
+//! Small helpers that several handlers share: randomness from the host, the
+//! ids made from it, and coordinates rounded for keeping.
+
+const ID_BYTES: usize = 16;
+
+/// `N` random bytes from the host.
+#[cfg(target_arch = "wasm32")]
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    // SAFETY: the buffer is exactly as long as we say it is.
+    unsafe { wasi::random_get(bytes.as_mut_ptr(), bytes.len()) }
+        .expect("the host always has randomness to give");
+    bytes
+}
+
+/// `N` bytes from std's randomly seeded hashers. Built for the host, as for
+/// the tests and benchmarks, there's no Compute host to ask, and nothing
+/// made from them needs to be unguessable.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        let hash = RandomState::new().build_hasher().finish().to_le_bytes();
+        chunk.copy_from_slice(&hash[..chunk.len()]);
+    }
+    bytes
+}
+
+/// A new random id, as 32 hex digits.
+pub fn new_id() -> String {
+    random_bytes::<ID_BYTES>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// `degrees` rounded to two decimal places, about 1km, which is as finely
+/// as subscriptions keep a place.
+pub fn round_coord(degrees: f64) -> f64 {
+    (degrees * 100.0).round() / 100.0
+}
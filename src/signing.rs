@@ -0,0 +1,55 @@
+//! HMAC-SHA256 signatures, for URLs and requests that must not be forged.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The HMAC-SHA256 of `message` under `key`, as lowercase hex.
+pub fn sign(key: &[u8], message: &[u8]) -> String {
+    hex(&mac(key, message).finalize().into_bytes())
+}
+
+/// Whether `signature` is the hex HMAC-SHA256 of `message` under `key`.
+pub fn verify(key: &[u8], message: &[u8], signature: &str) -> bool {
+    match unhex(signature) {
+        // `verify_slice` compares in constant time.
+        Some(signature) => mac(key, message).verify_slice(&signature).is_ok(),
+        None => false,
+    }
+}
+
+/// The SHA-256 of `bytes`, as lowercase hex.
+pub fn sha256(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Compare two byte strings in time that depends only on their lengths, so
+/// guessing a secret one byte at a time doesn't work.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn mac(key: &[u8], message: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Signatures are sent as lowercase hex, but either case decodes.
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(text.get(at..at + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,87 @@
+//! Snow conditions for `?mode=snow`.
+//!
+//! OpenWeatherMap gives snowfall but not what's on the ground, so snow depth
+//! and the freezing level come from the Open-Meteo forecast API. Either may
+//! be missing, in which case the snow report just leaves it out.
+
+use fastly::http::{header, HeaderValue, Method, Url};
+use fastly::{Error, Request};
+use serde::{Deserialize, Serialize};
+
+use crate::location::Location;
+
+const SNOW_BACKEND: &str = "api.open-meteo.com";
+const SNOW_TTL_SECS: u32 = 1800;
+
+/// The surrogate key responses are cached under, for `/admin/purge`
+pub const SURROGATE_KEY: &str = "snow";
+
+#[derive(Deserialize)]
+struct ForecastResponse {
+    current: CurrentForecast,
+}
+
+#[derive(Deserialize)]
+struct CurrentForecast {
+    /// Metres of snow on the ground
+    snow_depth: Option<f32>,
+    /// Metres above sea level
+    freezing_level_height: Option<f32>,
+}
+
+/// Snow on the ground and the freezing level, formatted for display
+#[derive(Serialize, Default)]
+pub struct SnowConditions {
+    depth: Option<String>,
+    freezing_level: Option<String>,
+}
+
+/// Fetch the snow conditions at `location`. Upstream failures leave the
+/// conditions empty rather than failing the page.
+pub fn fetch(location: &Location, units: &str) -> Result<SnowConditions, Error> {
+    let url = Url::parse_with_params(
+        "https://api.open-meteo.com/v1/forecast",
+        &[
+            ("latitude", format!("{:.2}", location.latitude())),
+            ("longitude", format!("{:.2}", location.longitude())),
+            ("current", String::from("snow_depth,freezing_level_height")),
+        ],
+    )?;
+    let mut beresp = Request::new(Method::GET, url)
+        .with_header(header::HOST, "api.open-meteo.com")
+        .with_ttl(SNOW_TTL_SECS)
+        .with_surrogate_key(HeaderValue::from_static(SURROGATE_KEY))
+        .send(SNOW_BACKEND)?;
+    if !beresp.get_status().is_success() {
+        println!("Snow conditions API returned {}", beresp.get_status());
+        return Ok(SnowConditions::default());
+    }
+    let current = beresp.take_body_json::<ForecastResponse>()?.current;
+
+    let imperial = units == "imperial";
+    Ok(SnowConditions {
+        depth: current.snow_depth.map(|metres| {
+            if imperial {
+                format!("{:.0} in", metres * 39.37)
+            } else {
+                format!("{:.0} cm", metres * 100.0)
+            }
+        }),
+        freezing_level: current.freezing_level_height.map(|metres| {
+            if imperial {
+                format!("{:.0} ft", metres * 3.281)
+            } else {
+                format!("{:.0} m", metres)
+            }
+        }),
+    })
+}
+
+/// Format a snowfall amount, which the weather API always reports in millimetres.
+pub fn format_snowfall(millimetres: f32, units: &str) -> String {
+    if units == "imperial" {
+        format!("{:.1} in", millimetres / 25.4)
+    } else {
+        format!("{:.1} cm", millimetres / 10.0)
+    }
+}
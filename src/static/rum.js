@@ -0,0 +1,30 @@
+// Report navigation timing and Largest Contentful Paint to /rum when the page is hidden.
+(function () {
+    if (!navigator.sendBeacon || !window.PerformanceObserver) return;
+
+    var lcp;
+    try {
+        new PerformanceObserver(function (list) {
+            var entries = list.getEntries();
+            lcp = entries[entries.length - 1].startTime;
+        }).observe({ type: 'largest-contentful-paint', buffered: true });
+    } catch (e) {}
+
+    function round(ms) {
+        return ms ? Math.round(ms) : null;
+    }
+
+    addEventListener('visibilitychange', function send() {
+        if (document.visibilityState !== 'hidden') return;
+        removeEventListener('visibilitychange', send);
+
+        var nav = performance.getEntriesByType('navigation')[0] || {};
+        navigator.sendBeacon('/rum', JSON.stringify({
+            page: location.pathname,
+            ttfb: round(nav.responseStart),
+            dcl: round(nav.domContentLoadedEventEnd),
+            load: round(nav.loadEventEnd),
+            lcp: round(lcp)
+        }));
+    });
+})();
@@ -0,0 +1,426 @@
+//! Webhook alerts: clients register at `/api/subscriptions`, and are told
+//! when the weather crosses their threshold.
+//!
+//! `POST /api/subscriptions` takes a JSON body naming an HTTPS callback, a
+//! place and a condition. Callbacks are only sent to hosts listed in
+//! `outbound_hosts` (see [`crate::outbound`]):
+//!
+//! ```json
+//! {"callback_url": "https://example.com/hook", "lat": 51.51, "lon": -0.13,
+//!  "condition": "wind_above", "threshold": 15}
+//! ```
+//!
+//! The conditions are `temp_above` and `temp_below` (°C), `wind_above`
+//! (m/s) and `pop_above` (percent, over the next hour). The 201 that comes
+//! back holds the subscription's `id` and its `secret`, and is the only
+//! place either is shown. `DELETE /api/subscriptions?id=<id>` removes it,
+//! and every webhook carries the hex HMAC-SHA256 of its body under the
+//! secret in `X-Weather-Signature`. Neither goes in webhook bodies, so
+//! whoever sees a delivery can't forge one or remove the subscription, and
+//! logs only name a subscription by the start of its id's hash. Places are
+//! kept to two decimal places, about a kilometre.
+//!
+//! Registering needs a signed request, so `api_auth` must be on (see
+//! [`crate::auth`]); without it, webhooks are off. Removing only needs the id.
+//!
+//! Each run of [`crate::dispatch`] checks up to [`MAX_CHECKS`] subscriptions
+//! against the current forecast, carrying on where the last run stopped,
+//! and posts to each callback whose condition has come true since it was
+//! last checked. A condition that stays true isn't sent again until it has
+//! been false. Callbacks that answer 404 or 410 are removed.
+//!
+//! Each subscription is its own document, under `subscription:<id>`, so
+//! registrations and dispatch runs only ever write the subscriptions they
+//! change. The KV Store can't list keys, so the ids are also kept in a
+//! [`kv::Index`].
+
+use std::io::Read;
+
+use chrono::{TimeZone, Utc};
+use fastly::http::{header, Method, StatusCode, Url};
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::dispatch::{Forecasts, Report};
+use crate::outbound::{self, Outcome};
+use crate::provider::Attributed;
+use crate::{kv, problem, shared, signing, APIResponse};
+
+/// How many subscriptions the service keeps, across all clients
+pub const MAX_SUBSCRIPTIONS: usize = 5000;
+
+/// How many subscriptions a dispatch run checks
+pub const MAX_CHECKS: usize = 200;
+
+const INDEX: kv::Index = kv::Index::new("subscriptions");
+const CURSOR_KEY: &str = "subscriptions_cursor";
+const RECORD_PREFIX: &str = "subscription:";
+const SIGNATURE_HEADER: &str = "X-Weather-Signature";
+
+// How many hex digits of an id's hash the logs show
+const LOG_ID_LEN: usize = 8;
+
+// A registration is a URL and a few numbers.
+const MAX_BODY_BYTES: u64 = 2048;
+const MAX_URL_LEN: usize = 512;
+
+/// What a subscription watches for
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Condition {
+    TempAbove,
+    TempBelow,
+    WindAbove,
+    PopAbove,
+}
+
+/// A registered webhook
+#[derive(Deserialize, Serialize)]
+struct Subscription {
+    id: String,
+    /// The key webhooks are signed with, which only the client is told.
+    /// Subscriptions from before there were secrets have none, and aren't
+    /// sent until they're registered again.
+    #[serde(default)]
+    secret: String,
+    /// The API client that registered it
+    client: String,
+    /// The index shard that lists it
+    index: String,
+    callback_url: String,
+    lat: f64,
+    lon: f64,
+    condition: Condition,
+    threshold: f32,
+    /// Whether the condition held at the last dispatch
+    #[serde(default)]
+    triggered: bool,
+}
+
+/// The body of a registration
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Registration {
+    callback_url: String,
+    lat: f64,
+    lon: f64,
+    condition: Condition,
+    threshold: f32,
+}
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    id: Option<String>,
+}
+
+/// The body of a webhook
+#[derive(Serialize)]
+struct Alert {
+    condition: Condition,
+    threshold: f32,
+    value: f32,
+    lat: f64,
+    lon: f64,
+    /// When the forecast was observed, in RFC 3339
+    observed: String,
+}
+
+impl Condition {
+    /// The forecast's value for this condition, in metric units.
+    fn value(self, api_response: &APIResponse) -> Option<f32> {
+        match self {
+            Condition::TempAbove | Condition::TempBelow => Some(api_response.current.temp),
+            Condition::WindAbove => Some(api_response.current.wind_speed),
+            Condition::PopAbove => api_response.hourly.first().map(|hour| hour.pop * 100.0),
+        }
+    }
+
+    fn is_met(self, value: f32, threshold: f32) -> bool {
+        match self {
+            Condition::TempBelow => value < threshold,
+            _ => value > threshold,
+        }
+    }
+
+    fn accepts(self, threshold: f32) -> bool {
+        let range = match self {
+            Condition::TempAbove | Condition::TempBelow => -90.0..=60.0,
+            Condition::WindAbove => 0.0..=120.0,
+            Condition::PopAbove => 0.0..=100.0,
+        };
+        range.contains(&threshold)
+    }
+}
+
+/// Serve `/api/subscriptions`: register a webhook for `client`, or remove
+/// one. `client` is who signed the request, if anyone did.
+pub fn handle(req: &mut Request, client: Option<&str>) -> Result<Response, Error> {
+    if req.get_method() == Method::DELETE {
+        return remove(req);
+    }
+    let client = match client {
+        Some(client) => client,
+        None => {
+            return Ok(problem::response(
+                StatusCode::FORBIDDEN,
+                "Registering a webhook needs a signed request, and api_auth is off",
+            ))
+        }
+    };
+
+    let mut body = Vec::new();
+    let read = req
+        .take_body()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body);
+    if read.is_err() || body.len() as u64 > MAX_BODY_BYTES {
+        return Ok(problem::response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "The body is too big",
+        ));
+    }
+    let registration = match serde_json::from_slice::<Registration>(&body) {
+        Ok(registration) => registration,
+        Err(_) => {
+            return Ok(problem::response(
+                StatusCode::BAD_REQUEST,
+                "Send callback_url, lat, lon, condition and threshold as JSON",
+            ))
+        }
+    };
+
+    let url = Url::parse(&registration.callback_url).ok();
+    if registration.callback_url.len() > MAX_URL_LEN
+        || !url
+            .as_ref()
+            .is_some_and(|url| outbound::is_allowed(url, &[]))
+    {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "callback_url must be an HTTPS URL on a host this service sends webhooks to",
+        ));
+    }
+    if !(-90.0..=90.0).contains(&registration.lat) || !(-180.0..=180.0).contains(&registration.lon)
+    {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "lat or lon is out of range",
+        ));
+    }
+    if !registration.condition.accepts(registration.threshold) {
+        return Ok(problem::response(
+            StatusCode::BAD_REQUEST,
+            "threshold is out of range for the condition",
+        ));
+    }
+
+    let count = INDEX.count();
+    if count >= MAX_SUBSCRIPTIONS {
+        return Ok(problem::response(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "There are too many subscriptions",
+        ));
+    }
+    let subscription = Subscription {
+        id: shared::new_id(),
+        secret: shared::new_id(),
+        client: client.to_string(),
+        index: INDEX.random_shard(),
+        callback_url: registration.callback_url,
+        lat: shared::round_coord(registration.lat),
+        lon: shared::round_coord(registration.lon),
+        condition: registration.condition,
+        threshold: registration.threshold,
+        triggered: false,
+    };
+    let body = json!({
+        "id": subscription.id,
+        "secret": subscription.secret,
+        "callback_url": subscription.callback_url,
+        "lat": subscription.lat,
+        "lon": subscription.lon,
+        "condition": subscription.condition,
+        "threshold": subscription.threshold,
+    });
+    if !save(&subscription) || !INDEX.add(&subscription.index, &subscription.id) {
+        return Ok(problem::response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Subscriptions aren't set up",
+        ));
+    }
+    println!(
+        "Client {} registered a subscription, {} in all",
+        client,
+        count + 1
+    );
+
+    Ok(Response::from_body(body.to_string())
+        .with_status(StatusCode::CREATED)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "no-store"))
+}
+
+/// Send the webhooks whose conditions have come true since they were last
+/// checked, for the next [`MAX_CHECKS`] subscriptions.
+pub fn dispatch(forecasts: &mut Forecasts) -> Report {
+    let listed = INDEX.entries();
+    let page = kv::next_page(&listed, CURSOR_KEY, MAX_CHECKS);
+    let mut report = Report {
+        checked: page.len(),
+        ..Report::default()
+    };
+    // Ids to take out of the index, because their subscription has gone
+    let mut gone = Vec::new();
+
+    for (index, id) in page {
+        let mut subscription = match load(id) {
+            Some(subscription) => subscription,
+            None => {
+                gone.push((index, id));
+                continue;
+            }
+        };
+        let api_response = match forecasts.at(subscription.lat, subscription.lon) {
+            Some(api_response) => api_response,
+            None => {
+                report.deferred += 1;
+                continue;
+            }
+        };
+        let value = match subscription.condition.value(api_response) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let is_met = subscription.condition.is_met(value, subscription.threshold);
+        if is_met && !subscription.triggered {
+            // Left untriggered, so the next check sends it
+            if report.is_full() {
+                report.deferred += 1;
+                continue;
+            }
+            match send(&subscription, value, api_response) {
+                Outcome::Sent => report.sent += 1,
+                Outcome::Gone => {
+                    println!("Webhook {} is gone, removing it", log_id(&subscription.id));
+                    report.removed += 1;
+                    kv::delete(&record_key(id));
+                    gone.push((index, id));
+                    continue;
+                }
+                // Left untriggered, so the next check tries again
+                Outcome::Failed(err) => {
+                    println!(
+                        "Couldn't deliver webhook {}: {}",
+                        log_id(&subscription.id),
+                        err
+                    );
+                    report.failed += 1;
+                    continue;
+                }
+            }
+        }
+        if is_met != subscription.triggered {
+            subscription.triggered = is_met;
+            if !save(&subscription) {
+                println!(
+                    "Couldn't save webhook {} after dispatching",
+                    log_id(&subscription.id)
+                );
+            }
+        }
+    }
+
+    INDEX.remove(&gone);
+    report
+}
+
+// Serve `DELETE /api/subscriptions?id=<id>`.
+fn remove(req: &Request) -> Result<Response, Error> {
+    let params: DeleteParams = req.get_query()?;
+    let id = params.id.unwrap_or_default();
+    let subscription = match Some(id.as_str()).filter(|id| is_id(id)).and_then(load) {
+        Some(subscription) => subscription,
+        None => {
+            return Ok(problem::response(
+                StatusCode::NOT_FOUND,
+                "There's no such subscription",
+            ))
+        }
+    };
+    if !kv::delete(&record_key(&subscription.id)) {
+        return Ok(problem::response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Subscriptions aren't set up",
+        ));
+    }
+    INDEX.remove(&[(&subscription.index, &subscription.id)]);
+    Ok(
+        Response::from_status(StatusCode::NO_CONTENT)
+            .with_header(header::CACHE_CONTROL, "no-store"),
+    )
+}
+
+// Post the alert for `subscription` to its callback.
+fn send(subscription: &Subscription, value: f32, api_response: &APIResponse) -> Outcome {
+    let observed = Utc
+        .timestamp_opt(api_response.current.dt, 0)
+        .single()
+        .unwrap_or_else(Utc::now)
+        .to_rfc3339();
+    if subscription.secret.is_empty() {
+        return Outcome::Failed(Error::msg(
+            "it was registered without a signing secret, and must be registered again",
+        ));
+    }
+    let body = json!(Attributed::new(&Alert {
+        condition: subscription.condition,
+        threshold: subscription.threshold,
+        value,
+        lat: subscription.lat,
+        lon: subscription.lon,
+        observed,
+    }))
+    .to_string();
+
+    // Checked when it was registered, and again in case outbound_hosts has
+    // changed since
+    let allowed =
+        Url::parse(&subscription.callback_url).is_ok_and(|url| outbound::is_allowed(&url, &[]));
+    if !allowed {
+        return Outcome::Failed(Error::msg("callback_url's host is no longer allowed"));
+    }
+    let signature = signing::sign(subscription.secret.as_bytes(), body.as_bytes());
+    outbound::send(
+        Request::post(&subscription.callback_url)
+            .with_header(SIGNATURE_HEADER, signature)
+            .with_content_type(fastly::mime::APPLICATION_JSON)
+            .with_body(body),
+    )
+}
+
+fn load(id: &str) -> Option<Subscription> {
+    kv::get_json(&record_key(id))
+}
+
+fn save(subscription: &Subscription) -> bool {
+    kv::put_json(&record_key(&subscription.id), subscription)
+}
+
+fn record_key(id: &str) -> String {
+    format!("{}{}", RECORD_PREFIX, id)
+}
+
+// How logs name a subscription: the id is what removes it, so only the
+// start of its hash is shown.
+fn log_id(id: &str) -> String {
+    let mut hash = signing::sha256(id.as_bytes());
+    hash.truncate(LOG_ID_LEN);
+    hash
+}
+
+// Ids are hex, so there's no reaching other keys through them.
+fn is_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
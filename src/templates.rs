@@ -0,0 +1,252 @@
+//! Every template the service renders, registered once at startup.
+//!
+//! Routes render by [`TemplateName`] through [`render`], so a new page is a
+//! template file, a line in `SOURCES` and a variant here, with no
+//! TinyTemplate setup of its own. The shared partials, such as `head`, are
+//! registered alongside and pulled in with `{{ call }}`, but aren't rendered
+//! on their own.
+
+use serde::Serialize;
+use tinytemplate::TinyTemplate;
+
+use crate::view::Fragment;
+
+mod drift;
+pub use drift::template_drift;
+
+/// A template that can be rendered on its own
+#[derive(Clone, Copy)]
+pub enum TemplateName {
+    /// The composed desktop page shell
+    Index,
+    /// The composed page shell for phones
+    Mobile,
+    /// The current conditions alone, for embedding in other sites
+    Widget,
+    /// A full-screen page that keeps itself fresh, for wall displays
+    Kiosk,
+    /// A plain page of the forecast, for paper
+    Print,
+    Amp,
+    /// Tables and text only, served at `/m`
+    Minimal,
+    Gemtext,
+    Snow,
+    Digest,
+    /// The themed 404 page
+    Error,
+    Unavailable,
+    Picker,
+    ShareUnusable,
+    Aviation,
+    Privacy,
+    /// What the JSON API offers, served at `/docs`
+    ApiDocs,
+    /// The page's inline script
+    Scripts,
+    Fragment(Fragment),
+}
+
+impl TemplateName {
+    /// The name the template is registered under.
+    pub fn name(self) -> &'static str {
+        match self {
+            TemplateName::Index => "weather",
+            TemplateName::Mobile => "weather_mobile",
+            TemplateName::Widget => "widget",
+            TemplateName::Kiosk => "kiosk",
+            TemplateName::Print => "print",
+            TemplateName::Amp => "amp",
+            TemplateName::Minimal => "minimal",
+            TemplateName::Gemtext => "gemtext",
+            TemplateName::Snow => "snow",
+            TemplateName::Digest => "digest",
+            TemplateName::Error => "not_found",
+            TemplateName::Unavailable => "unavailable",
+            TemplateName::Picker => "picker",
+            TemplateName::ShareUnusable => "share_unusable",
+            TemplateName::Aviation => "aviation",
+            TemplateName::Privacy => "privacy",
+            TemplateName::ApiDocs => "api_docs",
+            TemplateName::Scripts => "scripts",
+            TemplateName::Fragment(fragment) => match fragment {
+                Fragment::Current => "fragment_current",
+                Fragment::Conditions => "fragment_conditions",
+                Fragment::Daily => "fragment_daily",
+                Fragment::Radar => "fragment_radar",
+                Fragment::Marine => "fragment_marine",
+                Fragment::Fire => "fragment_fire",
+                Fragment::Astronomy => "fragment_astronomy",
+                Fragment::Garden => "fragment_garden",
+                Fragment::Exercise => "fragment_exercise",
+                Fragment::Laundry => "fragment_laundry",
+                Fragment::Roads => "fragment_roads",
+                Fragment::Chart => "fragment_chart",
+                Fragment::Wind => "fragment_wind",
+                Fragment::Precipitation => "fragment_precipitation",
+                Fragment::Weekend => "fragment_weekend",
+                Fragment::DayParts => "fragment_dayparts",
+            },
+        }
+    }
+}
+
+/// Every template, by the name it's registered under, partials included
+const SOURCES: [(&str, &str); 38] = [
+    ("head", include_str!("static/head.html")),
+    ("search", include_str!("static/search.html")),
+    ("scripts", include_str!("static/scripts.html")),
+    ("attribution", include_str!("static/attribution.html")),
+    ("weather_icon", include_str!("static/weather_icon.html")),
+    ("weather", include_str!("static/index.html")),
+    ("weather_mobile", include_str!("static/mobile.html")),
+    ("widget", include_str!("static/widget.html")),
+    ("kiosk", include_str!("static/kiosk.html")),
+    ("print", include_str!("static/print.html")),
+    ("amp", include_str!("static/amp.html")),
+    ("minimal", include_str!("static/minimal.html")),
+    ("gemtext", include_str!("static/forecast.gmi")),
+    ("snow", include_str!("static/snow.html")),
+    ("not_found", include_str!("static/404.html")),
+    ("unavailable", include_str!("static/unavailable.html")),
+    ("picker", include_str!("static/picker.html")),
+    ("share_unusable", include_str!("static/share.html")),
+    ("aviation", include_str!("static/metar.html")),
+    ("privacy", include_str!("static/privacy.html")),
+    ("api_docs", include_str!("static/docs.html")),
+    (
+        "fragment_current",
+        include_str!("static/fragments/current.html"),
+    ),
+    (
+        "fragment_conditions",
+        include_str!("static/fragments/conditions.html"),
+    ),
+    (
+        "fragment_daily",
+        include_str!("static/fragments/daily.html"),
+    ),
+    (
+        "fragment_radar",
+        include_str!("static/fragments/radar.html"),
+    ),
+    (
+        "fragment_marine",
+        include_str!("static/fragments/marine.html"),
+    ),
+    ("fragment_fire", include_str!("static/fragments/fire.html")),
+    (
+        "fragment_astronomy",
+        include_str!("static/fragments/astronomy.html"),
+    ),
+    (
+        "fragment_garden",
+        include_str!("static/fragments/garden.html"),
+    ),
+    (
+        "fragment_exercise",
+        include_str!("static/fragments/exercise.html"),
+    ),
+    (
+        "fragment_laundry",
+        include_str!("static/fragments/laundry.html"),
+    ),
+    (
+        "fragment_roads",
+        include_str!("static/fragments/roads.html"),
+    ),
+    (
+        "fragment_chart",
+        include_str!("static/fragments/chart.html"),
+    ),
+    ("fragment_wind", include_str!("static/fragments/wind.html")),
+    (
+        "fragment_precipitation",
+        include_str!("static/fragments/precipitation.html"),
+    ),
+    (
+        "fragment_weekend",
+        include_str!("static/fragments/weekend.html"),
+    ),
+    (
+        "fragment_dayparts",
+        include_str!("static/fragments/dayparts.html"),
+    ),
+    ("digest", include_str!("static/digest.txt")),
+];
+
+thread_local! {
+    // Parsed on first use and kept for the life of the instance. Leaking the
+    // one copy gives callers a plain `'static` reference to render from.
+    static TEMPLATES: &'static TinyTemplate<'static> = Box::leak(Box::new(register()));
+}
+
+/// Parse the templates now, so the first render doesn't pay for it.
+pub fn warm_up() {
+    TEMPLATES.with(|_| ());
+}
+
+/// Render `template` with `context`. Debug builds first log whatever the
+/// template uses that the context lacks or leaves blank, so the two drifting
+/// apart shows up in development rather than as an empty box.
+pub fn render(template: TemplateName, context: &impl Serialize) -> String {
+    let name = template.name();
+    if cfg!(debug_assertions) {
+        if let (Some(text), Ok(context)) = (source(name), serde_json::to_value(context)) {
+            for problem in template_drift(text, &context, &source) {
+                println!("Template {}: {}", name, problem);
+            }
+        }
+    }
+    TEMPLATES.with(|templates| templates.render(name, context).unwrap())
+}
+
+fn register() -> TinyTemplate<'static> {
+    let mut tt = TinyTemplate::new();
+    // The default already, but every template relies on it.
+    tt.set_default_formatter(&tinytemplate::format);
+    tt.add_formatter("url", format_url_component);
+    tt.add_formatter("gemtext", format_gemtext);
+    for (name, source) in SOURCES {
+        tt.add_template(name, source).unwrap();
+    }
+    tt
+}
+
+fn source(name: &str) -> Option<&'static str> {
+    SOURCES
+        .iter()
+        .find(|(template, _)| *template == name)
+        .map(|(_, source)| *source)
+}
+
+// Percent-encode everything but unreserved characters, which leaves nothing
+// that HTML, a URL or a JavaScript string would read specially.
+fn format_url_component(
+    value: &serde_json::Value,
+    output: &mut String,
+) -> tinytemplate::error::Result<()> {
+    let mut plain = String::new();
+    tinytemplate::format_unescaped(value, &mut plain)?;
+    for byte in plain.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            output.push(byte as char);
+        } else {
+            output.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Ok(())
+}
+
+// One line of plain text, without the markers that would make Gemini clients
+// read it as a link, heading, list item, quote or preformatted block.
+fn format_gemtext(
+    value: &serde_json::Value,
+    output: &mut String,
+) -> tinytemplate::error::Result<()> {
+    let mut plain = String::new();
+    tinytemplate::format_unescaped(value, &mut plain)?;
+    let line = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+    output.push_str(line.trim_start_matches(|c: char| "=>#*`".contains(c) || c.is_whitespace()));
+    Ok(())
+}
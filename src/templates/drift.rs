@@ -0,0 +1,323 @@
+//! Checks a template against the context it is rendered with, so a field
+//! renamed on one side and not the other fails the tests instead of
+//! rendering blank.
+
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+// A TinyTemplate template, parsed only as far as the context it reads.
+enum Node<'a> {
+    Value {
+        path: &'a str,
+        formatted: bool,
+    },
+    If {
+        path: &'a str,
+        negate: bool,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+    For {
+        name: &'a str,
+        path: &'a str,
+        body: Vec<Node<'a>>,
+    },
+    With {
+        path: &'a str,
+        name: &'a str,
+        body: Vec<Node<'a>>,
+    },
+    Call {
+        template: &'a str,
+        path: &'a str,
+    },
+}
+
+enum Token<'a> {
+    Value(&'a str),
+    Block(&'a str),
+}
+
+// A name a `for` or `with` block binds, and the loop position for `@first`
+// and the like.
+struct Binding<'v, 'a> {
+    name: &'a str,
+    value: &'v Value,
+    position: Option<(usize, usize)>,
+}
+
+// Where a template's field references come from
+struct Scope<'v, 'a> {
+    root: &'v Value,
+    bindings: Vec<Binding<'v, 'a>>,
+}
+
+/// What's wrong with rendering the TinyTemplate `source` with `context`:
+/// fields it reads that aren't there (which fail the render), and values it
+/// prints that are blank or can't be printed (which leave an empty box),
+/// following only the branches and loops `context` actually takes. Partials
+/// it calls are looked up with `partial` and checked too. Empty when
+/// everything's in order.
+pub fn template_drift<'a, F>(source: &'a str, context: &Value, partial: &F) -> Vec<String>
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    let mut problems = BTreeSet::new();
+    let scope = Scope {
+        root: context,
+        bindings: Vec::new(),
+    };
+    check(&parse(source), scope, partial, "", &mut problems);
+    problems.into_iter().collect()
+}
+
+fn check<'v, 'a, F>(
+    nodes: &[Node<'a>],
+    mut scope: Scope<'v, 'a>,
+    partial: &F,
+    within: &str,
+    problems: &mut BTreeSet<String>,
+) -> Scope<'v, 'a>
+where
+    F: Fn(&str) -> Option<&'a str>,
+{
+    for node in nodes {
+        let problem = match node {
+            Node::Value { path, formatted } => match lookup(&scope, path) {
+                Err(step) => Some(missing(path, step)),
+                Ok(Some(Value::Null)) => Some(format!("{{{}}} is null, so renders blank", path)),
+                Ok(Some(Value::String(text))) if text.trim().is_empty() => {
+                    Some(format!("{{{}}} is empty, so renders blank", path))
+                }
+                Ok(Some(Value::Array(_) | Value::Object(_))) if !formatted => {
+                    Some(format!("{{{}}} is a list or object, not text", path))
+                }
+                Ok(_) => None,
+            },
+            Node::If {
+                path,
+                negate,
+                then,
+                otherwise,
+            } => match lookup(&scope, path) {
+                Err(step) => Some(missing(path, step)),
+                Ok(value) => {
+                    let truthy = value.map_or_else(|| keyword(&scope, path), is_truthy);
+                    let branch = if truthy != *negate { then } else { otherwise };
+                    scope = check(branch, scope, partial, within, problems);
+                    None
+                }
+            },
+            Node::For { name, path, body } => match lookup(&scope, path) {
+                Err(step) => Some(missing(path, step)),
+                Ok(Some(Value::Array(items))) => {
+                    for (index, item) in items.iter().enumerate() {
+                        scope.bindings.push(Binding {
+                            name,
+                            value: item,
+                            position: Some((index, items.len())),
+                        });
+                        scope = check(body, scope, partial, within, problems);
+                        scope.bindings.pop();
+                    }
+                    None
+                }
+                Ok(_) => Some(format!("{} isn't a list to loop over", path)),
+            },
+            Node::With { path, name, body } => match lookup(&scope, path) {
+                Err(step) => Some(missing(path, step)),
+                Ok(value) => {
+                    scope.bindings.push(Binding {
+                        name,
+                        value: value.unwrap_or(&Value::Null),
+                        position: None,
+                    });
+                    scope = check(body, scope, partial, within, problems);
+                    scope.bindings.pop();
+                    None
+                }
+            },
+            Node::Call { template, path } => match (lookup(&scope, path), partial(template)) {
+                (Err(step), _) => Some(missing(path, step)),
+                (_, None) => Some(format!("calls {}, which isn't registered", template)),
+                (Ok(value), Some(source)) => {
+                    let called = Scope {
+                        root: value.unwrap_or(scope.root),
+                        bindings: Vec::new(),
+                    };
+                    let within = format!("{}in {}, ", within, template);
+                    check(&parse(source), called, partial, &within, problems);
+                    None
+                }
+            },
+        };
+        if let Some(problem) = problem {
+            problems.insert(format!("{}{}", within, problem));
+        }
+    }
+    scope
+}
+
+fn missing(path: &str, step: &str) -> String {
+    if path == step {
+        format!("{} is missing", path)
+    } else {
+        format!("{} is missing, at {}", path, step)
+    }
+}
+
+// The value at `path`: `Ok(None)` for the loop keywords, which aren't values
+// in the context, and the step that wasn't there when it's missing. As
+// TinyTemplate does, a name bound by an enclosing block shadows the context's
+// field of the same name.
+fn lookup<'v, 'a, 'p>(scope: &Scope<'v, 'a>, path: &'p str) -> Result<Option<&'v Value>, &'p str> {
+    let mut steps = path.split('.');
+    let first = steps.next().unwrap_or_default();
+    let mut current = match first {
+        "@index" | "@first" | "@last" => return Ok(None),
+        "@root" => scope.root,
+        _ => match scope
+            .bindings
+            .iter()
+            .rev()
+            .find(|binding| binding.name == first)
+        {
+            Some(binding) => binding.value,
+            None => step(scope.root, first).ok_or(first)?,
+        },
+    };
+    for next in steps {
+        current = step(current, next).ok_or(next)?;
+    }
+    Ok(Some(current))
+}
+
+fn step<'v>(value: &'v Value, step: &str) -> Option<&'v Value> {
+    match step.parse::<usize>() {
+        Ok(index) if value.is_array() => value.get(index),
+        _ => value.get(step),
+    }
+}
+
+// Whether a loop keyword such as `@first` holds in the innermost loop.
+fn keyword(scope: &Scope, path: &str) -> bool {
+    let position = scope
+        .bindings
+        .iter()
+        .rev()
+        .find_map(|binding| binding.position);
+    match (path, position) {
+        ("@index", Some((index, _))) => index != 0,
+        ("@first", Some((index, _))) => index == 0,
+        ("@last", Some((index, len))) => index + 1 == len,
+        _ => false,
+    }
+}
+
+// TinyTemplate's idea of truth, for `if`
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+fn parse(source: &str) -> Vec<Node<'_>> {
+    let mut tokens = tokenize(source).into_iter();
+    parse_until(&mut tokens, &[]).0
+}
+
+// Parse nodes up to the first of the block keywords `ends`, which is
+// returned with them.
+fn parse_until<'a>(
+    tokens: &mut std::vec::IntoIter<Token<'a>>,
+    ends: &[&str],
+) -> (Vec<Node<'a>>, Option<&'a str>) {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let block = match token {
+            Token::Value(value) => {
+                let mut parts = value.splitn(2, '|');
+                nodes.push(Node::Value {
+                    path: parts.next().unwrap_or_default().trim(),
+                    formatted: parts.next().is_some(),
+                });
+                continue;
+            }
+            Token::Block(block) => block,
+        };
+        let words: Vec<&str> = block.split_whitespace().collect();
+        match words.as_slice() {
+            [end] if ends.contains(end) => return (nodes, Some(end)),
+            ["if", "not", path] | ["if", path] => {
+                let (then, end) = parse_until(tokens, &["else", "endif"]);
+                let otherwise = if end == Some("else") {
+                    parse_until(tokens, &["endif"]).0
+                } else {
+                    Vec::new()
+                };
+                nodes.push(Node::If {
+                    path,
+                    negate: words[1] == "not",
+                    then,
+                    otherwise,
+                });
+            }
+            ["for", name, "in", path] => nodes.push(Node::For {
+                name,
+                path,
+                body: parse_until(tokens, &["endfor"]).0,
+            }),
+            ["with", path, "as", name] => nodes.push(Node::With {
+                path,
+                name,
+                body: parse_until(tokens, &["endwith"]).0,
+            }),
+            ["call", template, "with", path] => nodes.push(Node::Call { template, path }),
+            // Anything else fails when the template's added, long before it's rendered
+            _ => {}
+        }
+    }
+    (nodes, None)
+}
+
+// The values and blocks in a template, skipping its text, comments and
+// escaped braces.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find(['{', '\\']) {
+        let (skip, token, end) = if rest[start..].starts_with('\\') {
+            // The escaped character is only text
+            let escaped = rest[start + 1..].chars().next().map_or(0, char::len_utf8);
+            (start + 1 + escaped, None, None)
+        } else if rest[start..].starts_with("{#") {
+            (start, None, Some("#}"))
+        } else if rest[start..].starts_with("{{") {
+            (start, Some(true), Some("}}"))
+        } else {
+            (start, Some(false), Some("}"))
+        };
+        let Some(end) = end else {
+            rest = &rest[skip..];
+            continue;
+        };
+        let open = if token == Some(false) { 1 } else { 2 };
+        let Some(len) = rest[skip + open..].find(end) else {
+            break;
+        };
+        let inner = rest[skip + open..skip + open + len].trim();
+        match token {
+            Some(true) => tokens.push(Token::Block(inner)),
+            Some(false) => tokens.push(Token::Value(inner)),
+            None => {}
+        }
+        rest = &rest[skip + open + len + end.len()..];
+    }
+    tokens
+}
@@ -0,0 +1,67 @@
+//! Per-request timings, reported in a `Server-Timing` header and the logs.
+
+use std::time::{Duration, Instant};
+
+use fastly::Response;
+use serde_json::json;
+
+/// Durations of the named stages of handling a request.
+#[derive(Default)]
+pub struct Timings {
+    stages: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f`, recording how long it took under `stage`.
+    pub fn time<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.stages.push((stage, start.elapsed()));
+        result
+    }
+
+    /// Add the timings to a response as a `Server-Timing` header, so they
+    /// show up in browser devtools, and log them for `fastly log-tail`.
+    pub fn report(&self, path: &str, request_id: &str, resp: &mut Response) {
+        self.add_header(resp);
+        self.log(path, request_id);
+    }
+
+    /// Add the timings so far as a `Server-Timing` header. Streamed pages
+    /// send their headers before rendering, so only the log has the rest.
+    pub fn add_header(&self, resp: &mut Response) {
+        if self.stages.is_empty() {
+            return;
+        }
+
+        let header = self
+            .stages
+            .iter()
+            .map(|(stage, duration)| format!("{};dur={:.1}", stage, millis(*duration)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        resp.set_header("Server-Timing", header);
+    }
+
+    /// Log the timings for `fastly log-tail`, with the request's id to tie
+    /// them to its other log lines.
+    pub fn log(&self, path: &str, request_id: &str) {
+        if self.stages.is_empty() {
+            return;
+        }
+
+        let mut log = json!({ "event": "timing", "path": path, "request_id": request_id });
+        for (stage, duration) in &self.stages {
+            log[format!("{}_ms", stage)] = json!(millis(*duration));
+        }
+        println!("{}", log);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
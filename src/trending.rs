@@ -0,0 +1,199 @@
+//! Popular locations, counted per page view and served at `/api/trending`.
+//!
+//! The KV Store can't list or atomically increment keys, so views are kept in
+//! documents per hour (`trending:YYYYMMDDHH`) mapping geohash to counts, each
+//! split into [`kv::SHARDS`] so concurrent page views rarely overwrite each
+//! other's increments. Only one view in `trending_sample` is written, counting
+//! for that many, so a busy page isn't a KV write every time.
+//!
+//! Once an hour is over its shards are summed into `trending:YYYYMMDDHH:all`,
+//! and the 23 finished hours before the current one into
+//! `trending:YYYYMMDDHH:day`, by whichever request first needs them. Reads
+//! combine that with the current hour's shards, and each POP keeps the sum
+//! for a few minutes rather than reading them every time.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use fastly::cache::simple::{get_or_set_with, CacheEntry};
+use fastly::http::{header, StatusCode};
+use fastly::{Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::location::Location;
+use crate::{config, geohash, kv, shared};
+
+const GEOHASH_PRECISION: usize = 5;
+
+// Keep hourly documents small. Once full, only places already listed are counted.
+const MAX_PLACES_PER_HOUR: usize = 500;
+
+// As long as an `/api/trending` response may be cached for anyway.
+const TOTALS_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+// Write one view in this many unless `trending_sample` says otherwise.
+const DEFAULT_SAMPLE: u64 = 10;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+/// View counts for one location within an hour
+#[derive(Serialize, Deserialize, Clone)]
+struct Place {
+    geohash: String,
+    city: String,
+    country: String,
+    latitude: f64,
+    longitude: f64,
+    views: u64,
+}
+
+#[derive(Deserialize)]
+struct TrendingParams {
+    limit: Option<usize>,
+}
+
+/// Count a page view for the visitor's location, if it's one of those sampled.
+pub fn record_view(location: &Location) {
+    let sample = sample_rate();
+    let [a, b, c, d] = shared::random_bytes::<4>();
+    if u64::from(u32::from_le_bytes([a, b, c, d])) % sample != 0 {
+        return;
+    }
+
+    let key = kv::random_shard(&hour_key(0));
+    let hash = geohash::encode(location.latitude(), location.longitude(), GEOHASH_PRECISION);
+
+    let mut places: HashMap<String, Place> = kv::get_json(&key).unwrap_or_default();
+    if let Some(place) = places.get_mut(&hash) {
+        place.views += sample;
+    } else if places.len() < MAX_PLACES_PER_HOUR {
+        // Round coordinates so the store never holds a precise visitor location.
+        places.insert(
+            hash.clone(),
+            Place {
+                geohash: hash,
+                city: location.city().to_string(),
+                country: location.country_code().to_string(),
+                latitude: shared::round_coord(location.latitude()),
+                longitude: shared::round_coord(location.longitude()),
+                views: sample,
+            },
+        );
+    } else {
+        return;
+    }
+
+    kv::put_json(&key, &places);
+}
+
+/// Serve `/api/trending?limit=N`: the most viewed locations over the last day.
+pub fn handle(req: &Request) -> Response {
+    let limit = req
+        .get_query::<TrendingParams>()
+        .ok()
+        .and_then(|params| params.limit)
+        .unwrap_or(DEFAULT_LIMIT)
+        .clamp(1, MAX_LIMIT);
+
+    let mut places = ranking();
+    places.truncate(limit);
+
+    Response::from_body(json!({ "period_hours": 24, "locations": places }).to_string())
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+        .with_header(header::CACHE_CONTROL, "public, max-age=300")
+}
+
+// Every place viewed in the last day, most viewed first.
+fn ranking() -> Vec<Place> {
+    let mut summed = None;
+    let cached = get_or_set_with(format!("{}:totals", hour_key(0)), || {
+        let places = sum_last_day();
+        let value = serde_json::to_string(&places)?;
+        summed = Some(places);
+        Ok(CacheEntry {
+            value: value.into(),
+            ttl: TOTALS_TTL,
+        })
+    });
+
+    match cached {
+        Ok(Some(entry)) => match summed {
+            Some(places) => places,
+            None => serde_json::from_str(&entry.into_string()).unwrap_or_else(|_| sum_last_day()),
+        },
+        // Without the cache, sum them every time
+        _ => summed.unwrap_or_else(sum_last_day),
+    }
+}
+
+fn sum_last_day() -> Vec<Place> {
+    let mut totals = finished_hours();
+    add(&mut totals, sum_shards(&hour_key(0)));
+
+    let mut places: Vec<Place> = totals.into_values().collect();
+    places.sort_by(|a, b| b.views.cmp(&a.views).then_with(|| a.city.cmp(&b.city)));
+    places
+}
+
+// The 23 finished hours before this one, summed once and kept.
+fn finished_hours() -> HashMap<String, Place> {
+    let key = format!("{}:day", hour_key(0));
+    if let Some(totals) = kv::get_json(&key) {
+        return totals;
+    }
+
+    let mut totals = HashMap::new();
+    for hours_ago in 1..24 {
+        add(&mut totals, finished_hour(hours_ago));
+    }
+    kv::put_json(&key, &totals);
+    totals
+}
+
+// The shards of a finished hour, summed once and kept. Views counted at the
+// very end of the hour may miss the sum, which is close enough for a ranking.
+fn finished_hour(hours_ago: i64) -> HashMap<String, Place> {
+    let hour = hour_key(hours_ago);
+    let key = format!("{}:all", hour);
+    if let Some(totals) = kv::get_json(&key) {
+        return totals;
+    }
+
+    let totals = sum_shards(&hour);
+    kv::put_json(&key, &totals);
+    totals
+}
+
+fn sum_shards(hour: &str) -> HashMap<String, Place> {
+    let mut totals = HashMap::new();
+    for key in kv::shard_keys(hour) {
+        add(&mut totals, kv::get_json(&key).unwrap_or_default());
+    }
+    totals
+}
+
+fn add(totals: &mut HashMap<String, Place>, places: HashMap<String, Place>) {
+    for (hash, place) in places {
+        totals
+            .entry(hash)
+            .and_modify(|total| total.views += place.views)
+            .or_insert(place);
+    }
+}
+
+fn hour_key(hours_ago: i64) -> String {
+    let hour = Utc::now() - Duration::hours(hours_ago);
+    format!("trending:{}", hour.format("%Y%m%d%H"))
+}
+
+// One view in this many is written. Anything that isn't a positive number
+// falls back to the default.
+fn sample_rate() -> u64 {
+    config::get("trending_sample")
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&sample| sample > 0)
+        .unwrap_or(DEFAULT_SAMPLE)
+}
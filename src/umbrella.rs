@@ -0,0 +1,75 @@
+//! Whether to take an umbrella today, at a glance.
+//!
+//! The next hour's minutely forecast says whether it's raining now or about
+//! to, and the hourly chance of precipitation covers the rest of the day up
+//! to local sunset, which is when most people are out. After sunset, or
+//! where the sun doesn't set, the rest of the local day counts instead.
+
+use serde::Serialize;
+
+use crate::APIResponse;
+
+// Minutely amounts, in mm/h, below which it's barely spitting
+const DRIZZLE: f32 = 0.1;
+// Chances of precipitation that make it likely, or worth allowing for
+const LIKELY: f32 = 0.5;
+const POSSIBLE: f32 = 0.25;
+
+const DAY_SECS: i64 = 86_400;
+
+/// How much today calls for an umbrella
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Umbrella {
+    No,
+    Maybe,
+    Yes,
+}
+
+impl Umbrella {
+    /// The call for what's left of today in `api_response`.
+    pub fn today(api_response: &APIResponse) -> Self {
+        let now = api_response.current.dt;
+        let wet_soon = api_response
+            .minutely
+            .iter()
+            .any(|minute| minute.precipitation >= DRIZZLE);
+
+        let local_midnight =
+            now + DAY_SECS - (now + i64::from(api_response.timezone_offset)).rem_euclid(DAY_SECS);
+        let until = api_response
+            .daily
+            .first()
+            .map(|today| today.sunset)
+            .filter(|&sunset| sunset > now)
+            .unwrap_or(local_midnight);
+        let chance = api_response
+            .hourly
+            .iter()
+            .take_while(|hour| hour.dt < until)
+            .map(|hour| hour.pop)
+            .fold(0.0, f32::max);
+
+        if wet_soon || chance >= LIKELY {
+            Umbrella::Yes
+        } else if chance >= POSSIBLE {
+            Umbrella::Maybe
+        } else {
+            Umbrella::No
+        }
+    }
+
+    /// Whether to take one, counting a maybe as a yes.
+    pub fn is_needed(&self) -> bool {
+        *self != Umbrella::No
+    }
+
+    /// The chip on the page
+    pub fn label(&self) -> &'static str {
+        match self {
+            Umbrella::No => "No umbrella needed",
+            Umbrella::Maybe => "Maybe take an umbrella",
+            Umbrella::Yes => "Take an umbrella",
+        }
+    }
+}
@@ -0,0 +1,1004 @@
+//! HTML rendering for the weather pages.
+//!
+//! Everything interpolated into a template is HTML-escaped, including city
+//! names and the API's condition descriptions, which are whatever the
+//! upstream or a visitor's cookie says. Values that end up in a URL inside a
+//! script or event handler, where HTML escaping isn't enough, go through the
+//! `url` formatter instead. Only the stylesheets compiled into the binary
+//! are written `unescaped`, along with the plain-text digest email and the
+//! Gemtext page, which aren't HTML. The Gemtext page's free text goes through
+//! the `gemtext` formatter, so a city name can't start a line of its own.
+
+use std::io::{self, Write};
+
+use chrono::{DateTime, Datelike, FixedOffset, Weekday};
+use fastly::http::{header, StatusCode};
+use fastly::Response;
+use serde::Serialize;
+use weather_helpers::{HeatRisk, Locale};
+
+use crate::astronomy::AstronomyCard;
+use crate::aviation::AviationContext;
+use crate::chart::TemperatureChart;
+use crate::conditions;
+use crate::confidence::{Confidence, DailyConfidence};
+use crate::dayparts::DayParts;
+use crate::exercise::ExerciseCard;
+use crate::experiments::Assignments;
+use crate::fire::FireCard;
+use crate::garden::GardenCard;
+use crate::icons;
+use crate::iconset::{self, Icon};
+use crate::laundry::LaundryCard;
+use crate::location::Location;
+use crate::marine::MarineReport;
+use crate::precipitation::PrecipitationTotals;
+use crate::precision::Precision;
+use crate::pressure::{Pressure, Trend};
+use crate::privacy::PrivacyContext;
+use crate::provider::{self, Provider};
+use crate::radar::{self, RadarMap};
+use crate::roads::RoadCard;
+use crate::snow::{self, SnowConditions};
+use crate::templates::{render, TemplateName};
+use crate::umbrella::Umbrella;
+use crate::weekend::WeekendCard;
+use crate::wind::WindRose;
+use crate::APIResponse;
+use crate::{assets, auth, compose, csp};
+
+// Stylesheet inlined into the AMP page. AMP rejects documents whose custom CSS
+// exceeds 75,000 bytes, so keep an eye on this at compile time.
+const AMP_CSS: &str = include_str!("static/amp.css");
+const AMP_RTL_CSS: &str = include_str!("static/amp-rtl.css");
+const _: () = assert!(
+    AMP_CSS.len() + AMP_RTL_CSS.len() <= 75_000,
+    "AMP custom CSS is over budget"
+);
+
+// Where the shell takes the inline script, whose nonce changes with every
+// response. It's not a route, so only composing the page fills it in.
+const SCRIPTS_INCLUDE: &str = "/inline/scripts";
+
+// Where the shell takes the favicon, which shows the temperature where the
+// visitor is. Like the script, it's only filled in by composing the page.
+const FAVICON_INCLUDE: &str = "/inline/favicon";
+
+/// Context for TinyTemplate
+#[derive(Serialize)]
+struct TemplateContext {
+    day: String,
+    day_short: String,
+    date: String,
+    city: String,
+    /// Ask the visitor to search for their city, as we couldn't find it
+    show_location_banner: bool,
+    /// The class picking the card's background photo, for the season there
+    background: &'static str,
+    map_url: String,
+    temp: String,
+    /// The temperature with its unit, as the locale writes it
+    temp_label: String,
+    /// `None` when the API leaves out the minutely forecast
+    falling: Option<Falling>,
+    wind: String,
+    humidity: String,
+    description: String,
+    /// The current conditions, for the current weather card
+    icon: Icon,
+    /// The same, for today on the daily forecast
+    day_icon: Icon,
+    /// The temperature favicon, as a `data:` URI
+    favicon: String,
+    /// `None` when the daily forecast doesn't reach three days ahead
+    next_days: Option<Vec<NextDay>>,
+    units: String,
+    is_metric: bool,
+    /// What wind speeds are in: the API gives mph for imperial, m/s otherwise
+    wind_unit: &'static str,
+    forecast_compact: bool,
+    canonical_url: String,
+    radar: Option<RadarMap>,
+    marine: Option<MarineReport>,
+    fire: Option<FireCard>,
+    astronomy: Option<AstronomyCard>,
+    garden: Option<GardenCard>,
+    exercise: Option<ExerciseCard>,
+    laundry: Option<LaundryCard>,
+    roads: Option<RoadCard>,
+    chart: Option<TemperatureChart>,
+    wind_rose: Option<WindRose>,
+    precipitation: Option<PrecipitationTotals>,
+    weekend: Option<WeekendCard>,
+    day_parts: Option<DayParts>,
+    /// `None` when the API leaves out the pressure
+    pressure: Option<Pressure>,
+    umbrella: Umbrella,
+    umbrella_label: &'static str,
+    /// `None` unless it's hot enough to be a risk
+    heat: Option<HeatAdvisory>,
+    /// `None` when the API leaves out the dew point, as does `comfort`
+    dew_point: Option<String>,
+    comfort: Option<&'static str>,
+    /// Set with `?details=on`
+    details: Option<Details>,
+    uvi: String,
+    uv_risk: &'static str,
+    uv_minutes: Option<u32>,
+    skin_type: u8,
+    lang: &'static str,
+    dir: &'static str,
+    is_rtl: bool,
+    /// Who to credit for the forecast
+    provider: &'static Provider,
+}
+
+/// Context for the AMP template, which needs its stylesheet inlined
+#[derive(Serialize)]
+struct AmpContext<'a> {
+    #[serde(flatten)]
+    weather: &'a TemplateContext,
+    css: &'static str,
+    rtl_css: &'static str,
+}
+
+/// The extra readings on the conditions card
+#[derive(Serialize)]
+struct Details {
+    /// `None` when the API leaves it out, as with `visibility`
+    clouds: Option<Reading>,
+    visibility: Option<Reading>,
+}
+
+/// A reading, and what it means in words
+#[derive(Serialize)]
+struct Reading {
+    value: String,
+    label: &'static str,
+}
+
+/// Context for the shared `<head>` partial
+#[derive(Serialize)]
+pub struct HeadContext {
+    /// Load the mirrored stylesheet for right-to-left languages
+    rtl: bool,
+}
+
+impl HeadContext {
+    pub fn new(rtl: bool) -> Self {
+        HeadContext { rtl }
+    }
+}
+
+/// Context for the snow report, which reuses the current conditions fragment
+#[derive(Serialize)]
+struct SnowContext<'a> {
+    head: HeadContext,
+    weather: &'a TemplateContext,
+    conditions: SnowConditions,
+    snowfall_now: Option<String>,
+    week_total: String,
+    days: Vec<SnowDay>,
+}
+
+/// Snowfall forecast for one day
+#[derive(Serialize)]
+struct SnowDay {
+    day: String,
+    snow: String,
+    has_snow: bool,
+}
+
+/// Context for the page shell, which must not depend on the visitor's location
+#[derive(Serialize)]
+struct ShellContext<'a> {
+    head: HeadContext,
+    lang: &'a str,
+    dir: &'a str,
+    units: &'a str,
+    is_metric: bool,
+    canonical_url: &'a str,
+    rum_integrity: String,
+    provider: &'static Provider,
+}
+
+/// Context for the page's inline script, which is rendered afresh for every
+/// response with a new nonce
+#[derive(Serialize)]
+struct ScriptsContext<'a> {
+    nonce: &'a str,
+}
+
+/// Context for the shared city search partial
+#[derive(Serialize)]
+struct SearchContext {
+    /// Put the cursor in the box, on pages that are there to search
+    autofocus: bool,
+}
+
+/// Context for the 404 page
+#[derive(Serialize)]
+struct NotFoundContext<'a> {
+    head: HeadContext,
+    search: SearchContext,
+    path: &'a str,
+}
+
+/// Context for the location picker
+#[derive(Serialize)]
+struct PickerContext {
+    head: HeadContext,
+    search: SearchContext,
+}
+
+/// Context for the API docs
+#[derive(Serialize)]
+struct ApiDocsContext {
+    head: HeadContext,
+    /// Whether requests must be signed
+    auth: bool,
+}
+
+/// Context for the page shown for an expired or forged share link
+#[derive(Serialize)]
+struct ShareUnusableContext {
+    head: HeadContext,
+    expired: bool,
+}
+
+/// Context for the page shown when the weather API turns us away
+#[derive(Serialize)]
+struct UnavailableContext {
+    head: HeadContext,
+    retry_secs: Option<u32>,
+}
+
+/// Where the forecast is for
+pub struct Place {
+    pub city: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// The configured default location, shown because the visitor couldn't
+    /// be located
+    pub is_default: bool,
+    /// Where to get the map, when it isn't the visitor's own `/map.png`
+    pub map_url: Option<String>,
+}
+
+impl From<&Location> for Place {
+    fn from(location: &Location) -> Self {
+        Place {
+            city: location.city().to_string(),
+            latitude: location.latitude(),
+            longitude: location.longitude(),
+            is_default: location.is_default(),
+            map_url: None,
+        }
+    }
+}
+
+/// Per-request choices that shape the rendered page
+pub struct PageOptions<'a> {
+    pub units: &'a str,
+    pub view: View,
+    pub experiments: &'a Assignments,
+    pub canonical_url: &'a str,
+    pub show_radar: bool,
+    pub marine: Option<MarineReport>,
+    pub fire: Option<FireCard>,
+    pub astronomy: Option<AstronomyCard>,
+    pub garden: Option<GardenCard>,
+    pub exercise: Option<ExerciseCard>,
+    pub laundry: Option<LaundryCard>,
+    pub roads: Option<RoadCard>,
+    /// How sure each day's forecast is, when it's been compared
+    pub confidence: Option<DailyConfidence>,
+    pub chart: Option<TemperatureChart>,
+    pub wind_rose: Option<WindRose>,
+    pub precipitation: Option<PrecipitationTotals>,
+    pub weekend: Option<WeekendCard>,
+    pub day_parts: Option<DayParts>,
+    pub pressure_trend: Option<Trend>,
+    /// Show cloud cover and visibility on the conditions card
+    pub show_details: bool,
+    pub skin_type: Option<u8>,
+    pub locale: Locale,
+    pub device: Device,
+    /// The nonce the page's inline script runs under; see [`crate::csp`]
+    pub nonce: &'a str,
+    /// Leave out the background photo, for visitors saving data
+    pub save_data: bool,
+}
+
+/// The kind of device the page is laid out for
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    /// The full two-column page, with every card
+    Desktop,
+    /// A compact page: the forecast and the cards that warn of danger
+    Mobile,
+}
+
+impl Device {
+    fn shell(&self) -> TemplateName {
+        match self {
+            Device::Desktop => TemplateName::Index,
+            Device::Mobile => TemplateName::Mobile,
+        }
+    }
+}
+
+/// The page variants that can be rendered from a weather report
+pub enum View {
+    Standard,
+    Layout(Layout),
+    Amp,
+    Fragment(Fragment),
+    Snow(SnowConditions),
+    /// Tables and text only, for feature phones and slow connections
+    Minimal,
+    /// Gemtext, for gateways that serve the forecast to Gemini clients
+    Gemtext,
+    /// The morning email, as plain text; see [`crate::digest`]
+    Digest,
+}
+
+/// Other layouts of the composed page, each with a shell of its own
+#[derive(Clone, Copy)]
+pub enum Layout {
+    /// The current conditions alone, for embedding in an iframe
+    Widget,
+    /// Large and refreshing itself, for wall displays
+    Kiosk,
+    /// Black on white, without buttons or scripts, for paper
+    Print,
+}
+
+impl Layout {
+    /// The layout served at `path`, if any.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/widget" => Some(Layout::Widget),
+            "/kiosk" => Some(Layout::Kiosk),
+            "/print" => Some(Layout::Print),
+            _ => None,
+        }
+    }
+
+    fn shell(&self) -> TemplateName {
+        match self {
+            Layout::Widget => TemplateName::Widget,
+            Layout::Kiosk => TemplateName::Kiosk,
+            Layout::Print => TemplateName::Print,
+        }
+    }
+}
+
+/// The per-location parts of the page shell
+#[derive(Clone, Copy)]
+pub enum Fragment {
+    Current,
+    Conditions,
+    Daily,
+    Radar,
+    Marine,
+    Fire,
+    Astronomy,
+    Garden,
+    Exercise,
+    Laundry,
+    Roads,
+    Chart,
+    Wind,
+    Precipitation,
+    Weekend,
+    DayParts,
+}
+
+impl Fragment {
+    /// The fragment served at `path`, if any.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/fragment/current" => Some(Fragment::Current),
+            "/fragment/conditions" => Some(Fragment::Conditions),
+            "/fragment/daily" => Some(Fragment::Daily),
+            "/fragment/radar" => Some(Fragment::Radar),
+            "/fragment/marine" => Some(Fragment::Marine),
+            "/fragment/fire" => Some(Fragment::Fire),
+            "/fragment/astronomy" => Some(Fragment::Astronomy),
+            "/fragment/garden" => Some(Fragment::Garden),
+            "/fragment/exercise" => Some(Fragment::Exercise),
+            "/fragment/laundry" => Some(Fragment::Laundry),
+            "/fragment/roads" => Some(Fragment::Roads),
+            "/fragment/chart" => Some(Fragment::Chart),
+            "/fragment/wind" => Some(Fragment::Wind),
+            "/fragment/precipitation" => Some(Fragment::Precipitation),
+            "/fragment/weekend" => Some(Fragment::Weekend),
+            "/fragment/dayparts" => Some(Fragment::DayParts),
+            _ => None,
+        }
+    }
+
+    /// Whether the fragment shows the minutely precipitation forecast.
+    pub fn shows_minutely(&self) -> bool {
+        matches!(self, Fragment::Current | Fragment::Conditions)
+    }
+
+    /// Whether the fragment shows anything from the hourly forecast.
+    pub fn shows_hourly(&self) -> bool {
+        matches!(
+            self,
+            Fragment::Current
+                | Fragment::Exercise
+                | Fragment::Laundry
+                | Fragment::Roads
+                | Fragment::Chart
+                | Fragment::Wind
+                | Fragment::Precipitation
+                | Fragment::DayParts
+        )
+    }
+}
+
+/// Basic struct with minimal info about the next days
+#[derive(Serialize)]
+struct NextDay {
+    day: String,
+    temp: String,
+    /// The temperature with its unit, as the locale writes it
+    temp_label: String,
+    icon: Icon,
+    range: RangeBar,
+    /// `None` unless the day's been compared with a second forecast
+    confidence: Option<ConfidenceBadge>,
+}
+
+/// How sure a day's forecast is
+#[derive(Serialize)]
+struct ConfidenceBadge {
+    label: &'static str,
+    /// `high`, `medium` or `low`, for styling
+    level: &'static str,
+}
+
+impl From<Confidence> for ConfidenceBadge {
+    fn from(confidence: Confidence) -> Self {
+        ConfidenceBadge {
+            label: confidence.label(),
+            level: match confidence {
+                Confidence::High => "high",
+                Confidence::Medium => "medium",
+                Confidence::Low => "low",
+            },
+        }
+    }
+}
+
+/// The rain or snow falling now, going by the current conditions. Rain is
+/// in millimetres; snow is in centimetres, or inches for imperial units.
+#[derive(Serialize)]
+struct Falling {
+    amount: String,
+    unit: &'static str,
+    is_snow: bool,
+    /// The Feather icon that goes with it
+    icon: &'static str,
+}
+
+impl Falling {
+    fn new(
+        millimetres: f32,
+        is_snow: bool,
+        units: &str,
+        locale: Locale,
+        precision: Precision,
+    ) -> Self {
+        let (amount, unit) = match (is_snow, units) {
+            (false, _) => (millimetres, "mm"),
+            (true, "imperial") => (millimetres / 25.4, "in"),
+            (true, _) => (millimetres / 10.0, "cm"),
+        };
+        Falling {
+            amount: precision.precipitation(locale, amount),
+            unit,
+            is_snow,
+            icon: if is_snow { "cloud-snow" } else { "cloud-rain" },
+        }
+    }
+}
+
+/// The heat safety banner, when the heat index calls for one
+#[derive(Serialize)]
+struct HeatAdvisory {
+    label: &'static str,
+    /// The band, such as `extreme-caution`, for styling
+    level: &'static str,
+    advice: &'static str,
+    /// Whether the sun is strong enough to burn as well
+    sunscreen: bool,
+}
+
+impl HeatAdvisory {
+    /// The advisory for a temperature in `units`, a relative humidity and
+    /// a UV index, or `None` when the heat index is below caution.
+    fn new(temp: f32, humidity: f32, uvi: f32, units: &str) -> Option<Self> {
+        let heat_index =
+            weather_helpers::heat_index(weather_helpers::to_celsius(temp, units), humidity);
+        let risk = weather_helpers::heat_risk(heat_index)?;
+        Some(HeatAdvisory {
+            label: risk.label(),
+            level: match risk {
+                HeatRisk::Caution => "caution",
+                HeatRisk::ExtremeCaution => "extreme-caution",
+                HeatRisk::Danger => "danger",
+                HeatRisk::ExtremeDanger => "extreme-danger",
+            },
+            advice: risk.advice(),
+            // Where the WHO advises sun protection
+            sunscreen: uvi >= 3.0,
+        })
+    }
+}
+
+/// A day's low to high, as a bar on a scale shared by the whole week, so
+/// the forecast shows which days swing the most
+#[derive(Serialize)]
+struct RangeBar {
+    low: String,
+    high: String,
+    /// Where the bar starts and how long it is, out of [`RangeBar::SCALE`]
+    x: u32,
+    width: u32,
+}
+
+impl RangeBar {
+    const SCALE: f32 = 100.0;
+
+    /// The bar for `low` to `high`, on a scale from `week_low` to `week_high`.
+    fn new(
+        low: f32,
+        high: f32,
+        (week_low, week_high): (f32, f32),
+        locale: Locale,
+        precision: Precision,
+    ) -> Self {
+        let span = (week_high - week_low).max(1.0);
+        // Even a steady day gets a sliver, so there's something to see, and
+        // the sliver has to fit when the day sits at the top of the week.
+        let x = ((low - week_low) / span * Self::SCALE)
+            .round()
+            .clamp(0.0, Self::SCALE - 2.0);
+        let width = ((high - low) / span * Self::SCALE)
+            .round()
+            .clamp(2.0, Self::SCALE - x);
+        RangeBar {
+            low: precision.temp(locale, low),
+            high: precision.temp(locale, high),
+            x: x as u32,
+            width: width as u32,
+        }
+    }
+}
+
+pub fn generate_view(
+    api_response: APIResponse,
+    location: &Place,
+    local: DateTime<FixedOffset>,
+    options: PageOptions,
+) -> String {
+    let mut page = Vec::new();
+    // Writing to a `Vec` can't fail, and the templates only produce UTF-8.
+    write_view(api_response, location, local, options, &mut page).unwrap();
+    String::from_utf8(page).unwrap()
+}
+
+/// Render the view to `out`. The composed page is written as it's composed,
+/// so a streaming body can send the top of the page before the rest is ready.
+pub fn write_view(
+    api_response: APIResponse,
+    location: &Place,
+    local: DateTime<FixedOffset>,
+    options: PageOptions,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let PageOptions {
+        units,
+        view,
+        experiments,
+        canonical_url,
+        show_radar,
+        marine,
+        fire,
+        astronomy,
+        garden,
+        exercise,
+        laundry,
+        roads,
+        confidence,
+        chart,
+        wind_rose,
+        precipitation,
+        weekend,
+        day_parts,
+        pressure_trend,
+        show_details,
+        skin_type,
+        locale,
+        device,
+        nonce,
+        save_data,
+    } = options;
+
+    // Without a preference, assume fair skin that burns easily.
+    let skin_type = skin_type.filter(|skin| (1..=6).contains(skin)).unwrap_or(2);
+    let uvi = api_response.current.uvi;
+
+    let precision = Precision::configured();
+    let icons = iconset::configured();
+
+    // Get the data for the next three days and put them in a vector to iterate them later in
+    // the template. A short daily forecast hides the card rather than leaving gaps in it.
+    let week = api_response
+        .daily
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(low, high), daily| {
+            (low.min(daily.temp.min), high.max(daily.temp.max))
+        });
+    let next_days: Option<Vec<NextDay>> = (api_response.daily.len() > 3).then(|| {
+        api_response.daily[1..4]
+            .iter()
+            .map(|daily| NextDay {
+                day: locale
+                    .weekday_short(weekday_of(daily.dt, &local))
+                    .to_string(),
+                temp: precision.temp(locale, daily.temp.day),
+                temp_label: precision.temp_label(locale, daily.temp.day, units),
+                icon: {
+                    let weather = daily.weather.first();
+                    icons
+                        .icon(
+                            weather.map_or(0, |weather| weather.id),
+                            weather.map_or("", |weather| weather.icon.as_str()),
+                            weather.map_or("", |weather| weather.description.as_str()),
+                        )
+                        .with_class("day-icon")
+                },
+                range: RangeBar::new(daily.temp.min, daily.temp.max, week, locale, precision),
+                confidence: confidence
+                    .as_ref()
+                    .and_then(|confidence| confidence.on(daily.dt))
+                    .map(ConfidenceBadge::from),
+            })
+            .collect()
+    });
+
+    // The API can send an empty list of conditions, which leaves them blank.
+    let conditions = api_response.current.weather.first();
+    let umbrella = Umbrella::today(&api_response);
+    let current_icon = || {
+        icons.icon(
+            conditions.map_or(0, |weather| weather.id),
+            conditions.map_or("", |weather| weather.icon.as_str()),
+            conditions.map_or("", |weather| weather.description.as_str()),
+        )
+    };
+
+    // Fill the template context
+    let context = TemplateContext {
+        day: weather_helpers::weekday_full(local.weekday(), locale).to_string(),
+        day_short: locale.weekday_short(local.weekday()).to_string(),
+        date: locale.format_date(local.day(), local.month(), local.year()),
+        lang: locale.code(),
+        dir: if locale.is_rtl() { "rtl" } else { "ltr" },
+        is_rtl: locale.is_rtl(),
+        city: location.city.clone(),
+        show_location_banner: location.is_default,
+        background: if save_data {
+            "bg-none"
+        } else {
+            assets::background_class(weather_helpers::get_season(location.latitude, local))
+        },
+        map_url: location
+            .map_url
+            .clone()
+            .unwrap_or_else(|| String::from("/map.png")),
+        temp: precision.temp(locale, api_response.current.temp),
+        temp_label: precision.temp_label(locale, api_response.current.temp, units),
+        falling: api_response.minutely.first().map(|minute| {
+            let is_snow = conditions
+                .is_some_and(|weather| weather_helpers::is_snow(weather.id, &weather.icon));
+            Falling::new(minute.precipitation, is_snow, units, locale, precision)
+        }),
+        wind: precision.wind(locale, api_response.current.wind_speed),
+        humidity: locale.format_number(api_response.current.humidity, 0),
+        description: conditions
+            .map(|weather| weather.description.clone())
+            .unwrap_or_default(),
+        icon: current_icon().with_class("weather-icon"),
+        day_icon: current_icon().with_class("day-icon"),
+        favicon: icons::favicon_data_uri(
+            weather_helpers::round_temperature(api_response.current.temp),
+            conditions.map_or(0, |weather| weather.id),
+            conditions.map_or("", |weather| weather.icon.as_str()),
+        ),
+        next_days,
+        units: units.to_string(),
+        is_metric: units == "metric",
+        wind_unit: if units == "imperial" { "mph" } else { "m/s" },
+        forecast_compact: device == Device::Mobile
+            || experiments.variant("forecast_layout") == Some("compact"),
+        canonical_url: canonical_url.to_string(),
+        radar: show_radar
+            .then(|| RadarMap::around(location.latitude, location.longitude, radar::PAGE_ZOOM)),
+        marine,
+        fire,
+        astronomy,
+        garden,
+        exercise,
+        laundry,
+        roads,
+        chart,
+        wind_rose,
+        precipitation,
+        weekend,
+        day_parts,
+        pressure: api_response
+            .current
+            .pressure
+            .map(|hpa| Pressure::new(hpa, pressure_trend, units)),
+        umbrella,
+        umbrella_label: umbrella.label(),
+        heat: HeatAdvisory::new(
+            api_response.current.temp,
+            api_response.current.humidity,
+            uvi,
+            units,
+        ),
+        dew_point: api_response
+            .current
+            .dew_point
+            .map(|dew_point| precision.temp_label(locale, dew_point, units)),
+        comfort: api_response.current.dew_point.map(|dew_point| {
+            conditions::comfort_level(weather_helpers::to_celsius(dew_point, units))
+        }),
+        details: show_details.then(|| Details {
+            clouds: api_response.current.clouds.map(|clouds| Reading {
+                value: format!("{:.0}%", clouds),
+                label: conditions::cloud_cover(clouds),
+            }),
+            visibility: api_response.current.visibility.map(|metres| Reading {
+                value: format_distance(metres, units),
+                label: conditions::visibility(metres),
+            }),
+        }),
+        uvi: locale.format_number(uvi, 0),
+        uv_risk: conditions::uv_risk(uvi),
+        uv_minutes: conditions::safe_exposure_minutes(uvi, skin_type),
+        skin_type,
+        provider: provider::current(),
+    };
+
+    let shell_template = match &view {
+        View::Layout(layout) => layout.shell(),
+        _ => device.shell(),
+    };
+    let page = match view {
+        View::Standard | View::Layout(_) => {
+            let render_shell = || {
+                render(
+                    shell_template,
+                    &ShellContext {
+                        head: HeadContext::new(context.is_rtl),
+                        lang: context.lang,
+                        dir: context.dir,
+                        units,
+                        is_metric: context.is_metric,
+                        canonical_url,
+                        rum_integrity: csp::integrity(crate::RUM_JS),
+                        provider: provider::current(),
+                    },
+                )
+            };
+
+            // Only cache shells for the units the API understands, so junk query
+            // strings can't fill the cache. The canonical URL covers both the
+            // host and the units, so with the language and layout it makes a
+            // good key.
+            let shell = match units {
+                "metric" | "imperial" | "standard" => compose::cached_shell(
+                    &format!(
+                        "{}#{}#{}",
+                        canonical_url,
+                        context.lang,
+                        shell_template.name()
+                    ),
+                    render_shell,
+                ),
+                _ => render_shell(),
+            };
+
+            return compose::compose_to(
+                &shell,
+                |path| match path {
+                    SCRIPTS_INCLUDE => {
+                        Some(render(TemplateName::Scripts, &ScriptsContext { nonce }))
+                    }
+                    FAVICON_INCLUDE => Some(format!(
+                        r#"<link rel="icon" href="{}" type="image/svg+xml">"#,
+                        context.favicon
+                    )),
+                    path => Fragment::from_path(path)
+                        .map(|fragment| render(TemplateName::Fragment(fragment), &context)),
+                },
+                out,
+            );
+        }
+        View::Fragment(fragment) => render(TemplateName::Fragment(fragment), &context),
+        View::Minimal => render(TemplateName::Minimal, &context),
+        View::Gemtext => render(TemplateName::Gemtext, &context),
+        View::Digest => render(TemplateName::Digest, &context),
+        View::Amp => render(
+            TemplateName::Amp,
+            &AmpContext {
+                weather: &context,
+                css: AMP_CSS,
+                rtl_css: if context.is_rtl { AMP_RTL_CSS } else { "" },
+            },
+        ),
+        View::Snow(conditions) => {
+            let days: Vec<SnowDay> = api_response
+                .daily
+                .iter()
+                .map(|daily| {
+                    let snow = daily.snow.unwrap_or(0.0);
+                    SnowDay {
+                        day: locale
+                            .weekday_short(weekday_of(daily.dt, &local))
+                            .to_string(),
+                        snow: snow::format_snowfall(snow, units),
+                        has_snow: snow > 0.0,
+                    }
+                })
+                .collect();
+            let week_total: f32 = api_response.daily.iter().filter_map(|d| d.snow).sum();
+
+            render(
+                TemplateName::Snow,
+                &SnowContext {
+                    head: HeadContext::new(context.is_rtl),
+                    weather: &context,
+                    conditions,
+                    snowfall_now: api_response
+                        .current
+                        .snow
+                        .as_ref()
+                        .map(|snow| snow::format_snowfall(snow.one_hour, units)),
+                    week_total: snow::format_snowfall(week_total, units),
+                    days,
+                },
+            )
+        }
+    };
+    out.write_all(page.as_bytes())
+}
+
+// A distance in metres, in kilometres or miles to suit `units`. Short ones
+// are shown in metres, as fog is measured.
+fn format_distance(metres: f32, units: &str) -> String {
+    match units {
+        "imperial" => format!("{:.1} mi", metres / 1609.344),
+        _ if metres < 1000.0 => format!("{:.0} m", metres),
+        _ => format!("{:.0} km", metres / 1000.0),
+    }
+}
+
+// The weekday of a forecast timestamp, on the same clock as the rest of the page.
+fn weekday_of(dt: i32, local: &DateTime<FixedOffset>) -> Weekday {
+    weather_helpers::local_time(dt.into(), local.offset().local_minus_utc()).weekday()
+}
+
+/// Render the themed 404 page, logging the path so broken links stand out.
+pub fn not_found(path: &str) -> Response {
+    println!("Not found: {}", path);
+
+    let body = render(
+        TemplateName::Error,
+        &NotFoundContext {
+            head: HeadContext::new(false),
+            search: SearchContext { autofocus: false },
+            path,
+        },
+    );
+
+    Response::from_body(body)
+        .with_status(StatusCode::NOT_FOUND)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+}
+
+/// Render `/docs`, the endpoints the JSON API offers.
+pub fn api_docs() -> Response {
+    let body = render(
+        TemplateName::ApiDocs,
+        &ApiDocsContext {
+            head: HeadContext::new(false),
+            auth: auth::required(),
+        },
+    );
+
+    Response::from_body(body)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(header::CACHE_CONTROL, "public, max-age=3600")
+}
+
+/// Render the location picker, for visitors who turned IP geolocation off.
+pub fn location_picker() -> Response {
+    let body = render(
+        TemplateName::Picker,
+        &PickerContext {
+            head: HeadContext::new(false),
+            search: SearchContext { autofocus: true },
+        },
+    );
+
+    Response::from_body(body)
+        .with_status(StatusCode::OK)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(header::CACHE_CONTROL, "private, no-cache")
+        .with_header(header::VARY, "Cookie")
+}
+
+/// Render the page for a share link that's `expired`, or otherwise can't be
+/// trusted.
+pub fn share_unusable(expired: bool) -> Response {
+    let body = render(
+        TemplateName::ShareUnusable,
+        &ShareUnusableContext {
+            head: HeadContext::new(false),
+            expired,
+        },
+    );
+
+    let status = if expired {
+        StatusCode::GONE
+    } else {
+        StatusCode::FORBIDDEN
+    };
+    Response::from_body(body)
+        .with_status(status)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(header::CACHE_CONTROL, "no-store")
+}
+
+/// Render the page shown when the weather API is out of quota or rejects our
+/// key, passing on its `Retry-After`. It's never cached, so the forecast comes
+/// back as soon as the API does.
+pub fn unavailable(retry_after: Option<&str>) -> Response {
+    let body = render(
+        TemplateName::Unavailable,
+        &UnavailableContext {
+            head: HeadContext::new(false),
+            // `Retry-After` can also be an HTTP date, which is harder to put in words.
+            retry_secs: retry_after.and_then(|value| value.trim().parse().ok()),
+        },
+    );
+
+    let mut resp = Response::from_body(body)
+        .with_status(StatusCode::SERVICE_UNAVAILABLE)
+        .with_content_type(fastly::mime::TEXT_HTML_UTF_8)
+        .with_header(header::CACHE_CONTROL, "no-store");
+    if let Some(retry_after) = retry_after {
+        resp.set_header(header::RETRY_AFTER, retry_after);
+    }
+    resp
+}
+
+/// Render the METAR/TAF page for an aerodrome.
+pub fn aviation(context: &AviationContext) -> String {
+    render(TemplateName::Aviation, context)
+}
+
+/// Render the `/privacy` page.
+pub fn privacy(context: &PrivacyContext) -> String {
+    render(TemplateName::Privacy, context)
+}
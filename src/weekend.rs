@@ -0,0 +1,110 @@
+//! The weekend outlook: the next weekend in the daily forecast, with a
+//! summary of both days together for planning trips and outings.
+//!
+//! Which days make the weekend depends on the visitor's language, since it
+//! falls on Friday and Saturday in much of the Middle East. When today is
+//! part of the weekend, it's this one; once the forecast no longer reaches a
+//! weekend day there's no card.
+
+use chrono::Datelike;
+use serde::Serialize;
+use weather_helpers::Locale;
+
+use crate::iconset::{self, Icon};
+use crate::precision::Precision;
+use crate::{precipitation, APIResponse};
+
+// Daily rain and snow, in millimetres, that makes for a wet weekend
+const WET: f32 = 1.0;
+
+/// Context for the weekend fragment
+#[derive(Serialize)]
+pub struct WeekendCard {
+    days: Vec<WeekendDay>,
+    /// The coldest low and warmest high across the weekend
+    low: String,
+    high: String,
+    summary: String,
+}
+
+/// One day of the weekend
+#[derive(Serialize)]
+struct WeekendDay {
+    name: &'static str,
+    icon: Icon,
+    low: String,
+    high: String,
+}
+
+impl WeekendCard {
+    /// The next weekend in `api_response`'s daily forecast for a visitor
+    /// reading `locale`, with amounts in `units`.
+    pub fn new(api_response: &APIResponse, units: &str, locale: Locale) -> Option<Self> {
+        let weekend = locale.weekend();
+        let precision = Precision::configured();
+        let icons = iconset::configured();
+        let weekday = |dt: i32| {
+            weather_helpers::local_time(dt.into(), api_response.timezone_offset).weekday()
+        };
+        let days: Vec<_> = api_response
+            .daily
+            .iter()
+            .skip_while(|daily| !weekend.contains(&weekday(daily.dt)))
+            .take_while(|daily| weekend.contains(&weekday(daily.dt)))
+            .take(weekend.len())
+            .collect();
+        if days.is_empty() {
+            return None;
+        }
+
+        let low = days
+            .iter()
+            .map(|daily| daily.temp.min)
+            .fold(f32::MAX, f32::min);
+        let high = days
+            .iter()
+            .map(|daily| daily.temp.max)
+            .fold(f32::MIN, f32::max);
+        let wet_days = days
+            .iter()
+            .filter(|daily| daily.rain.unwrap_or(0.0) + daily.snow.unwrap_or(0.0) >= WET)
+            .count();
+        let total: f32 = days
+            .iter()
+            .map(|daily| daily.rain.unwrap_or(0.0) + daily.snow.unwrap_or(0.0))
+            .sum();
+        let summary = match wet_days {
+            0 => String::from("Dry"),
+            wet if wet == days.len() => format!(
+                "Wet, with {} in all",
+                precipitation::format_rainfall(total, units)
+            ),
+            _ => format!(
+                "Partly wet, with {} in all",
+                precipitation::format_rainfall(total, units)
+            ),
+        };
+
+        Some(WeekendCard {
+            days: days
+                .iter()
+                .map(|daily| WeekendDay {
+                    name: weather_helpers::weekday_full(weekday(daily.dt), locale),
+                    icon: {
+                        let weather = daily.weather.first();
+                        icons.icon(
+                            weather.map_or(0, |weather| weather.id),
+                            weather.map_or("", |weather| weather.icon.as_str()),
+                            weather.map_or("", |weather| weather.description.as_str()),
+                        )
+                    },
+                    low: precision.temp(locale, daily.temp.min),
+                    high: precision.temp(locale, daily.temp.max),
+                })
+                .collect(),
+            low: precision.temp(locale, low),
+            high: precision.temp(locale, high),
+            summary,
+        })
+    }
+}
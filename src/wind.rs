@@ -0,0 +1,102 @@
+//! The wind rose: which way the wind blows from over the next 24 hours, for
+//! sailors and drone pilots planning around it.
+//!
+//! Each of the eight compass points gets a petal as long as the share of
+//! hours the wind comes from there, pointing the way it comes from. The
+//! petals are worked out here and drawn by the template as inline SVG.
+
+use serde::Serialize;
+
+use crate::APIResponse;
+
+const HOURS: usize = 24;
+
+// The rose's centre, and the radius of the longest petal, in SVG units
+const CENTRE: f32 = 50.0;
+const RADIUS: f32 = 40.0;
+// Even a direction the wind comes from only once gets a visible petal.
+const MIN_RADIUS: f32 = 6.0;
+// Half a petal's width, in degrees, leaving a gap between neighbours
+const HALF_WIDTH: f32 = 18.0;
+
+/// Context for the wind fragment
+#[derive(Serialize)]
+pub struct WindRose {
+    petals: Vec<Petal>,
+    /// The compass point the wind comes from most often
+    dominant: &'static str,
+    /// The strongest hourly wind speed from there
+    dominant_speed: String,
+}
+
+/// One compass point's wedge
+#[derive(Serialize)]
+struct Petal {
+    direction: &'static str,
+    hours: usize,
+    /// The wedge's corners, for a `polygon`'s `points`
+    points: String,
+}
+
+impl WindRose {
+    /// The rose of the next 24 hours in `api_response`. `None` when the
+    /// forecast gives no wind directions.
+    pub fn new(api_response: &APIResponse) -> Option<Self> {
+        let mut hours = [0; COMPASS_POINTS.len()];
+        let mut strongest = [0.0f32; COMPASS_POINTS.len()];
+        for hour in api_response.hourly.iter().take(HOURS) {
+            if let Some(degrees) = hour.wind_deg {
+                let point = compass_point(degrees);
+                hours[point] += 1;
+                strongest[point] = strongest[point].max(hour.wind_speed);
+            }
+        }
+
+        let most = *hours.iter().max()?;
+        if most == 0 {
+            return None;
+        }
+        // The first point with the most hours, going clockwise from north
+        let dominant = hours.iter().position(|&count| count == most)?;
+
+        let petals = hours
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(point, &count)| {
+                let radius = MIN_RADIUS + (RADIUS - MIN_RADIUS) * count as f32 / most as f32;
+                let bearing = point as f32 * 45.0;
+                Petal {
+                    direction: COMPASS_POINTS[point],
+                    hours: count,
+                    points: [bearing - HALF_WIDTH, bearing + HALF_WIDTH]
+                        .iter()
+                        .map(|&side| corner(side, radius))
+                        .fold(corner(0.0, 0.0), |points, corner| {
+                            format!("{} {}", points, corner)
+                        }),
+                }
+            })
+            .collect();
+
+        Some(WindRose {
+            petals,
+            dominant: COMPASS_POINTS[dominant],
+            dominant_speed: format!("{:.0}", strongest[dominant]),
+        })
+    }
+}
+
+// The point `radius` out from the centre on a compass bearing, with north up.
+fn corner(bearing: f32, radius: f32) -> String {
+    let (sin, cos) = bearing.to_radians().sin_cos();
+    format!("{:.1},{:.1}", CENTRE + radius * sin, CENTRE - radius * cos)
+}
+
+/// The eight compass points, clockwise from north
+pub const COMPASS_POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+/// Which of the eight compass points a bearing in degrees is closest to.
+pub fn compass_point(degrees: f32) -> usize {
+    (degrees.rem_euclid(360.0) / 45.0).round() as usize % COMPASS_POINTS.len()
+}
@@ -0,0 +1,204 @@
+//! Good weather windows at `/api/windows`, for scheduling outdoor work.
+//!
+//! Clients give the conditions they need, and get back each run of hours in
+//! the forecast that meets them, earliest first:
+//!
+//! * `min_temp`: the coldest it may be, in the requested units
+//! * `max_pop`: the highest chance of precipitation, in percent
+//! * `hours`: how long a window must be at least, 1 unless given
+//!
+//! Add `format=ics` for an iCalendar feed with an event per window, which
+//! calendar apps can subscribe to.
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use fastly::http::{header, StatusCode};
+use fastly::{Error, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::provider::{self, Attributed};
+use crate::{problem, APIResponse};
+
+#[derive(Deserialize)]
+struct WindowParams {
+    min_temp: Option<f32>,
+    max_pop: Option<u32>,
+    hours: Option<usize>,
+    format: Option<String>,
+}
+
+/// What a window must be like, as asked for
+pub struct Constraints {
+    min_temp: Option<f32>,
+    /// From 0 to 1, as the API gives it
+    max_pop: Option<f32>,
+    hours: usize,
+    ics: bool,
+}
+
+/// The `/api/windows` response
+#[derive(Serialize)]
+struct Windows {
+    windows: Vec<Window>,
+}
+
+/// A run of hours meeting the constraints
+#[derive(Serialize)]
+struct Window {
+    /// RFC 3339, in the location's time zone; `end` is when the last hour ends
+    start: String,
+    end: String,
+    hours: usize,
+    low: f32,
+    high: f32,
+    /// The highest chance of precipitation during the window, in percent
+    max_pop: u32,
+    #[serde(skip)]
+    start_time: DateTime<FixedOffset>,
+    #[serde(skip)]
+    end_time: DateTime<FixedOffset>,
+}
+
+impl Constraints {
+    /// The constraints in `req`'s query string, or the 400 to send when they
+    /// don't make sense.
+    pub fn from_request(req: &Request) -> Result<Self, Box<Response>> {
+        let params: WindowParams = req
+            .get_query()
+            .map_err(|_| error("min_temp, max_pop and hours must be numbers"))?;
+        if params.max_pop.is_some_and(|pop| pop > 100) {
+            return Err(error("max_pop is a percentage, up to 100"));
+        }
+        let hours = params.hours.unwrap_or(1);
+        if !(1..=48).contains(&hours) {
+            return Err(error("hours must be between 1 and 48"));
+        }
+        Ok(Constraints {
+            min_temp: params.min_temp,
+            max_pop: params.max_pop.map(|pop| pop as f32 / 100.0),
+            hours,
+            ics: params.format.as_deref() == Some("ics"),
+        })
+    }
+}
+
+/// Serve `/api/windows` from the hourly forecast in `api_response`.
+pub fn handle(constraints: &Constraints, api_response: &APIResponse) -> Result<Response, Error> {
+    let tz = FixedOffset::east_opt(api_response.timezone_offset)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+
+    let mut windows = Vec::new();
+    let mut run: Vec<_> = Vec::new();
+    // A sentinel after the last hour closes the final run.
+    for hour in api_response.hourly.iter().map(Some).chain([None]) {
+        let fits = hour.is_some_and(|hour| {
+            constraints.min_temp.is_none_or(|min| hour.temp >= min)
+                && constraints.max_pop.is_none_or(|max| hour.pop <= max)
+        });
+        if fits {
+            run.extend(hour);
+            continue;
+        }
+        if run.len() >= constraints.hours {
+            let (first, last) = (run[0], run[run.len() - 1]);
+            let start_time = tz.timestamp_opt(first.dt, 0).single();
+            let end_time = tz.timestamp_opt(last.dt + 3600, 0).single();
+            if let (Some(start_time), Some(end_time)) = (start_time, end_time) {
+                windows.push(Window {
+                    start: start_time.to_rfc3339(),
+                    end: end_time.to_rfc3339(),
+                    hours: run.len(),
+                    low: run.iter().map(|hour| hour.temp).fold(f32::MAX, f32::min),
+                    high: run.iter().map(|hour| hour.temp).fold(f32::MIN, f32::max),
+                    max_pop: (run.iter().map(|hour| hour.pop).fold(0.0, f32::max) * 100.0).round()
+                        as u32,
+                    start_time,
+                    end_time,
+                });
+            }
+        }
+        run.clear();
+    }
+
+    let resp = if constraints.ics {
+        Response::from_body(calendar(&windows))
+            .with_content_type("text/calendar; charset=utf-8".parse().unwrap())
+    } else {
+        Response::from_body(serde_json::to_string(&Attributed::new(&Windows {
+            windows,
+        }))?)
+        .with_content_type(fastly::mime::APPLICATION_JSON)
+    };
+    Ok(resp
+        .with_status(StatusCode::OK)
+        .with_header(header::CACHE_CONTROL, "private, max-age=300"))
+}
+
+// An iCalendar feed with an event for each window. The forecast is redone
+// every hour, so each event is named for its start, which keeps it the same
+// event in calendars that refresh the feed.
+fn calendar(windows: &[Window]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let utc = |time: &DateTime<FixedOffset>| {
+        time.with_timezone(&Utc)
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    let mut ics =
+        String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//enviark//weather//EN\r\n");
+    for window in windows {
+        ics.push_str(&format!(
+            "BEGIN:VEVENT\r\nUID:window-{}@weather\r\nDTSTAMP:{}\r\nDTSTART:{}\r\nDTEND:{}\r\n\
+             SUMMARY:Good weather window\r\nDESCRIPTION:{}° to {}°\\, up to {}% chance of \
+             precipitation. Weather data by {}.\r\nEND:VEVENT\r\n",
+            utc(&window.start_time),
+            stamp,
+            utc(&window.start_time),
+            utc(&window.end_time),
+            weather_helpers::round_temperature(window.low),
+            weather_helpers::round_temperature(window.high),
+            window.max_pop,
+            provider::current().name,
+        ));
+    }
+    ics.push_str("END:VCALENDAR\r\n");
+    ics
+}
+
+fn error(message: &str) -> Box<Response> {
+    Box::new(problem::response(StatusCode::BAD_REQUEST, message))
+}
+
+/// The runs of consecutive hourly scores at or above `threshold`, best first by
+/// average score and then by length, as ranges of indices into `scores`.
+pub fn best_windows(scores: &[u8], threshold: u8) -> Vec<std::ops::Range<usize>> {
+    let mut windows = Vec::new();
+    let mut start = None;
+    for (i, &score) in scores.iter().enumerate() {
+        match (score >= threshold, start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                windows.push(from..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        windows.push(from..scores.len());
+    }
+
+    let average = |window: &std::ops::Range<usize>| {
+        scores[window.clone()]
+            .iter()
+            .map(|&s| u32::from(s))
+            .sum::<u32>() as f32
+            / window.len() as f32
+    };
+    windows.sort_by(|a, b| {
+        average(b)
+            .partial_cmp(&average(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.len().cmp(&a.len()))
+    });
+    windows
+}
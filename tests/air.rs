@@ -0,0 +1,22 @@
+use weather::exercise::{air_advice, AirAdvice};
+
+#[test]
+fn good_air_brings_no_warning() {
+    for aqi in 1..=3 {
+        assert_eq!(air_advice(aqi, 4, 5), None);
+    }
+}
+
+#[test]
+fn warnings_start_at_their_thresholds() {
+    assert_eq!(air_advice(4, 4, 5), Some(AirAdvice::Caution));
+    assert_eq!(air_advice(5, 4, 5), Some(AirAdvice::Avoid));
+}
+
+#[test]
+fn stricter_thresholds_warn_sooner() {
+    assert_eq!(air_advice(2, 2, 3), Some(AirAdvice::Caution));
+    assert_eq!(air_advice(3, 2, 3), Some(AirAdvice::Avoid));
+    // With both the same, there's no in between
+    assert_eq!(air_advice(3, 3, 3), Some(AirAdvice::Avoid));
+}
@@ -0,0 +1,194 @@
+//! METAR and TAF decoding, over real reports and the garbage a feed can
+//! send instead.
+
+use weather::aviation::{Metar, Taf};
+
+// A report, then what it decodes to: wind as direction, speed, gust and
+// unit, visibility, temperature, dew point, pressure and flight category.
+type MetarCase = (
+    &'static str,
+    Option<(Option<u16>, u16, Option<u16>, &'static str)>,
+    Option<&'static str>,
+    Option<&'static str>,
+    Option<&'static str>,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+const METARS: [MetarCase; 6] = [
+    (
+        "METAR EGLL 141220Z 24012KT 9999 FEW040 15/08 Q1016",
+        Some((Some(240), 12, None, "kt")),
+        Some("10 km or more"),
+        Some("15°C"),
+        Some("8°C"),
+        Some("1016 hPa"),
+        Some("VFR"),
+    ),
+    (
+        "KJFK 141251Z VRB03KT 10SM -RA BKN012 OVC025 M02/M05 A2992 RMK AO2 SLP132",
+        Some((None, 3, None, "kt")),
+        Some("10 mi"),
+        Some("-2°C"),
+        Some("-5°C"),
+        Some("29.92 inHg"),
+        Some("MVFR"),
+    ),
+    (
+        "SPECI LFPG 141300Z 27015G28KT 4000 +TSRA SCT010CB 12/11 Q1002",
+        Some((Some(270), 15, Some(28), "kt")),
+        Some("4000 m"),
+        Some("12°C"),
+        Some("11°C"),
+        Some("1002 hPa"),
+        Some("IFR"),
+    ),
+    (
+        "UUEE 141230Z 22008MPS CAVOK M12/ Q0998",
+        Some((Some(220), 8, None, "m/s")),
+        Some("10 km or more, no cloud below 5000 ft"),
+        Some("-12°C"),
+        None,
+        Some("998 hPa"),
+        Some("VFR"),
+    ),
+    (
+        "KSFO 141256Z 00000KT 1/2SM FG VV002 11/11 A3001",
+        Some((Some(0), 0, None, "kt")),
+        Some("1/2 mi"),
+        Some("11°C"),
+        Some("11°C"),
+        Some("30.01 inHg"),
+        Some("LIFR"),
+    ),
+    // Multi-byte characters where the groups split, and groups that don't
+    // parse, are skipped rather than taking the page down.
+    (
+        "EDDF 141250Z 2€0KT €1013 Q1€13 ÄÖÜ ///// 9999 BKN€10 M€/€ NOSIG",
+        None,
+        Some("10 km or more"),
+        None,
+        None,
+        None,
+        Some("VFR"),
+    ),
+];
+
+#[test]
+fn metar_groups() {
+    for (raw, wind, visibility, temperature, dew_point, pressure, category) in METARS {
+        let metar = Metar::parse(raw).unwrap();
+        assert_eq!(
+            metar
+                .wind
+                .as_ref()
+                .map(|wind| (wind.direction, wind.speed, wind.gust, wind.unit)),
+            wind,
+            "{}",
+            raw
+        );
+        assert_eq!(metar.visibility.as_deref(), visibility, "{}", raw);
+        assert_eq!(metar.temperature.as_deref(), temperature, "{}", raw);
+        assert_eq!(metar.dew_point.as_deref(), dew_point, "{}", raw);
+        assert_eq!(metar.pressure.as_deref(), pressure, "{}", raw);
+        assert_eq!(metar.flight_category, category, "{}", raw);
+    }
+}
+
+#[test]
+fn metar_weather_and_clouds() {
+    let metar =
+        Metar::parse("SPECI LFPG 141300Z 27015G28KT 4000 +TSRA SCT010CB 12/11 Q1002").unwrap();
+    assert_eq!(metar.station, "LFPG");
+    assert_eq!(metar.observed.as_deref(), Some("day 14 at 13:00 UTC"));
+    assert_eq!(metar.weather, ["+TSRA"]);
+    assert_eq!(metar.clouds.len(), 1);
+    assert_eq!(metar.clouds[0].cover, "scattered");
+    assert_eq!(metar.clouds[0].base.as_deref(), Some("1000 ft"));
+    assert_eq!(metar.clouds[0].kind.as_deref(), Some("CB"));
+
+    // Remarks aren't decoded, so their groups don't count.
+    let remarks =
+        Metar::parse("KJFK 141251Z VRB03KT 10SM CLR 20/10 A2992 RMK TSB05 Q1013").unwrap();
+    assert!(remarks.weather.is_empty());
+    assert_eq!(remarks.pressure.as_deref(), Some("29.92 inHg"));
+}
+
+#[test]
+fn metar_needs_a_station() {
+    for raw in [
+        "",
+        "METAR",
+        "METAR egll 141220Z",
+        "EG€L 141220Z 24012KT",
+        "€€€€",
+    ] {
+        assert!(Metar::parse(raw).is_none(), "{}", raw);
+    }
+}
+
+#[test]
+fn taf_periods() {
+    let taf = Taf::parse(
+        "TAF KJFK 141130Z 1412/1518 24012KT P6SM FEW250 \
+         FM141800 27015G25KT 5SM -SHRA BKN030 \
+         PROB30 TEMPO 1420/1422 2SM TSRA OVC015CB \
+         BECMG 1502/1504 VRB05KT",
+    )
+    .unwrap();
+
+    assert_eq!(taf.station, "KJFK");
+    assert_eq!(taf.issued.as_deref(), Some("day 14 at 11:30 UTC"));
+    assert_eq!(
+        taf.valid.as_deref(),
+        Some("day 14 12:00 to day 15 18:00 UTC")
+    );
+
+    let periods: Vec<_> = taf
+        .periods
+        .iter()
+        .map(|period| {
+            (
+                period.change.as_str(),
+                period.valid.as_deref(),
+                period.visibility.as_deref(),
+                period.flight_category,
+            )
+        })
+        .collect();
+    assert_eq!(
+        periods,
+        [
+            ("Base", None, Some("more than 6 mi"), Some("VFR")),
+            ("From", Some("day 14 18:00 UTC"), Some("5 mi"), Some("MVFR")),
+            (
+                "30% chance, temporarily",
+                Some("day 14 20:00 to day 14 22:00 UTC"),
+                Some("2 mi"),
+                Some("IFR")
+            ),
+            (
+                "Becoming",
+                Some("day 15 02:00 to day 15 04:00 UTC"),
+                None,
+                None
+            ),
+        ]
+    );
+    assert_eq!(
+        taf.periods[1].wind.as_ref().and_then(|wind| wind.gust),
+        Some(25)
+    );
+    assert_eq!(taf.periods[2].weather, ["TSRA"]);
+    assert!(taf.periods[3].wind.as_ref().unwrap().direction.is_none());
+}
+
+#[test]
+fn taf_garbage() {
+    let taf =
+        Taf::parse("TAF AMD EGLL 141100Z 1412/1518 €€€ 2€0KT BKN€10 FM1€1200 TEMPO 14€/1€18 Q10€")
+            .unwrap();
+    assert_eq!(taf.station, "EGLL");
+    assert!(taf.periods.iter().all(|period| period.wind.is_none()));
+    assert!(taf.periods.iter().all(|period| period.clouds.is_empty()));
+}
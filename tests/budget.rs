@@ -0,0 +1,71 @@
+//! How freely the weather API can be called as the day's and the minute's
+//! budgets are spent.
+
+use chrono::{DateTime, TimeZone, Utc};
+use weather::budget::{Budget, Mode};
+
+const PER_DAY: u64 = 1000;
+const PER_MINUTE: u64 = 60;
+
+// 18:30:15 UTC, so 19,785 seconds to midnight and 45 to the next minute
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 14, 18, 30, 15).unwrap()
+}
+
+fn mode(today: u64, in_minute: u64) -> Mode {
+    Budget::with_counts(now(), today, in_minute, PER_DAY, PER_MINUTE).mode()
+}
+
+#[test]
+fn normal_until_nine_tenths_spent() {
+    assert_eq!(mode(0, 0), Mode::Normal);
+    assert_eq!(mode(899, 53), Mode::Normal);
+}
+
+#[test]
+fn conserves_near_either_budget() {
+    assert_eq!(mode(900, 0), Mode::Conserve);
+    assert_eq!(mode(999, 0), Mode::Conserve);
+    assert_eq!(mode(0, 54), Mode::Conserve);
+    assert_eq!(mode(0, 59), Mode::Conserve);
+}
+
+#[test]
+fn spent_day_waits_for_midnight() {
+    assert_eq!(mode(1000, 0), Mode::Exhausted { retry_secs: 19_785 });
+    assert_eq!(mode(1500, 0), Mode::Exhausted { retry_secs: 19_785 });
+    // The day's wait is the longer one, so it wins.
+    assert_eq!(mode(1000, 60), Mode::Exhausted { retry_secs: 19_785 });
+}
+
+#[test]
+fn spent_minute_waits_for_the_next() {
+    assert_eq!(mode(0, 60), Mode::Exhausted { retry_secs: 45 });
+    assert_eq!(mode(950, 75), Mode::Exhausted { retry_secs: 45 });
+}
+
+#[test]
+fn calls_move_through_the_modes() {
+    let modes: Vec<_> = [0, 899, 900, 999, 1000]
+        .iter()
+        .map(|&today| mode(today, 0))
+        .collect();
+    assert_eq!(
+        modes,
+        [
+            Mode::Normal,
+            Mode::Normal,
+            Mode::Conserve,
+            Mode::Conserve,
+            Mode::Exhausted { retry_secs: 19_785 },
+        ]
+    );
+}
+
+#[test]
+fn small_budgets() {
+    // With one call a day, any call spends it all.
+    let one = |today| Budget::with_counts(now(), today, 0, 1, PER_MINUTE).mode();
+    assert_eq!(one(0), Mode::Normal);
+    assert_eq!(one(1), Mode::Exhausted { retry_secs: 19_785 });
+}
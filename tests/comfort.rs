@@ -0,0 +1,24 @@
+use weather::conditions::comfort_level;
+use weather_helpers::to_celsius;
+
+#[test]
+fn comfort_follows_the_dew_point() {
+    assert_eq!(comfort_level(-5.0), "Dry");
+    assert_eq!(comfort_level(8.6), "Dry");
+    assert_eq!(comfort_level(12.0), "Comfortable");
+    assert_eq!(comfort_level(18.0), "Muggy");
+    assert_eq!(comfort_level(24.0), "Oppressive");
+}
+
+#[test]
+fn bands_start_at_their_thresholds() {
+    assert_eq!(comfort_level(10.0), "Comfortable");
+    assert_eq!(comfort_level(16.0), "Muggy");
+    assert_eq!(comfort_level(21.0), "Oppressive");
+}
+
+#[test]
+fn fahrenheit_dew_points_convert_first() {
+    // 65°F is a muggy 18°C.
+    assert_eq!(comfort_level(to_celsius(65.0, "imperial")), "Muggy");
+}
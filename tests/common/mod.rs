@@ -0,0 +1,129 @@
+//! Rendering helpers shared by the integration tests.
+
+use weather::chart::TemperatureChart;
+use weather::confidence::DailyConfidence;
+use weather::dayparts::DayParts;
+use weather::exercise::ExerciseCard;
+use weather::experiments::Assignments;
+use weather::laundry::LaundryCard;
+use weather::location::Location;
+use weather::openmeteo::Day;
+use weather::precipitation::PrecipitationTotals;
+use weather::roads::RoadCard;
+use weather::view::{self, Device, PageOptions, Place, View};
+use weather::weekend::WeekendCard;
+use weather::wind::WindRose;
+use weather::APIResponse;
+use weather_helpers::Locale;
+
+// 2026-10-14 12:00 in London, when the fixtures were observed.
+const OBSERVED: i64 = 1_791_975_600;
+const UTC_OFFSET: i32 = 3600;
+
+// Pages are rendered with a fixed nonce, so the goldens stay put.
+const NONCE: &str = "dGVzdG5vbmNl";
+
+const LATITUDE: f64 = 51.51;
+const LONGITUDE: f64 = -0.13;
+
+/// Render a fixture forecast for London, in metric units.
+pub fn render(fixture: &str, view: View, locale: Locale) -> String {
+    render_with(fixture, view, locale, Device::Desktop, "London")
+}
+
+/// Render a fixture forecast at London's coordinates, laid out for `device`
+/// and named `city`. Every card the forecast has enough for is shown, as
+/// with every flag on.
+pub fn render_with(
+    fixture: &str,
+    view: View,
+    locale: Locale,
+    device: Device,
+    city: &str,
+) -> String {
+    let api_response: APIResponse = serde_json::from_str(fixture).unwrap();
+    let raw: serde_json::Value = serde_json::from_str(fixture).unwrap();
+    let experiments = Assignments::default();
+    let location = Location::at(LATITUDE, LONGITUDE, city.to_string());
+    let place = Place {
+        city: city.to_string(),
+        latitude: LATITUDE,
+        longitude: LONGITUDE,
+        is_default: false,
+        map_url: None,
+    };
+
+    let units = "metric";
+    let exercise = ExerciseCard::new(&api_response, units, &air_quality(&raw));
+    let laundry = LaundryCard::new(&api_response, units);
+    let roads = RoadCard::new(&api_response, units, &location);
+    let confidence = DailyConfidence::compare(&api_response, units, &second_opinion(&raw));
+    let chart = TemperatureChart::new(&api_response, locale);
+    let wind_rose = WindRose::new(&api_response);
+    let precipitation = PrecipitationTotals::new(&api_response, units);
+    let weekend = WeekendCard::new(&api_response, units, locale);
+    let day_parts = DayParts::new(&api_response, locale);
+
+    view::generate_view(
+        api_response,
+        &place,
+        weather_helpers::local_time(OBSERVED, UTC_OFFSET),
+        PageOptions {
+            units,
+            view,
+            experiments: &experiments,
+            canonical_url: "https://weather.example/?units=metric",
+            show_radar: false,
+            marine: None,
+            fire: None,
+            astronomy: None,
+            garden: None,
+            exercise,
+            laundry,
+            roads,
+            confidence: Some(confidence),
+            chart,
+            wind_rose,
+            precipitation,
+            weekend,
+            day_parts,
+            pressure_trend: None,
+            show_details: false,
+            skin_type: None,
+            locale,
+            device,
+            nonce: NONCE,
+            save_data: false,
+        },
+    )
+}
+
+// Good air for the fixture's hours, turning moderate every fourth hour.
+fn air_quality(forecast: &serde_json::Value) -> Vec<(i64, u8)> {
+    let hours = forecast["hourly"].as_array().cloned().unwrap_or_default();
+    hours
+        .iter()
+        .enumerate()
+        .map(|(i, hour)| (hour["dt"].as_i64().unwrap(), if i % 4 == 3 { 3 } else { 1 }))
+        .collect()
+}
+
+// A second forecast that agrees about the first day, differs a little about
+// the second and a lot about the third, and has nothing after the fourth.
+fn second_opinion(forecast: &serde_json::Value) -> Vec<Day> {
+    let days = forecast["daily"].as_array().cloned().unwrap_or_default();
+    days.iter()
+        .take(4)
+        .zip([0.5, 2.5, 5.0, 0.0])
+        .map(|(day, gap)| {
+            let dt = day["dt"].as_i64().unwrap();
+            let temp = |field: &str| day["temp"][field].as_f64().unwrap() as f32;
+            Day {
+                date: weather_helpers::local_time(dt, UTC_OFFSET).date_naive(),
+                max: temp("max") + gap,
+                min: temp("min"),
+                pop: day["pop"].as_f64().map(|pop| pop as f32),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,20 @@
+use weather::wind::{compass_point, COMPASS_POINTS};
+
+fn point(degrees: f32) -> &'static str {
+    COMPASS_POINTS[compass_point(degrees)]
+}
+
+#[test]
+fn bearings_round_to_the_nearest_point() {
+    assert_eq!(point(0.0), "N");
+    assert_eq!(point(44.0), "NE");
+    assert_eq!(point(230.0), "SW");
+    assert_eq!(point(292.0), "W");
+}
+
+#[test]
+fn bearings_wrap_around_north() {
+    assert_eq!(point(350.0), "N");
+    assert_eq!(point(360.0), "N");
+    assert_eq!(point(-90.0), "W");
+}
@@ -0,0 +1,21 @@
+use weather::confidence::{forecast_confidence, Confidence};
+
+#[test]
+fn agreeing_forecasts_are_confident() {
+    assert_eq!(forecast_confidence(0.5, Some(0.05)), Confidence::High);
+    assert_eq!(forecast_confidence(1.9, None), Confidence::High);
+}
+
+#[test]
+fn disagreement_on_either_lowers_confidence() {
+    assert_eq!(forecast_confidence(2.0, Some(0.0)), Confidence::Medium);
+    assert_eq!(forecast_confidence(0.0, Some(0.2)), Confidence::Medium);
+    assert_eq!(forecast_confidence(4.0, Some(0.0)), Confidence::Low);
+    assert_eq!(forecast_confidence(0.0, Some(0.4)), Confidence::Low);
+}
+
+#[test]
+fn gaps_count_either_way() {
+    assert_eq!(forecast_confidence(-5.0, Some(-0.1)), Confidence::Low);
+    assert_eq!(forecast_confidence(1.0, Some(-0.3)), Confidence::Medium);
+}
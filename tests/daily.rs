@@ -0,0 +1,46 @@
+//! The daily forecast's range bars, at the edges of the week's scale.
+
+mod common;
+
+use weather::view::{Fragment, View};
+use weather_helpers::Locale;
+
+const FIXTURE: &str = include_str!("fixtures/onecall.json");
+
+// The fixture forecast with tomorrow's low and high replaced.
+fn with_tomorrow(low: f64, high: f64) -> String {
+    let mut forecast: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+    forecast["daily"][1]["temp"]["min"] = low.into();
+    forecast["daily"][1]["temp"]["max"] = high.into();
+    forecast.to_string()
+}
+
+#[test]
+fn steady_day_at_the_week_high() {
+    // Tomorrow's low and high are also the week's high, so its bar starts at
+    // the very end of the scale and still needs room for a sliver.
+    let daily = common::render(
+        &with_tomorrow(20.0, 20.0),
+        View::Fragment(Fragment::Daily),
+        Locale::En,
+    );
+    assert!(
+        daily.contains(r#"<rect class="day-range-bar" x="98" width="2""#),
+        "{}",
+        daily
+    );
+}
+
+#[test]
+fn steady_day_at_the_week_low() {
+    let daily = common::render(
+        &with_tomorrow(0.0, 0.0),
+        View::Fragment(Fragment::Daily),
+        Locale::En,
+    );
+    assert!(
+        daily.contains(r#"<rect class="day-range-bar" x="0" width="2""#),
+        "{}",
+        daily
+    );
+}
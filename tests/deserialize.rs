@@ -0,0 +1,102 @@
+//! The weather API's response variants, captured as fixtures, so changes
+//! upstream or to the serde structs that break parsing show up here first.
+
+mod common;
+
+use weather::view::{Fragment, View};
+use weather::APIResponse;
+use weather_helpers::Locale;
+
+const ONECALL: &str = include_str!("fixtures/onecall.json");
+// Outside precipitation radar coverage there's no minutely forecast.
+const NO_MINUTELY: &str = include_str!("fixtures/no_minutely.json");
+const ALERTS: &str = include_str!("fixtures/alerts.json");
+const EMPTY_WEATHER: &str = include_str!("fixtures/empty_weather.json");
+// One Call 3.0, with the extra fields it adds to every block.
+const ONECALL_V3: &str = include_str!("fixtures/onecall_v3.json");
+// Snowing, with the condition code the fixtures otherwise leave out.
+const SNOW: &str = include_str!("fixtures/snow.json");
+// A hot, humid, sunny afternoon.
+const HEATWAVE: &str = include_str!("fixtures/heatwave.json");
+
+const FIXTURES: [(&str, &str); 7] = [
+    ("onecall", ONECALL),
+    ("no_minutely", NO_MINUTELY),
+    ("alerts", ALERTS),
+    ("empty_weather", EMPTY_WEATHER),
+    ("onecall_v3", ONECALL_V3),
+    ("snow", SNOW),
+    ("heatwave", HEATWAVE),
+];
+
+#[test]
+fn every_fixture_parses() {
+    for (name, fixture) in FIXTURES {
+        if let Err(err) = serde_json::from_str::<APIResponse>(fixture) {
+            panic!("fixtures/{}.json doesn't parse: {}", name, err);
+        }
+    }
+}
+
+#[test]
+fn missing_minutely_hides_precipitation() {
+    let conditions = render(NO_MINUTELY, Fragment::Conditions);
+    assert!(!conditions.contains(r#"class="precipitation""#));
+    assert!(conditions.contains("HUMIDITY"));
+}
+
+#[test]
+fn snow_is_measured_in_centimetres() {
+    let conditions = render(SNOW, Fragment::Conditions);
+    assert!(conditions.contains(r#"href="/icons.svg#cloud-snow"></use></svg> SNOW</span>"#));
+    assert!(conditions.contains("1.2\n                cm</span>"));
+    assert!(!conditions.contains("RAIN"));
+
+    let rain = render(ONECALL, Fragment::Conditions);
+    assert!(rain.contains(r#"href="/icons.svg#cloud-rain"></use></svg> RAIN</span>"#));
+}
+
+#[test]
+fn heat_and_sun_bring_a_heat_safety_banner() {
+    let current = render(HEATWAVE, Fragment::Current);
+    assert!(current.contains(r#"<p class="heat heat-danger" role="alert">"#));
+    assert!(current.contains("Wear sunscreen, too."));
+
+    assert!(!render(ONECALL, Fragment::Current).contains(r#"class="heat"#));
+}
+
+#[test]
+fn missing_minutely_leaves_the_umbrella_to_the_hourly_chances() {
+    assert!(render(ONECALL, Fragment::Current).contains("Take an umbrella"));
+    assert!(render(NO_MINUTELY, Fragment::Current).contains("No umbrella needed"));
+}
+
+#[test]
+fn alerts_leave_the_page_unchanged() {
+    assert_eq!(
+        render(ALERTS, Fragment::Current),
+        render(ONECALL, Fragment::Current)
+    );
+}
+
+#[test]
+fn empty_weather_falls_back_to_default_icons() {
+    let current = render(EMPTY_WEATHER, Fragment::Current);
+    assert!(current.contains(r#"<h3 class="weather-desc"></h3>"#));
+
+    let daily = render(EMPTY_WEATHER, Fragment::Daily);
+    assert!(!daily.contains(r#"href="/icons.svg#cloud-rain""#));
+    assert!(daily.contains(r#"href="/icons.svg#sun""#));
+}
+
+#[test]
+fn onecall_v3_renders_like_v2_5() {
+    for fragment in [Fragment::Current, Fragment::Conditions, Fragment::Daily] {
+        let v3 = render(ONECALL_V3, fragment);
+        assert_eq!(v3, render(ONECALL, fragment));
+    }
+}
+
+fn render(fixture: &str, fragment: Fragment) -> String {
+    common::render(fixture, View::Fragment(fragment), Locale::En)
+}
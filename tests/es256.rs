@@ -0,0 +1,66 @@
+//! ES256 signing against the P-256, SHA-256 vectors in RFC 6979, A.2.5.
+
+use std::convert::TryInto;
+
+use weather::es256;
+
+const PRIVATE_KEY: &str = "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721";
+const PUBLIC_X: &str = "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+const PUBLIC_Y: &str = "7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299";
+
+// Messages, and the `r` and `s` of their signatures
+const SIGNATURES: [(&str, &str, &str); 2] = [
+    (
+        "sample",
+        "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+        "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+    ),
+    (
+        "test",
+        "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367",
+        "019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083",
+    ),
+];
+
+fn unhex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(&text[at..at + 2], 16).unwrap())
+        .collect()
+}
+
+fn private_key() -> [u8; 32] {
+    unhex(PRIVATE_KEY).try_into().unwrap()
+}
+
+#[test]
+fn public_key() {
+    let expected = [vec![0x04], unhex(PUBLIC_X), unhex(PUBLIC_Y)].concat();
+    assert_eq!(
+        es256::public_key(&private_key()).unwrap().to_vec(),
+        expected
+    );
+}
+
+#[test]
+fn deterministic_signatures() {
+    for (message, r, s) in SIGNATURES {
+        let signature = es256::sign(&private_key(), message.as_bytes()).unwrap();
+        assert_eq!(
+            signature.to_vec(),
+            [unhex(r), unhex(s)].concat(),
+            "{}",
+            message
+        );
+    }
+}
+
+#[test]
+fn invalid_keys() {
+    // Zero, and the order of the curve, are out of range.
+    let order = unhex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551");
+    for key in [[0; 32], order.try_into().unwrap()] {
+        assert!(es256::public_key(&key).is_none());
+        assert!(es256::sign(&key, b"sample").is_none());
+    }
+}
@@ -0,0 +1,98 @@
+//! Text from the weather API and from visitors is escaped wherever it's
+//! rendered, so a hostile upstream or cookie can't inject markup.
+
+mod common;
+
+use weather::view::{Device, Fragment, View};
+use weather_helpers::Locale;
+
+// The fixture forecast with markup in the current conditions.
+const HOSTILE: &str = include_str!("fixtures/hostile.json");
+
+const HOSTILE_CITY: &str = r#"Paris"><script>alert(1)</script><b x=""#;
+
+fn render(view: View) -> String {
+    common::render_with(HOSTILE, view, Locale::En, Device::Desktop, HOSTILE_CITY)
+}
+
+fn assert_no_markup(page: &str) {
+    assert!(!page.contains("<script>alert"), "{}", page);
+    assert!(!page.contains("<img src=x"), "{}", page);
+    assert!(!page.contains("<b x="), "{}", page);
+    assert!(!page.contains("onload="), "{}", page);
+}
+
+#[test]
+fn descriptions_are_escaped() {
+    let current = render(View::Fragment(Fragment::Current));
+    assert_no_markup(&current);
+    assert!(current.contains(
+        "&quot;heavy&quot; &lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; \
+         &lt;img src=x onerror=alert(1)&gt;"
+    ));
+}
+
+#[test]
+fn city_names_are_escaped() {
+    let current = render(View::Fragment(Fragment::Current));
+    assert!(current.contains(
+        r#"<span class="location">Paris&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;&lt;b x=&quot;</span>"#
+    ));
+    assert!(current.contains(r#"alt="Map of Paris&quot;&gt;"#));
+}
+
+#[test]
+fn unknown_icons_fall_back() {
+    let current = render(View::Fragment(Fragment::Current));
+    assert!(current.contains(r#"href="/icons.svg#sun""#));
+}
+
+#[test]
+fn every_view_is_escaped() {
+    for view in [
+        View::Standard,
+        View::Amp,
+        View::Fragment(Fragment::Conditions),
+        View::Fragment(Fragment::Daily),
+    ] {
+        assert_no_markup(&render(view));
+    }
+    let mobile = common::render_with(
+        HOSTILE,
+        View::Standard,
+        Locale::En,
+        Device::Mobile,
+        HOSTILE_CITY,
+    );
+    assert_no_markup(&mobile);
+}
+
+#[test]
+fn ordinary_text_is_untouched() {
+    let fixture = include_str!("fixtures/onecall.json");
+    let current = common::render(fixture, View::Fragment(Fragment::Current), Locale::En);
+    assert!(current.contains(r#"<span class="location">London</span>"#));
+    assert!(!current.contains("&amp;"));
+}
+
+#[test]
+fn gemtext_stays_on_its_lines() {
+    let mut forecast: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/onecall.json")).unwrap();
+    forecast["current"]["weather"][0]["description"] =
+        "light rain\n=> gemini://evil.example Free umbrellas".into();
+    let page = common::render_with(
+        &forecast.to_string(),
+        View::Gemtext,
+        Locale::En,
+        Device::Desktop,
+        "London\r\n## Owned\n\n=> gemini://evil.example",
+    );
+
+    for line in page.lines() {
+        assert!(!line.starts_with("## Owned"), "{}", page);
+        assert!(!line.starts_with("=> gemini://evil.example"), "{}", page);
+    }
+    assert!(page.starts_with("# Weather in London ## Owned => gemini://evil.example\n"));
+    assert!(page.contains("light rain => gemini://evil.example Free umbrellas."));
+}
@@ -0,0 +1,68 @@
+//! The One Call blocks each view asks the API to leave out.
+
+use weather::unused_forecast_blocks;
+
+#[test]
+fn full_pages_need_everything_but_alerts() {
+    for path in ["/", "/widget", "/kiosk", "/print"] {
+        assert_eq!(unused_forecast_blocks(path, None), ["alerts"], "{}", path);
+    }
+    assert_eq!(unused_forecast_blocks("/", Some("marine")), ["alerts"]);
+}
+
+#[test]
+fn lighter_pages_skip_the_hourly_forecast() {
+    for path in ["/amp", "/m"] {
+        assert_eq!(
+            unused_forecast_blocks(path, None),
+            ["alerts", "hourly"],
+            "{}",
+            path
+        );
+    }
+}
+
+#[test]
+fn snow_report_skips_minutely_and_hourly() {
+    assert_eq!(
+        unused_forecast_blocks("/", Some("snow")),
+        ["alerts", "minutely", "hourly"]
+    );
+    // Only the main page has a snow mode.
+    assert_eq!(
+        unused_forecast_blocks("/amp", Some("snow")),
+        ["alerts", "hourly"]
+    );
+}
+
+#[test]
+fn fragments_ask_for_what_they_show() {
+    for (fragment, exclude) in [
+        ("current", &["alerts"][..]),
+        ("conditions", &["alerts", "hourly"]),
+        ("daily", &["alerts", "minutely", "hourly"]),
+        ("radar", &["alerts", "minutely", "hourly"]),
+        ("marine", &["alerts", "minutely", "hourly"]),
+        ("fire", &["alerts", "minutely", "hourly"]),
+        ("astronomy", &["alerts", "minutely", "hourly"]),
+        ("garden", &["alerts", "minutely", "hourly"]),
+        ("weekend", &["alerts", "minutely", "hourly"]),
+        ("exercise", &["alerts", "minutely"]),
+        ("laundry", &["alerts", "minutely"]),
+        ("roads", &["alerts", "minutely"]),
+        ("chart", &["alerts", "minutely"]),
+        ("wind", &["alerts", "minutely"]),
+        ("precipitation", &["alerts", "minutely"]),
+        ("dayparts", &["alerts", "minutely"]),
+    ] {
+        let path = format!("/fragment/{}", fragment);
+        assert_eq!(unused_forecast_blocks(&path, None), exclude, "{}", path);
+        // Modes only change the main page.
+        assert_eq!(
+            unused_forecast_blocks(&path, Some("snow")),
+            exclude,
+            "{}",
+            path
+        );
+    }
+}
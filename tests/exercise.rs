@@ -0,0 +1,54 @@
+use weather::exercise::exercise_score;
+use weather::windows::best_windows;
+
+#[test]
+fn mild_dry_still_hours_are_perfect() {
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 0.0, Some(1)), 100);
+    assert_eq!(exercise_score(10.0, 75.0, 5.0, 0.0, None), 100);
+    assert_eq!(exercise_score(18.0, 60.0, 0.0, 0.0, Some(2)), 100);
+}
+
+#[test]
+fn heat_costs_more_than_cold() {
+    assert_eq!(exercise_score(24.0, 50.0, 3.0, 0.0, None), 70);
+    assert_eq!(exercise_score(4.0, 50.0, 3.0, 0.0, None), 82);
+    assert_eq!(exercise_score(40.0, 50.0, 3.0, 0.0, None), 0);
+    // Humid air counts sooner once it's warm.
+    assert_eq!(exercise_score(15.0, 70.0, 3.0, 0.0, None), 100);
+    assert_eq!(exercise_score(22.0, 70.0, 3.0, 0.0, None), 70);
+}
+
+#[test]
+fn wind_rain_and_poor_air() {
+    assert_eq!(exercise_score(15.0, 50.0, 10.0, 0.0, None), 80);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 0.5, None), 70);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 1.0, None), 40);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 0.0, Some(3)), 90);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 0.0, Some(4)), 70);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 0.0, Some(5)), 40);
+}
+
+#[test]
+fn scores_stay_between_0_and_100() {
+    assert_eq!(exercise_score(45.0, 100.0, 30.0, 1.0, Some(5)), 0);
+    // Chances of rain outside 0 to 1 are clamped.
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, -0.5, None), 100);
+    assert_eq!(exercise_score(15.0, 50.0, 3.0, 2.0, None), 40);
+}
+
+#[test]
+fn best_window_first() {
+    let scores = [40, 80, 85, 30, 95, 90, 92, 50, 75];
+    assert_eq!(best_windows(&scores, 70), [4..7, 1..3, 8..9]);
+    // A window running to the end of the forecast still counts.
+    assert_eq!(best_windows(&[75, 20, 75, 80], 70), [2..4, 0..1]);
+    assert!(best_windows(&scores, 99).is_empty());
+}
+
+#[test]
+fn ties_go_to_the_longer_window() {
+    assert_eq!(
+        best_windows(&[80, 0, 80, 80, 0, 80], 70),
+        [2..4, 0..1, 5..6]
+    );
+}
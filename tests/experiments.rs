@@ -0,0 +1,53 @@
+//! Hashing visitors into experiment buckets.
+
+use weather::experiments::bucket;
+
+#[test]
+fn buckets_are_stable() {
+    let first = bucket("3f2a9c1e8b7d4e6f", "forecast_layout");
+    for _ in 0..10 {
+        assert_eq!(bucket("3f2a9c1e8b7d4e6f", "forecast_layout"), first);
+    }
+    // Pinned to FNV-1a of "salt:visitor", so a change to the hash that would
+    // reshuffle every visitor shows up here.
+    assert_eq!(bucket("visitor", "salt"), 29);
+}
+
+#[test]
+fn buckets_are_between_0_and_99() {
+    for n in 0..1000 {
+        assert!(bucket(&format!("visitor-{}", n), "layout") < 100);
+    }
+}
+
+#[test]
+fn visitors_spread_evenly() {
+    let mut tenths = [0; 10];
+    for n in 0..10_000 {
+        tenths[bucket(&format!("{:016x}", n * 7919), "forecast_layout") as usize / 10] += 1;
+    }
+    // 1000 each on average
+    for (tenth, count) in tenths.iter().enumerate() {
+        assert!((850..=1150).contains(count), "{}: {:?}", tenth, tenths);
+    }
+}
+
+#[test]
+fn each_experiment_buckets_independently() {
+    // A visitor in the first tenth of one experiment isn't stuck at the front of all of them.
+    let front: Vec<_> = (0..1000)
+        .map(|n| format!("visitor-{}", n))
+        .filter(|visitor| bucket(visitor, "forecast_layout") < 10)
+        .collect();
+    let also_front = front
+        .iter()
+        .filter(|visitor| bucket(visitor, "chart_style") < 10)
+        .count();
+    assert!(front.len() > 50);
+    assert!(
+        also_front < front.len() / 3,
+        "{} of {}",
+        also_front,
+        front.len()
+    );
+}
@@ -0,0 +1,75 @@
+use weather::fire::{fire_danger, fire_weather_index};
+
+fn danger(temp: f32, humidity: f32, wind_speed: f32, rain: f32) -> &'static str {
+    fire_danger(fire_weather_index(temp, humidity, wind_speed, rain)).label()
+}
+
+#[test]
+fn each_danger_band() {
+    assert_eq!(danger(-10.0, 80.0, 2.0, 0.0), "Low");
+    assert_eq!(danger(20.0, 30.0, 5.0, 0.0), "Moderate");
+    assert_eq!(danger(30.0, 20.0, 8.0, 0.0), "High");
+    assert_eq!(danger(35.0, 10.0, 12.0, 0.0), "Very high");
+    assert_eq!(danger(40.0, 5.0, 30.0, 0.0), "Extreme");
+}
+
+#[test]
+fn bands_start_at_their_thresholds() {
+    for (index, label) in [
+        (0.0, "Low"),
+        (19.99, "Low"),
+        (20.0, "Moderate"),
+        (40.0, "High"),
+        (60.0, "Very high"),
+        (79.99, "Very high"),
+        (80.0, "Extreme"),
+        (100.0, "Extreme"),
+    ] {
+        assert_eq!(fire_danger(index).label(), label, "{}", index);
+    }
+}
+
+#[test]
+fn index_stays_between_0_and_100() {
+    assert_eq!(fire_weather_index(45.0, 0.0, 40.0, 0.0), 100.0);
+    // Cold, soaked fuels barely burn at all.
+    assert!(fire_weather_index(-40.0, 100.0, 0.0, 50.0) < 0.1);
+    for humidity in [0.0, 10.0, 50.0, 100.0] {
+        let index = fire_weather_index(20.0, humidity, 5.0, 0.0);
+        assert!((0.0..=100.0).contains(&index), "{}", humidity);
+    }
+}
+
+#[test]
+fn out_of_range_readings_are_clamped() {
+    let calm = fire_weather_index(20.0, 30.0, 0.0, 0.0);
+    assert_eq!(fire_weather_index(20.0, 30.0, -5.0, 0.0), calm);
+    assert_eq!(
+        fire_weather_index(20.0, 150.0, 0.0, 0.0),
+        fire_weather_index(20.0, 100.0, 0.0, 0.0)
+    );
+    assert_eq!(
+        fire_weather_index(20.0, -10.0, 0.0, 0.0),
+        fire_weather_index(20.0, 0.0, 0.0, 0.0)
+    );
+    let dry = fire_weather_index(20.0, 30.0, 5.0, 0.0);
+    assert_eq!(fire_weather_index(20.0, 30.0, 5.0, -3.0), dry);
+}
+
+#[test]
+fn humidity_bands_meet_smoothly() {
+    // The moisture formula changes at 10% and 50% humidity without a jump.
+    for (below, at) in [(9.99, 10.0), (49.99, 50.0), (50.0, 50.01)] {
+        let gap =
+            fire_weather_index(20.0, below, 0.0, 0.0) - fire_weather_index(20.0, at, 0.0, 0.0);
+        assert!(gap.abs() < 0.1, "{} {}", below, gap);
+    }
+    assert!(fire_weather_index(20.0, 10.0, 5.0, 0.0) > fire_weather_index(20.0, 50.0, 5.0, 0.0));
+}
+
+#[test]
+fn rain_damps_the_index() {
+    let dry = fire_weather_index(20.0, 30.0, 5.0, 0.0);
+    assert_eq!(fire_weather_index(20.0, 30.0, 5.0, 2.5), dry / 2.0);
+    assert!(fire_weather_index(20.0, 30.0, 5.0, 25.0) < dry / 10.0);
+}
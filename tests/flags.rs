@@ -0,0 +1,56 @@
+//! Reading feature flag values, and rolling flags out to a share of clients.
+
+use weather::flags::Flags;
+
+fn clients() -> impl Iterator<Item = Flags> {
+    (0..1000).map(|n| Flags::for_client(&format!("2001:db8::{:x}", n)))
+}
+
+#[test]
+fn switches() {
+    let flags = Flags::for_client("192.0.2.1");
+    for value in ["true", "on", " on "] {
+        assert!(flags.is_on("enable_radar", value), "{:?}", value);
+    }
+    for value in ["false", "off", ""] {
+        assert!(!flags.is_on("enable_radar", value), "{:?}", value);
+    }
+}
+
+#[test]
+fn garbage_is_off() {
+    let flags = Flags::for_client("192.0.2.1");
+    for value in ["yes", "TRUE", "1.5", "-25", "25 %", "%", "enabled", "∞"] {
+        assert!(!flags.is_on("enable_radar", value), "{:?}", value);
+    }
+}
+
+#[test]
+fn rollouts_reach_their_share() {
+    for (value, low, high) in [("25%", 200, 300), ("25", 200, 300), ("50%", 450, 550)] {
+        let on = clients()
+            .filter(|flags| flags.is_on("enable_radar", value))
+            .count();
+        assert!((low..=high).contains(&on), "{}: {}", value, on);
+    }
+    assert!(clients().all(|flags| !flags.is_on("enable_radar", "0%")));
+    assert!(clients().all(|flags| flags.is_on("enable_radar", "100%")));
+    assert!(clients().all(|flags| flags.is_on("enable_radar", "150")));
+}
+
+#[test]
+fn rollouts_only_grow() {
+    // Clients let in at 25% stay in as the rollout widens.
+    for flags in clients().filter(|flags| flags.is_on("enable_marine", "25%")) {
+        assert!(flags.is_on("enable_marine", "50%"));
+        assert!(flags.is_on("enable_marine", " 26 "));
+    }
+}
+
+#[test]
+fn each_flag_rolls_out_to_different_clients() {
+    let both = clients()
+        .filter(|flags| flags.is_on("enable_radar", "10%") && flags.is_on("enable_marine", "10%"))
+        .count();
+    assert!(both < 40, "{}", both);
+}
@@ -0,0 +1,42 @@
+use weather::garden::{frost_risk, growing_degree_days, GardenCard};
+use weather::APIResponse;
+use weather_helpers::Locale;
+
+fn frost(low: f32) -> Option<&'static str> {
+    frost_risk(low).map(|risk| risk.label())
+}
+
+#[test]
+fn frost_thresholds() {
+    assert_eq!(frost(-5.0), Some("Hard frost"));
+    assert_eq!(frost(-2.0), Some("Hard frost"));
+    assert_eq!(frost(-1.9), Some("Frost"));
+    assert_eq!(frost(0.0), Some("Frost"));
+    assert_eq!(frost(0.1), Some("Ground frost likely"));
+    assert_eq!(frost(3.0), Some("Ground frost likely"));
+    assert_eq!(frost(3.1), None);
+    assert_eq!(frost(15.0), None);
+}
+
+#[test]
+fn degree_days_above_the_base() {
+    assert_eq!(growing_degree_days(24.0, 12.0, 10.0), 8.0);
+    assert_eq!(growing_degree_days(30.0, 20.0, 5.0), 20.0);
+}
+
+#[test]
+fn days_below_the_base_give_none() {
+    assert_eq!(growing_degree_days(12.0, 8.0, 10.0), 0.0);
+    assert_eq!(growing_degree_days(8.0, 2.0, 10.0), 0.0);
+    // A freezing day doesn't set back the days already banked.
+    assert_eq!(growing_degree_days(-2.0, -10.0, 10.0), 0.0);
+    assert_eq!(growing_degree_days(-2.0, -10.0, -10.0), 4.0);
+}
+
+#[test]
+fn frost_nights_are_named_in_the_locale() {
+    let forecast: APIResponse = serde_json::from_str(include_str!("fixtures/cards.json")).unwrap();
+    let card = GardenCard::new(&forecast, "metric", Locale::De).unwrap();
+    let card = serde_json::to_value(&card).unwrap();
+    assert_eq!(card["frost_nights"], serde_json::json!(["Fr"]));
+}
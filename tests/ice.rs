@@ -0,0 +1,30 @@
+use weather::roads::{black_ice_risk, IceRisk};
+
+#[test]
+fn wet_roads_below_freezing_ice_over() {
+    assert_eq!(black_ice_risk(-1.0, Some(-4.0), 0.5), Some(IceRisk::Likely));
+    assert_eq!(black_ice_risk(0.0, None, 2.0), Some(IceRisk::Likely));
+}
+
+#[test]
+fn wet_roads_just_above_freezing_might() {
+    assert_eq!(
+        black_ice_risk(2.0, Some(-3.0), 0.5),
+        Some(IceRisk::Possible)
+    );
+    assert_eq!(black_ice_risk(3.5, Some(-3.0), 5.0), None);
+}
+
+#[test]
+fn dry_roads_stay_clear() {
+    assert_eq!(black_ice_risk(-5.0, Some(-12.0), 0.0), None);
+    assert_eq!(black_ice_risk(-5.0, None, 0.05), None);
+}
+
+#[test]
+fn frost_forms_in_near_saturated_air() {
+    assert_eq!(black_ice_risk(-1.0, Some(-2.0), 0.0), Some(IceRisk::Likely));
+    assert_eq!(black_ice_risk(1.5, Some(0.0), 0.0), Some(IceRisk::Possible));
+    // Above freezing, the dew point gives dew rather than frost.
+    assert_eq!(black_ice_risk(2.0, Some(1.0), 0.0), None);
+}
@@ -0,0 +1,68 @@
+use weather::laundry::drying_score;
+use weather::windows::best_windows;
+
+#[test]
+fn warm_dry_breezy_days_dry_well() {
+    assert!(drying_score(24.0, 35.0, 5.0, 0.0) >= 90);
+}
+
+#[test]
+fn cold_damp_still_days_barely_dry() {
+    assert!(drying_score(6.0, 95.0, 0.5, 0.0) <= 10);
+}
+
+#[test]
+fn rain_stops_drying() {
+    assert_eq!(drying_score(24.0, 35.0, 5.0, 1.0), 0);
+    assert!(drying_score(24.0, 35.0, 5.0, 0.5) < drying_score(24.0, 35.0, 5.0, 0.0));
+}
+
+#[test]
+fn drier_air_dries_faster() {
+    let mut last = 0;
+    for humidity in (0..=100).rev().step_by(10) {
+        let score = drying_score(18.0, humidity as f32, 3.0, 0.0);
+        assert!(
+            score >= last,
+            "{}% humidity scored {} after {}",
+            humidity,
+            score,
+            last
+        );
+        last = score;
+    }
+}
+
+#[test]
+fn gales_are_worse_than_a_breeze() {
+    assert!(drying_score(18.0, 50.0, 15.0, 0.0) < drying_score(18.0, 50.0, 5.0, 0.0));
+}
+
+#[test]
+fn scores_stay_in_range() {
+    for &(temp, humidity, wind, pop) in &[
+        (-20.0, 100.0, 0.0, 0.0),
+        (45.0, 0.0, 6.0, 0.0),
+        (30.0, -10.0, 40.0, 2.0),
+        (10.0, 50.0, -3.0, -1.0),
+    ] {
+        assert!(drying_score(temp, humidity, wind, pop) <= 100);
+    }
+}
+
+#[test]
+fn best_windows_are_ranked_by_average_score() {
+    let scores = [90, 95, 10, 70, 75, 80, 72, 20, 100];
+    assert_eq!(best_windows(&scores, 70), vec![8..9, 0..2, 3..7]);
+}
+
+#[test]
+fn best_windows_prefer_longer_runs_on_ties() {
+    assert_eq!(best_windows(&[80, 0, 80, 80], 70), vec![2..4, 0..1]);
+}
+
+#[test]
+fn best_windows_is_empty_without_good_hours() {
+    assert!(best_windows(&[10, 20, 30], 70).is_empty());
+    assert!(best_windows(&[], 70).is_empty());
+}
@@ -0,0 +1,53 @@
+//! Pages in right-to-left languages, which need `dir="rtl"` and the mirrored
+//! stylesheet as well as the translations.
+
+mod common;
+
+use weather::view::{Layout, View};
+use weather_helpers::Locale;
+
+const FIXTURE: &str = include_str!("fixtures/onecall.json");
+
+#[test]
+fn rtl_pages_are_mirrored() {
+    for locale in [Locale::Ar, Locale::Fa, Locale::He] {
+        let page = common::render(FIXTURE, View::Standard, locale);
+        let root = format!(r#"<html lang="{}" dir="rtl">"#, locale.code());
+        assert!(page.contains(&root), "{}", page);
+        assert!(page.contains(r#"href="/style-rtl.css""#), "{}", page);
+    }
+}
+
+#[test]
+fn ltr_pages_are_not() {
+    for locale in [Locale::En, Locale::De] {
+        let page = common::render(FIXTURE, View::Standard, locale);
+        let root = format!(r#"<html lang="{}" dir="ltr">"#, locale.code());
+        assert!(page.contains(&root), "{}", page);
+        assert!(!page.contains("style-rtl.css"), "{}", page);
+    }
+}
+
+#[test]
+fn every_shell_sets_the_direction() {
+    for view in [
+        View::Amp,
+        View::Minimal,
+        View::Layout(Layout::Widget),
+        View::Layout(Layout::Kiosk),
+        View::Layout(Layout::Print),
+    ] {
+        let page = common::render(FIXTURE, view, Locale::Ar);
+        assert!(page.contains(r#"lang="ar" dir="rtl">"#), "{}", page);
+    }
+}
+
+#[test]
+fn amp_inlines_the_mirrored_styles() {
+    // AMP pages can't link stylesheets, so the RTL rules go in the page.
+    let rtl = common::render(FIXTURE, View::Amp, Locale::He);
+    let ltr = common::render(FIXTURE, View::Amp, Locale::En);
+    let mirrored = ".weather-gradient {\n    left: auto;\n    right: 0;\n}";
+    assert!(rtl.contains(mirrored), "{}", rtl);
+    assert!(!ltr.contains(mirrored), "{}", ltr);
+}
@@ -0,0 +1,68 @@
+//! HMAC-SHA256 against the test vectors in RFC 4231.
+
+use weather::signing;
+
+// Key, data and the HMAC-SHA256 of the data under the key, from test cases
+// 1 to 4, 6 and 7. Case 5 only checks a truncated output.
+const VECTORS: [(&[u8], &[u8], &str); 6] = [
+    (
+        &[0x0b; 20],
+        b"Hi There",
+        "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+    ),
+    (
+        b"Jefe",
+        b"what do ya want for nothing?",
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+    ),
+    (
+        &[0xaa; 20],
+        &[0xdd; 50],
+        "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+    ),
+    (
+        &[
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+            0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+        ],
+        &[0xcd; 50],
+        "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+    ),
+    (
+        &[0xaa; 131],
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+    ),
+    (
+        &[0xaa; 131],
+        b"This is a test using a larger than block-size key and a larger than block-size data. \
+          The key needs to be hashed before being used by the HMAC algorithm.",
+        "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+    ),
+];
+
+#[test]
+fn rfc_4231() {
+    for (key, data, mac) in VECTORS {
+        assert_eq!(signing::sign(key, data), mac);
+        assert!(signing::verify(key, data, mac));
+        assert!(signing::verify(key, data, &mac.to_uppercase()));
+    }
+}
+
+#[test]
+fn wrong_signatures() {
+    let (key, data, mac) = VECTORS[1];
+    assert!(!signing::verify(
+        key,
+        b"what do ya want for something?",
+        mac
+    ));
+    assert!(!signing::verify(b"Jeff", data, mac));
+    // Cut short, not hex, an odd length, or with a multi-byte character.
+    assert!(!signing::verify(key, data, &mac[..32]));
+    assert!(!signing::verify(key, data, &mac.replace('5', "g")));
+    assert!(!signing::verify(key, data, &mac[1..]));
+    assert!(!signing::verify(key, data, &format!("é{}", &mac[2..])));
+    assert!(!signing::verify(key, data, ""));
+}
@@ -0,0 +1,25 @@
+use weather::conditions::{cloud_cover, visibility};
+
+#[test]
+fn cloud_cover_bands() {
+    assert_eq!(cloud_cover(0.0), "Clear");
+    assert_eq!(cloud_cover(25.0), "Mostly clear");
+    assert_eq!(cloud_cover(50.0), "Partly cloudy");
+    assert_eq!(cloud_cover(75.0), "Mostly cloudy");
+    assert_eq!(cloud_cover(100.0), "Overcast");
+}
+
+#[test]
+fn visibility_bands() {
+    assert_eq!(visibility(200.0), "Fog");
+    assert_eq!(visibility(2500.0), "Poor visibility");
+    assert_eq!(visibility(8000.0), "Moderate visibility");
+    assert_eq!(visibility(10_000.0), "Good visibility");
+}
+
+#[test]
+fn thresholds_belong_to_the_clearer_band() {
+    assert_eq!(visibility(1000.0), "Poor visibility");
+    assert_eq!(visibility(4000.0), "Moderate visibility");
+    assert_eq!(cloud_cover(10.0), "Clear");
+}
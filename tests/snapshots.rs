@@ -0,0 +1,186 @@
+//! Snapshot tests for the rendered pages.
+//!
+//! Each test renders the fixture forecast in `tests/fixtures` and compares it
+//! with a golden file in `tests/golden`. The tests run under Viceroy, which
+//! can't write files, so a mismatch prints the new rendering instead: check
+//! it's what you meant, then paste it over the golden file.
+
+mod common;
+
+use weather::view::{Device, Fragment, View};
+use weather_helpers::Locale;
+
+const FIXTURE: &str = include_str!("fixtures/onecall.json");
+
+fn render(view: View, locale: Locale) -> String {
+    common::render(FIXTURE, view, locale)
+}
+
+fn assert_golden(name: &str, golden: &str, actual: &str) {
+    // Editors like to add a final newline, and the page template has CRLF
+    // line endings, so compare line by line and don't count trailing whitespace.
+    if !golden.trim_end().lines().eq(actual.trim_end().lines()) {
+        panic!(
+            "rendering doesn't match tests/golden/{name}. If the change is intended, \
+             replace the golden file with:\n--- {name} ---\n{actual}\n--- end {name} ---",
+            name = name,
+            actual = actual.trim_end()
+        );
+    }
+}
+
+#[test]
+fn page() {
+    let actual = render(View::Standard, Locale::En);
+    assert_golden("page.html", include_str!("golden/page.html"), &actual);
+}
+
+#[test]
+fn mobile_page() {
+    let actual = common::render_with(
+        FIXTURE,
+        View::Standard,
+        Locale::En,
+        Device::Mobile,
+        "London",
+    );
+    assert_golden(
+        "page_mobile.html",
+        include_str!("golden/page_mobile.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn amp() {
+    let actual = render(View::Amp, Locale::En);
+    assert_golden("amp.html", include_str!("golden/amp.html"), &actual);
+}
+
+#[test]
+fn minimal_page() {
+    let actual = render(View::Minimal, Locale::En);
+    assert_golden("minimal.html", include_str!("golden/minimal.html"), &actual);
+}
+
+#[test]
+fn gemtext_page() {
+    let actual = render(View::Gemtext, Locale::En);
+    assert_golden("forecast.gmi", include_str!("golden/forecast.gmi"), &actual);
+}
+
+#[test]
+fn current_fragment() {
+    let actual = render(View::Fragment(Fragment::Current), Locale::En);
+    assert_golden("current.html", include_str!("golden/current.html"), &actual);
+}
+
+#[test]
+fn conditions_fragment() {
+    let actual = render(View::Fragment(Fragment::Conditions), Locale::En);
+    assert_golden(
+        "conditions.html",
+        include_str!("golden/conditions.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn daily_fragment() {
+    let actual = render(View::Fragment(Fragment::Daily), Locale::En);
+    assert_golden("daily.html", include_str!("golden/daily.html"), &actual);
+}
+
+#[test]
+fn localized_daily_fragment() {
+    let actual = render(View::Fragment(Fragment::Daily), Locale::De);
+    assert_golden(
+        "daily_de.html",
+        include_str!("golden/daily_de.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn digest_email() {
+    let actual = render(View::Digest, Locale::En);
+    assert_golden("digest.txt", include_str!("golden/digest.txt"), &actual);
+}
+
+// The cards, from a fixture with two days of hours and a week of days
+const CARDS: &str = include_str!("fixtures/cards.json");
+
+fn render_card(fixture: &str, fragment: Fragment) -> String {
+    common::render(fixture, View::Fragment(fragment), Locale::En)
+}
+
+#[test]
+fn chart_fragment() {
+    let actual = render_card(CARDS, Fragment::Chart);
+    assert_golden("chart.html", include_str!("golden/chart.html"), &actual);
+}
+
+#[test]
+fn wind_fragment() {
+    let actual = render_card(CARDS, Fragment::Wind);
+    assert_golden("wind.html", include_str!("golden/wind.html"), &actual);
+}
+
+#[test]
+fn precipitation_fragment() {
+    let actual = render_card(CARDS, Fragment::Precipitation);
+    assert_golden(
+        "precipitation.html",
+        include_str!("golden/precipitation.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn weekend_fragment() {
+    let actual = render_card(CARDS, Fragment::Weekend);
+    assert_golden("weekend.html", include_str!("golden/weekend.html"), &actual);
+}
+
+#[test]
+fn dayparts_fragment() {
+    let actual = render_card(CARDS, Fragment::DayParts);
+    assert_golden(
+        "dayparts.html",
+        include_str!("golden/dayparts.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn exercise_fragment() {
+    let actual = render_card(CARDS, Fragment::Exercise);
+    assert_golden(
+        "exercise.html",
+        include_str!("golden/exercise.html"),
+        &actual,
+    );
+}
+
+#[test]
+fn laundry_fragment() {
+    let actual = render_card(CARDS, Fragment::Laundry);
+    assert_golden("laundry.html", include_str!("golden/laundry.html"), &actual);
+}
+
+#[test]
+fn roads_fragment() {
+    // Below freezing with snow falling
+    let actual = render_card(include_str!("fixtures/snow.json"), Fragment::Roads);
+    assert_golden("roads.html", include_str!("golden/roads.html"), &actual);
+}
+
+#[test]
+fn confidence_fragment() {
+    let actual = render_card(CARDS, Fragment::Daily);
+    assert_golden(
+        "daily_confidence.html",
+        include_str!("golden/daily_confidence.html"),
+        &actual,
+    );
+}
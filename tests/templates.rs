@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+use weather::templates::template_drift;
+
+fn drift(source: &str, context: Value) -> Vec<String> {
+    template_drift(source, &context, &|name: &str| match name {
+        "icon" => Some(r#"<i class="{class}">{label}</i>"#),
+        _ => None,
+    })
+}
+
+#[test]
+fn a_full_context_has_no_drift() {
+    let source =
+        r#"<h1>{city}</h1>{{ if alert }}<p>{alert.title}</p>{{ endif }}{{ call icon with icon }}"#;
+    let context = json!({
+        "city": "London",
+        "alert": null,
+        "icon": { "class": "weather-icon", "label": "broken clouds" },
+    });
+    assert!(drift(source, context).is_empty());
+}
+
+#[test]
+fn missing_fields_are_named() {
+    let source = "{city} {place.country} {{ if alert }}{{ endif }}";
+    let context = json!({ "place": {} });
+    assert_eq!(
+        drift(source, context),
+        [
+            "alert is missing",
+            "city is missing",
+            "place.country is missing, at country",
+        ]
+    );
+}
+
+#[test]
+fn blank_values_are_caught_unless_guarded() {
+    let source = "{description} {uvi} {{ if heat }}{heat}{{ endif }}";
+    let context = json!({ "description": " ", "uvi": null, "heat": "" });
+    assert_eq!(
+        drift(source, context),
+        [
+            "{description} is empty, so renders blank",
+            "{uvi} is null, so renders blank",
+        ]
+    );
+}
+
+#[test]
+fn only_the_branch_taken_is_checked() {
+    let source = "{{ if not snow }}{rain}{{ else }}{snow}{{ endif }}";
+    assert_eq!(
+        drift(source, json!({ "snow": false, "rain": "" })),
+        ["{rain} is empty, so renders blank"]
+    );
+    assert!(drift(source, json!({ "snow": "2 cm" })).is_empty());
+}
+
+#[test]
+fn every_item_of_a_loop_is_checked() {
+    let source = "{{ for day in days }}{day.name}{{ if @first }}{current}{{ endif }}{{ endfor }}";
+    let context = json!({ "days": [{ "name": "Mon" }, { "name": "" }], "current": "Now" });
+    assert_eq!(
+        drift(source, context),
+        ["{day.name} is empty, so renders blank"]
+    );
+
+    let context = json!({ "days": "Mon" });
+    assert_eq!(drift(source, context), ["days isn't a list to loop over"]);
+}
+
+#[test]
+fn partials_are_checked_with_their_context() {
+    let source = "{{ call icon with icon }}{{ call missing with icon }}";
+    let context = json!({ "icon": { "class": "day-icon" } });
+    assert_eq!(
+        drift(source, context),
+        [
+            "calls missing, which isn't registered",
+            "in icon, label is missing"
+        ]
+    );
+}
+
+#[test]
+fn text_comments_and_escaped_braces_are_ignored() {
+    let source = r#"{# {unused} #}<script>if (ok) \{ go() }</script>{{ with place as here }}{here.city | url}{{ endwith }}"#;
+    let context = json!({ "place": { "city": "São Paulo" } });
+    assert!(drift(source, context).is_empty());
+}
@@ -0,0 +1,52 @@
+use weather::conditions::{safe_exposure_minutes, uv_risk};
+
+#[test]
+fn who_categories() {
+    for (uvi, risk) in [
+        (0.0, "Low"),
+        (2.9, "Low"),
+        (3.0, "Moderate"),
+        (5.9, "Moderate"),
+        (6.0, "High"),
+        (8.0, "Very high"),
+        (10.9, "Very high"),
+        (11.0, "Extreme"),
+        (14.0, "Extreme"),
+    ] {
+        assert_eq!(uv_risk(uvi), risk, "{}", uvi);
+    }
+}
+
+#[test]
+fn minutes_for_each_skin_type() {
+    let minutes: Vec<_> = (1..=6)
+        .map(|skin_type| safe_exposure_minutes(8.0, skin_type))
+        .collect();
+    assert_eq!(
+        minutes,
+        [Some(16), Some(20), Some(25), Some(37), Some(50), Some(83)]
+    );
+}
+
+#[test]
+fn higher_uv_burns_sooner() {
+    assert_eq!(safe_exposure_minutes(1.0, 2), Some(166));
+    assert_eq!(safe_exposure_minutes(4.0, 2), Some(41));
+    assert_eq!(safe_exposure_minutes(12.0, 2), Some(13));
+}
+
+#[test]
+fn no_uv_means_no_limit() {
+    // Rather than dividing by zero, or a limit too long to mean anything
+    for uvi in [0.0, 0.5, 0.99, -1.0] {
+        for skin_type in 1..=6 {
+            assert_eq!(safe_exposure_minutes(uvi, skin_type), None, "{}", uvi);
+        }
+    }
+}
+
+#[test]
+fn unknown_skin_types() {
+    assert_eq!(safe_exposure_minutes(8.0, 0), None);
+    assert_eq!(safe_exposure_minutes(8.0, 7), None);
+}
@@ -0,0 +1,339 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Weekday};
+
+mod locale;
+pub use locale::Locale;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Season {
+    Summer,
+    Autumn,
+    Winter,
+    Spring,
+}
+
+// The Feather icon for an OpenWeather condition, from its code, such as 771
+// for squalls, and its icon, such as "50d". The icons only go as far as the
+// condition's group, so the code tells mist from dust or a tornado. Where the
+// code is left out, as 0, the icon alone picks one; where the icon is
+// unknown, the code's group does, and with neither it's the sun.
+pub fn get_feather_weather_icon(condition: u32, icon: &str) -> &'static str {
+    let atmosphere = match condition {
+        701 | 721 | 741 => Some("align-center"),
+        // Smoke, volcanic ash and tornadoes are dangerous
+        711 | 762 | 781 => Some("alert-triangle"),
+        731 | 751 | 761 | 771 => Some("wind"),
+        _ => None,
+    };
+    let by_icon = || {
+        let (code, night) = parse_icon(icon)?;
+        match code {
+            1 if night => Some("moon"),
+            1 => Some("sun"),
+            2..=4 => Some("cloud"),
+            9 => Some("cloud-drizzle"),
+            10 => Some("cloud-rain"),
+            11 => Some("cloud-lightning"),
+            13 => Some("cloud-snow"),
+            50 => Some("align-center"),
+            _ => None,
+        }
+    };
+    let by_group = || match condition {
+        200..=232 => Some("cloud-lightning"),
+        300..=321 => Some("cloud-drizzle"),
+        // Freezing rain
+        511 => Some("cloud-snow"),
+        500..=531 => Some("cloud-rain"),
+        600..=622 => Some("cloud-snow"),
+        700..=799 => Some("align-center"),
+        800 => Some("sun"),
+        801..=804 => Some("cloud"),
+        _ => None,
+    };
+
+    atmosphere
+        .or_else(by_icon)
+        .or_else(by_group)
+        .unwrap_or("sun")
+}
+
+// The number of an OpenWeather icon such as "10n", and whether it's the
+// night one.
+fn parse_icon(icon: &str) -> Option<(u8, bool)> {
+    let icon = icon.trim().trim_matches('"');
+    let (code, time) = icon.split_at_checked(2)?;
+    let night = match time {
+        "d" => false,
+        "n" => true,
+        _ => return None,
+    };
+    Some((code.parse().ok()?, night))
+}
+
+// The full name of a weekday in `locale`, such as "Monday" or "Montag".
+pub fn weekday_full(weekday: Weekday, locale: Locale) -> &'static str {
+    locale.weekday_name(weekday)
+}
+
+// The wall-clock time at a location for a UTC timestamp, using the offset the
+// weather API reports for it. An out-of-range offset falls back to UTC.
+pub fn local_time(timestamp: i64, utc_offset_secs: i32) -> DateTime<FixedOffset> {
+    let offset =
+        FixedOffset::east_opt(utc_offset_secs).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    offset
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_else(|| offset.timestamp_opt(0, 0).unwrap())
+}
+
+// The short weekday name ("Mon") at a UTC timestamp, given the location's offset.
+pub fn datetime_to_day(timestamp: i64, utc_offset_secs: i32) -> String {
+    local_time(timestamp, utc_offset_secs).weekday().to_string()
+}
+
+// Get your current season depending in your location.
+// Season start dates are fixed despite varying in real life by 1 day depending on the year.
+pub fn get_season(latitude: f64, local: DateTime<FixedOffset>) -> Season {
+    let today = (local.month(), local.day());
+
+    let (northern, southern) = if ((3, 20)..(6, 21)).contains(&today) {
+        (Season::Spring, Season::Autumn)
+    } else if ((6, 21)..(9, 22)).contains(&today) {
+        (Season::Summer, Season::Winter)
+    } else if ((9, 22)..(12, 21)).contains(&today) {
+        (Season::Autumn, Season::Spring)
+    } else {
+        // Winter runs over New Year, so it's whatever isn't one of the other three.
+        (Season::Winter, Season::Summer)
+    };
+
+    if latitude.is_sign_positive() {
+        northern
+    } else {
+        southern
+    }
+}
+
+// Convert a temperature reported in OpenWeatherMap `units` to °C.
+pub fn to_celsius(temp: f32, units: &str) -> f32 {
+    match units {
+        "imperial" => (temp - 32.0) * 5.0 / 9.0,
+        "standard" => temp - 273.15,
+        _ => temp,
+    }
+}
+
+// Convert a temperature in °C to OpenWeatherMap `units`.
+pub fn from_celsius(temp: f32, units: &str) -> f32 {
+    match units {
+        "imperial" => temp * 9.0 / 5.0 + 32.0,
+        "standard" => temp + 273.15,
+        _ => temp,
+    }
+}
+
+// Whether an OpenWeatherMap condition is snow, sleet included, going by its
+// code in the 600s or, where the code is left out, its 13d or 13n icon.
+pub fn is_snow(condition: u32, icon: &str) -> bool {
+    if condition == 0 {
+        icon.starts_with("13")
+    } else {
+        (600..700).contains(&condition)
+    }
+}
+
+// Round a reading to `decimals` places, halves away from zero, the way
+// people read it: 19.5 is 20 and -0.5 is -1, where casting to an integer
+// would cut 19.9 to 19 and -0.9 to 0. Readings are decimals in the API's
+// JSON, so rounding the shortest decimal form of the `f32` keeps 14.45 from
+// going down as the 14.4499998 it's stored as. Anything that rounds to zero
+// is plain 0, never -0.
+pub fn round_to(value: f32, decimals: usize) -> f64 {
+    let value: f64 = value.to_string().parse().unwrap_or_default();
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale + 0.0
+}
+
+// A temperature to the nearest whole degree.
+pub fn round_temperature(temp: f32) -> i32 {
+    round_to(temp, 0) as i32
+}
+
+// The heat index in °C, how hot it feels once humidity slows sweating, from
+// the temperature in °C and relative humidity in %. This is the NWS's:
+// Steadman's simple formula when that comes out under 80°F, otherwise the
+// Rothfusz regression with its adjustments for very dry and very humid air.
+pub fn heat_index(temp: f32, humidity: f32) -> f32 {
+    let t = temp * 9.0 / 5.0 + 32.0;
+    let rh = humidity.clamp(0.0, 100.0);
+
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let hi = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += (rh - 85.0) / 10.0 * ((87.0 - t) / 5.0);
+        }
+        hi
+    };
+    (hi - 32.0) * 5.0 / 9.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeatRisk {
+    Caution,
+    ExtremeCaution,
+    Danger,
+    ExtremeDanger,
+}
+
+impl HeatRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HeatRisk::Caution => "Caution",
+            HeatRisk::ExtremeCaution => "Extreme caution",
+            HeatRisk::Danger => "Danger",
+            HeatRisk::ExtremeDanger => "Extreme danger",
+        }
+    }
+
+    // What the heat can do to someone out in it, and what to do about it
+    pub fn advice(&self) -> &'static str {
+        match self {
+            HeatRisk::Caution => "Fatigue is possible with long exposure. Drink water regularly.",
+            HeatRisk::ExtremeCaution => {
+                "Heat cramps and exhaustion are possible. Drink plenty of water and rest in the shade."
+            }
+            HeatRisk::Danger => {
+                "Heat exhaustion is likely. Limit time outdoors and drink water even if you're not thirsty."
+            }
+            HeatRisk::ExtremeDanger => "Heat stroke is highly likely. Stay indoors somewhere cool.",
+        }
+    }
+}
+
+// The NWS heat index band for a heat index in °C: from 80°F (26.7°C)
+// caution, 90°F (32.2°C) extreme caution, 103°F (39.4°C) danger and 125°F
+// (51.7°C) extreme danger. `None` below 80°F.
+pub fn heat_risk(heat_index: f32) -> Option<HeatRisk> {
+    let f = heat_index * 9.0 / 5.0 + 32.0;
+    if f >= 125.0 {
+        Some(HeatRisk::ExtremeDanger)
+    } else if f >= 103.0 {
+        Some(HeatRisk::Danger)
+    } else if f >= 90.0 {
+        Some(HeatRisk::ExtremeCaution)
+    } else if f >= 80.0 {
+        Some(HeatRisk::Caution)
+    } else {
+        None
+    }
+}
+
+pub struct SolarWindow {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+// Sun times for a day. Anything the sun doesn't do that day, such as setting
+// in a polar summer, is `None`.
+pub struct SolarEvents {
+    pub solar_noon: DateTime<FixedOffset>,
+    pub sunrise: Option<DateTime<FixedOffset>>,
+    pub sunset: Option<DateTime<FixedOffset>>,
+    pub morning_blue_hour: Option<SolarWindow>,
+    pub morning_golden_hour: Option<SolarWindow>,
+    pub evening_golden_hour: Option<SolarWindow>,
+    pub evening_blue_hour: Option<SolarWindow>,
+}
+
+// Solar noon, sunrise, sunset and the photographers' golden and blue hours
+// for `date` at a location, using the sunrise equation. The times are within
+// a minute or two, which is plenty for planning a shoot.
+pub fn solar_events(lat: f64, lon: f64, date: NaiveDate, tz: FixedOffset) -> SolarEvents {
+    const J2000: f64 = 2_451_545.0;
+    const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    let days = date.signed_duration_since(epoch).num_days() as f64;
+
+    // Mean solar time at the location, in days since J2000.
+    let n = (UNIX_EPOCH_JD + days + 0.5 - J2000).round();
+    let mean_time = n - lon / 360.0;
+
+    let anomaly = (357.5291 + 0.985_600_28 * mean_time)
+        .rem_euclid(360.0)
+        .to_radians();
+    let center =
+        1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = J2000 + mean_time + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * longitude).sin();
+    let declination = (longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+
+    let to_time = |julian: f64| {
+        let secs = ((julian - UNIX_EPOCH_JD) * 86_400.0).round() as i64;
+        tz.timestamp_opt(secs, 0).unwrap()
+    };
+    // Hour angle, in days, at which the sun's centre is at `elevation` degrees.
+    let latitude = lat.to_radians();
+    let hour_angle = |elevation: f64| {
+        let cos = (elevation.to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        (-1.0..=1.0)
+            .contains(&cos)
+            .then(|| cos.acos().to_degrees() / 360.0)
+    };
+    let morning = |elevation: f64| hour_angle(elevation).map(|angle| to_time(transit - angle));
+    let evening = |elevation: f64| hour_angle(elevation).map(|angle| to_time(transit + angle));
+    let window = |start: Option<DateTime<FixedOffset>>, end: Option<DateTime<FixedOffset>>| {
+        Some(SolarWindow {
+            start: start?,
+            end: end?,
+        })
+    };
+
+    // Sunrise and sunset allow for refraction and the size of the sun's disc.
+    SolarEvents {
+        solar_noon: to_time(transit),
+        sunrise: morning(-0.833),
+        sunset: evening(-0.833),
+        morning_blue_hour: window(morning(-6.0), morning(-4.0)),
+        morning_golden_hour: window(morning(-4.0), morning(6.0)),
+        evening_golden_hour: window(evening(6.0), evening(-4.0)),
+        evening_blue_hour: window(evening(-4.0), evening(-6.0)),
+    }
+}
+
+// Name the phase of the moon, given as a fraction of the lunar cycle where
+// 0 and 1 are new moon and 0.5 is full moon.
+pub fn moon_phase_name(phase: f32) -> &'static str {
+    let phase = phase.rem_euclid(1.0);
+    if phase < 0.03 || phase > 0.97 {
+        "New moon"
+    } else if phase < 0.22 {
+        "Waxing crescent"
+    } else if phase < 0.28 {
+        "First quarter"
+    } else if phase < 0.47 {
+        "Waxing gibbous"
+    } else if phase < 0.53 {
+        "Full moon"
+    } else if phase < 0.72 {
+        "Waning gibbous"
+    } else if phase < 0.78 {
+        "Last quarter"
+    } else {
+        "Waning crescent"
+    }
+}
@@ -0,0 +1,437 @@
+use chrono::Weekday;
+
+// Languages the page can be rendered in. English is the fallback.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Locale {
+    En,
+    Ar,
+    De,
+    Es,
+    Fa,
+    Fr,
+    He,
+    It,
+    Nl,
+    Pt,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 10] = [
+        Locale::En,
+        Locale::Ar,
+        Locale::De,
+        Locale::Es,
+        Locale::Fa,
+        Locale::Fr,
+        Locale::He,
+        Locale::It,
+        Locale::Nl,
+        Locale::Pt,
+    ];
+
+    // The BCP 47 language tag, for `<html lang>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ar => "ar",
+            Locale::De => "de",
+            Locale::Es => "es",
+            Locale::Fa => "fa",
+            Locale::Fr => "fr",
+            Locale::He => "he",
+            Locale::It => "it",
+            Locale::Nl => "nl",
+            Locale::Pt => "pt",
+        }
+    }
+
+    // Whether the language is written right to left.
+    pub fn is_rtl(&self) -> bool {
+        matches!(self, Locale::Ar | Locale::Fa | Locale::He)
+    }
+
+    pub fn from_code(code: &str) -> Option<Locale> {
+        let primary = code.split(['-', '_']).next().unwrap_or_default();
+        // `iw` is the withdrawn code for Hebrew, still sent by some browsers.
+        if primary.eq_ignore_ascii_case("iw") {
+            return Some(Locale::He);
+        }
+        Locale::ALL
+            .iter()
+            .copied()
+            .find(|locale| locale.code().eq_ignore_ascii_case(primary))
+    }
+
+    // The main language of a country, from its ISO 3166-1 alpha-2 code.
+    // Multilingual countries get whichever language most people read.
+    pub fn for_country(country_code: &str) -> Locale {
+        match country_code.to_ascii_uppercase().as_str() {
+            "DE" | "AT" | "CH" | "LI" => Locale::De,
+            "ES" | "MX" | "AR" | "CO" | "CL" | "PE" | "VE" | "EC" | "GT" | "CU" | "BO" | "DO"
+            | "HN" | "PY" | "SV" | "NI" | "CR" | "PA" | "UY" => Locale::Es,
+            "FR" | "BE" | "LU" | "MC" | "SN" | "CI" => Locale::Fr,
+            "IT" | "SM" | "VA" => Locale::It,
+            "NL" | "SR" => Locale::Nl,
+            "PT" | "BR" | "AO" | "MZ" => Locale::Pt,
+            "SA" | "AE" | "EG" | "IQ" | "JO" | "KW" | "LB" | "LY" | "MA" | "DZ" | "TN" | "OM"
+            | "QA" | "BH" | "SY" | "YE" | "SD" | "PS" => Locale::Ar,
+            "IL" => Locale::He,
+            "IR" | "AF" | "TJ" => Locale::Fa,
+            _ => Locale::En,
+        }
+    }
+
+    // Pick a locale from an `Accept-Language` header, preferring higher
+    // quality values, then fall back to the visitor's country.
+    pub fn negotiate(accept_language: Option<&str>, country_code: &str) -> Locale {
+        let mut ranges: Vec<(f32, Locale)> = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.trim().split(';');
+                let locale = Locale::from_code(parts.next()?.trim())?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((quality, locale))
+            })
+            .collect();
+        // A stable sort keeps the header's order for equal qualities.
+        ranges.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        match ranges.first() {
+            Some((_, locale)) => *locale,
+            None => Locale::for_country(country_code),
+        }
+    }
+
+    pub fn weekday_name(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::En => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Locale::Ar => [
+                "الاثنين",
+                "الثلاثاء",
+                "الأربعاء",
+                "الخميس",
+                "الجمعة",
+                "السبت",
+                "الأحد",
+            ],
+            Locale::Fa => [
+                "دوشنبه",
+                "سه‌شنبه",
+                "چهارشنبه",
+                "پنجشنبه",
+                "جمعه",
+                "شنبه",
+                "یکشنبه",
+            ],
+            Locale::He => [
+                "יום שני",
+                "יום שלישי",
+                "יום רביעי",
+                "יום חמישי",
+                "יום שישי",
+                "שבת",
+                "יום ראשון",
+            ],
+            Locale::De => [
+                "Montag",
+                "Dienstag",
+                "Mittwoch",
+                "Donnerstag",
+                "Freitag",
+                "Samstag",
+                "Sonntag",
+            ],
+            Locale::Es => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+            Locale::Fr => [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+            Locale::It => [
+                "lunedì",
+                "martedì",
+                "mercoledì",
+                "giovedì",
+                "venerdì",
+                "sabato",
+                "domenica",
+            ],
+            Locale::Nl => [
+                "maandag",
+                "dinsdag",
+                "woensdag",
+                "donderdag",
+                "vrijdag",
+                "zaterdag",
+                "zondag",
+            ],
+            Locale::Pt => [
+                "segunda-feira",
+                "terça-feira",
+                "quarta-feira",
+                "quinta-feira",
+                "sexta-feira",
+                "sábado",
+                "domingo",
+            ],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    pub fn weekday_short(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::En => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            // Arabic and Persian don't abbreviate day names.
+            Locale::Ar | Locale::Fa => return self.weekday_name(weekday),
+            Locale::He => ["ב׳", "ג׳", "ד׳", "ה׳", "ו׳", "ש׳", "א׳"],
+            Locale::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+            Locale::Es => ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+            Locale::Fr => ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+            Locale::It => ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+            Locale::Nl => ["ma", "di", "wo", "do", "vr", "za", "zo"],
+            Locale::Pt => ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    // `month` is 1 to 12, as from `chrono::Datelike::month`.
+    pub fn month_name(&self, month: u32) -> &'static str {
+        let names = match self {
+            Locale::En => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Locale::Ar => [
+                "يناير",
+                "فبراير",
+                "مارس",
+                "أبريل",
+                "مايو",
+                "يونيو",
+                "يوليو",
+                "أغسطس",
+                "سبتمبر",
+                "أكتوبر",
+                "نوفمبر",
+                "ديسمبر",
+            ],
+            Locale::Fa => [
+                "ژانویه",
+                "فوریه",
+                "مارس",
+                "آوریل",
+                "مه",
+                "ژوئن",
+                "ژوئیه",
+                "اوت",
+                "سپتامبر",
+                "اکتبر",
+                "نوامبر",
+                "دسامبر",
+            ],
+            Locale::He => [
+                "ינואר",
+                "פברואר",
+                "מרץ",
+                "אפריל",
+                "מאי",
+                "יוני",
+                "יולי",
+                "אוגוסט",
+                "ספטמבר",
+                "אוקטובר",
+                "נובמבר",
+                "דצמבר",
+            ],
+            Locale::De => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Locale::Es => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            Locale::Fr => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Locale::It => [
+                "gennaio",
+                "febbraio",
+                "marzo",
+                "aprile",
+                "maggio",
+                "giugno",
+                "luglio",
+                "agosto",
+                "settembre",
+                "ottobre",
+                "novembre",
+                "dicembre",
+            ],
+            Locale::Nl => [
+                "januari",
+                "februari",
+                "maart",
+                "april",
+                "mei",
+                "juni",
+                "juli",
+                "augustus",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+            Locale::Pt => [
+                "janeiro",
+                "fevereiro",
+                "março",
+                "abril",
+                "maio",
+                "junho",
+                "julho",
+                "agosto",
+                "setembro",
+                "outubro",
+                "novembro",
+                "dezembro",
+            ],
+        };
+        names[(month.clamp(1, 12) - 1) as usize]
+    }
+
+    // A long date in the locale's usual order, such as "14 October 2026".
+    pub fn format_date(&self, day: u32, month: u32, year: i32) -> String {
+        let month = self.month_name(month);
+        match self {
+            Locale::De => format!("{}. {} {}", day, month, year),
+            Locale::Es | Locale::Pt => format!("{} de {} de {}", day, month, year),
+            Locale::He => format!("{} ב{} {}", day, month, year),
+            _ => format!("{} {} {}", day, month, year),
+        }
+    }
+
+    // A number rounded to `decimals` places, as `round_to` does,
+    // with the locale's decimal separator, such as "23,5" or "74.3".
+    pub fn format_number(&self, value: f32, decimals: usize) -> String {
+        let number = format!("{:.*}", decimals, crate::round_to(value, decimals));
+        match self {
+            Locale::De | Locale::Es | Locale::Fr | Locale::It | Locale::Nl | Locale::Pt => {
+                number.replace('.', ",")
+            }
+            _ => number,
+        }
+    }
+
+    // A temperature in OpenWeatherMap `units`, with its unit as the locale
+    // writes it: "74.3°F" in English, "23,5 °C" in most of Europe. Kelvin
+    // never takes the degree sign.
+    pub fn format_temperature(&self, value: f32, decimals: usize, units: &str) -> String {
+        let unit = match units {
+            "imperial" => "°F",
+            "standard" => "K",
+            _ => "°C",
+        };
+        let spaced = units == "standard"
+            || matches!(
+                self,
+                Locale::De | Locale::Es | Locale::Fr | Locale::It | Locale::Nl | Locale::Pt
+            );
+        let separator = if spaced { "\u{a0}" } else { "" };
+        format!(
+            "{}{}{}",
+            self.format_number(value, decimals),
+            separator,
+            unit
+        )
+    }
+
+    // The two days off at the end of the locale's usual week. Where Arabic
+    // and Hebrew are spoken the week starts on Sunday, so the weekend is
+    // Friday and Saturday; in Iran it's Thursday and Friday.
+    pub fn weekend(&self) -> [Weekday; 2] {
+        match self {
+            Locale::Ar | Locale::He => [Weekday::Fri, Weekday::Sat],
+            Locale::Fa => [Weekday::Thu, Weekday::Fri],
+            _ => [Weekday::Sat, Weekday::Sun],
+        }
+    }
+
+    // An hour of the day on the clock the locale reads, such as "3pm" or
+    // "15 Uhr".
+    pub fn format_hour(&self, hour: u32) -> String {
+        let hour = hour % 24;
+        match self {
+            Locale::En => {
+                let suffix = if hour < 12 { "am" } else { "pm" };
+                let twelve = match hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                };
+                format!("{}{}", twelve, suffix)
+            }
+            Locale::De => format!("{} Uhr", hour),
+            Locale::Fr => format!("{} h", hour),
+            _ => format!("{:02}:00", hour),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::{Datelike, TimeZone, Timelike, Utc, Weekday};
+use weather_helpers::{datetime_to_day, get_season, local_time, Locale, Season};
+
+const LONDON: f64 = 51.5;
+const SYDNEY: f64 = -33.9;
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> i64 {
+    Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+        .unwrap()
+        .timestamp()
+}
+
+#[test]
+fn local_time_applies_the_offset() {
+    let local = local_time(utc(2026, 10, 14, 12, 0), -4 * 3600);
+    assert_eq!(local.hour(), 8);
+    assert_eq!(local.offset().local_minus_utc(), -4 * 3600);
+}
+
+#[test]
+fn out_of_range_offset_falls_back_to_utc() {
+    let local = local_time(utc(2026, 10, 14, 12, 0), 100_000);
+    assert_eq!(local.offset().local_minus_utc(), 0);
+    assert_eq!(local.hour(), 12);
+}
+
+#[test]
+fn summer_time_moves_late_evening_into_tomorrow() {
+    // 23:30 UTC on Saturday 27 June is already Sunday under BST.
+    let instant = utc(2026, 6, 27, 23, 30);
+    assert_eq!(datetime_to_day(instant, 0), "Sat");
+    assert_eq!(datetime_to_day(instant, 3600), "Sun");
+}
+
+#[test]
+fn clocks_going_forward_keep_the_same_day() {
+    // UK clocks go forward at 01:00 UTC on 29 March 2026. Either side of the
+    // change, the API's offset gives a Sunday wall clock.
+    let before = local_time(utc(2026, 3, 29, 0, 30), 0);
+    let after = local_time(utc(2026, 3, 29, 1, 30), 3600);
+    assert_eq!((before.weekday(), before.hour()), (Weekday::Sun, 0));
+    assert_eq!((after.weekday(), after.hour()), (Weekday::Sun, 2));
+}
+
+#[test]
+fn clocks_going_back_repeat_the_hour() {
+    // US clocks go back at 06:00 UTC on 1 November 2026 in New York, so
+    // 01:30 happens twice, once in each offset.
+    let daylight = local_time(utc(2026, 11, 1, 5, 30), -4 * 3600);
+    let standard = local_time(utc(2026, 11, 1, 6, 30), -5 * 3600);
+    assert_eq!(daylight.hour(), 1);
+    assert_eq!(standard.hour(), 1);
+    assert_eq!(daylight.weekday(), standard.weekday());
+}
+
+#[test]
+fn dateline_neighbours_are_a_day_apart() {
+    // Kiritimati (UTC+14) and Niue (UTC-11) are 25 hours apart, so the same
+    // instant can fall on dates two days apart.
+    let instant = utc(2026, 10, 14, 10, 30);
+    let kiritimati = local_time(instant, 14 * 3600);
+    let niue = local_time(instant, -11 * 3600);
+    assert_eq!(kiritimati.day(), 15);
+    assert_eq!(niue.day(), 13);
+    assert_eq!(datetime_to_day(instant, 14 * 3600), "Thu");
+    assert_eq!(datetime_to_day(instant, -11 * 3600), "Tue");
+}
+
+#[test]
+fn day_names_cover_the_whole_week() {
+    // Monday 12 October 2026 at noon UTC, then a day at a time.
+    let monday = utc(2026, 10, 12, 12, 0);
+    let days: Vec<String> = (0..7)
+        .map(|day| datetime_to_day(monday + day * 86_400, 0))
+        .collect();
+    assert_eq!(days, ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]);
+}
+
+#[test]
+fn sunday_midnight_starts_monday() {
+    let midnight = utc(2026, 10, 19, 0, 0);
+    assert_eq!(datetime_to_day(midnight - 1, 0), "Sun");
+    assert_eq!(datetime_to_day(midnight, 0), "Mon");
+}
+
+#[test]
+fn offset_moves_the_week_boundary() {
+    // Midnight UTC between Sunday and Monday is still Sunday evening in
+    // New York and already Monday morning in Tokyo.
+    let midnight = utc(2026, 10, 19, 0, 0);
+    assert_eq!(datetime_to_day(midnight, -4 * 3600), "Sun");
+    assert_eq!(datetime_to_day(midnight, 9 * 3600), "Mon");
+    // And the Tokyo week starts nine hours before the UTC one.
+    assert_eq!(datetime_to_day(midnight - 9 * 3600, 9 * 3600), "Mon");
+    assert_eq!(datetime_to_day(midnight - 9 * 3600 - 1, 9 * 3600), "Sun");
+}
+
+#[test]
+fn seasons_are_mirrored_across_the_equator() {
+    let july = local_time(utc(2026, 7, 15, 12, 0), 0);
+    assert_eq!(get_season(LONDON, july), Season::Summer);
+    assert_eq!(get_season(SYDNEY, july), Season::Winter);
+
+    let october = local_time(utc(2026, 10, 14, 12, 0), 0);
+    assert_eq!(get_season(LONDON, october), Season::Autumn);
+    assert_eq!(get_season(SYDNEY, october), Season::Spring);
+}
+
+#[test]
+fn winter_spans_new_year() {
+    let new_year = local_time(utc(2027, 1, 1, 12, 0), 0);
+    assert_eq!(get_season(LONDON, new_year), Season::Winter);
+    assert_eq!(get_season(SYDNEY, new_year), Season::Summer);
+
+    let christmas = local_time(utc(2026, 12, 25, 12, 0), 0);
+    assert_eq!(get_season(LONDON, christmas), Season::Winter);
+}
+
+#[test]
+fn seasons_change_on_the_local_date() {
+    // 14:30 UTC on 19 March is already the 20th in Auckland (UTC+13).
+    let instant = utc(2026, 3, 19, 14, 30);
+    assert_eq!(get_season(LONDON, local_time(instant, 0)), Season::Winter);
+    assert_eq!(
+        get_season(-36.8, local_time(instant, 13 * 3600)),
+        Season::Autumn
+    );
+}
+
+#[test]
+fn hours_read_the_way_each_locale_tells_time() {
+    assert_eq!(Locale::En.format_hour(0), "12am");
+    assert_eq!(Locale::En.format_hour(12), "12pm");
+    assert_eq!(Locale::En.format_hour(15), "3pm");
+    assert_eq!(Locale::De.format_hour(15), "15 Uhr");
+    assert_eq!(Locale::Fr.format_hour(9), "9 h");
+    assert_eq!(Locale::It.format_hour(7), "07:00");
+}
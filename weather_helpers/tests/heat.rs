@@ -0,0 +1,41 @@
+use weather_helpers::{heat_index, heat_risk, HeatRisk};
+
+fn fahrenheit(temp: f32) -> f32 {
+    (temp - 32.0) * 5.0 / 9.0
+}
+
+fn heat_index_f(temp: f32, humidity: f32) -> f32 {
+    heat_index(fahrenheit(temp), humidity) * 9.0 / 5.0 + 32.0
+}
+
+#[test]
+fn heat_index_matches_the_nws_table() {
+    // Readings from the NWS heat index chart, which rounds to whole degrees
+    assert!((heat_index_f(90.0, 60.0) - 100.0).abs() < 1.0);
+    assert!((heat_index_f(96.0, 50.0) - 108.0).abs() < 1.0);
+    assert!((heat_index_f(86.0, 90.0) - 105.0).abs() < 1.0);
+    assert!((heat_index_f(104.0, 40.0) - 119.0).abs() < 1.0);
+}
+
+#[test]
+fn mild_air_feels_like_itself() {
+    // Below 80°F the simple formula stays within a degree or two
+    assert!((heat_index_f(70.0, 50.0) - 70.0).abs() < 2.0);
+    assert_eq!(heat_risk(heat_index(20.0, 90.0)), None);
+}
+
+#[test]
+fn bands_start_at_their_thresholds() {
+    assert_eq!(heat_risk(fahrenheit(79.9)), None);
+    assert_eq!(heat_risk(fahrenheit(80.0)), Some(HeatRisk::Caution));
+    assert_eq!(heat_risk(fahrenheit(90.0)), Some(HeatRisk::ExtremeCaution));
+    assert_eq!(heat_risk(fahrenheit(103.0)), Some(HeatRisk::Danger));
+    assert_eq!(heat_risk(fahrenheit(125.0)), Some(HeatRisk::ExtremeDanger));
+}
+
+#[test]
+fn humidity_raises_the_risk() {
+    // 32°C is caution in dry air and danger in humid air
+    assert_eq!(heat_risk(heat_index(32.0, 20.0)), Some(HeatRisk::Caution));
+    assert_eq!(heat_risk(heat_index(32.0, 80.0)), Some(HeatRisk::Danger));
+}
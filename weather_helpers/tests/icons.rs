@@ -0,0 +1,162 @@
+use weather_helpers::get_feather_weather_icon;
+
+// The Feather icons the page draws from
+const SPRITE: &str = include_str!("../../src/static/icons.svg");
+
+// Every template that draws an icon from the sprite
+const TEMPLATES: [&str; 19] = [
+    include_str!("../../src/static/404.html"),
+    include_str!("../../src/static/index.html"),
+    include_str!("../../src/static/metar.html"),
+    include_str!("../../src/static/mobile.html"),
+    include_str!("../../src/static/picker.html"),
+    include_str!("../../src/static/privacy.html"),
+    include_str!("../../src/static/share.html"),
+    include_str!("../../src/static/snow.html"),
+    include_str!("../../src/static/unavailable.html"),
+    include_str!("../../src/static/fragments/astronomy.html"),
+    include_str!("../../src/static/fragments/current.html"),
+    include_str!("../../src/static/fragments/exercise.html"),
+    include_str!("../../src/static/fragments/fire.html"),
+    include_str!("../../src/static/fragments/garden.html"),
+    include_str!("../../src/static/fragments/laundry.html"),
+    include_str!("../../src/static/fragments/marine.html"),
+    include_str!("../../src/static/fragments/radar.html"),
+    include_str!("../../src/static/fragments/roads.html"),
+    include_str!("../../src/static/weather_icon.html"),
+];
+
+// Every OpenWeather condition code, with the day icon it comes with
+const CONDITIONS: [(u32, &str); 55] = [
+    (200, "11d"),
+    (201, "11d"),
+    (202, "11d"),
+    (210, "11d"),
+    (211, "11d"),
+    (212, "11d"),
+    (221, "11d"),
+    (230, "11d"),
+    (231, "11d"),
+    (232, "11d"),
+    (300, "09d"),
+    (301, "09d"),
+    (302, "09d"),
+    (310, "09d"),
+    (311, "09d"),
+    (312, "09d"),
+    (313, "09d"),
+    (314, "09d"),
+    (321, "09d"),
+    (500, "10d"),
+    (501, "10d"),
+    (502, "10d"),
+    (503, "10d"),
+    (504, "10d"),
+    (511, "13d"),
+    (520, "09d"),
+    (521, "09d"),
+    (522, "09d"),
+    (531, "09d"),
+    (600, "13d"),
+    (601, "13d"),
+    (602, "13d"),
+    (611, "13d"),
+    (612, "13d"),
+    (613, "13d"),
+    (615, "13d"),
+    (616, "13d"),
+    (620, "13d"),
+    (621, "13d"),
+    (622, "13d"),
+    (701, "50d"),
+    (711, "50d"),
+    (721, "50d"),
+    (731, "50d"),
+    (741, "50d"),
+    (751, "50d"),
+    (761, "50d"),
+    (762, "50d"),
+    (771, "50d"),
+    (781, "50d"),
+    (800, "01d"),
+    (801, "02d"),
+    (802, "03d"),
+    (803, "04d"),
+    (804, "04d"),
+];
+
+fn is_feather_icon(name: &str) -> bool {
+    SPRITE.contains(&format!("<symbol id=\"{}\"", name))
+}
+
+#[test]
+fn every_condition_maps_to_a_feather_icon() {
+    for (condition, icon) in CONDITIONS {
+        for icon in [icon.to_string(), icon.replace('d', "n")] {
+            let feather = get_feather_weather_icon(condition, &icon);
+            assert!(
+                is_feather_icon(feather),
+                "{} ({}) maps to {}, which isn't a Feather icon",
+                condition,
+                icon,
+                feather
+            );
+        }
+    }
+}
+
+#[test]
+fn every_icon_the_templates_draw_is_in_the_sprite() {
+    for template in TEMPLATES {
+        let mut uses = template.split("/icons.svg#").skip(1).peekable();
+        assert!(uses.peek().is_some(), "a template draws no icons");
+        for usage in uses {
+            let href = &usage[..usage.find('"').unwrap()];
+            // Names picked in the template, or filled in from its context
+            let names: Vec<&str> = if href.starts_with("{{") {
+                href.split("}}")
+                    .map(|part| part.split("{{").next().unwrap())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            } else if href.starts_with('{') {
+                continue;
+            } else {
+                vec![href]
+            };
+            for name in names {
+                assert!(is_feather_icon(name), "{} isn't in the sprite", name);
+            }
+        }
+    }
+}
+
+#[test]
+fn clear_nights_show_the_moon() {
+    assert_eq!(get_feather_weather_icon(800, "01d"), "sun");
+    assert_eq!(get_feather_weather_icon(800, "01n"), "moon");
+    assert_eq!(get_feather_weather_icon(802, "03n"), "cloud");
+}
+
+#[test]
+fn atmosphere_codes_tell_the_icons_apart() {
+    assert_eq!(get_feather_weather_icon(701, "50d"), "align-center");
+    assert_eq!(get_feather_weather_icon(711, "50d"), "alert-triangle");
+    assert_eq!(get_feather_weather_icon(761, "50n"), "wind");
+    assert_eq!(get_feather_weather_icon(771, "50d"), "wind");
+    assert_eq!(get_feather_weather_icon(781, "50d"), "alert-triangle");
+}
+
+#[test]
+fn missing_codes_fall_back_to_the_icon() {
+    assert_eq!(get_feather_weather_icon(0, "10d"), "cloud-rain");
+    assert_eq!(get_feather_weather_icon(0, "\"13n\""), "cloud-snow");
+    assert_eq!(get_feather_weather_icon(0, "50d"), "align-center");
+}
+
+#[test]
+fn unknown_icons_fall_back_to_the_group() {
+    assert_eq!(get_feather_weather_icon(502, ""), "cloud-rain");
+    assert_eq!(get_feather_weather_icon(621, "99x"), "cloud-snow");
+    assert_eq!(get_feather_weather_icon(0, ""), "sun");
+    assert_eq!(get_feather_weather_icon(999, "nonsense"), "sun");
+}
@@ -0,0 +1,77 @@
+use weather_helpers::Locale;
+
+fn negotiate(accept_language: &str) -> Locale {
+    Locale::negotiate(Some(accept_language), "GB")
+}
+
+#[test]
+fn highest_quality_wins() {
+    assert_eq!(negotiate("fr;q=0.5, de;q=0.9, en;q=0.1"), Locale::De);
+    assert_eq!(negotiate("it, fr;q=0.9"), Locale::It);
+    // Equal qualities keep the header's order.
+    assert_eq!(negotiate("nl;q=0.8, es;q=0.8"), Locale::Nl);
+    assert_eq!(negotiate("pt-BR,pt;q=0.9,en-US;q=0.8,en;q=0.7"), Locale::Pt);
+}
+
+#[test]
+fn regional_variants_use_their_language() {
+    assert_eq!(negotiate("de-AT"), Locale::De);
+    assert_eq!(negotiate("es_MX"), Locale::Es);
+    assert_eq!(negotiate("FR-ca"), Locale::Fr);
+    assert_eq!(negotiate("iw-IL"), Locale::He);
+}
+
+#[test]
+fn unsupported_languages_are_skipped() {
+    assert_eq!(negotiate("ja, zh;q=0.9, fr;q=0.1"), Locale::Fr);
+    // Refused languages don't count at all.
+    assert_eq!(negotiate("de;q=0, es;q=0.2"), Locale::Es);
+}
+
+#[test]
+fn falls_back_to_the_country() {
+    assert_eq!(Locale::negotiate(Some("ja, zh-CN;q=0.9"), "AT"), Locale::De);
+    assert_eq!(Locale::negotiate(Some("*"), "BR"), Locale::Pt);
+    assert_eq!(Locale::negotiate(Some("de;q=0"), "GB"), Locale::En);
+    assert_eq!(Locale::negotiate(Some(""), "IL"), Locale::He);
+    assert_eq!(Locale::negotiate(None, "MX"), Locale::Es);
+    assert_eq!(Locale::negotiate(None, "??"), Locale::En);
+}
+
+#[test]
+fn wildcards_rank_below_named_languages() {
+    assert_eq!(negotiate("*, it;q=0.5"), Locale::It);
+    assert_eq!(Locale::negotiate(Some("*;q=0.9"), "NL"), Locale::Nl);
+}
+
+#[test]
+fn malformed_quality_counts_as_full() {
+    assert_eq!(negotiate("es;q=0.5, fr;q=high"), Locale::Fr);
+    assert_eq!(negotiate(",, ;q=1, de"), Locale::De);
+}
+
+#[test]
+fn dates_in_each_order() {
+    assert_eq!(Locale::En.format_date(14, 10, 2026), "14 October 2026");
+    assert_eq!(Locale::De.format_date(1, 3, 2026), "1. März 2026");
+    assert_eq!(
+        Locale::Es.format_date(14, 10, 2026),
+        "14 de octubre de 2026"
+    );
+    assert_eq!(Locale::Pt.format_date(2, 1, 2027), "2 de janeiro de 2027");
+    assert_eq!(Locale::Fr.format_date(14, 7, 2026), "14 juillet 2026");
+    assert_eq!(Locale::He.format_date(14, 10, 2026), "14 באוקטובר 2026");
+}
+
+#[test]
+fn every_locale_names_every_month() {
+    for locale in Locale::ALL {
+        for month in 1..=12 {
+            let date = locale.format_date(1, month, 2026);
+            assert!(date.starts_with('1') && date.ends_with("2026"), "{}", date);
+        }
+    }
+    // Months out of range are clamped rather than panicking.
+    assert_eq!(Locale::En.format_date(1, 0, 2026), "1 January 2026");
+    assert_eq!(Locale::En.format_date(1, 13, 2026), "1 December 2026");
+}
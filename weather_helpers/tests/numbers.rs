@@ -0,0 +1,59 @@
+use weather_helpers::Locale;
+
+#[test]
+fn decimal_separators_follow_the_locale() {
+    assert_eq!(Locale::En.format_number(74.26, 1), "74.3");
+    assert_eq!(Locale::Fr.format_number(23.5, 1), "23,5");
+    assert_eq!(Locale::De.format_number(1.94, 1), "1,9");
+    assert_eq!(Locale::He.format_number(23.5, 1), "23.5");
+}
+
+#[test]
+fn whole_numbers_are_rounded_not_truncated() {
+    assert_eq!(Locale::En.format_number(13.6, 0), "14");
+    assert_eq!(Locale::En.format_number(-2.6, 0), "-3");
+}
+
+#[test]
+fn rounding_to_zero_drops_the_sign() {
+    assert_eq!(Locale::En.format_number(-0.3, 0), "0");
+    assert_eq!(Locale::Fr.format_number(-0.04, 1), "0,0");
+}
+
+#[test]
+fn temperatures_take_the_locale_s_unit_spacing() {
+    assert_eq!(Locale::En.format_temperature(74.3, 1, "imperial"), "74.3°F");
+    assert_eq!(
+        Locale::Fr.format_temperature(23.5, 1, "metric"),
+        "23,5\u{a0}°C"
+    );
+    assert_eq!(
+        Locale::En.format_temperature(286.8, 0, "standard"),
+        "287\u{a0}K"
+    );
+}
+
+#[test]
+fn temperatures_round_to_the_nearest_degree() {
+    assert_eq!(weather_helpers::round_temperature(19.9), 20);
+    assert_eq!(weather_helpers::round_temperature(19.4), 19);
+    assert_eq!(weather_helpers::round_temperature(12.5), 13);
+}
+
+#[test]
+fn negative_temperatures_round_away_from_zero() {
+    assert_eq!(weather_helpers::round_temperature(-0.9), -1);
+    assert_eq!(weather_helpers::round_temperature(-0.5), -1);
+    assert_eq!(weather_helpers::round_temperature(-0.4), 0);
+    assert_eq!(weather_helpers::round_temperature(-12.5), -13);
+    assert_eq!(Locale::En.format_number(-0.6, 0), "-1");
+    assert_eq!(Locale::En.format_number(-2.5, 0), "-3");
+}
+
+#[test]
+fn halves_round_up_as_written() {
+    assert_eq!(Locale::En.format_number(12.5, 0), "13");
+    assert_eq!(Locale::En.format_number(14.45, 1), "14.5");
+    assert_eq!(Locale::De.format_number(-14.45, 1), "-14,5");
+    assert_eq!(weather_helpers::round_to(-0.04, 1).to_string(), "0");
+}
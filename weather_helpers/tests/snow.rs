@@ -0,0 +1,13 @@
+use weather_helpers::is_snow;
+
+#[test]
+fn snow_goes_by_the_condition_code() {
+    assert!(is_snow(600, "13d"));
+    assert!(is_snow(611, "13n"));
+    assert!(!is_snow(500, "10d"));
+    // Rain that the icon alone would call snow
+    assert!(!is_snow(511, "13d"));
+    // Without a code, the icon decides.
+    assert!(is_snow(0, "13n"));
+    assert!(!is_snow(0, "04d"));
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use weather_helpers::{moon_phase_name, solar_events, SolarEvents};
+
+const TROMSO: (f64, f64) = (69.65, 18.96);
+
+fn events(lat: f64, lon: f64, year: i32, month: u32, day: u32, offset_hours: i32) -> SolarEvents {
+    solar_events(
+        lat,
+        lon,
+        NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+        FixedOffset::east_opt(offset_hours * 3600).unwrap(),
+    )
+}
+
+fn hm(time: Option<DateTime<FixedOffset>>) -> Option<String> {
+    time.map(|time| time.format("%H:%M").to_string())
+}
+
+#[test]
+fn equinox_at_the_equator() {
+    let day = events(0.0, 0.0, 2026, 3, 20, 0);
+    assert_eq!(hm(Some(day.solar_noon)).as_deref(), Some("12:07"));
+    assert_eq!(hm(day.sunrise).as_deref(), Some("06:04"));
+    assert_eq!(hm(day.sunset).as_deref(), Some("18:10"));
+
+    // The sun climbs steeply at the equator, so its golden hours are short.
+    let golden = day.morning_golden_hour.unwrap();
+    assert_eq!((golden.end - golden.start).num_minutes(), 40);
+    let blue = day.evening_blue_hour.unwrap();
+    assert_eq!(blue.start, day.evening_golden_hour.unwrap().end);
+}
+
+#[test]
+fn times_are_in_the_local_offset() {
+    // London at midsummer, on British Summer Time
+    let day = events(51.5, -0.12, 2026, 6, 21, 1);
+    assert_eq!(hm(day.sunrise).as_deref(), Some("04:43"));
+    assert_eq!(hm(day.sunset).as_deref(), Some("21:21"));
+    assert_eq!(day.solar_noon.offset().local_minus_utc(), 3600);
+}
+
+#[test]
+fn sunset_after_midnight_falls_on_the_next_day() {
+    let reykjavik = events(64.1, -21.9, 2026, 6, 21, 0);
+    let sunset = reykjavik.sunset.unwrap();
+    assert_eq!(
+        sunset.date_naive(),
+        NaiveDate::from_ymd_opt(2026, 6, 22).unwrap()
+    );
+    assert!(sunset > reykjavik.sunrise.unwrap());
+}
+
+#[test]
+fn polar_day_has_no_sunrise_or_sunset() {
+    let day = events(TROMSO.0, TROMSO.1, 2026, 6, 21, 2);
+    assert_eq!(hm(Some(day.solar_noon)).as_deref(), Some("12:45"));
+    assert!(day.sunrise.is_none());
+    assert!(day.sunset.is_none());
+    assert!(day.morning_golden_hour.is_none());
+    assert!(day.evening_blue_hour.is_none());
+}
+
+#[test]
+fn polar_night_keeps_its_twilight() {
+    // The sun stays below the horizon, but climbs past the blue hour's -6°.
+    let day = events(TROMSO.0, TROMSO.1, 2026, 12, 21, 1);
+    assert!(day.sunrise.is_none());
+    assert!(day.sunset.is_none());
+    assert!(day.morning_golden_hour.is_none());
+    assert!(day.evening_golden_hour.is_none());
+    let morning = day.morning_blue_hour.unwrap();
+    let evening = day.evening_blue_hour.unwrap();
+    assert!(morning.end < day.solar_noon && day.solar_noon < evening.start);
+
+    // Near the pole there isn't even twilight.
+    let pole = events(-89.9, 0.0, 2026, 6, 21, 0);
+    assert!(pole.sunrise.is_none());
+    assert!(pole.morning_blue_hour.is_none());
+    assert!(pole.evening_blue_hour.is_none());
+}
+
+#[test]
+fn moon_phases() {
+    assert_eq!(moon_phase_name(0.0), "New moon");
+    assert_eq!(moon_phase_name(0.1), "Waxing crescent");
+    assert_eq!(moon_phase_name(0.25), "First quarter");
+    assert_eq!(moon_phase_name(0.4), "Waxing gibbous");
+    assert_eq!(moon_phase_name(0.5), "Full moon");
+    assert_eq!(moon_phase_name(0.6), "Waning gibbous");
+    assert_eq!(moon_phase_name(0.75), "Last quarter");
+    assert_eq!(moon_phase_name(0.9), "Waning crescent");
+}
+
+#[test]
+fn moon_phase_boundaries() {
+    // Each boundary starts the next phase.
+    for (phase, name) in [
+        (0.03, "Waxing crescent"),
+        (0.22, "First quarter"),
+        (0.28, "Waxing gibbous"),
+        (0.47, "Full moon"),
+        (0.53, "Waning gibbous"),
+        (0.72, "Last quarter"),
+        (0.78, "Waning crescent"),
+    ] {
+        assert_eq!(moon_phase_name(phase), name, "{}", phase);
+    }
+    // Either side of new moon, and the same phase a cycle on or back.
+    assert_eq!(moon_phase_name(0.97), "Waning crescent");
+    assert_eq!(moon_phase_name(0.98), "New moon");
+    assert_eq!(moon_phase_name(1.0), "New moon");
+    assert_eq!(moon_phase_name(1.5), "Full moon");
+    assert_eq!(moon_phase_name(-0.25), "Last quarter");
+}
@@ -0,0 +1,82 @@
+use chrono::Weekday;
+use weather_helpers::{weekday_full, Locale};
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[test]
+fn english_names() {
+    let names: Vec<&str> = WEEK
+        .iter()
+        .map(|&day| weekday_full(day, Locale::En))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday"
+        ]
+    );
+}
+
+#[test]
+fn localized_names() {
+    assert_eq!(weekday_full(Weekday::Mon, Locale::De), "Montag");
+    assert_eq!(weekday_full(Weekday::Wed, Locale::Es), "miércoles");
+    assert_eq!(weekday_full(Weekday::Sun, Locale::Fr), "dimanche");
+    assert_eq!(weekday_full(Weekday::Sat, Locale::He), "שבת");
+}
+
+#[test]
+fn every_locale_names_every_day() {
+    for locale in Locale::ALL {
+        for day in WEEK {
+            assert!(
+                !weekday_full(day, locale).trim().is_empty(),
+                "{:?} has no name for {}",
+                locale,
+                day
+            );
+            assert!(!locale.weekday_short(day).trim().is_empty());
+        }
+    }
+}
+
+#[test]
+fn day_names_are_distinct() {
+    for locale in Locale::ALL {
+        let mut names: Vec<&str> = WEEK.iter().map(|&day| weekday_full(day, locale)).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), 7, "{:?} repeats a day name", locale);
+    }
+}
+
+#[test]
+fn matches_chrono_short_names() {
+    // The mapping agrees with the chrono names that `datetime_to_day` returns.
+    for day in WEEK {
+        let english = weekday_full(day, Locale::En);
+        assert!(english.starts_with(&day.to_string()));
+    }
+}
+
+#[test]
+fn weekends_follow_the_locale() {
+    assert_eq!(Locale::En.weekend(), [Weekday::Sat, Weekday::Sun]);
+    assert_eq!(Locale::De.weekend(), [Weekday::Sat, Weekday::Sun]);
+    assert_eq!(Locale::He.weekend(), [Weekday::Fri, Weekday::Sat]);
+    assert_eq!(Locale::Fa.weekend(), [Weekday::Thu, Weekday::Fri]);
+}